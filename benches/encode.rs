@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ed::{Encode, FixedLength};
+
+type Nested = Vec<(Option<u32>, [u16; 4], Box<Option<u64>>)>;
+
+fn nested() -> Nested {
+    (0..10_000)
+        .map(|i| (Some(i), [i as u16; 4], Box::new(None)))
+        .collect()
+}
+
+fn encode_nested(c: &mut Criterion) {
+    let value = nested();
+    c.bench_function("encode_nested_two_pass", |b| {
+        b.iter(|| {
+            let mut bytes = Vec::with_capacity(value.encoding_length().unwrap());
+            value.encode_into(&mut bytes).unwrap();
+            bytes
+        })
+    });
+    c.bench_function("encode_nested_single_pass", |b| {
+        b.iter(|| value.encode_single_pass().unwrap())
+    });
+}
+
+fn encode_flat(c: &mut Criterion) {
+    let value: Vec<u64> = (0..10_000).collect();
+    c.bench_function("encode_flat_two_pass", |b| {
+        b.iter(|| {
+            let mut bytes = Vec::with_capacity(value.encoding_length().unwrap());
+            value.encode_into(&mut bytes).unwrap();
+            bytes
+        })
+    });
+    c.bench_function("encode_flat_single_pass", |b| {
+        b.iter(|| value.encode_single_pass().unwrap())
+    });
+}
+
+fn encode_fixed(c: &mut Criterion) {
+    let value = [(1u64, 2u32); 16];
+    c.bench_function("encode_fixed_two_pass", |b| {
+        b.iter(|| value.encode().unwrap())
+    });
+    c.bench_function("encode_fixed_exact", |b| {
+        b.iter(|| value.encode_fixed().unwrap())
+    });
+}
+
+criterion_group!(benches, encode_nested, encode_flat, encode_fixed);
+criterion_main!(benches);
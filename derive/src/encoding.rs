@@ -1,54 +1,69 @@
 use proc_macro2::{Literal, Span, TokenStream};
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
 use syn::*;
 
 // TODO: use correct spans so errors are shown on fields
 
 pub fn derive_encode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let remaining_check = remaining_last_check(&item);
+
+    match remote_path(&item) {
+        Ok(Some(remote)) => {
+            let output = remote_encode(&item, remote);
+            return quote!(#output #remaining_check).into();
+        }
+        Ok(None) => {}
+        Err(err) => return err.to_compile_error().into(),
+    }
 
     let output = match item.data.clone() {
-        Data::Struct(data) => struct_encode(item, data),
-        Data::Enum(data) => enum_encode(item, data),
-        Data::Union(_) => unimplemented!("Not implemented for unions"),
+        Data::Struct(data) => struct_encode(item.clone(), data),
+        Data::Enum(data) => enum_encode(item.clone(), data),
+        Data::Union(data) => return union_error(&data).into(),
     };
+    let async_impls = async_encode_impls(&item);
 
-    output.into()
+    quote!(#output #async_impls #remaining_check).into()
 }
 
 fn struct_encode(item: DeriveInput, data: DataStruct) -> TokenStream {
     let name = &item.ident;
 
-    let mut generics_sanitized = item.generics.clone();
-    generics_sanitized.params.iter_mut().for_each(|p| {
-        if let GenericParam::Type(ref mut ty) = p {
-            ty.default.take();
-        }
-    });
-    let gen_params = gen_param_input(&item.generics);
+    let (generics_sanitized, gen_params, where_preds) = impl_generics(&item.generics);
     let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Encode));
-    let where_preds = item
-        .generics
-        .where_clause
-        .as_ref()
-        .map(|w| {
-            let preds = w.predicates.clone().into_iter();
-            quote!(#(#preds,)*)
-        })
-        .unwrap_or_default();
 
-    let encode_into = fields_encode_into(iter_field_names(&data.fields), Some(quote!(self)));
-    let encoding_length =
-        fields_encoding_length(iter_field_names(&data.fields), Some(quote!(self)));
+    let encode_into = fields_encode_into(
+        &data.fields,
+        iter_field_names(&data.fields),
+        Some(quote!(self)),
+    );
+    let encoding_length = fields_encoding_length(
+        &data.fields,
+        iter_field_names(&data.fields),
+        Some(quote!(self)),
+    );
 
     let terminated = terminated_impl(&item);
+    let sensitive = sensitive_const(&item, quote!(::ed::Encode));
+    let digest = default_digest_impl(
+        &item,
+        quote!(#generics_sanitized),
+        quote!(#where_preds #terminated_bounds),
+    );
 
     quote! {
         impl#generics_sanitized ::ed::Encode for #name#gen_params
         where #where_preds #terminated_bounds
         {
+            #sensitive
+
             #[inline]
-            fn encode_into<__W: std::io::Write>(&self, mut dest: &mut __W) -> ::ed::Result<()> {
+            fn encode_into<__W: ::ed::io::Write>(&self, mut dest: &mut __W) -> ::ed::Result<()> {
                 #encode_into
 
                 Ok(())
@@ -61,32 +76,233 @@ fn struct_encode(item: DeriveInput, data: DataStruct) -> TokenStream {
         }
 
         #terminated
+        #digest
     }
 }
 
 fn enum_encode(item: DeriveInput, data: DataEnum) -> TokenStream {
     let name = &item.ident;
 
-    let mut generics_sanitized = item.generics.clone();
-    generics_sanitized.params.iter_mut().for_each(|p| {
-        if let GenericParam::Type(ref mut ty) = p {
-            ty.default.take();
-        }
-    });
-    let gen_params = gen_param_input(&item.generics);
+    let (generics_sanitized, gen_params, where_preds) = impl_generics(&item.generics);
     let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Encode));
-    let where_preds = item
-        .generics
-        .where_clause
-        .as_ref()
-        .map(|w| {
-            let preds = w.predicates.clone().into_iter();
-            quote!(#(#preds,)*)
-        })
-        .unwrap_or_default();
 
-    let arms = data
-        .variants
+    let arms = enum_encode_arms(&data, &quote!(Self));
+
+    let encode_into = quote! {
+        #[inline]
+        fn encode_into<__W: ::ed::io::Write>(&self, mut dest: &mut __W) -> ::ed::Result<()> {
+            match self {
+                #(#arms)*
+                _ => return Err(::ed::Error::UnencodableVariant)
+            }
+
+            Ok(())
+        }
+    };
+
+    let arms = enum_encoding_length_arms(&data, &quote!(Self));
+
+    let encoding_length = quote! {
+        #[inline]
+        fn encoding_length(&self) -> ::ed::Result<usize> {
+            Ok(1 + match self {
+                #(#arms)*
+                _ => return Err(::ed::Error::UnencodableVariant)
+            })
+        }
+    };
+
+    let terminated = terminated_impl(&item);
+    let sensitive = sensitive_const(&item, quote!(::ed::Encode));
+    let digest = default_digest_impl(
+        &item,
+        quote!(#generics_sanitized),
+        quote!(#where_preds #terminated_bounds),
+    );
+
+    quote! {
+        impl#generics_sanitized ::ed::Encode for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            #sensitive
+            #encode_into
+            #encoding_length
+        }
+
+        #terminated
+        #digest
+    }
+}
+
+pub fn derive_decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let remaining_check = remaining_last_check(&item);
+
+    match remote_path(&item) {
+        Ok(Some(remote)) => {
+            let output = remote_decode(&item, remote);
+            return quote!(#output #remaining_check).into();
+        }
+        Ok(None) => {}
+        Err(err) => return err.to_compile_error().into(),
+    }
+
+    let output = match item.data.clone() {
+        Data::Struct(data) => struct_decode(item.clone(), data),
+        Data::Enum(data) => enum_decode(item.clone(), data),
+        Data::Union(data) => return union_error(&data).into(),
+    };
+    let async_impls = async_decode_impls(&item);
+
+    quote!(#output #async_impls #remaining_check).into()
+}
+
+fn struct_decode(item: DeriveInput, data: DataStruct) -> TokenStream {
+    let name = &item.ident;
+
+    let label = name.to_string();
+    let decode = fields_decode(&data.fields, quote!(Self), &label);
+    let decode_into = fields_decode_into(&data.fields, None, &label);
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Decode));
+
+    let sensitive = sensitive_const(&item, quote!(::ed::Decode));
+    let accessors = struct_accessors(&item, &data);
+
+    quote! {
+        impl#generics ed::Decode for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            #sensitive
+
+            #[inline]
+            fn decode<__R: ::ed::io::Read>(mut input: __R) -> ed::Result<Self> {
+                let _sensitive = ::ed::wipe::sensitive(<Self as ::ed::Decode>::SENSITIVE);
+                Ok(#decode)
+            }
+
+            #[inline]
+            fn decode_into<__R: ::ed::io::Read>(&mut self, mut input: __R) -> ed::Result<()> {
+                let _sensitive = ::ed::wipe::sensitive(<Self as ::ed::Decode>::SENSITIVE);
+                #decode_into
+                Ok(())
+            }
+        }
+
+        #accessors
+    }
+}
+
+fn struct_accessors(item: &DeriveInput, data: &DataStruct) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+
+    let fields: Vec<_> = iter_fields(&data.fields).collect();
+    let field_names: Vec<_> = iter_field_names(&data.fields).collect();
+
+    let mut accessors = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let flag = match ed_flag(&field.attrs, "accessor") {
+            Some(flag) => flag,
+            None => continue,
+        };
+        // The offset is the sum of the fixed lengths of the preceding fields,
+        // which isn't known for fields encoded by another type.
+        if fields[..i].iter().any(|f| field_with(f).is_some()) {
+            return Error::new_spanned(
+                flag,
+                "`accessor` can't follow a field with `#[ed(with = \"...\")]`",
+            )
+            .to_compile_error();
+        }
+
+        let ty = &field.ty;
+        let prev_types: Vec<_> = fields[..i].iter().map(|f| f.ty.clone()).collect();
+
+        let field_name = field_names[i].to_string();
+        let field_name = field_name.trim_start_matches("r#");
+        let method = Ident::new(&format!("decode_{}", field_name), Span::call_site());
+        let doc = format!(
+            "Decodes the `{}` field directly from the encoding of a `{}`, without decoding the other fields.",
+            field_name, name
+        );
+        let (bound, decode) = match field_with(field) {
+            Some(with) => (quote!(), quote!(#with::decode(field_bytes))),
+            None => (
+                quote!(for<'__ed> #ty: ::ed::Decode,),
+                quote!(::ed::Decode::decode(field_bytes)),
+            ),
+        };
+
+        accessors.push(quote! {
+            #[doc = #doc]
+            #[inline]
+            pub fn #method(bytes: &[u8]) -> ::ed::Result<#ty>
+            where #bound #(for<'__ed> #prev_types: ::ed::FixedLength,)*
+            {
+                let offset = 0 #(+ <#prev_types as ::ed::FixedLength>::ENCODING_LENGTH)*;
+                let field_bytes = bytes.get(offset..).ok_or_else(|| {
+                    ::ed::Error::IOError(::ed::io::ErrorKind::UnexpectedEof.into())
+                })?;
+                #decode
+            }
+        });
+    }
+
+    if accessors.is_empty() {
+        return quote!();
+    }
+
+    quote! {
+        impl#generics #name#gen_params
+        where #where_preds
+        {
+            #(#accessors)*
+        }
+    }
+}
+
+fn enum_decode(item: DeriveInput, data: DataEnum) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Decode));
+
+    let arms = enum_decode_arms(&data, &quote!(Self), &name.to_string());
+
+    let sensitive = sensitive_const(&item, quote!(::ed::Decode));
+
+    quote! {
+        impl#generics ::ed::Decode for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            #sensitive
+
+            #[inline]
+            fn decode<__R: ::ed::io::Read>(mut input: __R) -> ::ed::Result<Self> {
+                let _sensitive = ::ed::wipe::sensitive(<Self as ::ed::Decode>::SENSITIVE);
+                let mut variant = [0; 1];
+                input.read_exact(&mut variant[..])?;
+                let [variant] = variant;
+
+                Ok(match variant {
+                    #(#arms),*
+                    n => return Err(::ed::Error::UnexpectedByte(n)),
+                })
+            }
+
+            // TODO: decode_into
+        }
+    }
+}
+
+fn enum_encode_arms(data: &DataEnum, path: &TokenStream) -> Vec<TokenStream> {
+    data.variants
         .iter()
         .filter(|v| filter_skipped_variants(*v))
         .enumerate()
@@ -94,192 +310,950 @@ fn enum_encode(item: DeriveInput, data: DataEnum) -> TokenStream {
             let i = i as u8;
             let ident = &v.ident;
             let destructure = variant_destructure(&v);
-            let encode = fields_encode_into(iter_field_destructure(&v), None);
-            quote!(Self::#ident #destructure => {
+            let encode = fields_encode_into(&v.fields, iter_field_destructure(&v), None);
+            quote!(#path::#ident #destructure => {
                 dest.write_all(&[ #i ][..])?;
                 #encode
             })
+        })
+        .collect()
+}
+
+fn enum_encoding_length_arms(data: &DataEnum, path: &TokenStream) -> Vec<TokenStream> {
+    data.variants
+        .iter()
+        .filter(|v| filter_skipped_variants(*v))
+        .map(|v| {
+            let arm = fields_encoding_length(&v.fields, iter_field_destructure(&v), None);
+            let ident = &v.ident;
+            let destructure = variant_destructure(&v);
+            quote!(#path::#ident #destructure => { #arm })
+        })
+        .collect()
+}
+
+fn enum_decode_arms(data: &DataEnum, path: &TokenStream, label: &str) -> Vec<TokenStream> {
+    data.variants
+        .iter()
+        .filter(|v| filter_skipped_variants(*v))
+        .enumerate()
+        .map(|(i, v)| {
+            let i = i as u8;
+            let ident = &v.ident;
+            let label = format!("{}::{}", label, ident);
+            let arm = fields_decode(&v.fields, quote!(#path::#ident), &label);
+            quote!(#i => { #arm })
+        })
+        .collect()
+}
+
+// The modules of the enabled async I/O integrations, whose `AsyncEncode` and
+// `AsyncDecode` traits are derived alongside `Encode` and `Decode`.
+fn async_modules() -> Vec<TokenStream> {
+    #[allow(unused_mut)]
+    let mut modules = vec![];
+    #[cfg(feature = "futures-io")]
+    modules.push(quote!(::ed::futures_io));
+    #[cfg(feature = "tokio")]
+    modules.push(quote!(::ed::tokio));
+    modules
+}
+
+// The custom encoding functions of fields marked `#[ed(with = "...")]` are
+// sync, so the async traits aren't derived for types with such fields.
+fn has_with_fields(item: &DeriveInput) -> bool {
+    iter_field_groups(item.clone())
+        .any(|fields| iter_fields(&fields).any(|f| field_with(&f).is_some()))
+}
+
+fn async_encode_impls(item: &DeriveInput) -> TokenStream {
+    if has_with_fields(item) {
+        return quote!();
+    }
+
+    let impls = async_modules()
+        .into_iter()
+        .map(|module| async_encode_impl(item, &module));
+    quote!(#(#impls)*)
+}
+
+fn async_encode_impl(item: &DeriveInput, module: &TokenStream) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let terminated_bounds = iter_terminated_bounds(item, quote!(#module::AsyncEncode));
+
+    let body = match &item.data {
+        Data::Struct(data) => {
+            fields_encode_async(iter_field_names(&data.fields), Some(quote!(self)), module)
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .filter(|v| filter_skipped_variants(*v))
+                .enumerate()
+                .map(|(i, v)| {
+                    let i = i as u8;
+                    let ident = &v.ident;
+                    let destructure = variant_destructure(&v);
+                    let encode = fields_encode_async(iter_field_destructure(&v), None, module);
+                    quote!(Self::#ident #destructure => {
+                        #module::AsyncEncode::encode_into_async(&#i, dest).await?;
+                        #encode
+                    })
+                });
+            quote! {
+                match self {
+                    #(#arms)*
+                    #[allow(unreachable_patterns)]
+                    _ => return Err(::ed::Error::UnencodableVariant)
+                }
+            }
+        }
+        Data::Union(data) => return union_error(&data),
+    };
+
+    quote! {
+        impl#generics #module::AsyncEncode for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            async fn encode_into_async<__W>(&self, dest: &mut __W) -> ::ed::Result<()>
+            where
+                __W: #module::AsyncWrite + ::core::marker::Unpin + ::core::marker::Send,
+            {
+                #body
+                Ok(())
+            }
+        }
+    }
+}
+
+fn async_decode_impls(item: &DeriveInput) -> TokenStream {
+    if has_with_fields(item) {
+        return quote!();
+    }
+
+    let impls = async_modules()
+        .into_iter()
+        .map(|module| async_decode_impl(item, &module));
+    quote!(#(#impls)*)
+}
+
+fn async_decode_impl(item: &DeriveInput, module: &TokenStream) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let terminated_bounds = iter_terminated_bounds(item, quote!(#module::AsyncDecode));
+
+    let label = name.to_string();
+    let body = match &item.data {
+        Data::Struct(data) => {
+            let decode = fields_decode_async(&data.fields, quote!(Self), &label, module);
+            quote!(Ok(#decode))
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .filter(|v| filter_skipped_variants(*v))
+                .enumerate()
+                .map(|(i, v)| {
+                    let i = i as u8;
+                    let ident = &v.ident;
+                    let label = format!("{}::{}", label, ident);
+                    let decode =
+                        fields_decode_async(&v.fields, quote!(Self::#ident), &label, module);
+                    quote!(#i => { #decode })
+                });
+            quote! {
+                let variant = <u8 as #module::AsyncDecode>::decode_async(&mut input).await?;
+
+                Ok(match variant {
+                    #(#arms),*
+                    n => return Err(::ed::Error::UnexpectedByte(n)),
+                })
+            }
+        }
+        Data::Union(data) => return union_error(&data),
+    };
+
+    quote! {
+        impl#generics #module::AsyncDecode for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            async fn decode_async<__R>(mut input: __R) -> ::ed::Result<Self>
+            where
+                __R: #module::AsyncRead + ::core::marker::Unpin + ::core::marker::Send,
+            {
+                #body
+            }
+        }
+    }
+}
+
+fn remote_encode(item: &DeriveInput, remote: Path) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+
+    let (encode_into, encoding_length) = match &item.data {
+        Data::Struct(data) => {
+            let names = || iter_field_names(&data.fields);
+            let encode_into = fields_encode_into(&data.fields, names(), Some(quote!(value)));
+            let encoding_length =
+                fields_encoding_length(&data.fields, names(), Some(quote!(value)));
+            (quote!(#encode_into Ok(())), quote!(Ok(#encoding_length)))
+        }
+        Data::Enum(data) => {
+            let path = quote!(#remote);
+            let encode_arms = enum_encode_arms(data, &path);
+            let length_arms = enum_encoding_length_arms(data, &path);
+            (
+                quote! {
+                    match value {
+                        #(#encode_arms)*
+                        #[allow(unreachable_patterns)]
+                        _ => return Err(::ed::Error::UnencodableVariant)
+                    }
+                    Ok(())
+                },
+                quote! {
+                    Ok(1 + match value {
+                        #(#length_arms)*
+                        #[allow(unreachable_patterns)]
+                        _ => return Err(::ed::Error::UnencodableVariant)
+                    })
+                },
+            )
+        }
+        Data::Union(data) => return union_error(data),
+    };
+
+    let encode_doc = format!(
+        "Writes the encoding of a `{}`, for use with `#[ed(with = \"{}\")]`.",
+        quote!(#remote).to_string().replace(' ', ""),
+        name
+    );
+    let length_doc = format!(
+        "Returns the encoding length of a `{}`.",
+        quote!(#remote).to_string().replace(' ', "")
+    );
+
+    quote! {
+        impl#generics #name#gen_params
+        where #where_preds
+        {
+            #[doc = #encode_doc]
+            #[inline]
+            pub fn encode_into<__W: ::ed::io::Write>(value: &#remote, mut dest: &mut __W) -> ::ed::Result<()> {
+                #encode_into
+            }
+
+            #[doc = #length_doc]
+            #[inline]
+            pub fn encoding_length(value: &#remote) -> ::ed::Result<usize> {
+                #encoding_length
+            }
+        }
+    }
+}
+
+fn remote_decode(item: &DeriveInput, remote: Path) -> TokenStream {
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+
+    let label = quote!(#remote).to_string().replace(' ', "");
+    let decode = match &item.data {
+        Data::Struct(data) => {
+            let decode = fields_decode(&data.fields, quote!(#remote), &label);
+            quote!(Ok(#decode))
+        }
+        Data::Enum(data) => {
+            let arms = enum_decode_arms(data, &quote!(#remote), &label);
+            quote! {
+                let mut variant = [0; 1];
+                input.read_exact(&mut variant[..])?;
+                let [variant] = variant;
+
+                Ok(match variant {
+                    #(#arms),*
+                    n => return Err(::ed::Error::UnexpectedByte(n)),
+                })
+            }
+        }
+        Data::Union(data) => return union_error(data),
+    };
+
+    let doc = format!(
+        "Decodes a `{}`, for use with `#[ed(with = \"{}\")]`.",
+        quote!(#remote).to_string().replace(' ', ""),
+        name
+    );
+
+    quote! {
+        impl#generics #name#gen_params
+        where #where_preds
+        {
+            #[doc = #doc]
+            #[inline]
+            pub fn decode<__R: ::ed::io::Read>(mut input: __R) -> ::ed::Result<#remote> {
+                #decode
+            }
+        }
+    }
+}
+
+fn remote_path(item: &DeriveInput) -> Result<Option<Path>> {
+    ed_value(&item.attrs, "remote")
+        .map(|remote| remote.parse().map_err(|err| Error::new(remote.span(), err)))
+        .transpose()
+}
+
+pub fn derive_decode_lenient(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Decode));
+
+    let decode_fields = match item.data.clone() {
+        Data::Struct(data) => fields_decode_lenient(&data.fields, None),
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .filter(|v| filter_skipped_variants(*v))
+                .enumerate()
+                .map(|(i, v)| {
+                    let i = i as u8;
+                    let arm = fields_decode_lenient(&v.fields, Some(v.ident.clone()));
+                    quote!(#i => { #arm })
+                });
+
+            quote! {
+                let offset = decoder.offset();
+                let variant: u8 = decoder.field("variant")?;
+                match variant {
+                    #(#arms),*
+                    n => {
+                        decoder.record(offset, "variant", ::ed::Error::UnexpectedByte(n));
+                        decoder.halt();
+                        None
+                    }
+                }
+            }
+        }
+        Data::Union(data) => return union_error(&data).into(),
+    };
+
+    let output = quote! {
+        impl#generics ::ed::lenient::DecodeLenient for #name#gen_params
+        where #where_preds #terminated_bounds
+        {
+            fn decode_fields(decoder: &mut ::ed::lenient::Decoder) -> Option<Self> {
+                #decode_fields
+            }
+        }
+    };
+
+    output.into()
+}
+
+pub fn derive_columns(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let name = &item.ident;
+
+    let fields = match item.data.clone() {
+        Data::Struct(data) => data.fields,
+        data => return struct_only_error(&data, "Columns can only be derived for structs").into(),
+    };
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    // Fields encoded with `#[ed(with = "...")]` don't implement the traits
+    // themselves, so they are left unbounded.
+    let bounds = iter_fields(&fields)
+        .filter(|field| field_with(field).is_none())
+        .map(|field| {
+            let ty = field.ty;
+            quote!(for<'__ed> #ty: ::ed::Encode + ::ed::Decode + ::ed::Terminated,)
         });
 
-    let encode_into = quote! {
-        #[inline]
-        fn encode_into<__W: std::io::Write>(&self, mut dest: &mut __W) -> ::ed::Result<()> {
-            match self {
-                #(#arms)*
-                _ => return Err(::ed::Error::UnencodableVariant)
+    let field_names: Vec<_> = iter_field_names(&fields).collect();
+    let vars: Vec<_> = (0..field_names.len())
+        .map(|i| Ident::new(&format!("__column{}", i), Span::call_site()))
+        .collect();
+    let withs: Vec<_> = iter_fields(&fields)
+        .map(|field| field_with(&field))
+        .collect();
+    let encodes = field_names
+        .iter()
+        .zip(&withs)
+        .map(|(name, with)| match with {
+            Some(with) => quote!(#with::encode_into(&row.#name, dest)?;),
+            None => quote!(::ed::Encode::encode_into(&row.#name, dest)?;),
+        });
+    let lengths = field_names
+        .iter()
+        .zip(&withs)
+        .map(|(name, with)| match with {
+            Some(with) => quote!(#with::encoding_length(&row.#name)?),
+            None => quote!(::ed::Encode::encoding_length(&row.#name)?),
+        });
+    // Rows without fields read nothing, so any number of them isn't backed by
+    // the input.
+    let empty_check = if field_names.is_empty() {
+        quote! {
+            if count > 0 {
+                return Err(::ed::Error::CountExceedsInput(count));
+            }
+        }
+    } else {
+        quote!()
+    };
+    let decodes = iter_fields(&fields)
+        .zip(&withs)
+        .map(|(field, with)| match with {
+            Some(with) => quote! {
+                ::ed::columnar::decode_column_with(count, &mut input, |input| #with::decode(input))?
+            },
+            None => {
+                let ty = field.ty;
+                quote!(::ed::columnar::decode_column::<#ty, _>(count, &mut input)?)
+            }
+        });
+
+    let output = quote! {
+        impl#generics ::ed::columnar::Columns for #name#gen_params
+        where #where_preds #(#bounds)*
+        {
+            #[inline]
+            fn encode_columns<__W: ::ed::io::Write>(rows: &[Self], dest: &mut __W) -> ::ed::Result<()> {
+                #(
+                    for row in rows {
+                        #encodes
+                    }
+                )*
+                Ok(())
+            }
+
+            #[inline]
+            fn columns_encoding_length(rows: &[Self]) -> ::ed::Result<usize> {
+                let mut length = 0;
+                #(
+                    for row in rows {
+                        length += #lengths;
+                    }
+                )*
+                Ok(length)
+            }
+
+            #[inline]
+            fn decode_columns<__R: ::ed::io::Read>(count: usize, mut input: __R) -> ::ed::Result<Vec<Self>> {
+                #empty_check
+                #(
+                    let mut #vars = #decodes.into_iter();
+                )*
+                Ok((0..count)
+                    .filter_map(|_| Some(Self { #(#field_names: #vars.next()?,)* }))
+                    .collect())
+            }
+        }
+    };
+
+    output.into()
+}
+
+pub fn derive_profile(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let bounds = iter_field_groups(item.clone()).map(|fields| {
+        let bounds = iter_fields(&fields)
+            .filter(|f| field_with(f).is_none())
+            .map(|f| {
+                let ty = f.ty;
+                quote!(for<'__ed> #ty: ::ed::profile::Profile,)
+            });
+        quote!(#(#bounds)*)
+    });
+
+    let body = match item.data.clone() {
+        Data::Struct(data) => {
+            let parts = fields_profile(
+                &data.fields,
+                iter_field_names(&data.fields),
+                Some(quote!(self)),
+                None,
+            );
+            quote!(#parts)
+        }
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .filter(|v| filter_skipped_variants(*v))
+                .map(|v| {
+                    let ident = &v.ident;
+                    let label = ident.to_string();
+                    let destructure = variant_destructure(&v);
+                    let parts =
+                        fields_profile(&v.fields, iter_field_destructure(&v), None, Some(&label));
+                    quote!(Self::#ident #destructure => {
+                        report.record(
+                            &::ed::profile::join(path, #label),
+                            ::ed::Encode::encoding_length(self)?,
+                        );
+                        #parts
+                    })
+                });
+            quote! {
+                match self {
+                    #(#arms)*
+                    #[allow(unreachable_patterns)]
+                    _ => return Err(::ed::Error::UnencodableVariant)
+                }
+            }
+        }
+        Data::Union(data) => return union_error(&data).into(),
+    };
+
+    let output = quote! {
+        impl#generics ::ed::profile::Profile for #name#gen_params
+        where #where_preds #(#bounds)* for<'__ed> #name#gen_params: ::ed::Encode,
+        {
+            fn profile_parts(
+                &self,
+                path: &str,
+                report: &mut ::ed::profile::SizeReport,
+            ) -> ::ed::Result<()> {
+                #body
+                Ok(())
             }
+        }
+    };
+
+    output.into()
+}
+
+pub fn derive_cbor(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let name = &item.ident;
+
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+    let bounds = iter_field_groups(item.clone()).map(|fields| {
+        let bounds = iter_fields(&fields)
+            .filter(|f| field_with(f).is_none())
+            .map(|f| {
+                let ty = f.ty;
+                quote!(for<'__ed> #ty: ::ed::cbor::Cbor,)
+            });
+        quote!(#(#bounds)*)
+    });
+
+    let body = match item.data.clone() {
+        Data::Struct(data) => fields_cbor(
+            &data.fields,
+            iter_field_names(&data.fields),
+            Some(quote!(self)),
+        ),
+        Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .filter(|v| filter_skipped_variants(*v))
+                .map(|v| {
+                    let ident = &v.ident;
+                    let label = ident.to_string();
+                    let destructure = variant_destructure(&v);
+                    match v.fields {
+                        Fields::Unit => quote!(Self::#ident #destructure => {
+                            ::ed::cbor::write_text(#label, dest)?;
+                        }),
+                        _ => {
+                            let fields = fields_cbor(&v.fields, iter_field_destructure(&v), None);
+                            quote!(Self::#ident #destructure => {
+                                ::ed::cbor::write_head(::ed::cbor::MAP, 1, dest)?;
+                                ::ed::cbor::write_text(#label, dest)?;
+                                #fields
+                            })
+                        }
+                    }
+                });
+            quote! {
+                match self {
+                    #(#arms)*
+                    #[allow(unreachable_patterns)]
+                    _ => return Err(::ed::Error::UnencodableVariant)
+                }
+            }
+        }
+        Data::Union(data) => return union_error(&data).into(),
+    };
+
+    let output = quote! {
+        impl#generics ::ed::cbor::Cbor for #name#gen_params
+        where #where_preds #(#bounds)*
+        {
+            fn write_cbor<__W: ::ed::io::Write>(&self, dest: &mut __W) -> ::ed::Result<()> {
+                #body
+                Ok(())
+            }
+        }
+    };
+
+    output.into()
+}
+
+pub fn derive_track(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
+    let name = &item.ident;
+    let name_str = name.to_string();
 
-            Ok(())
-        }
+    let fields = match item.data.clone() {
+        Data::Struct(data) => data.fields,
+        data => return struct_only_error(&data, "Track can only be derived for structs").into(),
     };
 
-    let arms = data
-        .variants
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
+
+    let field_names: Vec<_> = iter_field_names(&fields).collect();
+    let labels = field_names.iter().map(|name| {
+        let label = name.to_string();
+        label.trim_start_matches("r#").to_string()
+    });
+    let indexes = (0..field_names.len()).map(Literal::usize_unsuffixed);
+    let encodes = field_names
         .iter()
-        .filter(|v| filter_skipped_variants(*v))
-        .map(|v| {
-            let arm = fields_encoding_length(iter_field_destructure(&v), None);
-            let ident = &v.ident;
-            let destructure = variant_destructure(&v);
-            quote!(Self::#ident #destructure => { #arm })
+        .zip(iter_fields(&fields))
+        .zip(indexes.clone())
+        .map(|((name, field), i)| match field_with(&field) {
+            Some(with) => quote!(#i => #with::encode_into(&self.#name, dest),),
+            None => quote!(#i => ::ed::Encode::encode_into(&self.#name, dest),),
+        });
+    let lengths = field_names
+        .iter()
+        .zip(iter_fields(&fields))
+        .zip(indexes)
+        .map(|((name, field), i)| match field_with(&field) {
+            Some(with) => quote!(#i => #with::encoding_length(&self.#name),),
+            None => quote!(#i => ::ed::Encode::encoding_length(&self.#name),),
         });
 
-    let encoding_length = quote! {
-        #[inline]
-        fn encoding_length(&self) -> ::ed::Result<usize> {
-            Ok(1 + match self {
-                #(#arms)*
-                _ => return Err(::ed::Error::UnencodableVariant)
-            })
-        }
-    };
-
-    let terminated = terminated_impl(&item);
+    let selectors = field_names
+        .iter()
+        .zip(iter_fields(&fields))
+        .enumerate()
+        .map(|(i, (name, field))| {
+            let label = name.to_string();
+            let label = label.trim_start_matches("r#");
+            let constant = match field.ident {
+                Some(_) => Ident::new(&label.to_uppercase(), Span::call_site()),
+                None => Ident::new(&format!("FIELD_{}", label), Span::call_site()),
+            };
+            let doc = format!(
+                "Selects the `{}` field to modify through a `Tracked<{}>`.",
+                label, name_str
+            );
+            let vis = &field.vis;
+            let ty = &field.ty;
+            let i = Literal::usize_unsuffixed(i);
+            quote! {
+                #[doc = #doc]
+                #vis const #constant: ::ed::tracked::Field<Self, #ty> =
+                    ::ed::tracked::Field::new(#i, |value| &mut value.#name);
+            }
+        });
 
-    quote! {
-        impl#generics_sanitized ::ed::Encode for #name#gen_params
-        where #where_preds #terminated_bounds
+    let output = quote! {
+        impl#generics ::ed::tracked::Track for #name#gen_params
+        where #where_preds for<'__ed> #name#gen_params: ::ed::Encode,
         {
-            #encode_into
-            #encoding_length
-        }
+            const FIELDS: &'static [&'static str] = &[#(#labels),*];
 
-        #terminated
-    }
-}
+            #[inline]
+            fn encode_field_into<__W: ::ed::io::Write>(
+                &self,
+                index: usize,
+                dest: &mut __W,
+            ) -> ::ed::Result<()> {
+                match index {
+                    #(#encodes)*
+                    _ => Err(::ed::Error::UnencodableVariant),
+                }
+            }
 
-pub fn derive_decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let item = parse_macro_input!(item as DeriveInput);
+            #[inline]
+            fn field_encoding_length(&self, index: usize) -> ::ed::Result<usize> {
+                match index {
+                    #(#lengths)*
+                    _ => Err(::ed::Error::UnencodableVariant),
+                }
+            }
+        }
 
-    let output = match item.data.clone() {
-        Data::Struct(data) => struct_decode(item, data),
-        Data::Enum(data) => enum_decode(item, data),
-        Data::Union(_) => unimplemented!("Not implemented for unions"),
+        impl#generics #name#gen_params
+        where #where_preds
+        {
+            #(#selectors)*
+        }
     };
 
     output.into()
 }
 
-fn struct_decode(item: DeriveInput, data: DataStruct) -> TokenStream {
+pub fn derive_encoding_cost(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
     let name = &item.ident;
 
-    let decode = fields_decode(&data.fields, None);
-    let decode_into = fields_decode_into(&data.fields, None);
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
 
-    let mut generics = item.generics.clone();
-    generics.params.iter_mut().for_each(|p| {
-        if let GenericParam::Type(ref mut ty) = p {
-            ty.default.take();
-        }
+    // Skipped variants and fields still own their memory, so every field is
+    // counted.
+    let field_groups: Vec<Fields> = match item.data.clone() {
+        Data::Struct(data) => vec![data.fields],
+        Data::Enum(data) => data.variants.into_iter().map(|v| v.fields).collect(),
+        Data::Union(data) => return union_error(&data).into(),
+    };
+    let bounds = field_groups.iter().flat_map(iter_fields).map(|f| {
+        let ty = f.ty;
+        quote!(for<'__ed> #ty: ::ed::cost::EncodingCost,)
     });
-    let gen_params = gen_param_input(&item.generics);
-    let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Decode));
-    let where_preds = item
-        .generics
-        .where_clause
-        .as_ref()
-        .map(|w| {
-            let preds = w.predicates.clone().into_iter();
-            quote!(#(#preds,)*)
-        })
-        .unwrap_or_default();
 
-    quote! {
-        impl#generics ed::Decode for #name#gen_params
-        where #where_preds #terminated_bounds
-        {
-            #[inline]
-            fn decode<__R: std::io::Read>(mut input: __R) -> ed::Result<Self> {
-                Ok(#decode)
+    let body = match item.data.clone() {
+        Data::Struct(data) => {
+            let names = iter_field_names(&data.fields);
+            quote!(0 #(+ ::ed::cost::EncodingCost::heap_bytes(&self.#names))*)
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|v| {
+                let ident = &v.ident;
+                let destructure = variant_destructure(v);
+                let names = iter_field_destructure(v);
+                quote! {
+                    Self::#ident #destructure => {
+                        0 #(+ ::ed::cost::EncodingCost::heap_bytes(#names))*
+                    }
+                }
+            });
+            if data.variants.is_empty() {
+                quote!(match *self {})
+            } else {
+                quote! {
+                    match self {
+                        #(#arms)*
+                    }
+                }
             }
+        }
+        Data::Union(data) => return union_error(&data).into(),
+    };
 
+    let output = quote! {
+        impl#generics ::ed::cost::EncodingCost for #name#gen_params
+        where #where_preds #(#bounds)*
+        {
             #[inline]
-            fn decode_into<__R: std::io::Read>(&mut self, mut input: __R) -> ed::Result<()> {
-                #decode_into
-                Ok(())
+            fn heap_bytes(&self) -> usize {
+                #body
             }
         }
+    };
+
+    output.into()
+}
+
+fn fields_profile(
+    fields: &Fields,
+    field_names: impl Iterator<Item = TokenStream>,
+    parent: Option<TokenStream>,
+    variant: Option<&str>,
+) -> TokenStream {
+    // Fields of enum variants are already bound by reference.
+    let field_ref = |name: TokenStream| match &parent {
+        Some(parent) => quote!(&#parent.#name),
+        None => name,
+    };
+
+    let parts = iter_field_names(fields)
+        .zip(field_names)
+        .zip(iter_fields(fields))
+        .map(|((label, name), field)| {
+            let label = label.to_string();
+            let label = label.trim_start_matches("r#");
+            let label = match variant {
+                Some(variant) => format!("{}.{}", variant, label),
+                None => label.to_string(),
+            };
+            let path = quote!(&::ed::profile::join(path, #label));
+            let value = field_ref(name);
+            match field_with(&field) {
+                Some(with) => quote! {
+                    report.record(#path, #with::encoding_length(#value)?);
+                },
+                None => quote! {
+                    ::ed::profile::Profile::profile(#value, #path, report)?;
+                },
+            }
+        });
+
+    quote! {
+        #(#parts)*
     }
 }
 
-fn enum_decode(item: DeriveInput, data: DataEnum) -> TokenStream {
-    let name = &item.ident;
+/// Writes the fields of a struct or variant as a map for named fields, the
+/// field itself for a single unnamed field, an array for other unnamed
+/// fields, or null for no fields.
+fn fields_cbor(
+    fields: &Fields,
+    field_names: impl Iterator<Item = TokenStream>,
+    parent: Option<TokenStream>,
+) -> TokenStream {
+    // Fields of enum variants are already bound by reference.
+    let field_ref = |name: TokenStream| match &parent {
+        Some(parent) => quote!(&#parent.#name),
+        None => name,
+    };
+    let write = |field: &Field, value: TokenStream| match field_with(field) {
+        Some(with) => quote! {
+            ::ed::cbor::write_encoded(dest, |bytes| #with::encode_into(#value, bytes))?;
+        },
+        None => quote! {
+            ::ed::cbor::Cbor::write_cbor(#value, dest)?;
+        },
+    };
 
-    let mut generics = item.generics.clone();
-    generics.params.iter_mut().for_each(|p| {
-        if let GenericParam::Type(ref mut ty) = p {
-            ty.default.take();
+    match fields {
+        Fields::Named(_) => {
+            let entries = iter_field_names(fields)
+                .zip(field_names)
+                .zip(iter_fields(fields))
+                .map(|((label, name), field)| {
+                    let label = label.to_string();
+                    let label = label.trim_start_matches("r#");
+                    let write = write(&field, field_ref(name));
+                    quote! {
+                        map.entry_with(#label, |dest| {
+                            #write
+                            Ok(())
+                        })?;
+                    }
+                });
+            quote! {
+                let mut map = ::ed::cbor::MapWriter::new();
+                #(#entries)*
+                map.finish(dest)?;
+            }
         }
-    });
-    let gen_params = gen_param_input(&item.generics);
-    let terminated_bounds = iter_terminated_bounds(&item, quote!(::ed::Decode));
-    let where_preds = item
-        .generics
-        .where_clause
-        .as_ref()
-        .map(|w| {
-            let preds = w.predicates.clone().into_iter();
-            quote!(#(#preds,)*)
-        })
-        .unwrap_or_default();
+        Fields::Unnamed(_) => {
+            let writes: Vec<_> = field_names
+                .zip(iter_fields(fields))
+                .map(|(name, field)| write(&field, field_ref(name)))
+                .collect();
+            if writes.len() == 1 {
+                quote!(#(#writes)*)
+            } else {
+                let len = writes.len() as u64;
+                quote! {
+                    ::ed::cbor::write_head(::ed::cbor::ARRAY, #len, dest)?;
+                    #(#writes)*
+                }
+            }
+        }
+        Fields::Unit => quote!(::ed::cbor::write_null(dest)?;),
+    }
+}
 
-    let arms = data
-        .variants
+fn fields_decode_lenient(fields: &Fields, variant_name: Option<Ident>) -> TokenStream {
+    let field_names: Vec<_> = iter_field_names(fields).collect();
+    let vars: Vec<_> = (0..field_names.len())
+        .map(|i| Ident::new(&format!("__field{}", i), Span::call_site()))
+        .collect();
+    let decodes = field_names
         .iter()
-        .filter(|v| filter_skipped_variants(*v))
-        .enumerate()
-        .map(|(i, v)| {
-            let i = i as u8;
-            let arm = fields_decode(&v.fields, Some(v.ident.clone()));
-            quote!(#i => { #arm })
+        .zip(iter_fields(fields))
+        .map(|(name, field)| {
+            let label = match &variant_name {
+                Some(variant) => format!("{}.{}", variant, name),
+                None => name.to_string(),
+            };
+            match field_with(&field) {
+                Some(with) => quote!(decoder.field_with(#label, |input| #with::decode(input))),
+                None => {
+                    let ty = field.ty;
+                    quote!(decoder.field::<#ty>(#label))
+                }
+            }
         });
 
+    let item_name = match &variant_name {
+        Some(name) => quote!(Self::#name),
+        None => quote!(Self),
+    };
+
     quote! {
-        impl#generics ::ed::Decode for #name#gen_params
-        where #where_preds #terminated_bounds
-        {
-            #[inline]
-            fn decode<__R: std::io::Read>(mut input: __R) -> ::ed::Result<Self> {
-                let mut variant = [0; 1];
-                input.read_exact(&mut variant[..])?;
-                let variant = variant[0];
+        #(let #vars = #decodes;)*
+        Some(#item_name {
+            #(#field_names: #vars?,)*
+        })
+    }
+}
 
-                Ok(match variant {
-                    #(#arms),*
-                    n => return Err(::ed::Error::UnexpectedByte(n)),
-                })
-            }
+pub fn derive_terminated(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let item = parse_macro_input!(item as DeriveInput);
+    if let Err(err) = check_with_paths(&item) {
+        return err.to_compile_error().into();
+    }
 
-            // TODO: decode_into
-        }
+    if let Some(flag) = ed_flag(&item.attrs, "not_terminated") {
+        let message = "`#[derive(Terminated)]` can't be used with `#[ed(not_terminated)]`";
+        return Error::new_spanned(flag, message).to_compile_error().into();
     }
+
+    terminated_impl(&item).into()
 }
 
+// Implements `Terminated` if all fields are `Terminated`, unconditionally if
+// the type is marked with `#[ed(terminated)]`, or not at all if it is marked
+// with `#[ed(not_terminated)]`.
 fn terminated_impl(item: &DeriveInput) -> TokenStream {
+    let asserted = has_ed_flag(&item.attrs, "terminated");
+    if let Some(flag) = ed_flag(&item.attrs, "not_terminated") {
+        if asserted {
+            let message = "`#[ed(terminated)]` and `#[ed(not_terminated)]` can't be used together";
+            return Error::new_spanned(flag, message).to_compile_error();
+        }
+        return quote!();
+    }
+
     let name = &item.ident;
 
-    let mut generics = item.generics.clone();
-    generics.params.iter_mut().for_each(|p| {
-        if let GenericParam::Type(ref mut ty) = p {
-            ty.default.take();
-        }
-    });
-    let gen_params = gen_param_input(&item.generics);
-    let where_preds = item
-        .generics
-        .where_clause
-        .as_ref()
-        .map(|w| {
-            let preds = w.predicates.clone().into_iter();
-            quote!(#(#preds,)*)
-        })
-        .unwrap_or_default();
+    let (generics, gen_params, where_preds) = impl_generics(&item.generics);
 
     let bounds = iter_field_groups(item.clone()).map(|fields| {
         let bounds = fields
             .iter()
+            .filter(|f| !asserted && field_with(f).is_none())
             .map(|f| f.ty.clone())
-            .map(|ty| quote!(#ty: ::ed::Terminated,));
+            .map(|ty| quote!(for<'__ed> #ty: ::ed::Terminated,));
         quote!(#(#bounds)*)
     });
     let bounds = quote!(#(#bounds)*);
@@ -291,6 +1265,95 @@ fn terminated_impl(item: &DeriveInput) -> TokenStream {
     }
 }
 
+// A type is sensitive if it's marked as such or any of its fields are, e.g. a
+// struct holding a `Secret<_>`.
+fn sensitive_const(item: &DeriveInput, trait_path: TokenStream) -> TokenStream {
+    let marked = has_ed_flag(&item.attrs, "sensitive");
+    let fields = iter_field_groups(item.clone())
+        .flat_map(|fields| iter_fields(&fields).collect::<Vec<_>>())
+        .filter(|field| field_with(field).is_none())
+        .map(|field| {
+            let ty = field.ty;
+            quote!(<#ty as #trait_path>::SENSITIVE)
+        });
+    quote!(const SENSITIVE: bool = #marked #(|| #fields)*;)
+}
+
+fn default_digest_impl(
+    item: &DeriveInput,
+    generics: TokenStream,
+    bounds: TokenStream,
+) -> TokenStream {
+    let digest = match ed_value(&item.attrs, "digest") {
+        Some(digest) => digest,
+        None => return quote!(),
+    };
+    let digest: Path = match digest.parse() {
+        Ok(path) => path,
+        Err(err) => return Error::new(digest.span(), err).to_compile_error(),
+    };
+    let name = &item.ident;
+    let gen_params = gen_param_input(&item.generics);
+
+    quote! {
+        impl#generics ::ed::hash::DefaultDigest for #name#gen_params
+        where #bounds
+        {
+            type Digest = #digest;
+        }
+    }
+}
+
+fn ed_value(attrs: &[Attribute], key: &str) -> Option<LitStr> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("ed"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident(key) => match nv.lit {
+                Lit::Str(lit) => Some(lit),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+fn has_ed_flag(attrs: &[Attribute], flag: &str) -> bool {
+    ed_flag(attrs, flag).is_some()
+}
+
+fn ed_flag(attrs: &[Attribute], flag: &str) -> Option<Path> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("ed"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|nested| match nested {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident(flag) => Some(path),
+            _ => None,
+        })
+}
+
+fn union_error(data: &DataUnion) -> TokenStream {
+    Error::new_spanned(data.union_token, "Not implemented for unions").to_compile_error()
+}
+
+fn struct_only_error(data: &Data, message: &str) -> TokenStream {
+    let span = match data {
+        Data::Struct(data) => data.struct_token.span,
+        Data::Enum(data) => data.enum_token.span,
+        Data::Union(data) => data.union_token.span,
+    };
+    Error::new(span, message).to_compile_error()
+}
+
 fn iter_fields(fields: &Fields) -> Box<dyn Iterator<Item = Field>> {
     match fields.clone() {
         Fields::Named(fields) => Box::new(fields.named.into_iter()),
@@ -341,26 +1404,70 @@ fn iter_field_groups(item: DeriveInput) -> Box<dyn Iterator<Item = Fields>> {
                 .filter(filter_skipped_variants)
                 .map(|v| v.fields),
         ),
-        Data::Union(_) => unimplemented!("Not implemented for unions"),
+        // Unions are rejected by `union_error`.
+        Data::Union(_) => Box::new(None.into_iter()),
+    }
+}
+
+// `Remaining` fields consume the rest of the input, so they must come last.
+// Since they aren't `Terminated`, the derived impls would otherwise be
+// silently left out by the bounds below rather than failing to compile. See
+// `ed::remaining::RemainingMustBeLast` for how the check works.
+fn remaining_last_check(item: &DeriveInput) -> TokenStream {
+    let (generics, _, where_preds) = impl_generics(&item.generics);
+
+    let checks: Vec<_> = iter_field_groups(item.clone())
+        .flat_map(|fields| {
+            let len = fields.len();
+            iter_fields(&fields)
+                .take(len.saturating_sub(1))
+                .map(|field| {
+                    let ty = &field.ty;
+                    quote_spanned! {ty.span()=>
+                        let _ = <#ty as ::ed::remaining::RemainingMustBeLast<_>>::check;
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    if checks.is_empty() {
+        return quote!();
+    }
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn remaining_must_be_last#generics() where #where_preds {
+                #(#checks)*
+            }
+        };
     }
 }
 
+// The bounds on field types are written as `for<'__ed> Type: Trait` so that
+// when a concrete field type doesn't implement the trait (e.g. a struct
+// ending in a `Vec<u8>` is never `Terminated`), the impl is silently left out
+// rather than failing to compile, which would otherwise require the nightly
+// `trivial_bounds` feature.
 fn iter_terminated_bounds(item: &DeriveInput, add: TokenStream) -> TokenStream {
     let bounds = iter_field_groups(item.clone()).map(|fields| {
         if fields.len() == 0 {
             return quote!();
         }
 
+        // Fields encoded with `#[ed(with = "...")]` don't implement the
+        // traits themselves, so they are left unbounded.
         let bounds = iter_fields(&fields)
-            .map(|f| f.ty.clone())
             .enumerate()
-            .map(|(i, ty)| {
+            .filter(|(_, f)| field_with(f).is_none())
+            .map(|(i, f)| {
+                let ty = &f.ty;
                 let terminated = if i < fields.len() - 1 {
                     quote!(::ed::Terminated+)
                 } else {
                     quote!()
                 };
-                quote!(#ty: #terminated #add,)
+                quote!(for<'__ed> #ty: #terminated #add,)
             });
         quote!(#(#bounds)*)
     });
@@ -376,6 +1483,28 @@ fn variant_destructure(variant: &Variant) -> TokenStream {
     }
 }
 
+/// Splits the generics of a type into the parameters to declare on an impl,
+/// without defaults, the parameters to apply to the type, and the predicates
+/// of its where clause, each followed by a comma.
+fn impl_generics(generics: &Generics) -> (Generics, TokenStream, TokenStream) {
+    let mut params = generics.clone();
+    params.params.iter_mut().for_each(|p| {
+        if let GenericParam::Type(ref mut ty) = p {
+            ty.default.take();
+        }
+    });
+    let gen_params = gen_param_input(generics);
+    let where_preds = generics
+        .where_clause
+        .as_ref()
+        .map(|w| {
+            let preds = w.predicates.clone().into_iter();
+            quote!(#(#preds,)*)
+        })
+        .unwrap_or_default();
+    (params, gen_params, where_preds)
+}
+
 fn gen_param_input(generics: &Generics) -> TokenStream {
     let gen_params = generics.params.iter().map(|p| match p {
         GenericParam::Type(p) => {
@@ -400,55 +1529,148 @@ fn gen_param_input(generics: &Generics) -> TokenStream {
 }
 
 fn fields_encode_into(
+    fields: &Fields,
     field_names: impl Iterator<Item = TokenStream>,
     parent: Option<TokenStream>,
 ) -> TokenStream {
-    let field_names: Vec<_> = field_names.collect();
-    let mut field_names_minus_last = field_names.clone();
-    field_names_minus_last.pop();
-
     let parent_dot = parent.as_ref().map(|_| quote!(.));
 
+    let encodes =
+        field_names
+            .zip(iter_fields(fields))
+            .map(|(name, field)| match field_with(&field) {
+                Some(with) => quote!(#with::encode_into(&#parent#parent_dot#name, &mut dest)?;),
+                None => quote!(#parent#parent_dot#name.encode_into(&mut dest)?;),
+            });
+
     quote! {
-        #(#parent#parent_dot#field_names.encode_into(&mut dest)?;)*
+        #(#encodes)*
     }
 }
 
 fn fields_encoding_length(
+    fields: &Fields,
     field_names: impl Iterator<Item = TokenStream>,
     parent: Option<TokenStream>,
 ) -> TokenStream {
     let parent_dot = parent.as_ref().map(|_| quote!(.));
 
+    let lengths =
+        field_names
+            .zip(iter_fields(fields))
+            .map(|(name, field)| match field_with(&field) {
+                Some(with) => quote!(#with::encoding_length(&#parent#parent_dot#name)?),
+                None => quote!(#parent#parent_dot#name.encoding_length()?),
+            });
+
     quote! {
-        0 #( + #parent#parent_dot#field_names.encoding_length()?)*
+        0 #( + #lengths)*
     }
 }
 
-fn fields_decode(fields: &Fields, variant_name: Option<Ident>) -> TokenStream {
-    let field_names = iter_field_names(&fields);
+/// Returns a `map_err` call which adds the type and field to the context of a
+/// field's decode error, e.g. `Foo.bar`.
+fn field_context(label: &str, name: &TokenStream) -> TokenStream {
+    let field = name.to_string();
+    quote!(.map_err(|err| err.in_field(#label, #field)))
+}
 
-    let item_name = match variant_name {
-        Some(name) => quote!(Self::#name),
-        None => quote!(Self),
-    };
+fn fields_decode(fields: &Fields, item_name: TokenStream, label: &str) -> TokenStream {
+    let field_names: Vec<_> = iter_field_names(&fields).collect();
+    let decodes = field_names
+        .iter()
+        .zip(iter_fields(fields))
+        .map(|(name, field)| {
+            let context = field_context(label, name);
+            match field_with(&field) {
+                Some(with) => quote!(#with::decode(&mut input)#context?),
+                None => quote!(::ed::Decode::decode(&mut input)#context?),
+            }
+        });
 
     quote! {
         #item_name {
             #(
-                #field_names: ::ed::Decode::decode(&mut input)?,
+                #field_names: #decodes,
             )*
         }
     }
 }
 
-fn fields_decode_into(fields: &Fields, parent: Option<TokenStream>) -> TokenStream {
-    let field_names = iter_field_names(&fields);
+fn fields_decode_into(fields: &Fields, parent: Option<TokenStream>, label: &str) -> TokenStream {
     let parent = parent.unwrap_or(quote!(self));
+    let decodes = iter_field_names(&fields)
+        .zip(iter_fields(fields))
+        .map(|(name, field)| {
+            let context = field_context(label, &name);
+            match field_with(&field) {
+                Some(with) => quote!(#parent.#name = #with::decode(&mut input)#context?;),
+                None => quote!(#parent.#name.decode_into(&mut input)#context?;),
+            }
+        });
+
+    quote! {
+        #(#decodes)*
+    }
+}
+
+fn fields_encode_async(
+    field_names: impl Iterator<Item = TokenStream>,
+    parent: Option<TokenStream>,
+    module: &TokenStream,
+) -> TokenStream {
+    let encodes = field_names.map(|name| {
+        let value = match &parent {
+            Some(parent) => quote!(&#parent.#name),
+            None => quote!(#name),
+        };
+        quote!(#module::AsyncEncode::encode_into_async(#value, dest).await?;)
+    });
+
+    quote! {
+        #(#encodes)*
+    }
+}
+
+fn fields_decode_async(
+    fields: &Fields,
+    item_name: TokenStream,
+    label: &str,
+    module: &TokenStream,
+) -> TokenStream {
+    let field_names: Vec<_> = iter_field_names(&fields).collect();
+    let decodes = field_names
+        .iter()
+        .zip(iter_fields(fields))
+        .map(|(name, field)| {
+            let ty = &field.ty;
+            let context = field_context(label, name);
+            quote!(<#ty as #module::AsyncDecode>::decode_async(&mut input).await#context?)
+        });
 
     quote! {
-        #(
-            #parent.#field_names.decode_into(&mut input)?;
-        )*
+        #item_name {
+            #(
+                #field_names: #decodes,
+            )*
+        }
+    }
+}
+
+// Invalid paths are reported by `check_with_paths` before any code is
+// generated, so they can be skipped here.
+fn field_with(field: &Field) -> Option<Path> {
+    ed_value(&field.attrs, "with").and_then(|with| with.parse().ok())
+}
+
+fn check_with_paths(item: &DeriveInput) -> Result<()> {
+    for fields in iter_field_groups(item.clone()) {
+        for field in iter_fields(&fields) {
+            if let Some(with) = ed_value(&field.attrs, "with") {
+                with.parse::<Path>()
+                    .map_err(|err| Error::new(with.span(), err))?;
+            }
+        }
     }
+    Ok(())
 }
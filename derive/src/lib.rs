@@ -1,11 +1,46 @@
 mod encoding;
 
-#[proc_macro_derive(Encode, attributes(skip))]
+#[proc_macro_derive(Encode, attributes(skip, ed))]
 pub fn encode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     encoding::derive_encode(item)
 }
 
-#[proc_macro_derive(Decode, attributes(skip))]
+#[proc_macro_derive(Decode, attributes(skip, ed))]
 pub fn decode(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     encoding::derive_decode(item)
 }
+
+#[proc_macro_derive(Terminated, attributes(ed))]
+pub fn terminated(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_terminated(item)
+}
+
+#[proc_macro_derive(DecodeLenient, attributes(ed))]
+pub fn decode_lenient(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_decode_lenient(item)
+}
+
+#[proc_macro_derive(Columns, attributes(ed))]
+pub fn columns(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_columns(item)
+}
+
+#[proc_macro_derive(Profile, attributes(ed))]
+pub fn profile(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_profile(item)
+}
+
+#[proc_macro_derive(Cbor, attributes(ed))]
+pub fn cbor(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_cbor(item)
+}
+
+#[proc_macro_derive(Track)]
+pub fn track(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_track(item)
+}
+
+#[proc_macro_derive(EncodingCost)]
+pub fn encoding_cost(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    encoding::derive_encoding_cost(item)
+}
@@ -0,0 +1,325 @@
+//! Type-tagged containers for heterogeneous values.
+//!
+//! An [`Any`](struct.Any.html) holds the encoding of a value along with a
+//! numeric type ID, so values of different types can be stored in the same
+//! field (e.g. plugin payloads or extension fields) without defining a closed
+//! enum of every possible type. Types opt in by implementing
+//! [`Tagged`](trait.Tagged.html), usually via the [`tagged!`](../macro.tagged.html)
+//! macro, and a [`Registry`](struct.Registry.html) maps type IDs back to
+//! decoders.
+
+use super::capabilities::Capabilities;
+use super::{Decode, DecodeExt, Encode, Error, Result, Terminated};
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Write};
+
+/// A type which has a stable numeric ID, allowing it to be stored in an
+/// [`Any`](struct.Any.html).
+pub trait Tagged: Encode + Decode + 'static {
+    /// The ID written before the encoding of values of this type. IDs must be
+    /// unique among the types registered in a given `Registry`.
+    const TYPE_ID: u32;
+
+    /// The version of the type's schema, advertised to peers during
+    /// capability negotiation. Defaults to 0.
+    const VERSION: u16 = 0;
+}
+
+/// Implements [`Tagged`](any/trait.Tagged.html) for each of the given types,
+/// with the given type IDs, and optionally schema versions (written as
+/// `Type = id => version`).
+///
+/// ```rust
+/// # use ed::{Encode, Decode};
+/// #[derive(Encode, Decode)]
+/// struct Foo(u32);
+/// #[derive(Encode, Decode)]
+/// struct Bar(u64);
+///
+/// ed::tagged!(Foo = 1, Bar = 2 => 3);
+/// ```
+#[macro_export]
+macro_rules! tagged {
+    ($($type:ty = $id:expr $(=> $version:expr)?),* $(,)?) => {
+        $(
+            impl $crate::any::Tagged for $type {
+                const TYPE_ID: u32 = $id;
+                $(const VERSION: u16 = $version;)?
+            }
+        )*
+    };
+}
+
+/// Creates a [`Registry`](any/struct.Registry.html) containing decoders for
+/// each of the given [`Tagged`](any/trait.Tagged.html) types. Errors with
+/// `Error::DuplicateTypeId` if two of the types have the same type ID.
+///
+/// ```rust
+/// # use ed::{Encode, Decode};
+/// # #[derive(Encode, Decode)]
+/// # struct Foo(u32);
+/// # #[derive(Encode, Decode)]
+/// # struct Bar(u64);
+/// # ed::tagged!(Foo = 1, Bar = 2);
+/// let registry = ed::registry![Foo, Bar]?;
+/// # Ok::<(), ed::Error>(())
+/// ```
+#[macro_export]
+macro_rules! registry {
+    ($($type:ty),* $(,)?) => {
+        (|| -> $crate::Result<$crate::any::Registry> {
+            let mut registry = $crate::any::Registry::new();
+            $(registry.register::<$type>()?;)*
+            Ok(registry)
+        })()
+    };
+}
+
+/// The encoding of a value, tagged with the type ID of its type.
+///
+/// Encoded as the type ID (`u32`), followed by the length of the inner
+/// encoding (`u32`), followed by the inner encoding. Since the length is
+/// included, `Any` is `Terminated` regardless of the inner type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Any {
+    type_id: u32,
+    bytes: Vec<u8>,
+}
+
+impl Any {
+    /// Encodes the value and tags it with its type ID.
+    pub fn new<T: Tagged>(value: &T) -> Result<Self> {
+        Ok(Any {
+            type_id: T::TYPE_ID,
+            bytes: value.encode()?,
+        })
+    }
+
+    /// Returns the type ID of the contained value.
+    pub fn type_id(&self) -> u32 {
+        self.type_id
+    }
+
+    /// Returns the encoding of the contained value.
+    pub fn bytes(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    /// Returns true if the contained value has the type ID of `T`.
+    pub fn is<T: Tagged>(&self) -> bool {
+        self.type_id == T::TYPE_ID
+    }
+
+    /// Decodes the contained value as `T`. Errors with
+    /// `Error::UnexpectedTypeId` if the value was not tagged with the type ID
+    /// of `T`, or with `Error::TrailingBytes` if the value doesn't consume the
+    /// whole encoding.
+    pub fn downcast<T: Tagged>(&self) -> Result<T> {
+        if !self.is::<T>() {
+            return Err(Error::UnexpectedTypeId(self.type_id));
+        }
+        T::decode_all(self.bytes.as_slice())
+    }
+}
+
+impl Encode for Any {
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let length: u32 = self
+            .bytes
+            .len()
+            .try_into()
+            .map_err(|_| Error::LengthOverflow(self.bytes.len()))?;
+
+        self.type_id.encode_into(dest)?;
+        length.encode_into(dest)?;
+        dest.write_all(self.bytes.as_slice())?;
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> Result<usize> {
+        let length = self.bytes.len();
+        if u32::try_from(length).is_err() {
+            return Err(Error::LengthOverflow(length));
+        }
+        Ok(8 + length)
+    }
+}
+
+impl Decode for Any {
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let type_id = u32::decode(&mut input)?;
+        let length = u32::decode(&mut input)? as usize;
+
+        let mut bytes = Vec::new();
+        input.take(length as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != length {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(Any { type_id, bytes })
+    }
+}
+
+impl Terminated for Any {}
+
+type DecodeFn = fn(&[u8]) -> Result<Box<dyn std::any::Any>>;
+
+/// A mapping from type IDs to decoders, used to decode the contents of an
+/// [`Any`](struct.Any.html) without knowing its type statically.
+#[derive(Default)]
+pub struct Registry {
+    decoders: HashMap<u32, DecodeFn>,
+    capabilities: Capabilities,
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a decoder for `T` under its type ID.
+    ///
+    /// Errors with `Error::DuplicateTypeId`, leaving the registry unchanged,
+    /// if a type with the same ID has already been registered, since this
+    /// indicates conflicting type IDs.
+    pub fn register<T: Tagged>(&mut self) -> Result<&mut Self> {
+        if self.decoders.contains_key(&T::TYPE_ID) {
+            return Err(Error::DuplicateTypeId(T::TYPE_ID));
+        }
+        let decoder: DecodeFn = |bytes| Ok(Box::new(T::decode_all(bytes)?));
+        self.decoders.insert(T::TYPE_ID, decoder);
+        self.capabilities.add(T::TYPE_ID, T::VERSION);
+        Ok(self)
+    }
+
+    /// Returns the set of type IDs and schema versions in the registry, to be
+    /// sent to peers during a handshake.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Returns true if a decoder is registered for the given type ID.
+    pub fn contains(&self, type_id: u32) -> bool {
+        self.decoders.contains_key(&type_id)
+    }
+
+    /// Decodes the contained value using the decoder registered for its type
+    /// ID. The result can be downcast with `Box::<dyn Any>::downcast`.
+    ///
+    /// Errors with `Error::UnknownTypeId` if no decoder is registered for the
+    /// type ID, or with `Error::TrailingBytes` if the value doesn't consume
+    /// the whole encoding.
+    pub fn decode(&self, any: &Any) -> Result<Box<dyn std::any::Any>> {
+        let decoder = self
+            .decoders
+            .get(&any.type_id)
+            .ok_or(Error::UnknownTypeId(any.type_id))?;
+        decoder(any.bytes.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Foo(u32);
+
+    impl Encode for Foo {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            self.0.encode_into(dest)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            self.0.encoding_length()
+        }
+    }
+
+    impl Decode for Foo {
+        fn decode<R: Read>(input: R) -> Result<Self> {
+            Ok(Foo(u32::decode(input)?))
+        }
+    }
+
+    tagged!(Foo = 7, u16 = 8 => 2);
+
+    #[test]
+    fn encode_decode_any() {
+        let any = Any::new(&Foo(0x12345678)).unwrap();
+        let bytes = any.encode().unwrap();
+        assert_eq!(
+            bytes.as_slice(),
+            &[0, 0, 0, 7, 0, 0, 0, 4, 0x12, 0x34, 0x56, 0x78]
+        );
+        assert_eq!(Any::decode(bytes.as_slice()).unwrap(), any);
+    }
+
+    #[test]
+    fn decode_any_eof() {
+        let bytes = [0, 0, 0, 7, 0, 0, 0, 4, 0x12, 0x34];
+        assert!(Any::decode(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn downcast() {
+        let any = Any::new(&Foo(42)).unwrap();
+        assert!(any.is::<Foo>());
+        assert_eq!(any.downcast::<Foo>().unwrap().0, 42);
+        assert!(matches!(
+            any.downcast::<u16>(),
+            Err(Error::UnexpectedTypeId(7))
+        ));
+    }
+
+    #[test]
+    fn registry_decode() {
+        let registry = registry![Foo, u16].unwrap();
+        assert!(registry.contains(7));
+        assert!(registry.capabilities().supports(7, 0));
+        assert!(registry.capabilities().supports(8, 2));
+
+        let any = Any::new(&123u16).unwrap();
+        let value = registry.decode(&any).unwrap();
+        assert_eq!(*value.downcast::<u16>().unwrap(), 123);
+
+        let registry = Registry::new();
+        assert!(matches!(
+            registry.decode(&any),
+            Err(Error::UnknownTypeId(8))
+        ));
+    }
+
+    #[test]
+    fn registry_duplicate() {
+        assert!(matches!(
+            registry![Foo, Foo],
+            Err(Error::DuplicateTypeId(7))
+        ));
+
+        let mut registry = Registry::new();
+        registry.register::<Foo>().unwrap();
+        assert!(matches!(
+            registry.register::<Foo>(),
+            Err(Error::DuplicateTypeId(7))
+        ));
+        assert!(registry.contains(7));
+    }
+
+    #[test]
+    fn trailing_bytes() {
+        let any = Any {
+            type_id: 8,
+            bytes: vec![0, 1, 2],
+        };
+        let registry = registry![u16].unwrap();
+        assert!(matches!(
+            registry.decode(&any),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert!(matches!(
+            any.downcast::<u16>(),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+//! Encoding support for `ArrayVec` and `ArrayString`, using the `arrayvec`
+//! crate.
+//!
+//! `ArrayVec` is encoded like a `Vec` of the same elements, and `ArrayString`
+//! like a `String`: the elements or UTF-8 bytes one after another, read until
+//! the end of the input when decoding. The capacity is not part of the
+//! encoding.
+//!
+//! Decoding never allocates. It errors with `Error::CapacityExceeded` if the
+//! input holds more elements or bytes than fit in the capacity.
+
+use super::io::{Read, Write};
+use super::{limits, next_element, read_to_capacity};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::arrayvec::{ArrayString, ArrayVec};
+
+impl<T: Encode + Terminated, const CAP: usize> Encode for ArrayVec<T, CAP> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the elements of the vector one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(self, dest)
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Decode + Terminated, const CAP: usize> Decode for ArrayVec<T, CAP> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more than `CAP` elements.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut vec = ArrayVec::new();
+        vec.decode_into(input)?;
+        Ok(vec)
+    }
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more than `CAP` elements.
+    ///
+    /// Recursively calls `decode_into` for each existing element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => {
+                    let element = T::decode(&mut element_input)?;
+                    self.try_push(element)
+                        .map_err(|_| Error::CapacityExceeded(CAP))?;
+                }
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+impl<const CAP: usize> Encode for ArrayString<CAP> {
+    /// Encodes the UTF-8 bytes of the string.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_str().encode_into(dest)
+    }
+
+    /// Returns the length of the string in bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl<const CAP: usize> Decode for ArrayString<CAP> {
+    /// Decodes the rest of the input as a UTF-8 string. Errors with
+    /// `Error::CapacityExceeded` if it is longer than `CAP` bytes, or with
+    /// `Error::InvalidUtf8` if the bytes are not valid UTF-8.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut string = ArrayString::new();
+        string.decode_into(input)?;
+        Ok(string)
+    }
+
+    /// Decodes the rest of the input as a UTF-8 string, replacing the
+    /// contents of the string.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        let mut bytes = [0; CAP];
+        let len = read_to_capacity(input, &mut bytes)?;
+        let string = core::str::from_utf8(&bytes[..len]).map_err(|_| Error::InvalidUtf8)?;
+        self.clear();
+        self.try_push_str(string)
+            .map_err(|_| Error::CapacityExceeded(CAP))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_vec() {
+        let mut vec = ArrayVec::<u16, 3>::new();
+        vec.push(1);
+        vec.push(2);
+        let bytes = vec.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2]);
+        assert_eq!(vec.encoding_length().unwrap(), 4);
+        assert_eq!(ArrayVec::<u16, 3>::decode(bytes.as_slice()).unwrap(), vec);
+
+        vec.decode_into(&[0, 3, 0, 4, 0, 5][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[3, 4, 5]);
+        vec.decode_into(&[0, 6][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[6]);
+    }
+
+    #[test]
+    fn encode_decode_string() {
+        let string = ArrayString::<8>::from("héllo").unwrap();
+        let bytes = string.encode().unwrap();
+        assert_eq!(bytes, "héllo".as_bytes());
+        assert_eq!(string.encoding_length().unwrap(), 6);
+        assert_eq!(ArrayString::<8>::decode(bytes.as_slice()).unwrap(), string);
+        assert_eq!(
+            ArrayString::<6>::decode(bytes.as_slice()).unwrap().as_str(),
+            "héllo"
+        );
+        assert!(ArrayString::<0>::decode(&[][..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(matches!(
+            ArrayVec::<u8, 2>::decode(&[1, 2, 3][..]),
+            Err(Error::CapacityExceeded(2))
+        ));
+        assert!(matches!(
+            ArrayString::<5>::decode("héllo".as_bytes()),
+            Err(Error::CapacityExceeded(5))
+        ));
+        assert!(matches!(
+            ArrayString::<4>::decode(&[0xff][..]),
+            Err(Error::InvalidUtf8)
+        ));
+    }
+}
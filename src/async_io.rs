@@ -0,0 +1,491 @@
+//! The async encoding traits shared by the `tokio` and `futures-io`
+//! integrations.
+//!
+//! The two crates' async I/O traits, and the extension traits which provide
+//! `read_exact`, `write_all` and so on, have the same shape, so the traits
+//! and their impls are defined once by the macros below and expanded in the
+//! `tokio` and `futures_io` modules against each crate's traits. The
+//! expanding module must import `AsyncRead`, `AsyncWrite`, `AsyncReadExt` and
+//! `AsyncWriteExt`, along with the `ed` items the impls use.
+
+/// Defines `AsyncEncode` and `AsyncDecode` in the expanding module, along with
+/// their impls for the core types. `$io` names the crate in the docs.
+macro_rules! async_traits {
+    ($io:literal) => {
+        #[doc = concat!(
+            "A trait for values that can be encoded straight into an async `",
+            $io,
+            "` writer."
+        )]
+        pub trait AsyncEncode: Encode + Sync {
+            /// Writes the encoded representation of the value to the async writer.
+            ///
+            /// The default implementation encodes the value into a buffer with
+            /// [`Encode::encode`](../trait.Encode.html#method.encode) and writes it
+            /// all at once, which suits types whose encoding is small. Derived impls
+            /// write each field in turn.
+            fn encode_into_async<W>(&self, dest: &mut W) -> impl Future<Output = Result<()>> + Send
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                async move {
+                    let mut bytes = self.encode()?;
+                    let res = dest.write_all(&bytes).await;
+                    wipe::wipe(&mut bytes, Self::SENSITIVE);
+                    Ok(res?)
+                }
+            }
+
+            /// Encodes the elements of the slice one after another, in order.
+            ///
+            /// Used by the slice, array and vector impls, and overridden by `u8` to
+            /// write the whole slice at once rather than one byte at a time. There is
+            /// no need to override or call this outside of `ed`.
+            #[doc(hidden)]
+            fn encode_slice_into_async<'a, W>(
+                slice: &'a [Self],
+                dest: &'a mut W,
+            ) -> impl Future<Output = Result<()>> + Send + 'a
+            where
+                Self: Sized,
+                W: AsyncWrite + Unpin + Send,
+            {
+                async move {
+                    for element in slice.iter() {
+                        element.encode_into_async(dest).await?;
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        #[doc = concat!(
+            "A trait for values that can be decoded straight from an async `",
+            $io,
+            "` reader."
+        )]
+        pub trait AsyncDecode: Decode + Send {
+            /// Reads bytes from the async reader and returns the decoded value.
+            ///
+            /// Like [`Decode::decode`](../trait.Decode.html#method.decode), only as
+            /// many bytes as the encoding takes are read from a `Terminated` type,
+            /// while other types read until the end of the input.
+            fn decode_async<R>(input: R) -> impl Future<Output = Result<Self>> + Send
+            where
+                R: AsyncRead + Unpin + Send;
+
+            /// Decodes `N` values one after another, in order.
+            ///
+            /// Used by the array impls, and overridden by `u8` to read the whole
+            /// array at once rather than one byte at a time. There is no need to
+            /// override or call this outside of `ed`.
+            #[doc(hidden)]
+            fn decode_array_async<R, const N: usize>(
+                mut input: R,
+            ) -> impl Future<Output = Result<[Self; N]>> + Send
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                async move {
+                    let mut v: Vec<Self> = Vec::with_capacity(N);
+                    for _ in 0..N {
+                        v.push(Self::decode_async(&mut input).await?);
+                    }
+                    v.try_into()
+                        .map_err(|v: Vec<Self>| Error::LengthOverflow(v.len()))
+                }
+            }
+
+            /// Decodes values one after another until the end of the input.
+            ///
+            /// Used by the vector impl, and overridden by `u8` to read the rest of
+            /// the input straight into the vector. There is no need to override or
+            /// call this outside of `ed`.
+            #[doc(hidden)]
+            fn decode_vec_async<R>(mut input: R) -> impl Future<Output = Result<Vec<Self>>> + Send
+            where
+                Self: Terminated,
+                R: AsyncRead + Unpin + Send,
+            {
+                async move {
+                    let mut vec = Vec::new();
+                    let mut byte = [0; 1];
+                    // Each element is decoded from the byte read to check for the end
+                    // of the input, followed by the rest of the input.
+                    while input.read(&mut byte).await? != 0 {
+                        limits::count_elements(1)?;
+                        let mut peeked = &byte[..];
+                        vec.push(Self::decode_async((&mut peeked).chain(&mut input)).await?);
+                        // Like `Peeked::check_consumed`, rejects elements with an empty
+                        // encoding, whose byte would otherwise be silently dropped.
+                        if !peeked.is_empty() {
+                            return Err(Error::UnexpectedByte(byte[0]));
+                        }
+                    }
+                    Ok(vec)
+                }
+            }
+        }
+
+        impl AsyncEncode for u8 {
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                Ok(dest.write_all(&[*self]).await?)
+            }
+
+            async fn encode_slice_into_async<'a, W>(
+                slice: &'a [Self],
+                dest: &'a mut W,
+            ) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                Ok(dest.write_all(slice).await?)
+            }
+        }
+
+        impl AsyncDecode for u8 {
+            async fn decode_async<R>(mut input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                let mut byte = [0; 1];
+                input.read_exact(&mut byte).await?;
+                Ok(byte[0])
+            }
+
+            async fn decode_array_async<R, const N: usize>(mut input: R) -> Result<[Self; N]>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                let mut bytes = [0; N];
+                input.read_exact(&mut bytes).await?;
+                Ok(bytes)
+            }
+
+            async fn decode_vec_async<R>(mut input: R) -> Result<Vec<Self>>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                let mut bytes = Vec::new();
+                input.read_to_end(&mut bytes).await?;
+                limits::count_elements(bytes.len())?;
+                Ok(bytes)
+            }
+        }
+
+        async_fixed_impl!(
+            u16,
+            u32,
+            u64,
+            u128,
+            i8,
+            i16,
+            i32,
+            i64,
+            i128,
+            bool,
+            (),
+            core::cmp::Ordering
+        );
+        async_fixed_impl!(
+            core::num::NonZeroU8,
+            core::num::NonZeroU16,
+            core::num::NonZeroU32,
+            core::num::NonZeroU64,
+            core::num::NonZeroU128,
+            core::num::NonZeroI8,
+            core::num::NonZeroI16,
+            core::num::NonZeroI32,
+            core::num::NonZeroI64,
+            core::num::NonZeroI128
+        );
+
+        impl<T: AsyncEncode> AsyncEncode for Option<T> {
+            /// Writes a 0 byte for `None`, or a 1 byte followed by the encoding of the
+            /// inner value for `Some`.
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                match self {
+                    None => Ok(dest.write_all(&[0]).await?),
+                    Some(value) => {
+                        dest.write_all(&[1]).await?;
+                        value.encode_into_async(dest).await
+                    }
+                }
+            }
+        }
+
+        impl<T: AsyncDecode> AsyncDecode for Option<T> {
+            /// Decodes a 0 byte as `None`, or a 1 byte followed by the encoding of the
+            /// inner value as `Some`. Errors for all other values.
+            async fn decode_async<R>(mut input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                match u8::decode_async(&mut input).await? {
+                    0 => Ok(None),
+                    1 => Ok(Some(T::decode_async(input).await?)),
+                    byte => Err(Error::UnexpectedByte(byte)),
+                }
+            }
+        }
+
+        async_tuple_impl!(; A);
+        async_tuple_impl!(A; B);
+        async_tuple_impl!(A, B; C);
+        async_tuple_impl!(A, B, C; D);
+        async_tuple_impl!(A, B, C, D; E);
+        async_tuple_impl!(A, B, C, D, E; F);
+        async_tuple_impl!(A, B, C, D, E, F; G);
+        async_tuple_impl!(A, B, C, D, E, F, G; H);
+        async_tuple_impl!(A, B, C, D, E, F, G, H; I);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I; J);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J; K);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J, K; L);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L; M);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M; N);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N; O);
+        async_tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O; P);
+
+        impl<T: AsyncEncode + Terminated, const N: usize> AsyncEncode for [T; N] {
+            /// Writes the elements of the array one after another, in order.
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                T::encode_slice_into_async(&self[..], dest).await
+            }
+        }
+
+        impl<T: AsyncDecode + Terminated, const N: usize> AsyncDecode for [T; N] {
+            /// Decodes `N` elements one after another, in order.
+            async fn decode_async<R>(input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                T::decode_array_async(input).await
+            }
+        }
+
+        impl<T: AsyncEncode + Terminated> AsyncEncode for Vec<T> {
+            /// Writes the elements of the vector one after another, in order.
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                T::encode_slice_into_async(self, dest).await
+            }
+        }
+
+        impl<T: AsyncDecode + Terminated> AsyncDecode for Vec<T> {
+            /// Decodes elements one after another until the end of the input.
+            async fn decode_async<R>(input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                let mut scope = limits::AsyncLimits::new();
+                let mut decode = core::pin::pin!(async move {
+                    let _depth = limits::descend()?;
+                    T::decode_vec_async(input).await
+                });
+                core::future::poll_fn(|cx| scope.poll(|| decode.as_mut().poll(cx))).await
+            }
+        }
+
+        impl AsyncEncode for String {
+            /// Writes the UTF-8 bytes of the string.
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                Ok(dest.write_all(self.as_bytes()).await?)
+            }
+        }
+
+        impl AsyncDecode for String {
+            /// Reads the rest of the input, erroring if it isn't valid UTF-8.
+            async fn decode_async<R>(mut input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                let mut bytes = Vec::new();
+                input.read_to_end(&mut bytes).await?;
+                Self::decode(bytes.as_slice())
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+            fn block_on<F: Future>(future: F) -> F::Output {
+                fn raw_waker() -> RawWaker {
+                    fn clone(_: *const ()) -> RawWaker {
+                        raw_waker()
+                    }
+                    fn noop(_: *const ()) {}
+                    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+                    RawWaker::new(std::ptr::null(), &VTABLE)
+                }
+
+                let waker = unsafe { Waker::from_raw(raw_waker()) };
+                let mut cx = Context::from_waker(&waker);
+                let mut future = Box::pin(future);
+                loop {
+                    if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                        return output;
+                    }
+                }
+            }
+
+            fn roundtrip<T>(value: T)
+            where
+                T: AsyncEncode + AsyncDecode + PartialEq + std::fmt::Debug,
+            {
+                let mut bytes = vec![];
+                block_on(value.encode_into_async(&mut bytes)).unwrap();
+                assert_eq!(bytes, value.encode().unwrap());
+
+                let decoded: T = block_on(T::decode_async(bytes.as_slice())).unwrap();
+                assert_eq!(decoded, value);
+            }
+
+            #[test]
+            fn roundtrip_matches_sync() {
+                roundtrip(0x1234u16);
+                roundtrip(-5i64);
+                roundtrip(core::num::NonZeroU32::new(9).unwrap());
+                roundtrip(Some((true, 3u8)));
+                roundtrip(None::<u32>);
+                roundtrip([1u8, 2, 3]);
+                roundtrip([7u16, 8]);
+                roundtrip((1u32, [Some(2u8), None], "tail".to_string()));
+                roundtrip(vec![0u8, 1, 2, 3, 4]);
+                roundtrip(vec![(1u16, 2u32), (3, 4)]);
+            }
+
+            #[test]
+            fn decode_errors() {
+                let err = block_on(u32::decode_async(&[0, 0, 1][..])).unwrap_err();
+                assert!(err.is_unexpected_eof());
+
+                let err = block_on(Option::<u8>::decode_async(&[2, 0][..])).unwrap_err();
+                assert!(matches!(err, Error::UnexpectedByte(2)));
+
+                let err = block_on(Vec::<u16>::decode_async(&[0, 1, 0][..])).unwrap_err();
+                assert!(err.is_unexpected_eof());
+            }
+
+            #[test]
+            fn decode_vec_matches_sync() {
+                fn check<T>(bytes: &[u8])
+                where
+                    T: AsyncDecode + Terminated + PartialEq + std::fmt::Debug,
+                {
+                    let sync = Vec::<T>::decode(bytes);
+                    let not_sync = block_on(Vec::<T>::decode_async(bytes));
+                    match (sync, not_sync) {
+                        (Ok(sync), Ok(not_sync)) => assert_eq!(sync, not_sync),
+                        // The readers' EOF errors have different messages.
+                        (Err(sync), Err(not_sync)) if sync.is_unexpected_eof() => {
+                            assert!(not_sync.is_unexpected_eof())
+                        }
+                        (Err(sync), Err(not_sync)) => {
+                            assert_eq!(sync.to_string(), not_sync.to_string())
+                        }
+                        (sync, not_sync) => panic!("{:?} != {:?}", sync, not_sync),
+                    }
+                }
+
+                for bytes in [&[][..], &[0], &[1, 2], &[0, 1, 0], &[1, 0, 2, 3]] {
+                    check::<()>(bytes);
+                    check::<u8>(bytes);
+                    check::<u16>(bytes);
+                    check::<Option<()>>(bytes);
+                    check::<Option<u8>>(bytes);
+                }
+
+                let err = block_on(Vec::<()>::decode_async(&[7][..])).unwrap_err();
+                assert!(matches!(err, Error::UnexpectedByte(7)));
+            }
+
+            #[test]
+            fn decode_terminated_stops() {
+                let mut input = &[0, 0, 0, 5, 1, 9][..];
+                let value = block_on(u32::decode_async(&mut input)).unwrap();
+                assert_eq!(value, 5);
+                assert_eq!(input, &[1, 9]);
+            }
+        }
+    };
+}
+
+macro_rules! async_fixed_impl {
+    ($($type:ty),*) => {
+        $(
+            impl AsyncEncode for $type {
+                #[doc = "Encodes the value onto the stack, then writes it."]
+                async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+                where
+                    W: AsyncWrite + Unpin + Send,
+                {
+                    let mut bytes = [0; <$type as FixedLength>::ENCODING_LENGTH];
+                    self.encode_into_slice(&mut bytes)?;
+                    Ok(dest.write_all(&bytes).await?)
+                }
+            }
+
+            impl AsyncDecode for $type {
+                #[doc = "Reads exactly as many bytes as the encoding takes, then"]
+                #[doc = " decodes them."]
+                async fn decode_async<R>(mut input: R) -> Result<Self>
+                where
+                    R: AsyncRead + Unpin + Send,
+                {
+                    let mut bytes = [0; <$type as FixedLength>::ENCODING_LENGTH];
+                    input.read_exact(&mut bytes).await?;
+                    Self::decode(&bytes[..])
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! async_tuple_impl {
+    ($( $type:ident ),*; $last_type:ident) => {
+        impl<$($type: AsyncEncode + Terminated,)* $last_type: AsyncEncode> AsyncEncode for ($($type,)* $last_type,) {
+            #[doc = "Writes the fields of the tuple one after another, in"]
+            #[doc = " order."]
+            #[allow(non_snake_case)]
+            async fn encode_into_async<W>(&self, dest: &mut W) -> Result<()>
+            where
+                W: AsyncWrite + Unpin + Send,
+            {
+                let ($($type,)* $last_type,) = self;
+                $($type.encode_into_async(dest).await?;)*
+                $last_type.encode_into_async(dest).await
+            }
+        }
+
+        impl<$($type: AsyncDecode + Terminated,)* $last_type: AsyncDecode> AsyncDecode for ($($type,)* $last_type,) {
+            #[doc = "Decodes the fields of the tuple one after another, in"]
+            #[doc = " order."]
+            #[allow(unused_mut)]
+            async fn decode_async<R>(mut input: R) -> Result<Self>
+            where
+                R: AsyncRead + Unpin + Send,
+            {
+                Ok((
+                    $($type::decode_async(&mut input).await?,)*
+                    $last_type::decode_async(input).await?,
+                ))
+            }
+        }
+    }
+}
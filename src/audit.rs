@@ -0,0 +1,86 @@
+//! Link-time checks that the built-in decode paths cannot panic.
+//!
+//! Each function in this module decodes a type from a byte slice and is
+//! annotated with `#[no_panic]`, which fails the link if the compiler cannot
+//! prove the function never panics. Since the check relies on optimizations,
+//! it only applies to release builds, and only to functions which end up in a
+//! linked binary. The `audit` test links all of them:
+//!
+//! ```sh
+//! cargo test --release --features no-panic --test audit
+//! ```
+//!
+//! The proof is sensitive to inlining, so the release profile builds with a
+//! single codegen unit.
+//!
+//! The functions can also be used directly as entry points for decoding
+//! untrusted input. [`decode`](fn.decode.html) audits the decoding of any
+//! other type where it is linked. Types which derive `Decode` don't pass,
+//! since their errors box the field in which they occurred, and the check
+//! can't rule out a panic on allocation failure. Neither do long arrays of
+//! types other than `u8`, whose elements are decoded in a loop the check can't
+//! see through.
+//!
+//! Maps and sets are audited through
+//! [`decode_map_entries`](fn.decode_map_entries.html), which decodes the
+//! canonical entries of a map without building it. Inserting into the
+//! standard library's maps can panic on allocation failure and asserts
+//! internally, so collecting the entries into a map is left to the caller.
+
+use super::{order, Decode, Result};
+use alloc::string::String;
+use alloc::vec::Vec;
+use no_panic::no_panic;
+
+macro_rules! audit_fns {
+    ($($name:ident: $type:ty),* $(,)?) => {
+        $(
+            #[doc = "Decodes a `"]
+            #[doc = stringify!($type)]
+            #[doc = "` from the bytes, without panicking."]
+            #[no_panic]
+            pub fn $name(bytes: &[u8]) -> Result<$type> {
+                <$type>::decode(bytes)
+            }
+        )*
+    };
+}
+
+audit_fns!(
+    decode_u8: u8,
+    decode_u16: u16,
+    decode_u32: u32,
+    decode_u64: u64,
+    decode_u128: u128,
+    decode_i8: i8,
+    decode_i16: i16,
+    decode_i32: i32,
+    decode_i64: i64,
+    decode_i128: i128,
+    decode_bool: bool,
+    decode_unit: (),
+    decode_option: Option<u64>,
+    decode_tuple: (u8, bool, u32),
+    decode_array: [u16; 8],
+    decode_byte_array: [u8; 32],
+    decode_nested: Option<[(bool, u32); 4]>,
+    decode_vec: Vec<u16>,
+    decode_bytes: Vec<u8>,
+    decode_string: String,
+);
+
+/// Decodes the entries of a `BTreeMap<u8, u16>` from the bytes, in their
+/// canonical order, without panicking.
+#[no_panic]
+pub fn decode_map_entries(bytes: &[u8]) -> Result<Vec<(u8, u16)>> {
+    order::decode_entries(bytes, true, |(key, _)| key)
+}
+
+/// Decodes a `T` from the bytes, without panicking.
+///
+/// The check applies to each `T` this is called with in a linked release
+/// binary, so calling it with a type audits its impl.
+#[no_panic]
+pub fn decode<T: Decode>(bytes: &[u8]) -> Result<T> {
+    T::decode(bytes)
+}
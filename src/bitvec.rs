@@ -0,0 +1,135 @@
+//! Encoding support for `BitVec`, using the `bitvec` crate.
+//!
+//! A `BitVec` is encoded as its length in bits, as a big-endian `u32`,
+//! followed by the bits packed into `ceil(len / 8)` bytes. Bit `i` is bit
+//! `7 - i % 8` of byte `i / 8`, so bit 0 is the highest bit of the first
+//! byte, whatever the `BitOrder` and `BitStore` of the vector. The unused low
+//! bits of the last byte must be zero, and decoding errors with
+//! `Error::UnexpectedByte` otherwise, so every bit vector has exactly one
+//! encoding.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::bitvec::{order::BitOrder, store::BitStore, vec::BitVec};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// The number of bytes read at once while decoding, so that a huge length
+/// prefix can't cause a huge allocation before the input runs out.
+const CHUNK_LENGTH: usize = 4096;
+
+impl<T: BitStore, O: BitOrder> Encode for BitVec<T, O> {
+    /// Encodes the number of bits as a `u32`, followed by the bits packed
+    /// into bytes, highest bit first.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        u32::try_from(self.len())
+            .map_err(|_| Error::LengthOverflow(self.len()))?
+            .encode_into(dest)?;
+
+        let mut bytes = Vec::with_capacity(self.len().div_ceil(8));
+        let mut byte = 0;
+        for (i, bit) in self.iter().by_vals().enumerate() {
+            byte |= (bit as u8) << (7 - i % 8);
+            if i % 8 == 7 {
+                bytes.push(byte);
+                byte = 0;
+            }
+        }
+        if bytes.len() < self.len().div_ceil(8) {
+            bytes.push(byte);
+        }
+        dest.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Returns 4 plus the number of bytes the bits are packed into.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4 + self.len().div_ceil(8))
+    }
+}
+
+impl<T: BitStore, O: BitOrder> Decode for BitVec<T, O> {
+    /// Decodes a `u32` number of bits followed by the packed bits. Errors
+    /// with `Error::UnexpectedByte` if any of the unused low bits of the last
+    /// byte are set.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let len = u32::decode(&mut input)? as usize;
+
+        let mut bits = BitVec::new();
+        let mut chunk = Vec::new();
+        let mut remaining = len.div_ceil(8);
+        while remaining > 0 {
+            chunk.resize(remaining.min(CHUNK_LENGTH), 0);
+            input.read_exact(&mut chunk)?;
+            remaining -= chunk.len();
+            for byte in chunk.iter() {
+                for i in 0..8 {
+                    if bits.len() == len {
+                        // Only the last byte can have unused bits.
+                        if byte << i != 0 {
+                            return Err(Error::UnexpectedByte(*byte));
+                        }
+                        break;
+                    }
+                    bits.push(byte & (0x80 >> i) != 0);
+                }
+            }
+        }
+        Ok(bits)
+    }
+}
+
+impl<T: BitStore, O: BitOrder> Terminated for BitVec<T, O> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bitvec::{bitvec, order::Lsb0, order::Msb0};
+
+    #[test]
+    fn encode_decode() {
+        let bits = bitvec![u8, Msb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1];
+        let bytes = bits.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 10, 0b1011_0000, 0b1100_0000]);
+        assert_eq!(bits.encoding_length().unwrap(), 6);
+        assert_eq!(BitVec::<u8, Msb0>::decode(bytes.as_slice()).unwrap(), bits);
+
+        // The encoding doesn't depend on the order or storage of the bits.
+        let lsb = bitvec![usize, Lsb0; 1, 0, 1, 1, 0, 0, 0, 0, 1, 1];
+        assert_eq!(lsb.encode().unwrap(), bytes);
+        assert_eq!(
+            BitVec::<usize, Lsb0>::decode(bytes.as_slice()).unwrap(),
+            lsb
+        );
+
+        let empty = BitVec::<u8, Msb0>::new();
+        assert_eq!(empty.encode().unwrap(), vec![0, 0, 0, 0]);
+        assert!(BitVec::<u8, Msb0>::decode(&[0, 0, 0, 0][..])
+            .unwrap()
+            .is_empty());
+
+        let large = BitVec::<u8, Msb0>::repeat(true, 40_000);
+        let bytes = large.encode().unwrap();
+        assert_eq!(bytes.len(), 4 + 5000);
+        assert_eq!(BitVec::<u8, Msb0>::decode(bytes.as_slice()).unwrap(), large);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(matches!(
+            BitVec::<u8, Msb0>::decode(&[0, 0, 0, 3, 0b1011_0000][..]),
+            Err(Error::UnexpectedByte(0b1011_0000))
+        ));
+        assert!(BitVec::<u8, Msb0>::decode(&[0, 0, 0, 9, 0xff][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+
+        // A huge length fails once the input runs out.
+        let err = BitVec::<u8, Msb0>::decode(&[0xff, 0xff, 0xff, 0xff, 1][..]).unwrap_err();
+        assert!(err.is_unexpected_eof());
+    }
+}
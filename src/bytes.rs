@@ -0,0 +1,189 @@
+//! Encoding support for `Bytes` and `BytesMut`, using the `bytes` crate.
+//!
+//! Like `Vec<u8>`, `Bytes` and `BytesMut` are encoded as their bytes and
+//! decoded by reading the rest of the input, so they are not `Terminated`.
+//!
+//! Decoding from a generic reader has to copy the bytes out of it. When the
+//! input is itself a `Bytes`, e.g. a frame received by a network service,
+//! [`BytesReader`](struct.BytesReader.html) decodes values from it as usual
+//! but can also split off payloads as `Bytes` which share the input's memory
+//! rather than copying it:
+//!
+//! ```rust
+//! use bytes::Bytes;
+//! use ed::{bytes::BytesReader, Decode};
+//!
+//! let frame = Bytes::from_static(&[0, 1, 0, 3, b'a', b'b', b'c', b'd']);
+//! let mut reader = BytesReader::new(frame);
+//! let stream = u16::decode(&mut reader).unwrap();
+//! let length = u16::decode(&mut reader).unwrap();
+//! let payload = reader.split_to(length as usize).unwrap();
+//! assert_eq!((stream, &payload[..]), (1, &b"abc"[..]));
+//! assert_eq!(reader.into_rest(), Bytes::from_static(b"d"));
+//! ```
+
+use super::io::{self, Read, Write};
+use super::{limits, Decode, Encode, Result};
+use ::bytes::{Buf, Bytes, BytesMut};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+impl Encode for Bytes {
+    /// Encodes the bytes as-is.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self)?;
+        Ok(())
+    }
+
+    /// Returns the number of bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl Decode for Bytes {
+    /// Decodes the rest of the input as bytes.
+    ///
+    /// This copies the bytes out of the input. Use
+    /// [`BytesReader`](bytes/struct.BytesReader.html) to decode from a `Bytes`
+    /// without copying.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        limits::count_elements(bytes.len())?;
+        Ok(Bytes::from(bytes))
+    }
+}
+
+impl Encode for BytesMut {
+    /// Encodes the bytes as-is.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self)?;
+        Ok(())
+    }
+
+    /// Returns the number of bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl Decode for BytesMut {
+    /// Decodes the rest of the input as bytes.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut bytes = BytesMut::new();
+        bytes.decode_into(input)?;
+        Ok(bytes)
+    }
+
+    /// Decodes the rest of the input as bytes, reusing the existing buffer.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        self.clear();
+        let mut chunk = [0; 256];
+        loop {
+            match input.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => self.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        limits::count_elements(self.len())
+    }
+}
+
+/// A reader over a `Bytes` buffer which can split off parts of the input
+/// without copying them.
+#[derive(Clone, Debug, Default)]
+pub struct BytesReader {
+    bytes: Bytes,
+}
+
+impl BytesReader {
+    /// Returns a reader starting at the beginning of `bytes`.
+    pub fn new(bytes: Bytes) -> Self {
+        BytesReader { bytes }
+    }
+
+    /// Returns the number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns the next `n` bytes without copying them, advancing past them.
+    /// Errors with an `UnexpectedEof` I/O error if fewer than `n` bytes are
+    /// left.
+    pub fn split_to(&mut self, n: usize) -> Result<Bytes> {
+        if n > self.bytes.len() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(self.bytes.split_to(n))
+    }
+
+    /// Returns the rest of the input without copying it.
+    pub fn into_rest(self) -> Bytes {
+        self.bytes
+    }
+}
+
+impl From<Bytes> for BytesReader {
+    fn from(bytes: Bytes) -> Self {
+        BytesReader::new(bytes)
+    }
+}
+
+impl Read for BytesReader {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.bytes.len());
+        buf[..n].copy_from_slice(&self.bytes[..n]);
+        self.bytes.advance(n);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let bytes = Bytes::from_static(&[1, 2, 3]);
+        assert_eq!(bytes.encode().unwrap(), vec![1, 2, 3]);
+        assert_eq!(bytes.encoding_length().unwrap(), 3);
+        assert_eq!(Bytes::decode(&[1, 2, 3][..]).unwrap(), bytes);
+
+        let pair: (u8, BytesMut) = Decode::decode(&[7, 8, 9][..]).unwrap();
+        assert_eq!((pair.0, &pair.1[..]), (7, &[8, 9][..]));
+        assert_eq!(pair.1.encode().unwrap(), vec![8, 9]);
+
+        let mut reused = BytesMut::from(&[0; 64][..]);
+        reused.decode_into(&[4, 5][..]).unwrap();
+        assert_eq!(&reused[..], &[4, 5]);
+        assert!(reused.capacity() >= 64);
+    }
+
+    #[test]
+    fn reader() {
+        let input = Bytes::from(vec![0, 0, 0, 2, 10, 20, 30]);
+        let mut reader = BytesReader::new(input.clone());
+        assert_eq!(u32::decode(&mut reader).unwrap(), 2);
+        assert_eq!(reader.remaining(), 3);
+
+        let payload = reader.split_to(2).unwrap();
+        assert_eq!(&payload[..], &[10, 20]);
+        // The payload shares the memory of the input rather than copying it.
+        assert_eq!(payload.as_ptr(), input[4..].as_ptr());
+
+        assert!(reader.clone().split_to(2).unwrap_err().is_unexpected_eof());
+        assert_eq!(u8::decode(&mut reader).unwrap(), 30);
+        assert!(reader.into_rest().is_empty());
+    }
+}
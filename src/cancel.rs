@@ -0,0 +1,192 @@
+//! Cooperative cancellation for long-running decodes.
+//!
+//! Decoding reads its input incrementally through `std::io::Read`, so wrapping
+//! the input in a [`Cancellable`](struct.Cancellable.html) reader lets a
+//! decode of a huge input be aborted cleanly, either from another thread via a
+//! [`CancellationToken`](struct.CancellationToken.html), when a deadline
+//! passes, or when a budget callback declines to continue. The decode then
+//! fails with an I/O error which [`is_cancelled`](fn.is_cancelled.html)
+//! recognizes.
+
+use super::{Decode, Error, Result};
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The error returned by a cancelled `Cancellable` reader, wrapped in an I/O
+/// error.
+#[derive(Debug)]
+struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Decode cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A handle which can be cloned and shared across threads to cancel a decode.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new token which has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels any decodes using this token. Their next read will fail.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if `cancel` has been called on this token or any of its
+    /// clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+type BudgetFn<'a> = Box<dyn FnMut(u64) -> bool + 'a>;
+
+/// A reader adapter which fails reads once the decode has been cancelled.
+///
+/// Cancellation is checked before every read of the inner reader, so a decode
+/// stops at the next field boundary after it is cancelled.
+pub struct Cancellable<'a, R> {
+    inner: R,
+    token: Option<CancellationToken>,
+    deadline: Option<Instant>,
+    budget: Option<BudgetFn<'a>>,
+    bytes_read: u64,
+}
+
+impl<'a, R: Read> Cancellable<'a, R> {
+    /// Wraps the reader without any cancellation conditions.
+    pub fn new(inner: R) -> Self {
+        Cancellable {
+            inner,
+            token: None,
+            deadline: None,
+            budget: None,
+            bytes_read: 0,
+        }
+    }
+
+    /// Cancels reads once the token has been cancelled.
+    pub fn with_token(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Cancels reads once the deadline has passed.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Calls `budget` with the number of bytes read so far before every read,
+    /// cancelling reads once it returns false.
+    pub fn with_budget<F: FnMut(u64) -> bool + 'a>(mut self, budget: F) -> Self {
+        self.budget = Some(Box::new(budget));
+        self
+    }
+
+    /// Returns the number of bytes read from the inner reader so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn is_cancelled(&mut self) -> bool {
+        if self.token.as_ref().is_some_and(|t| t.is_cancelled()) {
+            return true;
+        }
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return true;
+        }
+        let bytes_read = self.bytes_read;
+        self.budget.as_mut().is_some_and(|b| !b(bytes_read))
+    }
+}
+
+impl<'a, R: Read> Read for Cancellable<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_cancelled() {
+            return Err(io::Error::other(Cancelled));
+        }
+
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+}
+
+/// Decodes a value from the reader, failing early if the token is cancelled.
+pub fn decode_cancellable<T: Decode, R: Read>(input: R, token: &CancellationToken) -> Result<T> {
+    T::decode(Cancellable::new(input).with_token(token.clone()))
+}
+
+/// Returns true if the error was caused by a `Cancellable` reader being
+/// cancelled.
+pub fn is_cancelled(err: &Error) -> bool {
+    match err.inner() {
+        Error::IOError(err) => err.get_ref().is_some_and(|err| err.is::<Cancelled>()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn token_cancel() {
+        let bytes = [0, 1, 0, 2];
+        let token = CancellationToken::new();
+        let value: Vec<u16> = decode_cancellable(&bytes[..], &token).unwrap();
+        assert_eq!(value, vec![1, 2]);
+
+        token.clone().cancel();
+        let err = decode_cancellable::<Vec<u16>, _>(&bytes[..], &token).unwrap_err();
+        assert!(is_cancelled(&err));
+    }
+
+    #[test]
+    fn deadline() {
+        let bytes = [0, 1, 0, 2];
+        let reader =
+            Cancellable::new(&bytes[..]).with_deadline(Instant::now() - Duration::from_secs(1));
+        let err = u32::decode(reader).unwrap_err();
+        assert!(is_cancelled(&err));
+    }
+
+    #[test]
+    fn budget() {
+        let bytes = [0, 1, 0, 2, 0, 3];
+        let mut reader = Cancellable::new(&bytes[..]).with_budget(|read| read < 4);
+        assert_eq!(u16::decode(&mut reader).unwrap(), 1);
+        assert_eq!(u16::decode(&mut reader).unwrap(), 2);
+        assert_eq!(reader.bytes_read(), 4);
+        assert!(is_cancelled(&u16::decode(&mut reader).unwrap_err()));
+    }
+
+    #[test]
+    fn other_errors_not_cancelled() {
+        let bytes = [0, 1];
+        let err = u32::decode(Cancellable::new(&bytes[..])).unwrap_err();
+        assert!(!is_cancelled(&err));
+
+        // An unrelated error with the same message isn't a cancellation.
+        let err = Error::IOError(io::Error::other("Decode cancelled"));
+        assert!(!is_cancelled(&err));
+    }
+}
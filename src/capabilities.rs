@@ -0,0 +1,216 @@
+//! Capability sets for negotiating which schemas two peers understand.
+//!
+//! Each schema is identified by a stable type ID (see
+//! [`Tagged`](../any/trait.Tagged.html)) and a version. Peers exchange their
+//! [`Capabilities`](struct.Capabilities.html) during a handshake, then use
+//! [`negotiate`](struct.Capabilities.html#method.negotiate) to find the
+//! schemas and versions they have in common before exchanging `ed` frames.
+
+use super::any::Tagged;
+use super::{Decode, Encode, Error, Result, Terminated};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+use std::io::{Read, Write};
+
+/// A set of supported schema versions, keyed by type ID.
+///
+/// Encoded compactly as the number of schemas (`u16`), followed by each schema
+/// in ascending type ID order: the type ID (`u32`), the number of supported
+/// versions (`u8`), then each version (`u16`) in ascending order. Decoding
+/// rejects any other ordering, empty version lists, or duplicates, so each set
+/// has exactly one valid encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    schemas: BTreeMap<u32, BTreeSet<u16>>,
+}
+
+impl Capabilities {
+    /// Creates an empty capability set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the given version of the schema with the given type ID as
+    /// supported.
+    pub fn add(&mut self, type_id: u32, version: u16) -> &mut Self {
+        self.schemas.entry(type_id).or_default().insert(version);
+        self
+    }
+
+    /// Marks the current version of `T` as supported.
+    pub fn add_type<T: Tagged>(&mut self) -> &mut Self {
+        self.add(T::TYPE_ID, T::VERSION)
+    }
+
+    /// Returns true if the given version of the schema is supported.
+    pub fn supports(&self, type_id: u32, version: u16) -> bool {
+        self.schemas
+            .get(&type_id)
+            .is_some_and(|versions| versions.contains(&version))
+    }
+
+    /// Returns the supported versions of the schema with the given type ID,
+    /// in ascending order.
+    pub fn versions(&self, type_id: u32) -> impl Iterator<Item = u16> + '_ {
+        self.schemas
+            .get(&type_id)
+            .into_iter()
+            .flat_map(|versions| versions.iter().copied())
+    }
+
+    /// Returns the type IDs of all supported schemas, in ascending order.
+    pub fn type_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.schemas.keys().copied()
+    }
+
+    /// Returns the capabilities supported by both `self` and `peer`.
+    pub fn negotiate(&self, peer: &Capabilities) -> Capabilities {
+        let schemas = self
+            .schemas
+            .iter()
+            .filter_map(|(type_id, versions)| {
+                let peer_versions = peer.schemas.get(type_id)?;
+                let common: BTreeSet<u16> = versions.intersection(peer_versions).copied().collect();
+                if common.is_empty() {
+                    None
+                } else {
+                    Some((*type_id, common))
+                }
+            })
+            .collect();
+
+        Capabilities { schemas }
+    }
+
+    /// Returns the highest version of the schema supported by both `self`
+    /// and `peer`, or `None` if there is no common version.
+    pub fn common_version(&self, peer: &Capabilities, type_id: u32) -> Option<u16> {
+        let ours = self.schemas.get(&type_id)?;
+        let theirs = peer.schemas.get(&type_id)?;
+        ours.intersection(theirs).last().copied()
+    }
+}
+
+impl Encode for Capabilities {
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let count: u16 = self
+            .schemas
+            .len()
+            .try_into()
+            .map_err(|_| Error::LengthOverflow(self.schemas.len()))?;
+        count.encode_into(dest)?;
+
+        for (type_id, versions) in self.schemas.iter() {
+            let count: u8 = versions
+                .len()
+                .try_into()
+                .map_err(|_| Error::LengthOverflow(versions.len()))?;
+            type_id.encode_into(dest)?;
+            count.encode_into(dest)?;
+            for version in versions.iter() {
+                version.encode_into(dest)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(2 + self
+            .schemas
+            .values()
+            .map(|versions| 5 + 2 * versions.len())
+            .sum::<usize>())
+    }
+}
+
+impl Decode for Capabilities {
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut schemas = BTreeMap::new();
+        let mut prev_type_id = None;
+
+        let count = u16::decode(&mut input)?;
+        for _ in 0..count {
+            let type_id = u32::decode(&mut input)?;
+            if prev_type_id.is_some_and(|prev| type_id <= prev) {
+                return Err(Error::NonCanonicalCapabilities);
+            }
+            prev_type_id = Some(type_id);
+
+            let version_count = u8::decode(&mut input)?;
+            if version_count == 0 {
+                return Err(Error::NonCanonicalCapabilities);
+            }
+
+            let mut versions = BTreeSet::new();
+            let mut prev_version = None;
+            for _ in 0..version_count {
+                let version = u16::decode(&mut input)?;
+                if prev_version.is_some_and(|prev| version <= prev) {
+                    return Err(Error::NonCanonicalCapabilities);
+                }
+                prev_version = Some(version);
+                versions.insert(version);
+            }
+
+            schemas.insert(type_id, versions);
+        }
+
+        Ok(Capabilities { schemas })
+    }
+}
+
+impl Terminated for Capabilities {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(entries: &[(u32, u16)]) -> Capabilities {
+        let mut caps = Capabilities::new();
+        for (type_id, version) in entries {
+            caps.add(*type_id, *version);
+        }
+        caps
+    }
+
+    #[test]
+    fn encode_decode() {
+        let value = caps(&[(2, 1), (1, 3), (1, 1)]);
+        let bytes = value.encode().unwrap();
+        assert_eq!(
+            bytes.as_slice(),
+            &[0, 2, 0, 0, 0, 1, 2, 0, 1, 0, 3, 0, 0, 0, 2, 1, 0, 1]
+        );
+        assert_eq!(bytes.len(), value.encoding_length().unwrap());
+        assert_eq!(Capabilities::decode(bytes.as_slice()).unwrap(), value);
+    }
+
+    #[test]
+    fn decode_non_canonical() {
+        let unordered_ids = [0, 2, 0, 0, 0, 2, 1, 0, 1, 0, 0, 0, 1, 1, 0, 1];
+        let unordered_versions = [0, 1, 0, 0, 0, 1, 2, 0, 3, 0, 1];
+        let no_versions = [0, 1, 0, 0, 0, 1, 0];
+        for bytes in [&unordered_ids[..], &unordered_versions, &no_versions] {
+            assert!(matches!(
+                Capabilities::decode(bytes),
+                Err(Error::NonCanonicalCapabilities)
+            ));
+        }
+    }
+
+    #[test]
+    fn negotiate() {
+        let ours = caps(&[(1, 1), (1, 2), (2, 1), (3, 1)]);
+        let theirs = caps(&[(1, 1), (1, 2), (1, 3), (2, 2), (3, 1)]);
+
+        let common = ours.negotiate(&theirs);
+        assert_eq!(common, caps(&[(1, 1), (1, 2), (3, 1)]));
+        assert_eq!(common.type_ids().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(common.versions(1).collect::<Vec<_>>(), vec![1, 2]);
+
+        assert_eq!(ours.common_version(&theirs, 1), Some(2));
+        assert_eq!(ours.common_version(&theirs, 2), None);
+        assert_eq!(ours.common_version(&theirs, 4), None);
+    }
+}
@@ -0,0 +1,676 @@
+//! Deterministic CBOR output, for inspecting values with external tooling.
+//!
+//! `ed` encodings are compact but not self-describing: without the type
+//! definitions, nothing can tell a `u32` from four `u8`s. Types which
+//! implement [`Cbor`](trait.Cbor.html), usually with `#[derive(Cbor)]`, can
+//! also be written as CBOR (RFC 8949), which generic tools such as
+//! `cbor.me` or `cbor-diag` can display. The output follows the core
+//! deterministic encoding requirements of RFC 8949 section 4.2.1, so equal
+//! values always produce the same bytes:
+//!
+//! - integers, lengths and tags use the shortest possible head, and integers
+//!   which don't fit in 64 bits are bignums (tags 2 and 3).
+//! - floats use the shortest of half, single or double precision which
+//!   represents the value exactly, and NaN is always `0xf97e00`.
+//! - map entries are sorted by the bytes of their encoded keys.
+//! - lengths are always definite.
+//!
+//! Derived impls write structs with named fields as maps from field names to
+//! values, tuple structs as arrays (or as their only field), and unit structs
+//! as null. Enum variants are written as a map from the variant name to its
+//! fields, or as just the name for unit variants. Fields with an
+//! `#[ed(with = "...")]` encoder are written as byte strings of their `ed`
+//! encoding.
+//!
+//! ```rust
+//! use ed::{cbor, Cbor};
+//!
+//! #[derive(Cbor)]
+//! struct Account {
+//!     name: String,
+//!     id: u16,
+//! }
+//!
+//! let account = Account { name: "ed".into(), id: 500 };
+//! assert_eq!(
+//!     cbor::to_cbor(&account).unwrap(),
+//!     // {"id": 500, "name": "ed"}
+//!     vec![0xa2, 0x62, b'i', b'd', 0x19, 0x01, 0xf4, 0x64, b'n', b'a', b'm', b'e', 0x62, b'e', b'd']
+//! );
+//! ```
+//!
+//! This is an output format only: CBOR can't be decoded back into values.
+
+use super::io::Write;
+use super::{Error, Result};
+#[cfg(not(feature = "std"))]
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    string::String,
+    vec::Vec,
+};
+use alloc::{rc::Rc, sync::Arc};
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+/// The major type of unsigned integers.
+pub const UNSIGNED: u8 = 0;
+/// The major type of negative integers.
+pub const NEGATIVE: u8 = 1;
+/// The major type of byte strings.
+pub const BYTES: u8 = 2;
+/// The major type of text strings.
+pub const TEXT: u8 = 3;
+/// The major type of arrays.
+pub const ARRAY: u8 = 4;
+/// The major type of maps.
+pub const MAP: u8 = 5;
+/// The major type of tagged values.
+pub const TAG: u8 = 6;
+/// The major type of simple values and floats.
+pub const SIMPLE: u8 = 7;
+
+/// The tag of a positive bignum.
+const POSITIVE_BIGNUM: u64 = 2;
+/// The tag of a negative bignum.
+const NEGATIVE_BIGNUM: u64 = 3;
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const HALF: u8 = 0xf9;
+const SINGLE: u8 = 0xfa;
+const DOUBLE: u8 = 0xfb;
+/// The canonical half-precision NaN.
+const NAN: [u8; 3] = [HALF, 0x7e, 0x00];
+
+/// A trait for values which can be written as deterministic CBOR. Usually
+/// implemented with `#[derive(Cbor)]`.
+pub trait Cbor {
+    /// Writes the value as deterministic CBOR.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()>;
+
+    /// Writes a slice of values, as an array by default. Overridden by `u8`,
+    /// so that byte vectors and arrays are written as byte strings.
+    #[doc(hidden)]
+    fn write_cbor_slice<W: Write>(values: &[Self], dest: &mut W) -> Result<()>
+    where
+        Self: Sized,
+    {
+        write_head(ARRAY, values.len() as u64, dest)?;
+        for value in values {
+            value.write_cbor(dest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the deterministic CBOR encoding of the value.
+pub fn to_cbor<T: Cbor + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    value.write_cbor(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Writes the head of a data item, with the argument in its shortest form.
+pub fn write_head<W: Write>(major: u8, argument: u64, dest: &mut W) -> Result<()> {
+    let major = major << 5;
+    if argument < 24 {
+        dest.write_all(&[major | argument as u8])?;
+    } else if argument <= u8::MAX as u64 {
+        dest.write_all(&[major | 24, argument as u8])?;
+    } else if argument <= u16::MAX as u64 {
+        dest.write_all(&[major | 25])?;
+        dest.write_all(&(argument as u16).to_be_bytes())?;
+    } else if argument <= u32::MAX as u64 {
+        dest.write_all(&[major | 26])?;
+        dest.write_all(&(argument as u32).to_be_bytes())?;
+    } else {
+        dest.write_all(&[major | 27])?;
+        dest.write_all(&argument.to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// Writes a byte string.
+pub fn write_bytes<W: Write>(bytes: &[u8], dest: &mut W) -> Result<()> {
+    write_head(BYTES, bytes.len() as u64, dest)?;
+    dest.write_all(bytes)?;
+    Ok(())
+}
+
+/// Writes a text string.
+pub fn write_text<W: Write>(text: &str, dest: &mut W) -> Result<()> {
+    write_head(TEXT, text.len() as u64, dest)?;
+    dest.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Writes null.
+pub fn write_null<W: Write>(dest: &mut W) -> Result<()> {
+    dest.write_all(&[NULL])?;
+    Ok(())
+}
+
+/// Writes whatever `encode` writes as a byte string, e.g. the `ed` encoding
+/// of a value which doesn't implement `Cbor`.
+pub fn write_encoded<W, F>(dest: &mut W, encode: F) -> Result<()>
+where
+    W: Write,
+    F: FnOnce(&mut Vec<u8>) -> Result<()>,
+{
+    let mut bytes = Vec::new();
+    encode(&mut bytes)?;
+    write_bytes(&bytes, dest)
+}
+
+/// Builds a map, sorting its entries by their encoded keys as deterministic
+/// CBOR requires.
+#[derive(Clone, Debug, Default)]
+pub struct MapWriter {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl MapWriter {
+    /// Returns a writer for an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry to the map.
+    pub fn entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        K: Cbor + ?Sized,
+        V: Cbor + ?Sized,
+    {
+        self.entry_with(key, |dest| value.write_cbor(dest))
+    }
+
+    /// Adds an entry to the map whose value is written by `write_value`.
+    pub fn entry_with<K, F>(&mut self, key: &K, write_value: F) -> Result<()>
+    where
+        K: Cbor + ?Sized,
+        F: FnOnce(&mut Vec<u8>) -> Result<()>,
+    {
+        let key = to_cbor(key)?;
+        let mut value = Vec::new();
+        write_value(&mut value)?;
+        self.entries.push((key, value));
+        Ok(())
+    }
+
+    /// Writes the map to `dest`. Errors with `Error::DuplicateKey` if two
+    /// entries have the same encoded key.
+    pub fn finish<W: Write>(mut self, dest: &mut W) -> Result<()> {
+        self.entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        if self.entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::DuplicateKey);
+        }
+        write_head(MAP, self.entries.len() as u64, dest)?;
+        for (key, value) in self.entries.iter() {
+            dest.write_all(key)?;
+            dest.write_all(value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a bignum, with the magnitude as big-endian bytes without leading
+/// zeros.
+fn write_bignum<W: Write>(tag: u64, magnitude: u128, dest: &mut W) -> Result<()> {
+    let bytes = magnitude.to_be_bytes();
+    let start = magnitude.leading_zeros() as usize / 8;
+    write_head(TAG, tag, dest)?;
+    write_bytes(&bytes[start..], dest)
+}
+
+/// Returns the half-precision bits of `value`, if it can be represented
+/// exactly. `value` must not be NaN.
+fn to_half(value: f32) -> Option<u16> {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    match exponent {
+        // Infinity.
+        0xff => Some(sign | 0x7c00),
+        // Zero. Single-precision subnormals are too small for half precision.
+        0 if mantissa == 0 => Some(sign),
+        0 => None,
+        _ => {
+            let exponent = exponent - 127;
+            if (-14..=15).contains(&exponent) {
+                // A normal half, which has 13 fewer mantissa bits.
+                if mantissa & 0x1fff != 0 {
+                    return None;
+                }
+                let exponent = (exponent + 15) as u16;
+                Some(sign | exponent << 10 | (mantissa >> 13) as u16)
+            } else if (-24..-14).contains(&exponent) {
+                // A subnormal half, in units of 2^-24.
+                let shift = (-1 - exponent) as u32;
+                let significand = 0x80_0000 | mantissa;
+                if significand & ((1 << shift) - 1) != 0 {
+                    return None;
+                }
+                Some(sign | (significand >> shift) as u16)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+impl Cbor for f32 {
+    /// Writes the float as a half if that is exact, or a single otherwise.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        if self.is_nan() {
+            dest.write_all(&NAN)?;
+        } else if let Some(half) = to_half(*self) {
+            dest.write_all(&[HALF])?;
+            dest.write_all(&half.to_be_bytes())?;
+        } else {
+            dest.write_all(&[SINGLE])?;
+            dest.write_all(&self.to_bits().to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Cbor for f64 {
+    /// Writes the float in the shortest precision which is exact.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let single = *self as f32;
+        if self.is_nan() || single as f64 == *self {
+            return single.write_cbor(dest);
+        }
+        dest.write_all(&[DOUBLE])?;
+        dest.write_all(&self.to_bits().to_be_bytes())?;
+        Ok(())
+    }
+}
+
+macro_rules! unsigned_impl {
+    ($type:ty) => {
+        impl Cbor for $type {
+            #[doc = "Writes the integer as an unsigned integer."]
+            fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+                write_head(UNSIGNED, *self as u64, dest)
+            }
+        }
+    };
+}
+
+unsigned_impl!(u16);
+unsigned_impl!(u32);
+unsigned_impl!(u64);
+unsigned_impl!(usize);
+
+impl Cbor for u8 {
+    /// Writes the integer as an unsigned integer.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_head(UNSIGNED, *self as u64, dest)
+    }
+
+    /// Writes the bytes as a byte string.
+    fn write_cbor_slice<W: Write>(values: &[Self], dest: &mut W) -> Result<()> {
+        write_bytes(values, dest)
+    }
+}
+
+macro_rules! signed_impl {
+    ($type:ty) => {
+        impl Cbor for $type {
+            #[doc = "Writes the integer as an unsigned or negative integer."]
+            fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+                if *self < 0 {
+                    // The argument of a negative integer is -1 - n, i.e. !n.
+                    write_head(NEGATIVE, !(*self as i64) as u64, dest)
+                } else {
+                    write_head(UNSIGNED, *self as u64, dest)
+                }
+            }
+        }
+    };
+}
+
+signed_impl!(i8);
+signed_impl!(i16);
+signed_impl!(i32);
+signed_impl!(i64);
+signed_impl!(isize);
+
+impl Cbor for u128 {
+    /// Writes the integer as an unsigned integer, or as a positive bignum if
+    /// it doesn't fit in 64 bits.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match u64::try_from(*self) {
+            Ok(value) => value.write_cbor(dest),
+            Err(_) => write_bignum(POSITIVE_BIGNUM, *self, dest),
+        }
+    }
+}
+
+impl Cbor for i128 {
+    /// Writes the integer as an unsigned or negative integer, or as a bignum
+    /// if it doesn't fit in 64 bits.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        if *self >= 0 {
+            return (*self as u128).write_cbor(dest);
+        }
+        let magnitude = !*self as u128;
+        match u64::try_from(magnitude) {
+            Ok(argument) => write_head(NEGATIVE, argument, dest),
+            Err(_) => write_bignum(NEGATIVE_BIGNUM, magnitude, dest),
+        }
+    }
+}
+
+impl Cbor for bool {
+    /// Writes `true` or `false`.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&[if *self { TRUE } else { FALSE }])?;
+        Ok(())
+    }
+}
+
+impl Cbor for () {
+    /// Writes null.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_null(dest)
+    }
+}
+
+impl Cbor for char {
+    /// Writes the character as a text string.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_text(self.encode_utf8(&mut [0; 4]), dest)
+    }
+}
+
+impl Cbor for str {
+    /// Writes a text string.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_text(self, dest)
+    }
+}
+
+impl Cbor for String {
+    /// Writes a text string.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_text(self, dest)
+    }
+}
+
+impl<T: Cbor> Cbor for Option<T> {
+    /// Writes the inner value, or null if there is none.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match self {
+            Some(value) => value.write_cbor(dest),
+            None => write_null(dest),
+        }
+    }
+}
+
+impl<T: Cbor> Cbor for [T] {
+    /// Writes an array of the elements, or a byte string for `[u8]`.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::write_cbor_slice(self, dest)
+    }
+}
+
+impl<T: Cbor, const N: usize> Cbor for [T; N] {
+    /// Writes an array of the elements, or a byte string for `[u8; N]`.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::write_cbor_slice(self, dest)
+    }
+}
+
+impl<T: Cbor> Cbor for Vec<T> {
+    /// Writes an array of the elements, or a byte string for `Vec<u8>`.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::write_cbor_slice(self, dest)
+    }
+}
+
+impl<T: Cbor> Cbor for VecDeque<T> {
+    /// Writes an array of the elements.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_head(ARRAY, self.len() as u64, dest)?;
+        for value in self.iter() {
+            value.write_cbor(dest)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Cbor> Cbor for BTreeSet<T> {
+    /// Writes an array of the elements, in order.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_head(ARRAY, self.len() as u64, dest)?;
+        for value in self.iter() {
+            value.write_cbor(dest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Cbor, S> Cbor for HashSet<T, S> {
+    /// Writes an array of the elements, sorted by their encodings so that the
+    /// output doesn't depend on the iteration order.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let mut values = self.iter().map(to_cbor).collect::<Result<Vec<_>>>()?;
+        values.sort_unstable();
+        write_head(ARRAY, values.len() as u64, dest)?;
+        for value in values.iter() {
+            dest.write_all(value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Cbor, V: Cbor> Cbor for BTreeMap<K, V> {
+    /// Writes a map, sorted by the encoded keys.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let mut map = MapWriter::new();
+        for (key, value) in self.iter() {
+            map.entry(key, value)?;
+        }
+        map.finish(dest)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Cbor, V: Cbor, S> Cbor for HashMap<K, V, S> {
+    /// Writes a map, sorted by the encoded keys.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let mut map = MapWriter::new();
+        for (key, value) in self.iter() {
+            map.entry(key, value)?;
+        }
+        map.finish(dest)
+    }
+}
+
+impl<T: Cbor + ?Sized> Cbor for &T {
+    /// Writes the referenced value.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).write_cbor(dest)
+    }
+}
+
+macro_rules! pointer_impl {
+    ($ptr:ident) => {
+        impl<T: Cbor + ?Sized> Cbor for $ptr<T> {
+            #[doc = "Writes the inner value."]
+            fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+                (**self).write_cbor(dest)
+            }
+        }
+    };
+}
+
+pointer_impl!(Box);
+pointer_impl!(Rc);
+pointer_impl!(Arc);
+
+impl<'a, B: Cbor + ToOwned + ?Sized> Cbor for alloc::borrow::Cow<'a, B> {
+    /// Writes the borrowed or owned value.
+    fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).write_cbor(dest)
+    }
+}
+
+macro_rules! tuple_impl {
+    ($length:expr; $($type:ident $index:tt),*) => {
+        impl<$($type: Cbor),*> Cbor for ($($type,)*) {
+            #[doc = "Writes an array of the elements."]
+            fn write_cbor<W: Write>(&self, dest: &mut W) -> Result<()> {
+                write_head(ARRAY, $length, dest)?;
+                $(self.$index.write_cbor(dest)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_impl!(1; A 0);
+tuple_impl!(2; A 0, B 1);
+tuple_impl!(3; A 0, B 1, C 2);
+tuple_impl!(4; A 0, B 1, C 2, D 3);
+tuple_impl!(5; A 0, B 1, C 2, D 3, E 4);
+tuple_impl!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_impl!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_impl!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_impl!(9; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_impl!(10; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_impl!(11; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_impl!(12; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex<T: Cbor + ?Sized>(value: &T) -> String {
+        to_cbor(value)
+            .unwrap()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    // Examples from RFC 8949 appendix A.
+
+    #[test]
+    fn integers() {
+        assert_eq!(hex(&0u8), "00");
+        assert_eq!(hex(&23u32), "17");
+        assert_eq!(hex(&24u16), "1818");
+        assert_eq!(hex(&1000u64), "1903e8");
+        assert_eq!(hex(&1_000_000u32), "1a000f4240");
+        assert_eq!(hex(&1_000_000_000_000u64), "1b000000e8d4a51000");
+        assert_eq!(hex(&u64::MAX), "1bffffffffffffffff");
+        assert_eq!(
+            hex(&18_446_744_073_709_551_616u128),
+            "c249010000000000000000"
+        );
+        assert_eq!(hex(&(u64::MAX as u128)), "1bffffffffffffffff");
+        assert_eq!(hex(&-1i8), "20");
+        assert_eq!(hex(&-100i32), "3863");
+        assert_eq!(hex(&-1000i64), "3903e7");
+        assert_eq!(hex(&-18_446_744_073_709_551_616i128), "3bffffffffffffffff");
+        assert_eq!(
+            hex(&-18_446_744_073_709_551_617i128),
+            "c349010000000000000000"
+        );
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(hex(&0.0f64), "f90000");
+        assert_eq!(hex(&-0.0f64), "f98000");
+        assert_eq!(hex(&1.5f64), "f93e00");
+        assert_eq!(hex(&65504.0f64), "f97bff");
+        assert_eq!(hex(&100_000.0f64), "fa47c35000");
+        assert_eq!(hex(&3.402_823_466_385_288_6e38f64), "fa7f7fffff");
+        assert_eq!(hex(&1.1f64), "fb3ff199999999999a");
+        assert_eq!(hex(&1.0e300f64), "fb7e37e43c8800759c");
+        assert_eq!(hex(&5.960_464_477_539_063e-8f64), "f90001");
+        assert_eq!(hex(&0.000_061_035_156_25f64), "f90400");
+        assert_eq!(hex(&-4.0f32), "f9c400");
+        assert_eq!(hex(&f64::INFINITY), "f97c00");
+        assert_eq!(hex(&f32::NEG_INFINITY), "f9fc00");
+        assert_eq!(hex(&f64::NAN), "f97e00");
+        assert_eq!(hex(&-f32::NAN), "f97e00");
+    }
+
+    #[test]
+    fn simple_values() {
+        assert_eq!(hex(&false), "f4");
+        assert_eq!(hex(&true), "f5");
+        assert_eq!(hex(&()), "f6");
+        assert_eq!(hex(&None::<u8>), "f6");
+        assert_eq!(hex(&Some(1u8)), "01");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(hex(""), "60");
+        assert_eq!(hex("IETF"), "6449455446");
+        assert_eq!(hex(&'\u{00fc}'), "62c3bc");
+        assert_eq!(hex(&String::from("\"\\")), "62225c");
+        assert_eq!(hex(&vec![1u8, 2, 3, 4]), "4401020304");
+        assert_eq!(hex(&[0u8; 0]), "40");
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(hex(&Vec::<u32>::new()), "80");
+        assert_eq!(hex(&[1u16, 2, 3]), "83010203");
+        assert_eq!(hex(&(1u8, [2u16, 3], [4u16, 5])), "8301820203820405");
+        let long: Vec<u32> = (1..=25).collect();
+        assert_eq!(
+            hex(&long),
+            "98190102030405060708090a0b0c0d0e0f101112131415161718181819"
+        );
+    }
+
+    #[test]
+    fn maps() {
+        let mut map = BTreeMap::new();
+        map.insert(3u8, 4u8);
+        map.insert(1, 2);
+        assert_eq!(hex(&map), "a201020304");
+
+        // Keys are sorted by their encodings, so shorter keys come first.
+        let mut map = HashMap::new();
+        map.insert("aa".to_string(), 1u8);
+        map.insert("b".to_string(), 2);
+        map.insert("a".to_string(), 3);
+        assert_eq!(hex(&map), "a361610361620262616101");
+
+        let mut map = MapWriter::new();
+        map.entry(&1u8, "x").unwrap();
+        map.entry(&1u16, "y").unwrap();
+        assert!(matches!(map.finish(&mut vec![]), Err(Error::DuplicateKey)));
+
+        let set: HashSet<i8> = vec![-1, 1, 0].into_iter().collect();
+        assert_eq!(hex(&set), "83000120");
+    }
+
+    #[test]
+    fn encoded() {
+        let mut bytes = vec![];
+        write_encoded(&mut bytes, |dest| {
+            dest.extend_from_slice(&[0, 1]);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(bytes, vec![0x42, 0, 1]);
+    }
+}
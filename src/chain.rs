@@ -0,0 +1,130 @@
+//! Decoding from non-contiguous input.
+//!
+//! Data often arrives as a sequence of separate buffers, e.g. packets from a
+//! socket or pages of shared memory. Rather than copying them into one
+//! contiguous `Vec<u8>` before decoding, a
+//! [`ChainReader`](struct.ChainReader.html) reads across the buffers in
+//! order, so a value may span any number of them.
+//!
+//! ```rust
+//! # use ed::chain::decode_chain;
+//! let packets = vec![vec![0x12], vec![], vec![0x34, 0x56], vec![0x78]];
+//! let value: u32 = decode_chain(&packets).unwrap();
+//! assert_eq!(value, 0x12345678);
+//! ```
+
+use super::{Decode, Result};
+use std::io::{self, Read};
+use std::iter::Fuse;
+
+/// A reader over a sequence of byte buffers, read one after another.
+pub struct ChainReader<I: Iterator> {
+    chunks: Fuse<I>,
+    current: Option<I::Item>,
+    offset: usize,
+}
+
+impl<I> ChainReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    /// Creates a reader over the buffers yielded by the iterator.
+    pub fn new<C: IntoIterator<IntoIter = I>>(chunks: C) -> Self {
+        ChainReader {
+            chunks: chunks.into_iter().fuse(),
+            current: None,
+            offset: 0,
+        }
+    }
+
+    /// Returns the unread bytes of the buffer currently being read, which
+    /// may be empty. Later buffers have not been read from the iterator yet.
+    pub fn current_chunk(&self) -> &[u8] {
+        match &self.current {
+            Some(chunk) => chunk.as_ref().get(self.offset..).unwrap_or_default(),
+            None => &[],
+        }
+    }
+
+    /// Returns the buffer currently being read (if any) along with the offset
+    /// of its first unread byte, and the iterator over the remaining buffers.
+    pub fn into_parts(self) -> (Option<(I::Item, usize)>, Fuse<I>) {
+        let offset = self.offset;
+        (self.current.map(|chunk| (chunk, offset)), self.chunks)
+    }
+}
+
+impl<I> Read for ChainReader<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let remaining = self.current_chunk();
+            if !remaining.is_empty() {
+                let n = remaining.len().min(buf.len());
+                buf[..n].copy_from_slice(&remaining[..n]);
+                self.offset += n;
+                return Ok(n);
+            }
+
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = Some(chunk);
+                    self.offset = 0;
+                }
+                None => {
+                    self.current = None;
+                    return Ok(0);
+                }
+            }
+        }
+    }
+}
+
+/// Decodes a value from a sequence of byte buffers, without first copying
+/// them into one contiguous buffer.
+pub fn decode_chain<T, C>(chunks: C) -> Result<T>
+where
+    T: Decode,
+    C: IntoIterator,
+    C::Item: AsRef<[u8]>,
+{
+    T::decode(ChainReader::new(chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_across_chunks() {
+        let chunks: Vec<&[u8]> = vec![&[0, 1], &[], &[2, 0], &[3, 4, 5]];
+        let value: (u16, u16, u16) = decode_chain(chunks.iter().copied()).unwrap();
+        assert_eq!(value, (1, 0x200, 0x304));
+
+        let value: Vec<u8> = decode_chain(chunks.iter().copied()).unwrap();
+        assert_eq!(value, vec![0, 1, 2, 0, 3, 4, 5]);
+
+        assert!(decode_chain::<[u16; 4], _>(chunks).is_err());
+    }
+
+    #[test]
+    fn remaining_input() {
+        let chunks = vec![vec![1, 2, 3], vec![4, 5]];
+        let mut reader = ChainReader::new(&chunks);
+        assert_eq!(u16::decode(&mut reader).unwrap(), 0x0102);
+        assert_eq!(reader.current_chunk(), &[3]);
+
+        let (current, rest) = reader.into_parts();
+        let (chunk, offset) = current.unwrap();
+        assert_eq!(&chunk[offset..], &[3]);
+        assert_eq!(rest.collect::<Vec<_>>(), vec![&vec![4, 5]]);
+    }
+}
@@ -0,0 +1,173 @@
+//! Encoding support for dates and times, using the `chrono` crate.
+//!
+//! `DateTime<Utc>` and `NaiveDateTime` are encoded like
+//! [`Timestamp`](../struct.Timestamp.html), as 12 bytes: the seconds since
+//! the Unix epoch as a big-endian `i64`, followed by the nanoseconds past the
+//! second as a big-endian `u32`. A `NaiveDateTime` is treated as a time in
+//! UTC. As in `chrono`, the nanoseconds are at least one billion during a leap
+//! second.
+//!
+//! `NaiveDate` is encoded as the number of days since the Unix epoch, as a
+//! big-endian `i32`.
+//!
+//! Decoding errors with `Error::InvalidTimestamp` for values which `chrono`
+//! can't represent, so every value has exactly one encoding.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+
+/// The number of days from 0001-01-01 to the Unix epoch, 1970-01-01.
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+impl Encode for DateTime<Utc> {
+    /// Encodes the seconds since the Unix epoch followed by the nanoseconds.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.timestamp().encode_into(dest)?;
+        self.timestamp_subsec_nanos().encode_into(dest)
+    }
+
+    /// Always returns Ok(12).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(12)
+    }
+}
+
+impl Decode for DateTime<Utc> {
+    /// Decodes the seconds and nanoseconds, erroring with
+    /// `Error::InvalidTimestamp` if they are out of range.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let seconds = i64::decode(&mut input)?;
+        let nanos = u32::decode(&mut input)?;
+        DateTime::from_timestamp(seconds, nanos).ok_or(Error::InvalidTimestamp)
+    }
+}
+
+impl Terminated for DateTime<Utc> {}
+
+impl FixedLength for DateTime<Utc> {
+    const ENCODING_LENGTH: usize = 12;
+}
+
+impl Encode for NaiveDateTime {
+    /// Encodes the seconds since the Unix epoch followed by the nanoseconds,
+    /// treating the time as UTC.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.and_utc().encode_into(dest)
+    }
+
+    /// Always returns Ok(12).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(12)
+    }
+}
+
+impl Decode for NaiveDateTime {
+    /// Decodes the seconds and nanoseconds, erroring with
+    /// `Error::InvalidTimestamp` if they are out of range.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        DateTime::<Utc>::decode(input).map(|time| time.naive_utc())
+    }
+}
+
+impl Terminated for NaiveDateTime {}
+
+impl FixedLength for NaiveDateTime {
+    const ENCODING_LENGTH: usize = 12;
+}
+
+impl Encode for NaiveDate {
+    /// Encodes the number of days since the Unix epoch as an `i32`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        // Can't overflow, since `chrono` limits years to well within the
+        // range of an `i32` of days.
+        (self.num_days_from_ce() - EPOCH_DAYS_FROM_CE).encode_into(dest)
+    }
+
+    /// Always returns Ok(4).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+impl Decode for NaiveDate {
+    /// Decodes the number of days since the Unix epoch, erroring with
+    /// `Error::InvalidTimestamp` if it is out of range.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let days = i32::decode(input)?;
+        days.checked_add(EPOCH_DAYS_FROM_CE)
+            .and_then(NaiveDate::from_num_days_from_ce_opt)
+            .ok_or(Error::InvalidTimestamp)
+    }
+}
+
+impl Terminated for NaiveDate {}
+
+impl FixedLength for NaiveDate {
+    const ENCODING_LENGTH: usize = 4;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_datetime() {
+        let time = DateTime::from_timestamp(0x12, 0x3456).unwrap();
+        let bytes = time.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0, 0, 0, 0x12, 0, 0, 0x34, 0x56]);
+        assert_eq!(time.encoding_length().unwrap(), 12);
+        assert_eq!(DateTime::<Utc>::decode(bytes.as_slice()).unwrap(), time);
+
+        let naive = time.naive_utc();
+        assert_eq!(naive.encode().unwrap(), bytes);
+        assert_eq!(NaiveDateTime::decode(bytes.as_slice()).unwrap(), naive);
+
+        let before = DateTime::from_timestamp(-1, 5).unwrap();
+        let bytes = before.encode().unwrap();
+        assert_eq!(&bytes[..8], &[0xff; 8]);
+        assert_eq!(DateTime::<Utc>::decode(bytes.as_slice()).unwrap(), before);
+    }
+
+    #[test]
+    fn encode_decode_date() {
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        assert_eq!(epoch.encode().unwrap(), vec![0, 0, 0, 0]);
+
+        let date = NaiveDate::from_ymd_opt(2000, 3, 1).unwrap();
+        let bytes = date.encode().unwrap();
+        assert_eq!(bytes, 11_017i32.to_be_bytes().to_vec());
+        assert_eq!(NaiveDate::decode(bytes.as_slice()).unwrap(), date);
+
+        let date = NaiveDate::from_ymd_opt(1969, 12, 31).unwrap();
+        assert_eq!(date.encode().unwrap(), vec![0xff; 4]);
+        assert_eq!(NaiveDate::decode(&[0xff; 4][..]).unwrap(), date);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let nanos = [0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff];
+        assert!(matches!(
+            DateTime::<Utc>::decode(&nanos[..]),
+            Err(Error::InvalidTimestamp)
+        ));
+        let seconds = [0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        assert!(matches!(
+            NaiveDateTime::decode(&seconds[..]),
+            Err(Error::InvalidTimestamp)
+        ));
+        assert!(matches!(
+            NaiveDate::decode(&[0x7f, 0xff, 0xff, 0xff][..]),
+            Err(Error::InvalidTimestamp)
+        ));
+    }
+}
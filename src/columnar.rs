@@ -0,0 +1,173 @@
+//! Column-by-column encoding of tables of structs.
+//!
+//! A `Vec<T>` is normally encoded row by row: every field of the first
+//! element, then every field of the second, and so on. For large tables of
+//! homogeneous rows (e.g. historical price data) it is often better to encode
+//! each field as a column, so similar values sit next to each other and
+//! compress well, and each column can be decoded in a tight loop.
+//!
+//! Wrapping the vector in [`Columnar`](struct.Columnar.html) opts in to the
+//! columnar layout. The element type must implement
+//! [`Columns`](trait.Columns.html), which can be derived:
+//!
+//! ```rust
+//! use ed::{columnar::Columnar, Columns, Decode, Encode};
+//!
+//! #[derive(Columns, Debug, PartialEq)]
+//! struct Candle {
+//!     time: u64,
+//!     price: u32,
+//! }
+//!
+//! let table = Columnar(vec![
+//!     Candle { time: 1, price: 10 },
+//!     Candle { time: 2, price: 11 },
+//! ]);
+//! let bytes = table.encode().unwrap();
+//! assert_eq!(&bytes[4..], &[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 10, 0, 0, 0, 11]);
+//! assert_eq!(Columnar::<Vec<Candle>>::decode(bytes.as_slice()).unwrap(), table);
+//! ```
+//!
+//! Rows of a struct without fields read nothing, so decoding a non-zero number
+//! of them errors with `Error::CountExceedsInput`.
+
+use super::{Decode, Encode, Error, Result, Terminated};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// A trait for types which can be encoded as columns, one per field. Usually
+/// implemented with `#[derive(Columns)]`.
+pub trait Columns: Sized {
+    /// Writes each field of every row, one column at a time.
+    fn encode_columns<W: Write>(rows: &[Self], dest: &mut W) -> Result<()>;
+
+    /// Returns the total encoding length of all of the columns.
+    fn columns_encoding_length(rows: &[Self]) -> Result<usize>;
+
+    /// Decodes `count` rows from their columns.
+    fn decode_columns<R: Read>(count: usize, input: R) -> Result<Vec<Self>>;
+}
+
+/// Decodes a column of `count` values. Used by implementations of
+/// [`Columns`](trait.Columns.html).
+pub fn decode_column<T: Decode, R: Read>(count: usize, input: R) -> Result<Vec<T>> {
+    decode_column_with(count, input, |input| T::decode(input))
+}
+
+/// Decodes a column of `count` values with `decode`, e.g. the `decode`
+/// function of a type given in `#[ed(with = "...")]`.
+pub fn decode_column_with<T, R, F>(count: usize, mut input: R, mut decode: F) -> Result<Vec<T>>
+where
+    R: Read,
+    F: FnMut(&mut R) -> Result<T>,
+{
+    // Limit the initial allocation, since `count` comes from the input.
+    let mut column = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        column.push(decode(&mut input)?);
+    }
+    Ok(column)
+}
+
+/// A vector which is encoded in columns rather than rows.
+///
+/// Encoded as the number of rows as a big-endian `u32`, followed by the
+/// columns.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Columnar<T>(pub T);
+
+impl<T: Columns> Encode for Columnar<Vec<T>> {
+    /// Encodes the row count followed by the columns.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let count = u32::try_from(self.0.len()).map_err(|_| Error::LengthOverflow(self.0.len()))?;
+        count.encode_into(dest)?;
+        T::encode_columns(&self.0, dest)
+    }
+
+    /// Returns 4 plus the encoding length of the columns.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4 + T::columns_encoding_length(&self.0)?)
+    }
+}
+
+impl<T: Columns> Decode for Columnar<Vec<T>> {
+    /// Decodes the row count followed by the columns.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let count = u32::decode(&mut input)? as usize;
+        T::decode_columns(count, input).map(Columnar)
+    }
+}
+
+impl<T: Columns> Terminated for Columnar<Vec<T>> {}
+
+macro_rules! tuple_columns_impl {
+    ($($type:ident $index:tt $var:ident),*) => {
+        impl<$($type: Encode + Decode + Terminated),*> Columns for ($($type,)*) {
+            #[inline]
+            fn encode_columns<W: Write>(rows: &[Self], dest: &mut W) -> Result<()> {
+                $(
+                    for row in rows {
+                        row.$index.encode_into(dest)?;
+                    }
+                )*
+                Ok(())
+            }
+
+            #[inline]
+            fn columns_encoding_length(rows: &[Self]) -> Result<usize> {
+                let mut length = 0;
+                $(
+                    for row in rows {
+                        length += row.$index.encoding_length()?;
+                    }
+                )*
+                Ok(length)
+            }
+
+            #[inline]
+            fn decode_columns<R: Read>(count: usize, mut input: R) -> Result<Vec<Self>> {
+                $(let mut $var = decode_column::<$type, _>(count, &mut input)?.into_iter();)*
+                Ok((0..count)
+                    .filter_map(|_| Some(($($var.next()?,)*)))
+                    .collect())
+            }
+        }
+    };
+}
+
+tuple_columns_impl!(A 0 a);
+tuple_columns_impl!(A 0 a, B 1 b);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c, D 3 d);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c, D 3 d, E 4 e);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c, D 3 d, E 4 e, F 5 f);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c, D 3 d, E 4 e, F 5 f, G 6 g);
+tuple_columns_impl!(A 0 a, B 1 b, C 2 c, D 3 d, E 4 e, F 5 f, G 6 g, H 7 h);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_columnar() {
+        let table = Columnar(vec![(1u16, true), (2, false), (3, true)]);
+        let bytes = table.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 3, 0, 1, 0, 2, 0, 3, 1, 0, 1]);
+        assert_eq!(table.encoding_length().unwrap(), bytes.len());
+
+        let decoded: Columnar<Vec<(u16, bool)>> = Decode::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, table);
+    }
+
+    #[test]
+    fn decode_truncated() {
+        let bytes = [0, 0, 0, 3, 0, 1, 0, 2, 0, 3, 1, 0];
+        assert!(Columnar::<Vec<(u16, bool)>>::decode(&bytes[..]).is_err());
+
+        let bytes = [0xff, 0xff, 0xff, 0xff];
+        assert!(Columnar::<Vec<(u8,)>>::decode(&bytes[..]).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Helpers for checking encoding compatibility across releases.
+//!
+//! A [`Fixtures`](struct.Fixtures.html) set collects encodings produced by
+//! previous releases of a type, values of the current type, and decoders for
+//! pinned older schemas. [`verify`](struct.Fixtures.html#method.verify) then
+//! checks that old bytes still decode as the current type and that current
+//! encodings are still decodable by the older schemas, so breaking changes are
+//! caught in tests before release.
+//!
+//! ```rust
+//! # use ed::compat::Fixtures;
+//! // v1 of the type was a u32, v2 split it into two u16 fields
+//! Fixtures::<(u16, u16)>::new("Foo")
+//!     .historical("v1", vec![0, 1, 0, 2])
+//!     .current("v2", (1, 2))
+//!     .decodable_by::<u32>("v1")
+//!     .assert();
+//!
+//! // Appending a field instead would break both directions
+//! let report = Fixtures::<(u32, u16)>::new("Foo")
+//!     .historical("v1", vec![0, 1, 0, 2])
+//!     .current("v2", (1, 2))
+//!     .decodable_by::<u32>("v1")
+//!     .verify()
+//!     .unwrap_err();
+//! assert_eq!(report.failures.len(), 2);
+//! ```
+//!
+//! With the `bincode` feature, [`compat::bincode`](bincode/index.html) helps
+//! migrate data stored with `bincode` to `ed`.
+
+use super::{Decode, DecodeExt, Encode, Result};
+use std::fmt;
+
+#[cfg(feature = "bincode")]
+pub mod bincode;
+
+type Check = Box<dyn Fn(&[u8]) -> Result<()>>;
+
+/// A set of fixture encodings and pinned schemas for the type `T`.
+pub struct Fixtures<T> {
+    name: String,
+    historical: Vec<(String, Vec<u8>)>,
+    current: Vec<(String, T)>,
+    pinned: Vec<(String, Check)>,
+}
+
+impl<T: Encode + Decode> Fixtures<T> {
+    /// Creates an empty fixture set. `name` is used in failure reports.
+    pub fn new(name: &str) -> Self {
+        Fixtures {
+            name: name.to_string(),
+            historical: vec![],
+            current: vec![],
+            pinned: vec![],
+        }
+    }
+
+    /// Adds bytes produced by a previous release, which must decode as the
+    /// current `T` without leaving trailing bytes.
+    pub fn historical(mut self, label: &str, bytes: Vec<u8>) -> Self {
+        self.historical.push((label.to_string(), bytes));
+        self
+    }
+
+    /// Adds a value of the current type, whose encoding must round-trip and
+    /// must be decodable by every pinned schema.
+    pub fn current(mut self, label: &str, value: T) -> Self {
+        self.current.push((label.to_string(), value));
+        self
+    }
+
+    /// Pins an older schema `U` which must still be able to decode the
+    /// encodings of all current values.
+    pub fn decodable_by<U: Decode>(mut self, label: &str) -> Self {
+        let check: Check = Box::new(|bytes| U::decode_all(bytes).map(|_| ()));
+        self.pinned.push((label.to_string(), check));
+        self
+    }
+
+    /// Runs all checks, returning a report of every failure.
+    pub fn verify(&self) -> std::result::Result<(), CompatReport> {
+        let mut failures = vec![];
+
+        for (label, bytes) in self.historical.iter() {
+            if let Err(err) = T::decode_all(bytes) {
+                failures.push(format!(
+                    "{}: historical fixture {} no longer decodes: {}",
+                    self.name, label, err
+                ));
+            }
+        }
+
+        for (label, value) in self.current.iter() {
+            let bytes = match value.encode() {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    failures.push(format!(
+                        "{}: current fixture {} failed to encode: {}",
+                        self.name, label, err
+                    ));
+                    continue;
+                }
+            };
+
+            if let Err(err) = T::decode_all(&bytes) {
+                failures.push(format!(
+                    "{}: current fixture {} does not round-trip: {}",
+                    self.name, label, err
+                ));
+            }
+
+            for (pinned_label, check) in self.pinned.iter() {
+                if let Err(err) = check(&bytes) {
+                    failures.push(format!(
+                        "{}: current fixture {} is not decodable by {}: {}",
+                        self.name, label, pinned_label, err
+                    ));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(CompatReport { failures })
+        }
+    }
+
+    /// Runs all checks, panicking with the report if any fail. Intended for
+    /// use in tests.
+    pub fn assert(&self) {
+        if let Err(report) = self.verify() {
+            panic!("{}", report);
+        }
+    }
+}
+
+/// The failures found by [`Fixtures::verify`](struct.Fixtures.html#method.verify).
+#[derive(Debug)]
+pub struct CompatReport {
+    /// A description of each failed check.
+    pub failures: Vec<String>,
+}
+
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} compatibility check(s) failed", self.failures.len())?;
+        for failure in self.failures.iter() {
+            write!(f, "\n  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompatReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_ok() {
+        Fixtures::<(u32, u16)>::new("Foo")
+            .historical("v1", vec![0, 0, 0, 1, 0, 2])
+            .current("v2", (1, 2))
+            .decodable_by::<(u16, u16, u16)>("v1")
+            .assert();
+    }
+
+    #[test]
+    fn verify_failures() {
+        let report = Fixtures::<(u32, u16)>::new("Foo")
+            .historical("v1", vec![0, 0, 0, 1])
+            .historical("v0", vec![0, 0, 0, 1, 0, 2, 3])
+            .current("v2", (1, 2))
+            .decodable_by::<u32>("v1")
+            .decodable_by::<(u32, u32)>("v0")
+            .verify()
+            .unwrap_err();
+
+        assert_eq!(report.failures.len(), 4);
+        assert!(report.failures[0].starts_with("Foo: historical fixture v1 no longer decodes: "));
+        assert!(<(u32, u16)>::decode_all(&[0, 0, 0, 1][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+        assert_eq!(
+            report.failures[2],
+            "Foo: current fixture v2 is not decodable by v1: 2 trailing bytes"
+        );
+    }
+}
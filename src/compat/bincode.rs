@@ -0,0 +1,144 @@
+//! Migrating stored data from `bincode` to `ed`.
+//!
+//! Types which used to be stored with `bincode` usually still derive `serde`'s
+//! `Deserialize` alongside `ed`'s `Encode`, so the same type can read the old
+//! bytes and write the new ones. [`migrate`](fn.migrate.html) does both in one
+//! step, for any such type:
+//!
+//! ```rust
+//! use ed::compat::bincode;
+//! use ed::Decode;
+//!
+//! // `bincode::serialize(&(1u16, "hi".to_string()))`
+//! let legacy = [1, 0, 2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i'];
+//! let bytes = bincode::migrate::<(u16, String)>(&legacy).unwrap();
+//! assert_eq!(bytes, vec![0, 1, b'h', b'i']);
+//! assert_eq!(<(u16, String)>::decode(bytes.as_slice()).unwrap(), (1, "hi".to_string()));
+//! ```
+//!
+//! Legacy data is decoded with the configuration used by `bincode` 1's
+//! `serialize` and `deserialize` functions: little-endian fixed-size
+//! integers, and `u64` lengths. Data written with a custom configuration, e.g.
+//! `bincode::options()` with variable-length integers, can be decoded with
+//! [`decode_with`](fn.decode_with.html). Unlike `bincode::deserialize`, bytes
+//! left over after the value are an error, since they mean the data was
+//! written as a different type.
+
+use crate::{Decode, DecodeExt, Encode, Error, Result};
+use ::bincode::{DefaultOptions, ErrorKind, Options};
+use ::serde::de::DeserializeOwned;
+
+impl From<::bincode::Error> for Error {
+    fn from(err: ::bincode::Error) -> Self {
+        match *err {
+            ErrorKind::Io(err) => Error::IOError(err),
+            err => Error::custom(err),
+        }
+    }
+}
+
+/// Decodes a value from bytes written by `bincode::serialize`, erroring if
+/// any bytes are left over.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .reject_trailing_bytes();
+    decode_with(bytes, options)
+}
+
+/// Decodes a value from bytes written by `bincode` with the given options.
+pub fn decode_with<T: DeserializeOwned, O: Options>(bytes: &[u8], options: O) -> Result<T> {
+    Ok(options.deserialize(bytes)?)
+}
+
+/// Decodes a value from bytes written by `bincode::serialize` and returns its
+/// `ed` encoding.
+pub fn migrate<T: DeserializeOwned + Encode>(bytes: &[u8]) -> Result<Vec<u8>> {
+    decode::<T>(bytes)?.encode()
+}
+
+/// Like [`migrate`](fn.migrate.html), but also checks that the `ed` encoding
+/// decodes back to a value equal to the legacy one, erroring with
+/// `Error::Custom` if not. This catches types whose `ed` encoding loses
+/// information, e.g. because of fields skipped by a custom impl.
+pub fn migrate_verified<T>(bytes: &[u8]) -> Result<Vec<u8>>
+where
+    T: DeserializeOwned + Encode + Decode + PartialEq,
+{
+    let value = decode::<T>(bytes)?;
+    let encoding = value.encode()?;
+    if T::decode_all(&encoding)? != value {
+        return Err(Error::custom(
+            "value changed when re-encoded with ed during migration",
+        ));
+    }
+    Ok(encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{Read, Write};
+    use ::serde::de::{Deserialize, Deserializer};
+
+    #[test]
+    fn decode_legacy() {
+        let legacy = [7, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, b'o', b'k'];
+        let value: (u32, Option<String>) = decode(&legacy).unwrap();
+        assert_eq!(value, (7, Some("ok".to_string())));
+
+        let err = decode::<(u32, Option<String>)>(&legacy[..10]).unwrap_err();
+        assert!(err.is_unexpected_eof());
+
+        let mut trailing = legacy.to_vec();
+        trailing.push(0);
+        assert!(matches!(
+            decode::<(u32, Option<String>)>(&trailing),
+            Err(Error::Custom(_))
+        ));
+    }
+
+    #[test]
+    fn migrate_legacy() {
+        let legacy = [3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2, 0, 3, 0];
+        let bytes = migrate::<Vec<u16>>(&legacy).unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2, 0, 3]);
+        assert_eq!(migrate_verified::<Vec<u16>>(&legacy).unwrap(), bytes);
+    }
+
+    /// A type whose `ed` encoding drops the second field.
+    #[derive(Debug, PartialEq)]
+    struct Lossy(u8, u8);
+
+    impl<'de> Deserialize<'de> for Lossy {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+            let (a, b) = <(u8, u8)>::deserialize(d)?;
+            Ok(Lossy(a, b))
+        }
+    }
+
+    impl Encode for Lossy {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            self.0.encode_into(dest)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            Ok(1)
+        }
+    }
+
+    impl Decode for Lossy {
+        fn decode<R: Read>(input: R) -> Result<Self> {
+            Ok(Lossy(u8::decode(input)?, 0))
+        }
+    }
+
+    #[test]
+    fn migrate_lossy() {
+        assert_eq!(migrate::<Lossy>(&[1, 2]).unwrap(), vec![1]);
+        assert!(matches!(
+            migrate_verified::<Lossy>(&[1, 2]),
+            Err(Error::Custom(_))
+        ));
+    }
+}
@@ -0,0 +1,638 @@
+//! Accounting for the memory used by decoding.
+//!
+//! Resource-metered systems (e.g. gas accounting or quota enforcement) need to
+//! charge for the memory a decode uses, not just the number of input bytes
+//! read. [`decode_with_accounting`](fn.decode_with_accounting.html) decodes a
+//! value and reports the number of heap bytes allocated while decoding it,
+//! including scratch buffers which were freed again and the reallocations of
+//! growing collections.
+//!
+//! Allocations are counted by the program's global allocator, which reports
+//! them through [`record_allocation`](fn.record_allocation.html):
+//!
+//! ```
+//! use ed::cost::{decode_with_accounting, record_allocation};
+//! use std::alloc::{GlobalAlloc, Layout, System};
+//!
+//! struct Counting;
+//!
+//! unsafe impl GlobalAlloc for Counting {
+//!     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+//!         record_allocation(layout.size());
+//!         System.alloc(layout)
+//!     }
+//!
+//!     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+//!         System.dealloc(ptr, layout)
+//!     }
+//!
+//!     unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+//!         record_allocation(new_size);
+//!         System.realloc(ptr, layout, new_size)
+//!     }
+//! }
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: Counting = Counting;
+//!
+//! let bytes = [0, 1, 0, 2, 0, 3];
+//! let (value, allocated): (Vec<u16>, usize) = decode_with_accounting(&bytes[..])?;
+//! assert_eq!(value, vec![1, 2, 3]);
+//! assert!(allocated >= 6);
+//! # Ok::<(), ed::Error>(())
+//! ```
+//!
+//! [`EncodingCost`](trait.EncodingCost.html) reports the heap memory a value
+//! holds on to instead, which can be derived and doesn't need an allocator
+//! hook. [`decode_with_retained_size`](fn.decode_with_retained_size.html)
+//! decodes a value and reports its retained size in one step. To bound the work
+//! done while decoding untrusted input, use the
+//! [`limits`](../limits/index.html) as well.
+
+use super::any::Any;
+use super::counted::Counted;
+use super::float::{F32Bits, F64Bits};
+use super::int::{Isize64, Usize64, I24, I48, U24, U48};
+use super::le::Le;
+use super::order::Unordered;
+use super::remaining::Remaining;
+use super::varint::Varint;
+use super::{Decode, Result, Timestamp};
+use std::borrow::{Cow, ToOwned};
+use std::cell::{Cell, RefCell};
+use std::cmp::{self, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::ffi::CString;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::num::{
+    NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU128, NonZeroU16,
+    NonZeroU32, NonZeroU64, NonZeroU8,
+};
+use std::ops::{Bound, Range, RangeFrom, RangeInclusive, RangeTo};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{
+    AtomicBool, AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicU16, AtomicU32, AtomicU64,
+    AtomicU8,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+// The number of bytes allocated within the innermost `decode_with_accounting`
+// on this thread, or `None` outside of one. Const-initialized and without a
+// destructor, so that it can be used from within a global allocator.
+thread_local! {
+    static ALLOCATED: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Counts an allocation of `bytes` bytes towards the enclosing
+/// [`decode_with_accounting`](fn.decode_with_accounting.html) calls on this
+/// thread, if any.
+///
+/// This is meant to be called by a global allocator for every allocation and
+/// reallocation (see the [module documentation](index.html)). It doesn't
+/// allocate, and does nothing outside of `decode_with_accounting`.
+#[inline]
+pub fn record_allocation(bytes: usize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        if let Some(total) = allocated.get() {
+            allocated.set(Some(total.saturating_add(bytes)));
+        }
+    });
+}
+
+/// Counts allocations until dropped, then adds them to any enclosing count.
+struct Accounting(Option<usize>);
+
+impl Accounting {
+    fn open() -> Self {
+        Accounting(ALLOCATED.try_with(|a| a.replace(Some(0))).unwrap_or(None))
+    }
+
+    fn allocated(&self) -> usize {
+        ALLOCATED.try_with(Cell::get).ok().flatten().unwrap_or(0)
+    }
+}
+
+impl Drop for Accounting {
+    fn drop(&mut self) {
+        let outer = self.0;
+        let _ = ALLOCATED.try_with(|allocated| {
+            let inner = allocated.get().unwrap_or(0);
+            allocated.set(outer.map(|outer| outer.saturating_add(inner)));
+        });
+    }
+}
+
+/// Decodes a value from the reader, returning it along with the number of
+/// bytes of heap memory allocated while decoding it, as reported to
+/// [`record_allocation`](fn.record_allocation.html).
+///
+/// This counts every allocation and reallocation made on the current thread,
+/// including ones which are freed before decoding finishes, so it is at least
+/// the value's [`heap_bytes`](trait.EncodingCost.html#tymethod.heap_bytes). It
+/// is always 0 if the global allocator doesn't report its allocations. Nested
+/// calls count towards the enclosing ones as well.
+pub fn decode_with_accounting<T, R>(input: R) -> Result<(T, usize)>
+where
+    T: Decode,
+    R: Read,
+{
+    let accounting = Accounting::open();
+    let value = T::decode(input)?;
+    Ok((value, accounting.allocated()))
+}
+
+/// A trait for values which can report how many bytes of heap memory they
+/// own.
+///
+/// This can be derived for structs and enums, which sum the heap memory owned
+/// by their fields.
+pub trait EncodingCost {
+    /// Returns the number of bytes of heap memory owned by the value,
+    /// including allocated but unused capacity. Memory used by the value
+    /// itself (e.g. on the stack) is not included.
+    fn heap_bytes(&self) -> usize;
+}
+
+/// Decodes a value from the reader, returning it along with the number of
+/// bytes of heap memory it retains, as reported by
+/// [`heap_bytes`](trait.EncodingCost.html#tymethod.heap_bytes) once it has been
+/// decoded.
+///
+/// Unlike [`decode_with_accounting`](fn.decode_with_accounting.html), this
+/// doesn't include memory which was allocated while decoding and freed again.
+pub fn decode_with_retained_size<T, R>(input: R) -> Result<(T, usize)>
+where
+    T: Decode + EncodingCost,
+    R: Read,
+{
+    let value = T::decode(input)?;
+    let cost = value.heap_bytes();
+    Ok((value, cost))
+}
+
+macro_rules! zero_cost_impl {
+    ($($type:ty),*) => {
+        $(
+            impl EncodingCost for $type {
+                #[inline]
+                fn heap_bytes(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+zero_cost_impl!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    bool,
+    (),
+    Infallible,
+    cmp::Ordering,
+    Duration,
+    NonZeroU8,
+    NonZeroU16,
+    NonZeroU32,
+    NonZeroU64,
+    NonZeroU128,
+    NonZeroI8,
+    NonZeroI16,
+    NonZeroI32,
+    NonZeroI64,
+    NonZeroI128,
+    AtomicBool,
+    AtomicU8,
+    AtomicU16,
+    AtomicU32,
+    AtomicU64,
+    AtomicI8,
+    AtomicI16,
+    AtomicI32,
+    AtomicI64,
+    Ipv4Addr,
+    Ipv6Addr,
+    IpAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    SocketAddr,
+    F32Bits,
+    F64Bits,
+    U24,
+    I24,
+    U48,
+    I48,
+    Usize64,
+    Isize64
+);
+
+impl<const MIN: i64, const MAX: i64> EncodingCost for Timestamp<MIN, MAX> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+}
+
+impl<T: ?Sized> EncodingCost for PhantomData<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+}
+
+/// A `Copy` value can't own heap memory.
+impl<T: Copy> EncodingCost for Cell<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Option<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.as_ref().map_or(0, EncodingCost::heap_bytes)
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Bound<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Bound::Included(value) | Bound::Excluded(value) => value.heap_bytes(),
+            Bound::Unbounded => 0,
+        }
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Range<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.start.heap_bytes() + self.end.heap_bytes()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for RangeInclusive<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.start().heap_bytes() + self.end().heap_bytes()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for RangeFrom<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.start.heap_bytes()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for RangeTo<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.end.heap_bytes()
+    }
+}
+
+impl<T: EncodingCost, const N: usize> EncodingCost for [T; N] {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.iter().map(EncodingCost::heap_bytes).sum()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Vec<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(EncodingCost::heap_bytes).sum::<usize>()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for VecDeque<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(EncodingCost::heap_bytes).sum::<usize>()
+    }
+}
+
+/// Counts the entries themselves, since the layout of the tree's nodes isn't
+/// public.
+impl<K: EncodingCost, V: EncodingCost> EncodingCost for BTreeMap<K, V> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.len() * size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_bytes() + value.heap_bytes())
+                .sum::<usize>()
+    }
+}
+
+/// Counts the entries themselves, since the layout of the tree's nodes isn't
+/// public.
+impl<T: EncodingCost> EncodingCost for BTreeSet<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.len() * size_of::<T>() + self.iter().map(EncodingCost::heap_bytes).sum::<usize>()
+    }
+}
+
+/// Counts the capacity for entries, since the layout of the table's control
+/// bytes isn't public.
+impl<K: EncodingCost, V: EncodingCost, S> EncodingCost for HashMap<K, V, S> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * size_of::<(K, V)>()
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_bytes() + value.heap_bytes())
+                .sum::<usize>()
+    }
+}
+
+impl EncodingCost for String {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl EncodingCost for PathBuf {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl EncodingCost for CString {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.as_bytes_with_nul().len()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Box<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        size_of::<T>() + (**self).heap_bytes()
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for Box<[T]> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.len() * size_of::<T>() + self.iter().map(EncodingCost::heap_bytes).sum::<usize>()
+    }
+}
+
+impl EncodingCost for Box<str> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+macro_rules! shared_ptr_cost_impl {
+    ($ptr:ident) => {
+        /// Includes the reference counts, and counts the whole allocation for
+        /// every pointer to it.
+        impl<T: EncodingCost> EncodingCost for $ptr<T> {
+            #[inline]
+            fn heap_bytes(&self) -> usize {
+                2 * size_of::<usize>() + size_of::<T>() + (**self).heap_bytes()
+            }
+        }
+
+        /// Includes the reference counts, and counts the whole allocation for
+        /// every pointer to it.
+        impl<T: EncodingCost> EncodingCost for $ptr<[T]> {
+            #[inline]
+            fn heap_bytes(&self) -> usize {
+                2 * size_of::<usize>()
+                    + self.len() * size_of::<T>()
+                    + self.iter().map(EncodingCost::heap_bytes).sum::<usize>()
+            }
+        }
+
+        /// Includes the reference counts, and counts the whole allocation for
+        /// every pointer to it.
+        impl EncodingCost for $ptr<str> {
+            #[inline]
+            fn heap_bytes(&self) -> usize {
+                2 * size_of::<usize>() + self.len()
+            }
+        }
+    };
+}
+
+shared_ptr_cost_impl!(Rc);
+shared_ptr_cost_impl!(Arc);
+
+/// Borrowed values don't own any heap memory.
+impl<'a, B: ToOwned + ?Sized> EncodingCost for Cow<'a, B>
+where
+    B::Owned: EncodingCost,
+{
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(owned) => owned.heap_bytes(),
+        }
+    }
+}
+
+impl<T: EncodingCost> EncodingCost for RefCell<T> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.borrow().heap_bytes()
+    }
+}
+
+macro_rules! wrapper_cost_impl {
+    ($($wrapper:ident),*) => {
+        $(
+            impl<T: EncodingCost> EncodingCost for $wrapper<T> {
+                #[inline]
+                fn heap_bytes(&self) -> usize {
+                    self.0.heap_bytes()
+                }
+            }
+        )*
+    };
+}
+
+wrapper_cost_impl!(Reverse, Unordered, Remaining, Varint, Le);
+
+impl<C: EncodingCost, L> EncodingCost for Counted<C, L> {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.0.heap_bytes()
+    }
+}
+
+impl EncodingCost for Any {
+    #[inline]
+    fn heap_bytes(&self) -> usize {
+        self.bytes().len()
+    }
+}
+
+macro_rules! tuple_cost_impl {
+    ($( $type:ident ),*) => {
+        impl<$($type: EncodingCost,)*> EncodingCost for ($($type,)*) {
+            #[allow(non_snake_case)]
+            #[inline]
+            fn heap_bytes(&self) -> usize {
+                let ($($type,)*) = self;
+                0 $(+ $type.heap_bytes())*
+            }
+        }
+    }
+}
+
+tuple_cost_impl!(A);
+tuple_cost_impl!(A, B);
+tuple_cost_impl!(A, B, C);
+tuple_cost_impl!(A, B, C, D);
+tuple_cost_impl!(A, B, C, D, E);
+tuple_cost_impl!(A, B, C, D, E, F);
+tuple_cost_impl!(A, B, C, D, E, F, G);
+tuple_cost_impl!(A, B, C, D, E, F, G, H);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K, L);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+tuple_cost_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Encode, Terminated};
+    use std::io::Write;
+
+    // Reports a fixed allocation when decoded, standing in for a counting
+    // global allocator.
+    struct Allocating<const N: usize>;
+
+    impl<const N: usize> Encode for Allocating<N> {
+        fn encode_into<W: Write>(&self, _: &mut W) -> Result<()> {
+            Ok(())
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl<const N: usize> Decode for Allocating<N> {
+        fn decode<R: Read>(_: R) -> Result<Self> {
+            record_allocation(N);
+            Ok(Allocating)
+        }
+    }
+
+    impl<const N: usize> Terminated for Allocating<N> {}
+
+    #[test]
+    fn accounting_scopes() {
+        record_allocation(1000);
+        let (_, allocated) = decode_with_accounting::<Allocating<10>, _>(&[][..]).unwrap();
+        assert_eq!(allocated, 10);
+
+        struct Nested(usize);
+
+        impl Encode for Nested {
+            fn encode_into<W: Write>(&self, _: &mut W) -> Result<()> {
+                Ok(())
+            }
+
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(0)
+            }
+        }
+
+        impl Decode for Nested {
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                record_allocation(5);
+                let (_, inner) = decode_with_accounting::<Allocating<7>, _>(input)?;
+                Ok(Nested(inner))
+            }
+        }
+
+        let (value, allocated) = decode_with_accounting::<Nested, _>(&[][..]).unwrap();
+        assert_eq!(value.0, 7);
+        assert_eq!(allocated, 12);
+
+        // Allocations of failed decodes are discarded along with the value.
+        assert!(decode_with_accounting::<(Allocating<3>, u8), _>(&[][..]).is_err());
+        let (_, allocated) = decode_with_accounting::<Allocating<2>, _>(&[][..]).unwrap();
+        assert_eq!(allocated, 2);
+    }
+
+    #[test]
+    fn fixed_size_costs() {
+        assert_eq!(123u32.heap_bytes(), 0);
+        assert_eq!((1u8, Some(2u64), [3u16; 4]).heap_bytes(), 0);
+    }
+
+    #[test]
+    fn decode_vec_with_retained_size() {
+        let bytes = [0, 1, 0, 2, 0, 3];
+        let (value, cost): (Vec<u16>, usize) = decode_with_retained_size(&bytes[..]).unwrap();
+        assert_eq!(value, vec![1, 2, 3]);
+        assert_eq!(cost, value.capacity() * 2);
+        assert!(cost >= 6);
+    }
+
+    #[test]
+    fn nested_costs() {
+        let value: Vec<Vec<u8>> = vec![Vec::with_capacity(10), Vec::with_capacity(20)];
+        let expected =
+            value.capacity() * size_of::<Vec<u8>>() + value[0].capacity() + value[1].capacity();
+        assert_eq!(value.heap_bytes(), expected);
+
+        let value = Box::new(Some(vec![1u32; 4]));
+        assert_eq!(
+            value.heap_bytes(),
+            size_of::<Option<Vec<u32>>>() + value.as_ref().as_ref().unwrap().capacity() * 4
+        );
+    }
+
+    #[test]
+    fn std_costs() {
+        let key: Rc<str> = Rc::from("key");
+        assert_eq!(key.heap_bytes(), 2 * size_of::<usize>() + 3);
+
+        let values: Box<[String]> = vec![String::with_capacity(4)].into_boxed_slice();
+        assert_eq!(values.heap_bytes(), size_of::<String>() + 4);
+
+        let mut map = BTreeMap::new();
+        map.insert(1u8, String::with_capacity(8));
+        assert_eq!(map.heap_bytes(), size_of::<(u8, String)>() + 8);
+
+        let owned: Cow<[u8]> = Cow::Owned(vec![1, 2, 3]);
+        assert_eq!(owned.heap_bytes(), 3);
+        assert_eq!(Cow::Borrowed(&[1u8, 2, 3][..]).heap_bytes(), 0);
+
+        assert_eq!((1u8..=2).heap_bytes(), 0);
+        assert_eq!(Unordered(vec![0u32; 2]).heap_bytes(), 8);
+        assert_eq!(Bound::Included(Box::new(1u64)).heap_bytes(), 8);
+    }
+}
@@ -0,0 +1,371 @@
+//! Collections prefixed with their number of elements.
+//!
+//! A `Vec<T>` is normally encoded as its elements one after another, which
+//! reads until the end of the input and so is not `Terminated`.
+//! [`Counted`](struct.Counted.html) instead prefixes the elements with their
+//! count, encoded as `L` (by default a big-endian `u32`), so that the
+//! collection can be followed by other values. This is the framing used by
+//! most foreign formats, as opposed to prefixing with the length in bytes.
+//!
+//! ```rust
+//! use ed::{counted::Counted, Decode, Encode};
+//!
+//! let names: Counted<Vec<Counted<Vec<u8>, u8>>, u16> =
+//!     Counted::new(vec![Counted::new(b"ab".to_vec()), Counted::new(vec![])]);
+//! let bytes = names.encode().unwrap();
+//! assert_eq!(bytes, vec![0, 2, 2, b'a', b'b', 0]);
+//! assert_eq!(Counted::decode(bytes.as_slice()).unwrap(), names);
+//! ```
+//!
+//! Every element must read at least one byte, so that a count can't describe
+//! more elements than the input holds. Decoding an element which reads nothing,
+//! e.g. a `()`, errors with `Error::CountExceedsInput` rather than looping as
+//! many times as the count says.
+
+use super::io::{self, Read, Write};
+use super::varint::Varint;
+use super::{limits, Decode, Encode, Error, Result, Terminated};
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// An integer type which can prefix a [`Counted`](struct.Counted.html)
+/// collection with its number of elements.
+pub trait Count: Encode + Decode + Terminated {
+    /// Converts a number of elements to a count, or returns `None` if it is
+    /// too large for the type.
+    fn from_len(len: usize) -> Option<Self>;
+
+    /// Converts the count to a number of elements, or returns `None` if it is
+    /// too large for a `usize`.
+    fn to_len(&self) -> Option<usize>;
+}
+
+macro_rules! count_impl {
+    ($type:ty) => {
+        impl Count for $type {
+            #[inline]
+            fn from_len(len: usize) -> Option<Self> {
+                <$type>::try_from(len).ok()
+            }
+
+            #[inline]
+            fn to_len(&self) -> Option<usize> {
+                usize::try_from(*self).ok()
+            }
+        }
+
+        impl Count for Varint<$type> {
+            #[inline]
+            fn from_len(len: usize) -> Option<Self> {
+                <$type>::try_from(len).ok().map(Varint)
+            }
+
+            #[inline]
+            fn to_len(&self) -> Option<usize> {
+                usize::try_from(self.0).ok()
+            }
+        }
+    };
+}
+
+impl Count for u8 {
+    #[inline]
+    fn from_len(len: usize) -> Option<Self> {
+        u8::try_from(len).ok()
+    }
+
+    #[inline]
+    fn to_len(&self) -> Option<usize> {
+        Some(usize::from(*self))
+    }
+}
+
+count_impl!(u16);
+count_impl!(u32);
+count_impl!(u64);
+
+/// A collection encoded as its number of elements, as an `L`, followed by the
+/// elements one after another. Unlike the collection itself, `Counted` is
+/// `Terminated`.
+///
+/// Implemented for `Vec<T>` and `VecDeque<T>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Counted<C, L = u32>(pub C, PhantomData<L>);
+
+impl<C, L> Counted<C, L> {
+    /// Wraps the collection.
+    pub fn new(inner: C) -> Self {
+        Counted(inner, PhantomData)
+    }
+
+    /// Returns the inner collection.
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C, L> Deref for Counted<C, L> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+
+impl<C, L> DerefMut for Counted<C, L> {
+    fn deref_mut(&mut self) -> &mut C {
+        &mut self.0
+    }
+}
+
+impl<C, L> From<C> for Counted<C, L> {
+    fn from(inner: C) -> Self {
+        Counted::new(inner)
+    }
+}
+
+/// Returns the count prefix for `len` elements, erroring with
+/// `Error::LengthOverflow` if it is too large for `L`.
+fn count<L: Count>(len: usize) -> Result<L> {
+    L::from_len(len).ok_or(Error::LengthOverflow(len))
+}
+
+/// Decodes a count prefix, erroring with `Error::OutOfRange` if it is too
+/// large for a `usize`.
+fn decode_count<L: Count, R: Read>(input: R) -> Result<usize> {
+    L::decode(input)?.to_len().ok_or(Error::OutOfRange)
+}
+
+impl<T: Encode + Terminated, L: Count> Encode for Counted<Vec<T>, L> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the number of elements followed by the elements, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        count::<L>(self.0.len())?.encode_into(dest)?;
+        T::encode_slice_into(&self.0, dest)
+    }
+
+    /// Returns the encoding length of the count plus the sum of the encoding
+    /// lengths of the elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(count::<L>(self.0.len())?.encoding_length()? + self.0.encoding_length()?)
+    }
+}
+
+impl<T: Decode + Terminated, L: Count> Decode for Counted<Vec<T>, L> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the number of elements followed by that many elements.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut value = Counted::new(Vec::new());
+        value.decode_into(input)?;
+        Ok(value)
+    }
+
+    /// Decodes the number of elements followed by that many elements,
+    /// reusing the existing vector.
+    ///
+    /// Recursively calls `decode_into` for each element which is already
+    /// present.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let len = decode_count::<L, _>(&mut input)?;
+        let _depth = limits::descend()?;
+
+        // The count is not trusted to preallocate, since a short input could
+        // claim a huge number of elements.
+        let mut input = Consumed::new(input);
+        let vec = &mut self.0;
+        for i in 0..len {
+            limits::count_elements(1)?;
+            match vec.get_mut(i) {
+                Some(element) => element.decode_into(&mut input)?,
+                None => vec.push(T::decode(&mut input)?),
+            }
+            input.check(len)?;
+        }
+        vec.truncate(len);
+
+        Ok(())
+    }
+}
+
+impl<T: Terminated, L: Count> Terminated for Counted<Vec<T>, L> {}
+
+impl<T: Encode + Terminated, L: Count> Encode for Counted<VecDeque<T>, L> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the number of elements followed by the elements, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        count::<L>(self.0.len())?.encode_into(dest)?;
+        self.0.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the count plus the sum of the encoding
+    /// lengths of the elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(count::<L>(self.0.len())?.encoding_length()? + self.0.encoding_length()?)
+    }
+}
+
+impl<T: Decode + Terminated, L: Count> Decode for Counted<VecDeque<T>, L> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the number of elements followed by that many elements.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let len = decode_count::<L, _>(&mut input)?;
+        let _depth = limits::descend()?;
+
+        let mut input = Consumed::new(input);
+        let mut deque = VecDeque::new();
+        for _ in 0..len {
+            limits::count_elements(1)?;
+            deque.push_back(T::decode(&mut input)?);
+            input.check(len)?;
+        }
+
+        Ok(Counted::new(deque))
+    }
+}
+
+impl<T: Terminated, L: Count> Terminated for Counted<VecDeque<T>, L> {}
+
+/// A reader which records whether anything was read from it, to reject
+/// elements which read nothing.
+struct Consumed<R> {
+    inner: R,
+    read: bool,
+}
+
+impl<R: Read> Consumed<R> {
+    #[inline]
+    fn new(inner: R) -> Self {
+        Consumed { inner, read: false }
+    }
+
+    /// Errors with `Error::CountExceedsInput(len)` if nothing was read since
+    /// the last check.
+    #[inline]
+    fn check(&mut self, len: usize) -> Result<()> {
+        if !core::mem::replace(&mut self.read, false) {
+            return Err(Error::CountExceedsInput(len));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Consumed<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read |= n > 0;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::DecodeOptions;
+
+    #[test]
+    fn encode_decode_vec() {
+        let value: Counted<Vec<u16>> = Counted::new(vec![1, 2, 3]);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 3, 0, 1, 0, 2, 0, 3]);
+        assert_eq!(value.encoding_length().unwrap(), 10);
+        assert_eq!(
+            Counted::<Vec<u16>>::decode(bytes.as_slice()).unwrap(),
+            value
+        );
+
+        // Being terminated, a counted vector can be followed by other values.
+        let pair: (Counted<Vec<u8>, u8>, u8) = Decode::decode(&[2, 5, 6, 7][..]).unwrap();
+        assert_eq!((pair.0.into_inner(), pair.1), (vec![5, 6], 7));
+    }
+
+    #[test]
+    fn decode_into_vec() {
+        let nested: Counted<Vec<Counted<Vec<u8>, u8>>, u8> =
+            Counted::new(vec![Counted::new(vec![4, 5])]);
+        let bytes = nested.encode().unwrap();
+        assert_eq!(bytes, vec![1, 2, 4, 5]);
+
+        let mut decoded: Counted<Vec<Counted<Vec<u8>, u8>>, u8> = Counted::new(vec![
+            Counted::new(Vec::with_capacity(8)),
+            Counted::new(vec![9]),
+        ]);
+        let ptr = decoded[0].as_ptr();
+        decoded.decode_into(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, nested);
+        assert_eq!(decoded[0].as_ptr(), ptr);
+    }
+
+    #[test]
+    fn encode_decode_deque() {
+        let value: Counted<VecDeque<u8>, Varint<u32>> = Counted::new((0..200).collect());
+        let bytes = value.encode().unwrap();
+        assert_eq!(&bytes[..2], &[0xc8, 0x01]);
+        assert_eq!(bytes.len(), 202);
+        assert_eq!(value.encoding_length().unwrap(), 202);
+        assert_eq!(Counted::decode(bytes.as_slice()).unwrap(), value);
+    }
+
+    #[test]
+    fn reject_elements_reading_nothing() {
+        // The count alone would otherwise drive up to `u64::MAX` iterations.
+        let bytes = [0xff; 8];
+        assert!(matches!(
+            Counted::<Vec<()>, u64>::decode(&bytes[..]),
+            Err(Error::CountExceedsInput(_))
+        ));
+        assert!(matches!(
+            Counted::<VecDeque<PhantomData<u8>>, u64>::decode(&bytes[..]),
+            Err(Error::CountExceedsInput(_))
+        ));
+
+        let mut value: Counted<Vec<()>, u8> = Counted::new(vec![(); 2]);
+        assert!(matches!(
+            value.decode_into(&[2][..]),
+            Err(Error::CountExceedsInput(2))
+        ));
+        value.decode_into(&[0][..]).unwrap();
+        assert!(value.is_empty());
+    }
+
+    #[test]
+    fn count_overflow() {
+        let value: Counted<Vec<u8>, u8> = Counted::new(vec![0; 256]);
+        assert!(matches!(value.encode(), Err(Error::LengthOverflow(256))));
+        assert!(matches!(
+            value.encoding_length(),
+            Err(Error::LengthOverflow(256))
+        ));
+    }
+
+    #[test]
+    fn decode_short_input() {
+        // A huge count with few elements fails without preallocating.
+        let err = Counted::<Vec<u16>>::decode(&[0xff, 0xff, 0xff, 0xff, 0, 1][..]).unwrap_err();
+        assert!(err.is_unexpected_eof());
+
+        let options = DecodeOptions {
+            max_elements: Some(2),
+            ..Default::default()
+        };
+        let err = options
+            .decode::<Counted<Vec<u16>>, _>(&[0, 0, 0, 3, 0, 1, 0, 2, 0, 3][..])
+            .unwrap_err();
+        assert!(matches!(err, Error::MaxElementsExceeded(2)));
+    }
+}
@@ -0,0 +1,214 @@
+//! Constant-time decoding for fixed-size secret material.
+//!
+//! When parsing attacker-supplied ciphertexts, MACs, or keys, data-dependent
+//! branches and early returns can leak information through timing. The types
+//! in this module always read their full fixed length, never branch on the
+//! bytes they read, and compare in constant time, reporting validation
+//! failures only after all work has been done.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::fmt;
+use core::hint::black_box;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+/// Compares two byte slices in time which depends only on their lengths, not
+/// their contents.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff = black_box(diff | (x ^ y));
+    }
+    diff == 0
+}
+
+/// A fixed-size byte array which is decoded and compared in constant time.
+///
+/// Encoded as its raw `N` bytes. Decoding performs a single `read_exact` of
+/// all `N` bytes rather than decoding element by element, and `PartialEq`
+/// compares every byte without exiting early.
+///
+/// The bytes are left out of the `Debug` output, and with the `zeroize`
+/// feature they are wiped when the value is dropped. `CtBytes` isn't `Copy`,
+/// so that each copy is an explicit `clone` which is wiped in turn.
+#[derive(Clone)]
+pub struct CtBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> CtBytes<N> {
+    /// Decodes `N` bytes and checks them against `expected` in constant time,
+    /// e.g. for verifying a MAC. Errors with `Error::VerificationFailed` if
+    /// they differ, only after all bytes have been read and compared.
+    pub fn decode_verified<R: Read>(input: R, expected: &[u8; N]) -> Result<Self> {
+        let value = Self::decode(input)?;
+        if value.ct_eq(expected) {
+            Ok(value)
+        } else {
+            Err(Error::VerificationFailed)
+        }
+    }
+
+    /// Compares the bytes to `other` in constant time.
+    pub fn ct_eq(&self, other: &[u8; N]) -> bool {
+        ct_eq(&self.0[..], &other[..])
+    }
+}
+
+impl<const N: usize> fmt::Debug for CtBytes<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CtBytes<{}>([REDACTED])", N)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<const N: usize> Drop for CtBytes<N> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<const N: usize> PartialEq for CtBytes<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(&other.0)
+    }
+}
+
+impl<const N: usize> Eq for CtBytes<N> {}
+
+impl<const N: usize> Encode for CtBytes<N> {
+    const SENSITIVE: bool = true;
+
+    /// Writes the raw bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&self.0[..])?;
+        Ok(())
+    }
+
+    /// Always returns Ok(N).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(N)
+    }
+}
+
+impl<const N: usize> Decode for CtBytes<N> {
+    const SENSITIVE: bool = true;
+
+    /// Reads exactly `N` bytes.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut value = CtBytes([0; N]);
+        value.decode_into(input)?;
+        Ok(value)
+    }
+
+    /// Reads exactly `N` bytes into the existing array.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        input.read_exact(&mut self.0[..])?;
+        Ok(())
+    }
+}
+
+impl<const N: usize> Terminated for CtBytes<N> {}
+
+impl<const N: usize> FixedLength for CtBytes<N> {
+    const ENCODING_LENGTH: usize = N;
+}
+
+/// Accumulates the results of several constant-time checks, so a decoder can
+/// validate every field before deciding whether to fail.
+///
+/// ```rust
+/// # use ed::ct::CtValidation;
+/// let mut validation = CtValidation::new();
+/// validation.check_eq(&[1, 2, 3], &[1, 2, 3]);
+/// validation.check_eq(&[4, 5], &[4, 6]);
+/// assert!(validation.finish().is_err());
+/// ```
+#[derive(Default)]
+pub struct CtValidation {
+    diff: u8,
+}
+
+impl CtValidation {
+    /// Creates an accumulator with no failed checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records whether the two byte slices are equal, comparing them in
+    /// constant time.
+    pub fn check_eq(&mut self, a: &[u8], b: &[u8]) -> &mut Self {
+        self.diff = black_box(self.diff | !ct_eq(a, b) as u8);
+        self
+    }
+
+    /// Records the result of a check computed by the caller, without
+    /// branching on it.
+    pub fn check(&mut self, ok: bool) -> &mut Self {
+        self.diff = black_box(self.diff | !ok as u8);
+        self
+    }
+
+    /// Errors with `Error::VerificationFailed` if any check failed.
+    pub fn finish(&self) -> Result<()> {
+        if self.diff == 0 {
+            Ok(())
+        } else {
+            Err(Error::VerificationFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_eq_slices() {
+        assert!(ct_eq(&[1, 2, 3], &[1, 2, 3]));
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2, 4]));
+        assert!(!ct_eq(&[1, 2, 3], &[1, 2]));
+    }
+
+    #[test]
+    fn encode_decode_ct_bytes() {
+        let bytes = [1, 2, 3, 4, 5];
+        let value: CtBytes<4> = Decode::decode(&bytes[..]).unwrap();
+        assert_eq!(value, CtBytes([1, 2, 3, 4]));
+        assert_eq!(value.encode().unwrap(), vec![1, 2, 3, 4]);
+
+        assert!(CtBytes::<8>::decode(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn ct_bytes_debug_is_redacted() {
+        let value = CtBytes([0xab; 4]);
+        assert_eq!(format!("{:?}", value), "CtBytes<4>([REDACTED])");
+    }
+
+    #[test]
+    fn decode_verified() {
+        let bytes = [1, 2, 3, 4];
+        assert!(CtBytes::decode_verified(&bytes[..], &[1, 2, 3, 4]).is_ok());
+        assert!(matches!(
+            CtBytes::decode_verified(&bytes[..], &[1, 2, 3, 5]),
+            Err(Error::VerificationFailed)
+        ));
+    }
+
+    #[test]
+    fn validation() {
+        let mut validation = CtValidation::new();
+        validation.check_eq(&[1], &[1]).check(true);
+        assert!(validation.finish().is_ok());
+        validation.check(false).check_eq(&[1], &[1]);
+        assert!(validation.finish().is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Fixed-point decimal numbers.
+//!
+//! [`FixedDecimal<SCALE>`](struct.FixedDecimal.html) represents a decimal
+//! number with exactly `SCALE` digits after the decimal point, stored as an
+//! `i128` count of units of `10^-SCALE`. It is intended for token amounts and
+//! prices, where floats are unacceptable: arithmetic is exact integer
+//! arithmetic, and the value is encoded as its 16-byte big-endian count of
+//! units, so every value has exactly one encoding.
+//!
+//! The scale is part of the type rather than the encoding, so values of
+//! different scales can't be mixed up. `SCALE` can be at most 38.
+//!
+//! ```rust
+//! use ed::{decimal::FixedDecimal, Encode};
+//!
+//! type Amount = FixedDecimal<6>;
+//!
+//! let price: Amount = "12.5".parse().unwrap();
+//! let quantity = Amount::from_int(3).unwrap();
+//! let total = price.checked_mul(quantity).unwrap();
+//! assert_eq!(total.to_string(), "37.500000");
+//! assert_eq!(total.raw(), 37_500_000);
+//! assert_eq!(total.encode().unwrap()[12..], [0x02, 0x3c, 0x34, 0x60]);
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::{Add, Neg, Sub};
+use core::str::FromStr;
+
+/// A decimal number with `SCALE` digits after the decimal point, encoded as
+/// its number of units of `10^-SCALE` as a big-endian `i128`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FixedDecimal<const SCALE: u32>(i128);
+
+impl<const SCALE: u32> FixedDecimal<SCALE> {
+    /// The number of units in 1. Fails to compile if `SCALE` is over 38.
+    const UNITS: i128 = 10i128.pow(SCALE);
+
+    /// The number of digits after the decimal point.
+    pub const SCALE: u32 = SCALE;
+
+    /// Zero.
+    pub const ZERO: Self = FixedDecimal(0);
+
+    /// One.
+    pub const ONE: Self = FixedDecimal(Self::UNITS);
+
+    /// The smallest value of the type.
+    pub const MIN: Self = FixedDecimal(i128::MIN);
+
+    /// The largest value of the type.
+    pub const MAX: Self = FixedDecimal(i128::MAX);
+
+    /// Returns the value with the given number of units of `10^-SCALE`, e.g.
+    /// `FixedDecimal::<2>::from_raw(150)` is 1.50.
+    pub const fn from_raw(raw: i128) -> Self {
+        FixedDecimal(raw)
+    }
+
+    /// Returns the number of units of `10^-SCALE` in the value.
+    pub const fn raw(self) -> i128 {
+        self.0
+    }
+
+    /// Returns the integer as a decimal, or `None` if it is out of range.
+    pub fn from_int(value: i128) -> Option<Self> {
+        value.checked_mul(Self::UNITS).map(FixedDecimal)
+    }
+
+    /// Returns the integer part of the value, rounded towards zero.
+    pub fn trunc(self) -> i128 {
+        self.0 / Self::UNITS
+    }
+
+    /// Returns the sum, or `None` on overflow.
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(FixedDecimal)
+    }
+
+    /// Returns the difference, or `None` on overflow.
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(FixedDecimal)
+    }
+
+    /// Returns the product rounded towards zero, or `None` on overflow.
+    ///
+    /// The intermediate product of the raw values must fit in an `i128`.
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        self.0
+            .checked_mul(other.0)
+            .map(|product| FixedDecimal(product / Self::UNITS))
+    }
+
+    /// Returns the quotient rounded towards zero, or `None` if `other` is
+    /// zero or on overflow.
+    ///
+    /// The dividend scaled by `10^SCALE` must fit in an `i128`.
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        self.0
+            .checked_mul(Self::UNITS)?
+            .checked_div(other.0)
+            .map(FixedDecimal)
+    }
+
+    /// Returns the negation, or `None` for `MIN`.
+    pub fn checked_neg(self) -> Option<Self> {
+        self.0.checked_neg().map(FixedDecimal)
+    }
+}
+
+impl<const SCALE: u32> Add for FixedDecimal<SCALE> {
+    type Output = Self;
+
+    /// Panics on overflow in debug builds, like integer addition.
+    fn add(self, other: Self) -> Self {
+        FixedDecimal(self.0 + other.0)
+    }
+}
+
+impl<const SCALE: u32> Sub for FixedDecimal<SCALE> {
+    type Output = Self;
+
+    /// Panics on overflow in debug builds, like integer subtraction.
+    fn sub(self, other: Self) -> Self {
+        FixedDecimal(self.0 - other.0)
+    }
+}
+
+impl<const SCALE: u32> Neg for FixedDecimal<SCALE> {
+    type Output = Self;
+
+    /// Panics on overflow in debug builds, like integer negation.
+    fn neg(self) -> Self {
+        FixedDecimal(-self.0)
+    }
+}
+
+impl<const SCALE: u32> fmt::Display for FixedDecimal<SCALE> {
+    /// Formats the value with exactly `SCALE` digits after the decimal point.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = Self::UNITS.unsigned_abs();
+        let magnitude = self.0.unsigned_abs();
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+        write!(f, "{}", magnitude / units)?;
+        if SCALE > 0 {
+            write!(f, ".{:0width$}", magnitude % units, width = SCALE as usize)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const SCALE: u32> FromStr for FixedDecimal<SCALE> {
+    type Err = Error;
+
+    /// Parses a decimal such as `-12.5`, with at most `SCALE` digits after
+    /// the decimal point. Errors with `Error::InvalidDecimal` if the string is
+    /// malformed, has too many fractional digits, or is out of range.
+    fn from_str(s: &str) -> Result<Self> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let (int, frac) = match digits.split_once('.') {
+            Some((int, frac)) if !frac.is_empty() => (int, frac),
+            Some(_) => return Err(Error::InvalidDecimal),
+            None => (digits, ""),
+        };
+        if int.is_empty() || frac.len() > SCALE as usize {
+            return Err(Error::InvalidDecimal);
+        }
+
+        let mut magnitude = 0u128;
+        for byte in int.bytes().chain(frac.bytes()) {
+            if !byte.is_ascii_digit() {
+                return Err(Error::InvalidDecimal);
+            }
+            magnitude = magnitude
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(u128::from(byte - b'0')))
+                .ok_or(Error::InvalidDecimal)?;
+        }
+        // Pad the fractional digits out to `SCALE` digits.
+        let padding = SCALE - frac.len() as u32;
+        let magnitude = magnitude
+            .checked_mul(10u128.pow(padding))
+            .ok_or(Error::InvalidDecimal)?;
+
+        let raw = if negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        };
+        raw.map(FixedDecimal).ok_or(Error::InvalidDecimal)
+    }
+}
+
+impl<const SCALE: u32> Encode for FixedDecimal<SCALE> {
+    /// Encodes the number of units as 16 big-endian bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.0.encode_into(dest)
+    }
+
+    /// Always returns Ok(16).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(16)
+    }
+}
+
+impl<const SCALE: u32> Decode for FixedDecimal<SCALE> {
+    /// Decodes the number of units from 16 big-endian bytes.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        i128::decode(input).map(FixedDecimal)
+    }
+}
+
+impl<const SCALE: u32> Terminated for FixedDecimal<SCALE> {}
+
+impl<const SCALE: u32> FixedLength for FixedDecimal<SCALE> {
+    const ENCODING_LENGTH: usize = 16;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Amount = FixedDecimal<4>;
+
+    #[test]
+    fn arithmetic() {
+        let a: Amount = "1.5".parse().unwrap();
+        let b = Amount::from_raw(2_5000);
+        assert_eq!(a + b, Amount::from_int(4).unwrap());
+        assert_eq!(a - b, "-1".parse().unwrap());
+        assert_eq!(a.checked_mul(b).unwrap(), "3.75".parse().unwrap());
+        assert_eq!(a.checked_div(b).unwrap(), "0.6".parse().unwrap());
+        assert_eq!(
+            Amount::ONE
+                .checked_div(Amount::from_int(3).unwrap())
+                .unwrap()
+                .raw(),
+            3333
+        );
+        assert_eq!((-a).trunc(), -1);
+
+        assert!(Amount::ONE.checked_div(Amount::ZERO).is_none());
+        assert!(Amount::MAX.checked_add(Amount::from_raw(1)).is_none());
+        assert!(Amount::MIN.checked_neg().is_none());
+        assert!(Amount::from_int(i128::MAX).is_none());
+    }
+
+    #[test]
+    fn display_parse() {
+        let cases = [
+            ("0", "0.0000"),
+            ("12.5", "12.5000"),
+            ("-0.0001", "-0.0001"),
+            ("-3", "-3.0000"),
+            ("007.1234", "7.1234"),
+        ];
+        for (input, output) in cases.iter() {
+            let value: Amount = input.parse().unwrap();
+            assert_eq!(value.to_string(), *output);
+        }
+        assert_eq!(
+            Amount::MIN.to_string().parse::<Amount>().unwrap(),
+            Amount::MIN
+        );
+        assert_eq!(
+            Amount::MAX.to_string().parse::<Amount>().unwrap(),
+            Amount::MAX
+        );
+        assert_eq!(FixedDecimal::<0>::from_raw(-7).to_string(), "-7");
+
+        for input in ["", "-", ".5", "1.", "1.23456", "1e3", "+1", "1.-2"].iter() {
+            assert!(matches!(
+                input.parse::<Amount>(),
+                Err(Error::InvalidDecimal)
+            ));
+        }
+        let too_large = "17014118346046923173168730371588410.5728";
+        assert!(too_large.parse::<Amount>().is_err());
+    }
+
+    #[test]
+    fn encode_decode() {
+        let value: Amount = "-1".parse().unwrap();
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(bytes[..], (-10_000i128).to_be_bytes());
+        assert_eq!(value.encoding_length().unwrap(), Amount::ENCODING_LENGTH);
+        assert_eq!(Amount::decode(bytes.as_slice()).unwrap(), value);
+    }
+}
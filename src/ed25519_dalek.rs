@@ -0,0 +1,123 @@
+//! Encoding support for Ed25519 keys and signatures, using the
+//! `ed25519-dalek` crate.
+//!
+//! A `VerifyingKey` is encoded as its 32-byte compressed Edwards point, and a
+//! `Signature` as its 64 bytes, `R` followed by `s`, as defined by RFC 8032.
+//!
+//! Decoding a `VerifyingKey` errors with `Error::InvalidKey` if the bytes are
+//! not a valid point. Decoding a `Signature` errors with
+//! `Error::InvalidSignature` if any of the 3 high bits of `s` are set, which
+//! no valid signature has. The signature itself is only checked when it is
+//! verified.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::ed25519_dalek::{Signature, VerifyingKey};
+
+impl Encode for VerifyingKey {
+    /// Encodes the compressed point as 32 bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.as_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(32).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(32)
+    }
+}
+
+impl Decode for VerifyingKey {
+    /// Decodes a compressed point from 32 bytes. Errors with
+    /// `Error::InvalidKey` if the bytes are not a valid point.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let bytes = <[u8; 32]>::decode(input)?;
+        VerifyingKey::from_bytes(&bytes).map_err(|_| Error::InvalidKey)
+    }
+}
+
+impl Terminated for VerifyingKey {}
+
+impl FixedLength for VerifyingKey {
+    const ENCODING_LENGTH: usize = 32;
+}
+
+impl Encode for Signature {
+    /// Encodes `R` followed by `s`, as 64 bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(64).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(64)
+    }
+}
+
+impl Decode for Signature {
+    /// Decodes `R` followed by `s` from 64 bytes. Errors with
+    /// `Error::InvalidSignature` if any of the 3 high bits of `s` are set.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let bytes = <[u8; 64]>::decode(input)?;
+        // `s` is little-endian and must be less than the group order, which is
+        // below 2^253.
+        if bytes[63] & 0b1110_0000 != 0 {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(Signature::from_bytes(&bytes))
+    }
+}
+
+impl Terminated for Signature {}
+
+impl FixedLength for Signature {
+    const ENCODING_LENGTH: usize = 64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::ed25519_dalek::{Signer, SigningKey, Verifier};
+
+    #[test]
+    fn encode_decode() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let key = signing_key.verifying_key();
+        let bytes = key.encode().unwrap();
+        assert_eq!(bytes, key.as_bytes().to_vec());
+        assert_eq!(key.encoding_length().unwrap(), 32);
+        assert_eq!(VerifyingKey::decode(bytes.as_slice()).unwrap(), key);
+
+        let signature = signing_key.sign(b"message");
+        let bytes = signature.encode().unwrap();
+        assert_eq!(bytes.len(), 64);
+        let decoded = Signature::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, signature);
+        assert!(key.verify(b"message", &decoded).is_ok());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        // The y-coordinate 2 has no corresponding x-coordinate.
+        let mut bytes = [0; 32];
+        bytes[0] = 2;
+        assert!(matches!(
+            VerifyingKey::decode(&bytes[..]),
+            Err(Error::InvalidKey)
+        ));
+
+        let mut bytes = [0; 64];
+        bytes[63] = 0x20;
+        assert!(matches!(
+            Signature::decode(&bytes[..]),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
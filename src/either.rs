@@ -0,0 +1,115 @@
+//! Encoding support for `Either`, using the `either` crate.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::either::Either;
+
+impl<L: Encode, R: Encode> Encode for Either<L, R> {
+    const SENSITIVE: bool = L::SENSITIVE || R::SENSITIVE;
+
+    /// Encodes a 0 byte followed by the left value, or a 1 byte followed by
+    /// the right value.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match self {
+            Either::Left(value) => {
+                dest.write_all(&[0])?;
+                value.encode_into(dest)
+            }
+            Either::Right(value) => {
+                dest.write_all(&[1])?;
+                value.encode_into(dest)
+            }
+        }
+    }
+
+    /// Returns 1 plus the encoding length of the inner value.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        match self {
+            Either::Left(value) => Ok(1 + value.encoding_length()?),
+            Either::Right(value) => Ok(1 + value.encoding_length()?),
+        }
+    }
+}
+
+impl<L: Decode, R: Decode> Decode for Either<L, R> {
+    const SENSITIVE: bool = L::SENSITIVE || R::SENSITIVE;
+
+    /// Decodes a 0 byte followed by the left value as `Left`, or a 1 byte
+    /// followed by the right value as `Right`. Errors for all other values.
+    #[inline]
+    fn decode<I: Read>(mut input: I) -> Result<Self> {
+        let mut buf = [0; 1];
+        input.read_exact(&mut buf[..])?;
+        let [byte] = buf;
+
+        match byte {
+            0 => Ok(Either::Left(L::decode(input)?)),
+            1 => Ok(Either::Right(R::decode(input)?)),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+
+    /// Decodes a value as in `decode`. If the decoded variant matches the
+    /// existing one, `decode_into` is called on the inner value.
+    #[inline]
+    fn decode_into<I: Read>(&mut self, mut input: I) -> Result<()> {
+        let mut buf = [0; 1];
+        input.read_exact(&mut buf[..])?;
+        let [byte] = buf;
+
+        match (byte, &mut *self) {
+            (0, Either::Left(value)) => value.decode_into(input)?,
+            (0, _) => *self = Either::Left(L::decode(input)?),
+            (1, Either::Right(value)) => value.decode_into(input)?,
+            (1, _) => *self = Either::Right(R::decode(input)?),
+            (byte, _) => return Err(Error::UnexpectedByte(byte)),
+        }
+
+        Ok(())
+    }
+}
+
+impl<L: Terminated, R: Terminated> Terminated for Either<L, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_either() {
+        let value: Either<u16, bool> = Either::Left(0x1234);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0x12, 0x34]);
+        assert_eq!(value.encoding_length().unwrap(), 3);
+        assert_eq!(
+            Either::<u16, bool>::decode(bytes.as_slice()).unwrap(),
+            value
+        );
+
+        let value: Either<u16, bool> = Either::Right(true);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![1, 1]);
+        assert_eq!(
+            Either::<u16, bool>::decode(bytes.as_slice()).unwrap(),
+            value
+        );
+
+        assert!(matches!(
+            Either::<u16, bool>::decode(&[2, 0][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+    }
+
+    #[test]
+    fn decode_into_either() {
+        let mut value: Either<u16, bool> = Either::Left(1);
+        value.decode_into(&[0, 0, 5][..]).unwrap();
+        assert_eq!(value, Either::Left(5));
+        value.decode_into(&[1, 0][..]).unwrap();
+        assert_eq!(value, Either::Right(false));
+        value.decode_into(&[0, 0, 6][..]).unwrap();
+        assert_eq!(value, Either::Left(6));
+    }
+}
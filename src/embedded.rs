@@ -0,0 +1,211 @@
+//! Encoding and decoding over `embedded-io` readers and writers.
+//!
+//! Firmware usually talks to peripherals such as UARTs and radios through the
+//! `embedded_io` traits rather than `std::io`. The functions in this module
+//! encode and decode directly over those traits, so `ed` types can be sent to
+//! and received from peripherals without writing an adapter for each one.
+//!
+//! With the `embedded-io-async` feature, the `*_async` functions do the same
+//! over the async traits from `embedded_io_async`. Since decoding is
+//! synchronous, async decoding first reads the full encoding (whose length
+//! must be known) into a buffer.
+
+use super::{wipe, Decode, Encode, FixedLength, Result};
+use embedded_io::{ErrorKind, ReadExactError};
+use std::io;
+
+/// Converts an `embedded_io` error into an `std::io` error of the equivalent
+/// kind.
+pub fn to_io_error<E: embedded_io::Error>(err: E) -> io::Error {
+    let kind = match err.kind() {
+        ErrorKind::NotFound => io::ErrorKind::NotFound,
+        ErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+        ErrorKind::ConnectionRefused => io::ErrorKind::ConnectionRefused,
+        ErrorKind::ConnectionReset => io::ErrorKind::ConnectionReset,
+        ErrorKind::ConnectionAborted => io::ErrorKind::ConnectionAborted,
+        ErrorKind::NotConnected => io::ErrorKind::NotConnected,
+        ErrorKind::AddrInUse => io::ErrorKind::AddrInUse,
+        ErrorKind::AddrNotAvailable => io::ErrorKind::AddrNotAvailable,
+        ErrorKind::BrokenPipe => io::ErrorKind::BrokenPipe,
+        ErrorKind::AlreadyExists => io::ErrorKind::AlreadyExists,
+        ErrorKind::InvalidInput => io::ErrorKind::InvalidInput,
+        ErrorKind::InvalidData => io::ErrorKind::InvalidData,
+        ErrorKind::TimedOut => io::ErrorKind::TimedOut,
+        ErrorKind::Interrupted => io::ErrorKind::Interrupted,
+        ErrorKind::Unsupported => io::ErrorKind::Unsupported,
+        ErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+        ErrorKind::WriteZero => io::ErrorKind::WriteZero,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(kind, format!("{:?}", err))
+}
+
+fn read_exact_error<E: embedded_io::Error>(err: ReadExactError<E>) -> io::Error {
+    match err {
+        ReadExactError::UnexpectedEof => io::ErrorKind::UnexpectedEof.into(),
+        ReadExactError::Other(err) => to_io_error(err),
+    }
+}
+
+/// Wraps an `embedded_io` reader or writer so it can be used as an
+/// `std::io` reader or writer.
+pub struct Embedded<T>(pub T);
+
+impl<T: embedded_io::Read> io::Read for Embedded<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(to_io_error)
+    }
+}
+
+impl<T: embedded_io::Write> io::Write for Embedded<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(to_io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(to_io_error)
+    }
+}
+
+/// Writes the encoding of the value to an `embedded_io` writer.
+pub fn encode_into<T, W>(value: &T, dest: &mut W) -> Result<()>
+where
+    T: Encode + ?Sized,
+    W: embedded_io::Write,
+{
+    value.encode_into(&mut Embedded(dest))
+}
+
+/// Decodes a value from an `embedded_io` reader.
+pub fn decode<T, R>(input: R) -> Result<T>
+where
+    T: Decode,
+    R: embedded_io::Read,
+{
+    T::decode(Embedded(input))
+}
+
+/// Encodes the value and writes it to an async `embedded_io_async` writer.
+#[cfg(feature = "embedded-io-async")]
+pub async fn encode_async<T, W>(value: &T, dest: &mut W) -> Result<()>
+where
+    T: Encode + ?Sized,
+    W: embedded_io_async::Write,
+{
+    let mut bytes = value.encode()?;
+    let res = dest.write_all(&bytes).await.map_err(to_io_error);
+    wipe::wipe(&mut bytes, T::SENSITIVE);
+    Ok(res?)
+}
+
+/// Reads exactly `length` bytes from an async `embedded_io_async` reader and
+/// decodes a value from them.
+#[cfg(feature = "embedded-io-async")]
+pub async fn decode_async_exact<T, R>(input: &mut R, length: usize) -> Result<T>
+where
+    T: Decode,
+    R: embedded_io_async::Read,
+{
+    let mut bytes = vec![0; length];
+    let res = match input.read_exact(&mut bytes).await {
+        Ok(()) => T::decode(bytes.as_slice()),
+        Err(err) => Err(read_exact_error(err).into()),
+    };
+    wipe::wipe(&mut bytes, T::SENSITIVE);
+    res
+}
+
+/// Decodes a fixed-length value from an async `embedded_io_async` reader.
+#[cfg(feature = "embedded-io-async")]
+pub async fn decode_async<T, R>(input: &mut R) -> Result<T>
+where
+    T: Decode + FixedLength,
+    R: embedded_io_async::Read,
+{
+    decode_async_exact(input, T::ENCODING_LENGTH).await
+}
+
+/// Reads exactly enough bytes from an `embedded_io` reader to decode a
+/// fixed-length value, using `read_exact` rather than reading byte by byte.
+pub fn decode_fixed<T, R>(mut input: R) -> Result<T>
+where
+    T: Decode + FixedLength,
+    R: embedded_io::Read,
+{
+    let mut bytes = vec![0; T::ENCODING_LENGTH];
+    let res = match input.read_exact(&mut bytes) {
+        Ok(()) => T::decode(bytes.as_slice()),
+        Err(err) => Err(read_exact_error(err).into()),
+    };
+    wipe::wipe(&mut bytes, T::SENSITIVE);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn encode_decode_embedded() {
+        let mut bytes: Vec<u8> = vec![];
+        encode_into(&(0x1234u16, Some(true)), &mut bytes).unwrap();
+        assert_eq!(bytes, vec![0x12, 0x34, 1, 1]);
+
+        let value: (u16, Option<bool>) = decode(bytes.as_slice()).unwrap();
+        assert_eq!(value, (0x1234, Some(true)));
+        let value: (u16, bool) = decode_fixed(&bytes[1..]).unwrap();
+        assert_eq!(value, (0x3401, true));
+
+        let err = decode_fixed::<u64, _>(bytes.as_slice()).unwrap_err();
+        assert!(matches!(err, Error::IOError(err) if err.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn error_kinds() {
+        let err = to_io_error(ErrorKind::TimedOut);
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        fn raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+
+        let waker = unsafe { Waker::from_raw(raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[cfg(feature = "embedded-io-async")]
+    #[test]
+    fn encode_decode_async() {
+        let mut bytes: Vec<u8> = vec![];
+        block_on(encode_async(&(7u32, false), &mut bytes)).unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 7, 0]);
+
+        let mut input = bytes.as_slice();
+        let value: (u32, bool) = block_on(decode_async(&mut input)).unwrap();
+        assert_eq!(value, (7, false));
+
+        let mut input = &bytes[..3];
+        assert!(block_on(decode_async::<u32, _>(&mut input)).is_err());
+
+        let mut input = &bytes[..];
+        let value: Vec<u8> = block_on(decode_async_exact(&mut input, 4)).unwrap();
+        assert_eq!(value, vec![0, 0, 0, 7]);
+    }
+}
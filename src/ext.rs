@@ -0,0 +1,396 @@
+//! Convenience extension traits for [`Encode`](../trait.Encode.html) and
+//! [`Decode`](../trait.Decode.html) types: converting encodings to and from
+//! text, and decoding whole buffers, streams of values or with resource
+//! limits.
+
+use super::io::Read;
+#[cfg(feature = "std")]
+use super::limits::DecodeOptions;
+use super::wipe::wipe;
+use super::{next_element, Decode, Encode, Error, Result};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+const HEX_CHARS: &[u8; 16] = b"0123456789abcdef";
+
+/// Extension methods available on every [`Encode`](../trait.Encode.html)
+/// type, for producing text representations of the encoding.
+pub trait EncodeExt: Encode {
+    /// Returns the encoding of the value as a lowercase hex string.
+    fn encode_hex(&self) -> Result<String> {
+        let mut bytes = self.encode()?;
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes.iter() {
+            hex.push(HEX_CHARS[(byte >> 4) as usize] as char);
+            hex.push(HEX_CHARS[(byte & 0x0f) as usize] as char);
+        }
+        wipe(&mut bytes, Self::SENSITIVE);
+        Ok(hex)
+    }
+
+    /// Returns the encoding of the value as a standard, padded base64 string.
+    #[cfg(feature = "base64")]
+    fn encode_base64(&self) -> Result<String> {
+        use base64::Engine;
+
+        let mut bytes = self.encode()?;
+        let b64 = base64::engine::general_purpose::STANDARD.encode(&bytes);
+        wipe(&mut bytes, Self::SENSITIVE);
+        Ok(b64)
+    }
+
+    /// Returns an adapter which formats the encoding of the value as hex,
+    /// for use in `Debug` or `Display` contexts such as log statements.
+    fn hex_debug(&self) -> HexDebug<'_, Self> {
+        HexDebug::new(self)
+    }
+}
+
+impl<T: Encode + ?Sized> EncodeExt for T {}
+
+/// Extension methods available on every [`Decode`](../trait.Decode.html)
+/// type, for decoding values from text representations of their encoding or
+/// with resource limits.
+pub trait DecodeExt: Decode {
+    /// Decodes a value from a hex string. Both uppercase and lowercase digits
+    /// are accepted.
+    ///
+    /// Errors with `Error::InvalidHex` if the string has an odd length or
+    /// contains a non-hex character, and with `Error::TrailingBytes` if it
+    /// encodes more bytes than the value was decoded from.
+    fn decode_hex(hex: &str) -> Result<Self> {
+        let mut bytes = hex_to_bytes(hex)?;
+        let value = Self::decode_all(bytes.as_slice());
+        wipe(&mut bytes, Self::SENSITIVE);
+        value
+    }
+
+    /// Decodes a value from a standard, padded base64 string. Errors with
+    /// `Error::InvalidBase64` if the string isn't valid base64, and with
+    /// `Error::TrailingBytes` if it encodes more bytes than the value was
+    /// decoded from.
+    #[cfg(feature = "base64")]
+    fn decode_base64(b64: &str) -> Result<Self> {
+        use base64::Engine;
+
+        let mut bytes = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|_| Error::InvalidBase64)?;
+        let value = Self::decode_all(bytes.as_slice());
+        wipe(&mut bytes, Self::SENSITIVE);
+        value
+    }
+
+    /// Decodes a value from the bytes, erroring with `Error::TrailingBytes`
+    /// if any bytes remain after it.
+    ///
+    /// For canonical encodings, this ensures that no two distinct inputs
+    /// decode to the same value.
+    fn decode_all(mut bytes: &[u8]) -> Result<Self> {
+        let value = Self::decode(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(Error::TrailingBytes(bytes.len()));
+        }
+        Ok(value)
+    }
+
+    /// Decodes a value from the bytes into `self` as in
+    /// [`decode_into`](../trait.Decode.html#method.decode_into), erroring with
+    /// `Error::TrailingBytes` if any bytes remain after it.
+    fn decode_into_all(&mut self, mut bytes: &[u8]) -> Result<()> {
+        self.decode_into(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(Error::TrailingBytes(bytes.len()));
+        }
+        Ok(())
+    }
+
+    /// Decodes a value from the start of the bytes, returning it along with
+    /// the number of bytes it was decoded from.
+    ///
+    /// This allows several values to be decoded one after another from the
+    /// same buffer:
+    ///
+    /// ```rust
+    /// use ed::DecodeExt;
+    ///
+    /// let bytes = [0, 1, 2, 0, 0, 0, 3];
+    /// let (a, n) = u16::decode_from(&bytes).unwrap();
+    /// let (b, m) = u8::decode_from(&bytes[n..]).unwrap();
+    /// let (c, _) = u32::decode_from(&bytes[n + m..]).unwrap();
+    /// assert_eq!((a, b, c), (1, 2, 3));
+    /// ```
+    fn decode_from(bytes: &[u8]) -> Result<(Self, usize)> {
+        let mut rest = bytes;
+        let value = Self::decode(&mut rest)?;
+        Ok((value, bytes.len() - rest.len()))
+    }
+
+    /// Decodes the next value from a stream of values, returning `None` if
+    /// the stream ended cleanly before the value started.
+    ///
+    /// If the stream ends after the value started, the value was truncated,
+    /// and an error is returned for which
+    /// [`Error::is_unexpected_eof`](../enum.Error.html#method.is_unexpected_eof)
+    /// is true.
+    ///
+    /// ```rust
+    /// use ed::DecodeExt;
+    ///
+    /// let mut stream = &[0, 1, 0, 2, 0][..];
+    /// assert_eq!(u16::decode_next(&mut stream).unwrap(), Some(1));
+    /// assert_eq!(u16::decode_next(&mut stream).unwrap(), Some(2));
+    /// assert!(u16::decode_next(&mut stream).unwrap_err().is_unexpected_eof());
+    /// assert_eq!(u16::decode_next(&mut stream).unwrap(), None);
+    /// ```
+    ///
+    /// Since the end of the stream is detected by trying to read the first
+    /// byte of the value, values with an empty encoding are never returned:
+    /// the byte read ahead is rejected with `Error::UnexpectedByte` instead.
+    fn decode_next<R: Read>(mut input: R) -> Result<Option<Self>> {
+        match next_element(&mut input)? {
+            Some(mut input) => {
+                let value = Self::decode(&mut input)?;
+                input.check_consumed()?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes a value from the reader, enforcing the limits in `options`.
+    /// See [`DecodeOptions`](../limits/struct.DecodeOptions.html).
+    #[cfg(feature = "std")]
+    fn decode_with<R: Read>(input: R, options: &DecodeOptions) -> Result<Self> {
+        options.decode(input)
+    }
+}
+
+impl<T: Decode> DecodeExt for T {}
+
+/// A formatting adapter which prints the encoding of the wrapped value as hex.
+///
+/// When constructed with [`truncated`](#method.truncated), at most
+/// `max_bytes` bytes are printed, followed by the total length of the
+/// encoding. See [`fmt_encoding`](fn.fmt_encoding.html).
+pub struct HexDebug<'a, T: ?Sized> {
+    value: &'a T,
+    max_bytes: Option<usize>,
+}
+
+impl<'a, T: Encode + ?Sized> HexDebug<'a, T> {
+    /// Creates an adapter which prints the full encoding of `value`.
+    pub fn new(value: &'a T) -> Self {
+        HexDebug {
+            value,
+            max_bytes: None,
+        }
+    }
+
+    /// Creates an adapter which prints at most `max_bytes` bytes of the
+    /// encoding of `value`.
+    pub fn truncated(value: &'a T, max_bytes: usize) -> Self {
+        HexDebug {
+            value,
+            max_bytes: Some(max_bytes),
+        }
+    }
+}
+
+impl<'a, T: Encode + ?Sized> fmt::Debug for HexDebug<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_encoding(self.value, f, self.max_bytes)
+    }
+}
+
+impl<'a, T: Encode + ?Sized> fmt::Display for HexDebug<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_encoding(self.value, f, self.max_bytes)
+    }
+}
+
+/// Writes the encoding of `value` to the formatter as lowercase hex.
+///
+/// If `max_bytes` is `Some` and the encoding is longer than the limit, only
+/// the first `max_bytes` bytes are written, followed by `...` and the total
+/// length, e.g. `0102... (40 bytes)`. The value is encoded before anything is
+/// written, so if encoding fails only an `<encoding error: ...>` marker is
+/// written in place of the hex, rather than failing the formatter. Errors
+/// from the formatter itself are returned.
+pub fn fmt_encoding<T: Encode + ?Sized>(
+    value: &T,
+    f: &mut fmt::Formatter,
+    max_bytes: Option<usize>,
+) -> fmt::Result {
+    let mut bytes = match value.encode() {
+        Ok(bytes) => bytes,
+        Err(err) => return write!(f, "<encoding error: {}>", err),
+    };
+
+    let res = write_hex(&bytes, f, max_bytes.unwrap_or(usize::MAX));
+    wipe(&mut bytes, T::SENSITIVE);
+    res
+}
+
+fn write_hex(bytes: &[u8], f: &mut fmt::Formatter, max_bytes: usize) -> fmt::Result {
+    for byte in bytes.iter().take(max_bytes) {
+        write!(f, "{:02x}", byte)?;
+    }
+    if bytes.len() > max_bytes {
+        write!(f, "... ({} bytes)", bytes.len())?;
+    }
+    Ok(())
+}
+
+pub(crate) fn hex_to_bytes(hex: &str) -> Result<Vec<u8>> {
+    let pairs = hex.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err(Error::InvalidHex);
+    }
+
+    pairs
+        .map(|pair| Ok(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Result<u8> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        _ => Err(Error::InvalidHex),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::Write;
+
+    #[test]
+    fn encode_hex() {
+        assert_eq!(0x12abu16.encode_hex().unwrap(), "12ab");
+        assert_eq!(vec![0u8, 255].encode_hex().unwrap(), "00ff");
+    }
+
+    #[test]
+    fn decode_hex() {
+        assert_eq!(u16::decode_hex("12ab").unwrap(), 0x12ab);
+        assert_eq!(u16::decode_hex("12AB").unwrap(), 0x12ab);
+    }
+
+    #[test]
+    fn decode_hex_invalid() {
+        assert!(matches!(u16::decode_hex("12a"), Err(Error::InvalidHex)));
+        assert!(matches!(u16::decode_hex("12ag"), Err(Error::InvalidHex)));
+        assert!(matches!(
+            u16::decode_hex("12ab00"),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+
+    #[test]
+    fn decode_all() {
+        assert_eq!(u16::decode_all(&[0x12, 0xab]).unwrap(), 0x12ab);
+        assert!(matches!(
+            u16::decode_all(&[0x12, 0xab, 0, 0]),
+            Err(Error::TrailingBytes(2))
+        ));
+        assert!(u16::decode_all(&[0x12]).is_err());
+
+        let mut value = (0u8, 0u8);
+        value.decode_into_all(&[1, 2]).unwrap();
+        assert_eq!(value, (1, 2));
+        assert!(matches!(
+            value.decode_into_all(&[3, 4, 5]),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+
+    #[test]
+    fn decode_from() {
+        let bytes = [0x12, 0xab, 1, 7];
+        assert_eq!(u16::decode_from(&bytes).unwrap(), (0x12ab, 2));
+        assert_eq!(
+            Option::<u8>::decode_from(&bytes[2..]).unwrap(),
+            (Some(7), 2)
+        );
+        assert_eq!(Vec::<u8>::decode_from(&bytes).unwrap().1, 4);
+        assert!(u32::decode_from(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn decode_next() {
+        let mut stream = &[1, 7, 0, 1][..];
+        assert_eq!(
+            Option::<u8>::decode_next(&mut stream).unwrap(),
+            Some(Some(7))
+        );
+        assert_eq!(Option::<u8>::decode_next(&mut stream).unwrap(), Some(None));
+        let err = Option::<u8>::decode_next(&mut stream).unwrap_err();
+        assert!(err.is_unexpected_eof());
+        assert_eq!(Option::<u8>::decode_next(&mut stream).unwrap(), None);
+
+        assert!(!Error::UnexpectedByte(0).is_unexpected_eof());
+    }
+
+    #[test]
+    fn hex_debug() {
+        let value = (0x1234u16, vec![0xabu8, 0xcd]);
+        assert_eq!(format!("{:?}", value.hex_debug()), "1234abcd");
+        assert_eq!(format!("{}", HexDebug::new(&value)), "1234abcd");
+        assert_eq!(
+            format!("{:?}", HexDebug::truncated(&value, 2)),
+            "1234... (4 bytes)"
+        );
+        assert_eq!(format!("{:?}", HexDebug::truncated(&value, 4)), "1234abcd");
+    }
+
+    struct Unencodable;
+
+    impl Encode for Unencodable {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            dest.write_all(&[0xab])?;
+            Err(Error::UnencodableVariant)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            Ok(2)
+        }
+    }
+
+    #[test]
+    fn hex_debug_errors() {
+        assert_eq!(
+            format!("{:?}", HexDebug::new(&Unencodable)),
+            "<encoding error: Unencodable variant>"
+        );
+
+        struct Failing;
+
+        impl fmt::Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        assert!(fmt::write(&mut Failing, format_args!("{}", HexDebug::new(&1u8))).is_err());
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn base64_roundtrip() {
+        let b64 = 0x12345678u32.encode_base64().unwrap();
+        assert_eq!(b64, "EjRWeA==");
+        assert_eq!(u32::decode_base64(&b64).unwrap(), 0x12345678);
+        assert!(matches!(
+            u32::decode_base64("EjRWeA="),
+            Err(Error::InvalidBase64)
+        ));
+        assert!(matches!(
+            u32::decode_base64("EjRWeAA="),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+}
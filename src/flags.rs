@@ -0,0 +1,216 @@
+//! Booleans packed into bits.
+//!
+//! A `bool` is encoded as a whole byte, so a struct with many `bool` fields
+//! wastes most of its encoding. [`Flags<N>`](struct.Flags.html) instead packs
+//! `N` booleans (at most 128) into `ceil(N / 8)` bytes.
+//!
+//! The flags are encoded as a big-endian integer in which flag `i` is bit
+//! `i`, so flag 0 is the lowest bit of the last byte. The unused high bits of
+//! the first byte must be zero, and decoding errors with
+//! `Error::UnexpectedByte` otherwise, so every set of flags has exactly one
+//! encoding.
+//!
+//! ```rust
+//! use ed::{flags::Flags, Decode, Encode};
+//!
+//! let mut flags = Flags::<10>::new();
+//! flags.set(0, true);
+//! flags.set(9, true);
+//! assert_eq!(flags.encode().unwrap(), vec![0b10, 0b1]);
+//! assert!(flags.get(9) && !flags.get(1));
+//!
+//! assert!(Flags::<10>::decode(&[0b100, 0][..]).is_err());
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::fmt;
+
+/// Up to 128 booleans, encoded as `ceil(N / 8)` bytes.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Flags<const N: usize>(u128);
+
+impl<const N: usize> Flags<N> {
+    /// The encoding length in bytes. Fails to compile if `N` is over 128.
+    const LENGTH: usize = {
+        assert!(N <= 128, "`Flags` can hold at most 128 flags");
+        N.div_ceil(8)
+    };
+
+    /// Returns flags which are all unset.
+    pub fn new() -> Self {
+        // Evaluating the length checks `N` at compile time.
+        let _ = Self::LENGTH;
+        Flags(0)
+    }
+
+    /// Returns the flags from the bits of an integer, in which flag `i` is
+    /// bit `i`, or `None` if any bit at or above `N` is set.
+    pub fn from_bits(bits: u128) -> Option<Self> {
+        let _ = Self::LENGTH;
+        if N < 128 && bits >> N != 0 {
+            return None;
+        }
+        Some(Flags(bits))
+    }
+
+    /// Returns the flags as the bits of an integer, in which flag `i` is bit
+    /// `i`.
+    pub fn bits(self) -> u128 {
+        self.0
+    }
+
+    /// Returns whether flag `i` is set.
+    ///
+    /// Panics if `i` is not less than `N`.
+    pub fn get(self, i: usize) -> bool {
+        assert!(i < N, "flag index {} out of range for {} flags", i, N);
+        self.0 >> i & 1 == 1
+    }
+
+    /// Sets or clears flag `i`.
+    ///
+    /// Panics if `i` is not less than `N`.
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < N, "flag index {} out of range for {} flags", i, N);
+        if value {
+            self.0 |= 1 << i;
+        } else {
+            self.0 &= !(1 << i);
+        }
+    }
+
+    /// Returns the number of flags which are set.
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns true if no flags are set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the flags, in order.
+    pub fn iter(self) -> impl Iterator<Item = bool> {
+        (0..N).map(move |i| self.get(i))
+    }
+
+    /// Returns the flags as an array of booleans.
+    pub fn to_array(self) -> [bool; N] {
+        core::array::from_fn(|i| self.get(i))
+    }
+}
+
+impl<const N: usize> From<[bool; N]> for Flags<N> {
+    fn from(values: [bool; N]) -> Self {
+        let mut flags = Flags::new();
+        for (i, value) in values.iter().enumerate() {
+            flags.set(i, *value);
+        }
+        flags
+    }
+}
+
+impl<const N: usize> fmt::Debug for Flags<N> {
+    /// Formats the flags as binary digits, with flag 0 last.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Flags({:0width$b})", self.0, width = N)
+    }
+}
+
+impl<const N: usize> Encode for Flags<N> {
+    /// Encodes the flags as `ceil(N / 8)` big-endian bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let bytes = self.0.to_be_bytes();
+        dest.write_all(&bytes[bytes.len() - Self::LENGTH..])?;
+        Ok(())
+    }
+
+    /// Always returns Ok(ceil(N / 8)).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(Self::LENGTH)
+    }
+}
+
+impl<const N: usize> Decode for Flags<N> {
+    /// Decodes the flags from `ceil(N / 8)` big-endian bytes. Errors with
+    /// `Error::UnexpectedByte` if any of the unused high bits of the first
+    /// byte are set.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut bytes = [0; 16];
+        let offset = bytes.len() - Self::LENGTH;
+        input.read_exact(&mut bytes[offset..])?;
+        Flags::from_bits(u128::from_be_bytes(bytes)).ok_or(Error::UnexpectedByte(bytes[offset]))
+    }
+}
+
+impl<const N: usize> Terminated for Flags<N> {}
+
+impl<const N: usize> FixedLength for Flags<N> {
+    const ENCODING_LENGTH: usize = Self::LENGTH;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let mut flags = Flags::<12>::from([
+            true, false, true, false, false, false, false, false, false, false, false, true,
+        ]);
+        assert_eq!(flags.bits(), 0b1000_0000_0101);
+        assert_eq!(flags.count(), 3);
+        flags.set(2, false);
+        flags.set(3, true);
+        assert_eq!(flags.bits(), 0b1000_0000_1001);
+        assert_eq!(
+            flags.iter().take(4).collect::<Vec<_>>(),
+            vec![true, false, false, true]
+        );
+        assert_eq!(Flags::from(flags.to_array()), flags);
+        assert!(Flags::<12>::new().is_empty());
+        assert!(Flags::<12>::from_bits(1 << 12).is_none());
+        assert_eq!(
+            format!("{:?}", Flags::<4>::from_bits(0b10).unwrap()),
+            "Flags(0010)"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn out_of_range() {
+        Flags::<8>::new().get(8);
+    }
+
+    #[test]
+    fn encode_decode() {
+        let flags = Flags::<3>::from([true, true, false]);
+        assert_eq!(flags.encode().unwrap(), vec![0b011]);
+        assert_eq!(flags.encoding_length().unwrap(), 1);
+        assert_eq!(Flags::<3>::decode(&[0b011][..]).unwrap(), flags);
+
+        let flags = Flags::<128>::from_bits(u128::MAX).unwrap();
+        assert_eq!(flags.encode().unwrap(), vec![0xff; 16]);
+        assert_eq!(Flags::<128>::decode(&[0xff; 16][..]).unwrap(), flags);
+
+        assert_eq!(<Flags<9> as FixedLength>::ENCODING_LENGTH, 2);
+        assert_eq!(Flags::<0>::new().encode().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn decode_padding() {
+        assert!(matches!(
+            Flags::<3>::decode(&[0b1011][..]),
+            Err(Error::UnexpectedByte(0b1011))
+        ));
+        assert!(matches!(
+            Flags::<9>::decode(&[0x02, 0][..]),
+            Err(Error::UnexpectedByte(0x02))
+        ));
+        assert!(Flags::<9>::decode(&[0x01, 0xff][..]).is_ok());
+    }
+}
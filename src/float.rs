@@ -0,0 +1,212 @@
+//! Floating point numbers with a deterministic encoding.
+//!
+//! `ed` provides no encodings for `f32` or `f64`, since floats have values
+//! which are equal but have distinct bit patterns, which could otherwise
+//! encode the same value in different ways. [`F32Bits`](struct.F32Bits.html)
+//! and [`F64Bits`](struct.F64Bits.html) opt into an encoding of the IEEE-754
+//! bit pattern as big-endian bytes, with every NaN encoded as the same
+//! canonical quiet NaN. Decoding errors with `Error::NonCanonicalNan` for any
+//! other NaN, so that every encoding decodes to a distinct value.
+//!
+//! Other values are encoded exactly, so `0.0` and `-0.0` have different
+//! encodings. For the same reason, the wrappers compare values by their
+//! (canonicalized) bits rather than numerically, which makes them `Eq`,
+//! `Ord` and `Hash`.
+//!
+//! ```rust
+//! use ed::{float::F64Bits, Decode, Encode};
+//!
+//! assert_eq!(F64Bits(1.5).encode().unwrap(), vec![0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+//! assert_eq!(
+//!     F64Bits(-f64::NAN).encode().unwrap(),
+//!     F64Bits(f64::NAN).encode().unwrap()
+//! );
+//! assert_eq!(F64Bits::decode(&[0x3f, 0xf8, 0, 0, 0, 0, 0, 0][..]).unwrap(), F64Bits(1.5));
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+
+macro_rules! float_impl {
+    ($name:ident, $float:ty, $bits:ty, $nan:expr, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $name(pub $float);
+
+        impl $name {
+            /// The bit pattern all NaNs are encoded as.
+            pub const CANONICAL_NAN: $bits = $nan;
+
+            /// Returns the wrapped float.
+            pub fn into_inner(self) -> $float {
+                self.0
+            }
+
+            /// Returns the bits of the float, with any NaN replaced by
+            /// [`CANONICAL_NAN`](#associatedconstant.CANONICAL_NAN).
+            pub fn to_bits(self) -> $bits {
+                if self.0.is_nan() {
+                    Self::CANONICAL_NAN
+                } else {
+                    self.0.to_bits()
+                }
+            }
+        }
+
+        impl From<$float> for $name {
+            fn from(value: $float) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for $float {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.to_bits() == other.to_bits()
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                let canonical = |value: &Self| <$float>::from_bits(value.to_bits());
+                canonical(self).total_cmp(&canonical(other))
+            }
+        }
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.to_bits().hash(state);
+            }
+        }
+
+        impl Encode for $name {
+            #[doc = "Encodes the bits of the float as big-endian bytes, with any"]
+            #[doc = " NaN encoded as the canonical NaN."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                dest.write_all(&self.to_bits().to_be_bytes())?;
+                Ok(())
+            }
+
+            #[doc = "Always returns the size of the float in bytes."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(core::mem::size_of::<$float>())
+            }
+        }
+
+        impl Decode for $name {
+            #[doc = "Decodes the bits of the float from big-endian bytes. Errors"]
+            #[doc = " with `Error::NonCanonicalNan` for a NaN other than the"]
+            #[doc = " canonical NaN."]
+            #[inline]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let mut bytes = [0; core::mem::size_of::<$float>()];
+                input.read_exact(&mut bytes)?;
+                let value = <$float>::from_bits(<$bits>::from_be_bytes(bytes));
+                if value.is_nan() && value.to_bits() != Self::CANONICAL_NAN {
+                    return Err(Error::NonCanonicalNan);
+                }
+                Ok($name(value))
+            }
+        }
+
+        impl Terminated for $name {}
+
+        impl FixedLength for $name {
+            const ENCODING_LENGTH: usize = core::mem::size_of::<$float>();
+        }
+    };
+}
+
+float_impl!(
+    F32Bits,
+    f32,
+    u32,
+    0x7fc0_0000,
+    "An `f32` encoded as its bits."
+);
+float_impl!(
+    F64Bits,
+    f64,
+    u64,
+    0x7ff8_0000_0000_0000,
+    "An `f64` encoded as its bits."
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let cases: &[(f32, [u8; 4])] = &[
+            (0.0, [0, 0, 0, 0]),
+            (-0.0, [0x80, 0, 0, 0]),
+            (1.0, [0x3f, 0x80, 0, 0]),
+            (-2.5, [0xc0, 0x20, 0, 0]),
+            (f32::INFINITY, [0x7f, 0x80, 0, 0]),
+            (f32::NAN, [0x7f, 0xc0, 0, 0]),
+        ];
+        for (value, bytes) in cases.iter() {
+            let value = F32Bits(*value);
+            assert_eq!(value.encode().unwrap(), bytes.to_vec());
+            assert_eq!(value.encoding_length().unwrap(), 4);
+            assert_eq!(F32Bits::decode(&bytes[..]).unwrap(), value);
+        }
+
+        let value = F64Bits(core::f64::consts::PI);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes.len(), F64Bits::ENCODING_LENGTH);
+        assert_eq!(
+            F64Bits::decode(bytes.as_slice()).unwrap().0,
+            core::f64::consts::PI
+        );
+    }
+
+    #[test]
+    fn canonical_nan() {
+        let payload = f32::from_bits(0x7f80_0001);
+        let negative = f64::from_bits(0xfff8_0000_0000_0000);
+        assert!(payload.is_nan() && negative.is_nan());
+
+        assert_eq!(F32Bits(payload).encode().unwrap(), vec![0x7f, 0xc0, 0, 0]);
+        assert_eq!(
+            F64Bits(negative).encode().unwrap(),
+            F64Bits(f64::NAN).encode().unwrap()
+        );
+        assert_eq!(F32Bits(payload), F32Bits(f32::NAN));
+
+        assert!(matches!(
+            F32Bits::decode(&[0x7f, 0x80, 0, 1][..]),
+            Err(Error::NonCanonicalNan)
+        ));
+        assert!(matches!(
+            F64Bits::decode(&[0xff, 0xf8, 0, 0, 0, 0, 0, 0][..]),
+            Err(Error::NonCanonicalNan)
+        ));
+    }
+
+    #[test]
+    fn ordering() {
+        assert_ne!(F64Bits(0.0), F64Bits(-0.0));
+        assert!(F64Bits(-0.0) < F64Bits(0.0));
+        assert!(F64Bits(f64::INFINITY) < F64Bits(f64::NAN));
+        assert!(F64Bits(-1.0) < F64Bits(1.0));
+    }
+}
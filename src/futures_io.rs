@@ -0,0 +1,36 @@
+//! Async encoding and decoding over the `futures::io` traits.
+//!
+//! The same traits as in [`tokio`](../tokio/index.html), defined over the
+//! runtime-agnostic `AsyncRead` and `AsyncWrite` traits from `futures-io`,
+//! which are implemented by the I/O types of runtimes such as `async-std` and
+//! `smol`, and by `tokio`'s through `tokio-util`'s compat adapters.
+//!
+//! With the `futures-io` feature, `#[derive(Encode)]` and `#[derive(Decode)]`
+//! also implement these traits, for types whose fields all implement them.
+//! Types with fields encoded `with` a custom encoding are left out, since the
+//! custom encoding functions are sync.
+//!
+//! ```rust
+//! use ed::futures_io::{AsyncDecode, AsyncEncode, AsyncRead, AsyncWrite};
+//!
+//! async fn echo<S>(stream: &mut S) -> ed::Result<()>
+//! where
+//!     S: AsyncRead + AsyncWrite + Unpin + Send,
+//! {
+//!     let value = <(u32, Option<u8>)>::decode_async(&mut *stream).await?;
+//!     value.encode_into_async(stream).await
+//! }
+//! ```
+//!
+//! As with `tokio`, only the default [`limits`](../limits/index.html) apply to
+//! async decoding. Bound the length of untrusted input with
+//! `AsyncReadExt::take` instead.
+
+use super::{limits, wipe, Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::futures_util::io::{AsyncReadExt, AsyncWriteExt};
+use core::convert::TryInto;
+use core::future::Future;
+
+pub use ::futures_util::io::{AsyncRead, AsyncWrite};
+
+async_traits!("futures-io");
@@ -0,0 +1,96 @@
+//! Encoding support for `GenericArray`, using the `generic-array` crate.
+//!
+//! A `GenericArray<T, N>` is encoded exactly like the array `[T; N]`: its
+//! elements one after another, with no length prefix. This is the type many
+//! crypto crates use for digests and keys, e.g. `digest::Output<D>`, so these
+//! can be used as fields directly.
+
+// The last 0.14 releases deprecate everything in favor of 1.x, but the crypto
+// crates this support is for still use 0.14.
+#![allow(deprecated)]
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::generic_array::{ArrayLength, GenericArray};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+impl<T: Encode + Terminated, N: ArrayLength<T>> Encode for GenericArray<T, N> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the elements of the array one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(self.as_slice(), dest)
+    }
+
+    /// Returns the sum of the encoding lengths of the elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Decode + Terminated, N: ArrayLength<T>> Decode for GenericArray<T, N> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes `N` elements one after another, in order.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut elements = Vec::with_capacity(N::USIZE);
+        for _ in 0..N::USIZE {
+            elements.push(T::decode(&mut input)?);
+        }
+        // Can't fail since exactly N elements were decoded, but mapping the
+        // error keeps this path free of panics.
+        GenericArray::from_exact_iter(elements).ok_or(Error::LengthOverflow(N::USIZE))
+    }
+
+    /// Decodes `N` elements one after another, in order.
+    ///
+    /// Recursively calls `decode_into` for each element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        T::decode_slice_into(self.as_mut_slice(), input)
+    }
+}
+
+impl<T: Terminated, N: ArrayLength<T>> Terminated for GenericArray<T, N> {}
+
+impl<T: FixedLength, N: ArrayLength<T>> FixedLength for GenericArray<T, N> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH * N::USIZE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::generic_array::typenum::{U3, U4};
+
+    #[test]
+    fn encode_decode() {
+        let bytes: GenericArray<u8, U4> = GenericArray::clone_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(bytes.encode().unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(bytes.encoding_length().unwrap(), 4);
+        assert_eq!(<GenericArray<u8, U4>>::ENCODING_LENGTH, 4);
+        assert_eq!(
+            GenericArray::<u8, U4>::decode(&[1, 2, 3, 4, 5][..]).unwrap(),
+            bytes
+        );
+
+        let words: GenericArray<u16, U3> = GenericArray::clone_from_slice(&[1, 2, 3]);
+        let encoded = words.encode().unwrap();
+        assert_eq!(encoded, [1u16, 2, 3].encode().unwrap());
+        assert_eq!(<GenericArray<u16, U3>>::ENCODING_LENGTH, 6);
+
+        let mut decoded = GenericArray::<u16, U3>::default();
+        decoded.decode_into(encoded.as_slice()).unwrap();
+        assert_eq!(decoded, words);
+        assert!(GenericArray::<u16, U3>::decode(&[0, 1, 0, 2][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+    }
+}
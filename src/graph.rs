@@ -0,0 +1,359 @@
+//! Encoding of object graphs with shared references.
+//!
+//! Wrapping an `Rc<T>` in a [`Shared<T>`](struct.Shared.html) (or an `Arc<T>`
+//! in a [`SharedArc<T>`](struct.SharedArc.html)) opts it in to back-reference
+//! encoding: the first time a given allocation is encoded, its value is
+//! written in full, and every later occurrence of the same allocation is
+//! written as the index of the first one. Decoding reconstructs the sharing,
+//! so the decoded values point to the same allocations in the same places.
+//!
+//! Each shared value is encoded as a tag byte, followed by either the encoding
+//! of the inner value (tag 0) or the `u32` index of an earlier shared value
+//! (tag 1). Indices are assigned in the order values are first encountered.
+//!
+//! Back-references are resolved within a graph scope. Calling
+//! [`encode`](fn.encode.html) or [`decode`](fn.decode.html) (and related
+//! functions) opens a scope for the whole value; otherwise each outermost
+//! shared value opens its own scope, so sharing is only preserved within it.
+//! Values must be decoded the same way they were encoded.
+//!
+//! Reference cycles (possible through interior mutability) cannot be
+//! represented, so encoding fails with `Error::CycleDetected` if a value is
+//! reached again while it is still being encoded.
+
+use super::{limits, Decode, Encode, Error, Result, Terminated};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread::LocalKey;
+
+#[derive(Default)]
+struct EncodeScope {
+    indices: HashMap<usize, u32>,
+    in_progress: HashSet<usize>,
+}
+
+#[derive(Default)]
+struct DecodeScope {
+    values: Vec<Option<Box<dyn Any>>>,
+}
+
+thread_local! {
+    static ENCODE_SCOPE: RefCell<Option<EncodeScope>> = const { RefCell::new(None) };
+    static LENGTH_SCOPE: RefCell<Option<EncodeScope>> = const { RefCell::new(None) };
+    static DECODE_SCOPE: RefCell<Option<DecodeScope>> = const { RefCell::new(None) };
+}
+
+/// Removes a scope when dropped, if it was opened by the current call.
+struct ScopeGuard<S: 'static> {
+    key: &'static LocalKey<RefCell<Option<S>>>,
+    opened: bool,
+}
+
+impl<S: 'static> Drop for ScopeGuard<S> {
+    fn drop(&mut self) {
+        if self.opened {
+            self.key.with(|scope| *scope.borrow_mut() = None);
+        }
+    }
+}
+
+fn open_scope<S: Default + 'static>(key: &'static LocalKey<RefCell<Option<S>>>) -> ScopeGuard<S> {
+    let opened = key.with(|scope| {
+        let mut scope = scope.borrow_mut();
+        if scope.is_none() {
+            *scope = Some(S::default());
+            true
+        } else {
+            false
+        }
+    });
+    ScopeGuard { key, opened }
+}
+
+fn with_scope<S, T>(key: &'static LocalKey<RefCell<Option<S>>>, f: impl FnOnce(&mut S) -> T) -> T {
+    key.with(|scope| {
+        f(scope
+            .borrow_mut()
+            .as_mut()
+            .expect("Graph scope is not open"))
+    })
+}
+
+/// Returns the index of an earlier occurrence of the allocation, or assigns it
+/// a new index and marks it as in progress.
+fn visit(key: &'static LocalKey<RefCell<Option<EncodeScope>>>, ptr: usize) -> Result<Option<u32>> {
+    with_scope(key, |scope| {
+        if scope.in_progress.contains(&ptr) {
+            return Err(Error::CycleDetected);
+        }
+        if let Some(index) = scope.indices.get(&ptr) {
+            return Ok(Some(*index));
+        }
+        let index = scope.indices.len() as u32;
+        scope.indices.insert(ptr, index);
+        scope.in_progress.insert(ptr);
+        Ok(None)
+    })
+}
+
+fn finish_visit(key: &'static LocalKey<RefCell<Option<EncodeScope>>>, ptr: usize) {
+    with_scope(key, |scope| scope.in_progress.remove(&ptr));
+}
+
+macro_rules! shared_impl {
+    ($name:ident, $ptr:ident, $ptr_doc:expr) => {
+        #[doc = "A shared `"]
+        #[doc = $ptr_doc]
+        #[doc = "<T>` which is encoded with back-references to earlier"]
+        #[doc = " occurrences of the same allocation."]
+        #[derive(Debug, Default, PartialEq, Eq)]
+        pub struct $name<T>(pub $ptr<T>);
+
+        impl<T> $name<T> {
+            #[doc = "Moves the value into a new shared allocation."]
+            pub fn new(value: T) -> Self {
+                $name($ptr::new(value))
+            }
+
+            #[doc = "Returns the inner pointer."]
+            pub fn into_inner(self) -> $ptr<T> {
+                self.0
+            }
+
+            #[doc = "Returns true if both values point to the same allocation."]
+            pub fn ptr_eq(&self, other: &Self) -> bool {
+                $ptr::ptr_eq(&self.0, &other.0)
+            }
+
+            fn addr(&self) -> usize {
+                $ptr::as_ptr(&self.0) as *const () as usize
+            }
+        }
+
+        impl<T> Clone for $name<T> {
+            fn clone(&self) -> Self {
+                $name(self.0.clone())
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> From<$ptr<T>> for $name<T> {
+            fn from(ptr: $ptr<T>) -> Self {
+                $name(ptr)
+            }
+        }
+
+        impl<T: Encode> Encode for $name<T> {
+            const SENSITIVE: bool = T::SENSITIVE;
+
+            #[doc = "Encodes a 0 byte followed by the inner value if this is"]
+            #[doc = " the first occurrence of the allocation, otherwise encodes"]
+            #[doc = " a 1 byte followed by the index of the first occurrence."]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                let _guard = open_scope(&ENCODE_SCOPE);
+                let addr = self.addr();
+                match visit(&ENCODE_SCOPE, addr)? {
+                    Some(index) => {
+                        dest.write_all(&[1])?;
+                        index.encode_into(dest)
+                    }
+                    None => {
+                        dest.write_all(&[0])?;
+                        let res = (*self.0).encode_into(dest);
+                        finish_visit(&ENCODE_SCOPE, addr);
+                        res
+                    }
+                }
+            }
+
+            #[doc = "Returns 5 for a back-reference, otherwise 1 plus the"]
+            #[doc = " encoding length of the inner value."]
+            fn encoding_length(&self) -> Result<usize> {
+                let _guard = open_scope(&LENGTH_SCOPE);
+                let addr = self.addr();
+                match visit(&LENGTH_SCOPE, addr)? {
+                    Some(_) => Ok(5),
+                    None => {
+                        let res = (*self.0).encoding_length();
+                        finish_visit(&LENGTH_SCOPE, addr);
+                        Ok(1 + res?)
+                    }
+                }
+            }
+        }
+
+        impl<T: Decode + 'static> Decode for $name<T> {
+            const SENSITIVE: bool = T::SENSITIVE;
+
+            #[doc = "Decodes a new shared value, or resolves a back-reference"]
+            #[doc = " to an earlier one."]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let _guard = open_scope(&DECODE_SCOPE);
+                match u8::decode(&mut input)? {
+                    0 => {
+                        let index = with_scope(&DECODE_SCOPE, |scope| {
+                            scope.values.push(None);
+                            scope.values.len() - 1
+                        });
+                        let value = {
+                            let _depth = limits::descend()?;
+                            $ptr::new(T::decode(input)?)
+                        };
+                        with_scope(&DECODE_SCOPE, |scope| {
+                            if let Some(slot) = scope.values.get_mut(index) {
+                                *slot = Some(Box::new(value.clone()));
+                            }
+                        });
+                        Ok($name(value))
+                    }
+                    1 => {
+                        let index = u32::decode(input)?;
+                        with_scope(&DECODE_SCOPE, |scope| {
+                            match scope.values.get(index as usize) {
+                                Some(Some(value)) => value
+                                    .downcast_ref::<$ptr<T>>()
+                                    .cloned()
+                                    .map($name)
+                                    .ok_or(Error::InvalidBackReference(index)),
+                                Some(None) => Err(Error::CycleDetected),
+                                None => Err(Error::InvalidBackReference(index)),
+                            }
+                        })
+                    }
+                    byte => Err(Error::UnexpectedByte(byte)),
+                }
+            }
+        }
+
+        impl<T: Terminated> Terminated for $name<T> {}
+    };
+}
+
+shared_impl!(Shared, Rc, "Rc");
+shared_impl!(SharedArc, Arc, "Arc");
+
+/// Encodes the value within a single graph scope, so sharing is preserved
+/// across the whole value.
+pub fn encode<T: Encode + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let _length_guard = open_scope(&LENGTH_SCOPE);
+    let _encode_guard = open_scope(&ENCODE_SCOPE);
+    value.encode()
+}
+
+/// Writes the encoding of the value within a single graph scope.
+pub fn encode_into<T: Encode + ?Sized, W: Write>(value: &T, dest: &mut W) -> Result<()> {
+    let _guard = open_scope(&ENCODE_SCOPE);
+    value.encode_into(dest)
+}
+
+/// Returns the encoding length of the value within a single graph scope.
+pub fn encoding_length<T: Encode + ?Sized>(value: &T) -> Result<usize> {
+    let _guard = open_scope(&LENGTH_SCOPE);
+    value.encoding_length()
+}
+
+/// Decodes a value which was encoded with [`encode`](fn.encode.html) or
+/// [`encode_into`](fn.encode_into.html), resolving back-references across
+/// the whole value.
+pub fn decode<T: Decode, R: Read>(input: R) -> Result<T> {
+    let _guard = open_scope(&DECODE_SCOPE);
+    T::decode(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_shared() {
+        let a = Shared::new(0x1234u16);
+        let b = Shared::new(0x5678u16);
+        let value = vec![a.clone(), b.clone(), a.clone(), b, a];
+
+        let bytes = encode(&value).unwrap();
+        assert_eq!(
+            bytes,
+            vec![0, 0x12, 0x34, 0, 0x56, 0x78, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 0]
+        );
+        assert_eq!(encoding_length(&value).unwrap(), bytes.len());
+
+        let decoded: Vec<Shared<u16>> = decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, value);
+        assert!(decoded[0].ptr_eq(&decoded[2]));
+        assert!(decoded[0].ptr_eq(&decoded[4]));
+        assert!(decoded[1].ptr_eq(&decoded[3]));
+        assert!(!decoded[0].ptr_eq(&decoded[1]));
+    }
+
+    #[test]
+    fn nested_shared_arc() {
+        let inner = SharedArc::new(7u8);
+        let outer = SharedArc::new((inner.clone(), inner.clone()));
+        let value = (outer.clone(), outer);
+
+        let bytes = encode(&value).unwrap();
+        assert_eq!(bytes, vec![0, 0, 7, 1, 0, 0, 0, 1, 1, 0, 0, 0, 0]);
+
+        let decoded: (SharedArc<(SharedArc<u8>, SharedArc<u8>)>, _) =
+            decode(bytes.as_slice()).unwrap();
+        assert!(decoded.0.ptr_eq(&decoded.1));
+        let (a, b) = &*decoded.0;
+        assert!(a.ptr_eq(b));
+    }
+
+    #[test]
+    fn outermost_scope() {
+        let a = Shared::new(1u8);
+        let bytes = (a.clone(), a).encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn cycle_detected() {
+        #[derive(Default)]
+        struct Node(RefCell<Option<Shared<Node>>>);
+
+        impl Encode for Node {
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                self.0.encode_into(dest)
+            }
+
+            fn encoding_length(&self) -> Result<usize> {
+                self.0.encoding_length()
+            }
+        }
+
+        let node = Shared::new(Node::default());
+        let cell = &node.0 .0;
+        *cell.borrow_mut() = Some(node.clone());
+        assert!(matches!(encode(&node), Err(Error::CycleDetected)));
+        *cell.borrow_mut() = None;
+    }
+
+    #[test]
+    fn invalid_back_reference() {
+        let bytes = [0, 1, 1, 0, 0, 0, 5];
+        assert!(matches!(
+            decode::<(Shared<u8>, Shared<u8>), _>(&bytes[..]),
+            Err(Error::InvalidBackReference(5))
+        ));
+
+        let bytes = [0, 1, 1, 0, 0, 0, 0];
+        assert!(matches!(
+            decode::<(Shared<u8>, Shared<u16>), _>(&bytes[..]),
+            Err(Error::InvalidBackReference(0))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Digests of canonical encodings, using the `digest` crate.
+//!
+//! Since every value has exactly one encoding, hashing the encoding gives a
+//! stable identifier for the value. [`CanonicalHash`](trait.CanonicalHash.html)
+//! streams the encoding directly into the hasher, without first collecting it
+//! into a `Vec<u8>`.
+//!
+//! A type can declare the digest it is usually hashed with by implementing
+//! [`DefaultDigest`](trait.DefaultDigest.html), or with the derive attribute
+//! `#[ed(digest = "sha2::Sha256")]`:
+//!
+//! ```rust,ignore
+//! use ed::{hash::DefaultDigest, Encode};
+//!
+//! #[derive(Encode)]
+//! #[ed(digest = "sha2::Sha256")]
+//! struct Header {
+//!     height: u64,
+//!     parent: [u8; 32],
+//! }
+//!
+//! let id = Header { height: 1, parent: [0; 32] }.digest()?;
+//! ```
+
+use super::io::{self, Write};
+use super::{Encode, Result};
+use digest::{Digest, Output};
+
+/// A writer which feeds everything written to it into a hasher.
+pub struct DigestWriter<D>(pub D);
+
+impl<D: Digest> Write for DigestWriter<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Digest::update(&mut self.0, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A trait for hashing the encoding of a value, implemented for all types
+/// which implement `Encode`.
+pub trait CanonicalHash: Encode {
+    /// Streams the encoding of the value into a new `D` hasher and returns the
+    /// digest. Errors if the value cannot be encoded.
+    fn canonical_hash<D: Digest>(&self) -> Result<Output<D>> {
+        let mut writer = DigestWriter(D::new());
+        self.encode_into(&mut writer)?;
+        Ok(writer.0.finalize())
+    }
+}
+
+impl<T: Encode + ?Sized> CanonicalHash for T {}
+
+/// A trait for types which have a default digest, usually implemented with
+/// `#[ed(digest = "...")]`.
+pub trait DefaultDigest: Encode {
+    /// The digest used by [`digest`](#method.digest).
+    type Digest: Digest;
+
+    /// Returns the canonical hash of the value using the default digest.
+    fn digest(&self) -> Result<Output<Self::Digest>> {
+        self.canonical_hash::<Self::Digest>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digest::{consts::U8, FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+    /// A toy FNV-1a digest, so the tests don't depend on a hash crate.
+    #[derive(Clone)]
+    struct Fnv(u64);
+
+    impl Default for Fnv {
+        fn default() -> Self {
+            Fnv(0xcbf2_9ce4_8422_2325)
+        }
+    }
+
+    impl HashMarker for Fnv {}
+
+    impl OutputSizeUser for Fnv {
+        type OutputSize = U8;
+    }
+
+    impl Update for Fnv {
+        fn update(&mut self, data: &[u8]) {
+            for byte in data {
+                self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+    }
+
+    impl FixedOutput for Fnv {
+        fn finalize_into(self, out: &mut Output<Self>) {
+            out.copy_from_slice(&self.0.to_be_bytes());
+        }
+    }
+
+    impl Reset for Fnv {
+        fn reset(&mut self) {
+            *self = Fnv::default();
+        }
+    }
+
+    struct Point(u16, u16);
+
+    impl Encode for Point {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            (self.0, self.1).encode_into(dest)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            Ok(4)
+        }
+    }
+
+    impl DefaultDigest for Point {
+        type Digest = Fnv;
+    }
+
+    #[test]
+    fn canonical_hash() {
+        let value = (1u16, 2u16);
+        let hash = value.canonical_hash::<Fnv>().unwrap();
+        assert_eq!(hash, Fnv::digest(value.encode().unwrap()));
+        assert_ne!(hash, (2u16, 1u16).canonical_hash::<Fnv>().unwrap());
+    }
+
+    #[test]
+    fn default_digest() {
+        assert_eq!(
+            Point(1, 2).digest().unwrap(),
+            (1u16, 2u16).canonical_hash::<Fnv>().unwrap()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Encoding support for `Vec` and `String`, using the `heapless` crate.
+//!
+//! `heapless::Vec` is encoded like a `Vec` of the same elements, and
+//! `heapless::String` like a `String`: the elements or UTF-8 bytes one after
+//! another, read until the end of the input when decoding. The capacity is not
+//! part of the encoding.
+//!
+//! Decoding never allocates, so these work on targets without an allocator.
+//! It errors with `Error::CapacityExceeded` if the input holds more elements
+//! or bytes than fit in the capacity.
+
+use super::io::{Read, Write};
+use super::{limits, next_element, read_to_capacity};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::heapless::{String, Vec};
+
+impl<T: Encode + Terminated, const N: usize> Encode for Vec<T, N> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the elements of the vector one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(self, dest)
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Decode + Terminated, const N: usize> Decode for Vec<T, N> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more than `N` elements.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut vec = Vec::new();
+        vec.decode_into(input)?;
+        Ok(vec)
+    }
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more than `N` elements.
+    ///
+    /// Recursively calls `decode_into` for each existing element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => {
+                    let element = T::decode(&mut element_input)?;
+                    self.push(element).map_err(|_| Error::CapacityExceeded(N))?;
+                }
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Encode for String<N> {
+    /// Encodes the UTF-8 bytes of the string.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_str().encode_into(dest)
+    }
+
+    /// Returns the length of the string in bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl<const N: usize> Decode for String<N> {
+    /// Decodes the rest of the input as a UTF-8 string. Errors with
+    /// `Error::CapacityExceeded` if it is longer than `N` bytes, or with
+    /// `Error::InvalidUtf8` if the bytes are not valid UTF-8.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut string = String::new();
+        string.decode_into(input)?;
+        Ok(string)
+    }
+
+    /// Decodes the rest of the input as a UTF-8 string, replacing the
+    /// contents of the string.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        let mut bytes = [0; N];
+        let len = read_to_capacity(input, &mut bytes)?;
+        let string = core::str::from_utf8(&bytes[..len]).map_err(|_| Error::InvalidUtf8)?;
+        self.clear();
+        self.push_str(string)
+            .map_err(|_| Error::CapacityExceeded(N))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::TryFrom;
+
+    #[test]
+    fn encode_decode_vec() {
+        let vec = Vec::<u16, 3>::from_slice(&[1, 2]).unwrap();
+        let bytes = vec.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2]);
+        assert_eq!(vec.encoding_length().unwrap(), 4);
+        assert_eq!(Vec::<u16, 3>::decode(bytes.as_slice()).unwrap(), vec);
+
+        let mut vec = vec;
+        vec.decode_into(&[0, 3, 0, 4, 0, 5][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[3, 4, 5]);
+        vec.decode_into(&[0, 6][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[6]);
+    }
+
+    #[test]
+    fn encode_decode_string() {
+        let string = String::<8>::try_from("héllo").unwrap();
+        let bytes = string.encode().unwrap();
+        assert_eq!(bytes, "héllo".as_bytes());
+        assert_eq!(string.encoding_length().unwrap(), 6);
+        assert_eq!(String::<8>::decode(bytes.as_slice()).unwrap(), string);
+        assert_eq!(String::<6>::decode(bytes.as_slice()).unwrap(), "héllo");
+        assert!(String::<0>::decode(&[][..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(matches!(
+            Vec::<u8, 2>::decode(&[1, 2, 3][..]),
+            Err(Error::CapacityExceeded(2))
+        ));
+        assert!(matches!(
+            String::<5>::decode("héllo".as_bytes()),
+            Err(Error::CapacityExceeded(5))
+        ));
+        assert!(matches!(
+            String::<4>::decode(&[0xff][..]),
+            Err(Error::InvalidUtf8)
+        ));
+    }
+}
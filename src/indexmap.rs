@@ -0,0 +1,177 @@
+//! Encoding support for `IndexMap` and `IndexSet`, using the `indexmap`
+//! crate.
+//!
+//! An `IndexMap` is encoded as each key followed by its value, and an
+//! `IndexSet` as its elements, one after another in insertion order. Unlike
+//! `HashMap`, the iteration order of these types is deterministic, so it is
+//! preserved rather than sorted away, and decoding restores it.
+//!
+//! Since the order is part of the encoding, every value has exactly one
+//! encoding, and decoding errors with `Error::DuplicateKey` if a key or
+//! element appears more than once. Note however that equality of `IndexMap`
+//! and `IndexSet` ignores order, so two equal collections may have different
+//! encodings. Call `sort_keys` (or `sort`) before encoding if equal
+//! collections must always have the same encoding, e.g. when hashing state.
+
+use super::io::{Read, Write};
+use super::{limits, next_element, Decode, Encode, Error, Result, Terminated};
+use ::indexmap::{IndexMap, IndexSet};
+use core::hash::{BuildHasher, Hash};
+
+impl<K, V, S> Encode for IndexMap<K, V, S>
+where
+    K: Encode + Terminated,
+    V: Encode + Terminated,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Encodes each key followed by its value, in insertion order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for (key, value) in self.iter() {
+            key.encode_into(dest)?;
+            value.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the encoding lengths of all keys and values.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for (key, value) in self.iter() {
+            sum += key.encoding_length()? + value.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<K, V, S> Decode for IndexMap<K, V, S>
+where
+    K: Decode + Terminated + Hash + Eq,
+    V: Decode + Terminated,
+    S: BuildHasher + Default,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Decodes keys and values one after another until the input is
+    /// exhausted, keeping them in the order they were decoded. Errors with
+    /// `Error::DuplicateKey` if a key appears more than once.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let _depth = limits::descend()?;
+        let mut map = IndexMap::with_hasher(S::default());
+        while let Some(mut entry_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            let key = K::decode(&mut entry_input)?;
+            if map.contains_key(&key) {
+                return Err(Error::DuplicateKey);
+            }
+            let value = V::decode(&mut entry_input)?;
+            entry_input.check_consumed()?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<T: Encode + Terminated, S> Encode for IndexSet<T, S> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the elements one after another, in insertion order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for element in self.iter() {
+            element.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T, S> Decode for IndexSet<T, S>
+where
+    T: Decode + Terminated + Hash + Eq,
+    S: BuildHasher + Default,
+{
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes elements one after another until the input is exhausted,
+    /// keeping them in the order they were decoded. Errors with
+    /// `Error::DuplicateKey` if an element appears more than once.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let _depth = limits::descend()?;
+        let mut set = IndexSet::with_hasher(S::default());
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            let element = T::decode(&mut element_input)?;
+            element_input.check_consumed()?;
+            if !set.insert(element) {
+                return Err(Error::DuplicateKey);
+            }
+        }
+        Ok(set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_map() {
+        let mut map = IndexMap::new();
+        map.insert(2u16, Some(8u8));
+        map.insert(1, None);
+        let bytes = map.encode().unwrap();
+        assert_eq!(bytes, vec![0, 2, 1, 8, 0, 1, 0]);
+        assert_eq!(map.encoding_length().unwrap(), 7);
+
+        let decoded = IndexMap::<u16, Option<u8>>::decode(bytes.as_slice()).unwrap();
+        assert!(decoded.iter().eq(map.iter()));
+        assert!(IndexMap::<u16, u8>::decode(&[][..]).unwrap().is_empty());
+        assert!(IndexMap::<u16, u8>::decode(&[0, 1][..]).is_err());
+    }
+
+    #[test]
+    fn encode_decode_set() {
+        let set: IndexSet<u16> = vec![3, 1, 2].into_iter().collect();
+        let bytes = set.encode().unwrap();
+        assert_eq!(bytes, vec![0, 3, 0, 1, 0, 2]);
+        assert_eq!(set.encoding_length().unwrap(), 6);
+        let decoded = IndexSet::<u16>::decode(bytes.as_slice()).unwrap();
+        assert!(decoded.iter().eq(set.iter()));
+    }
+
+    #[test]
+    fn duplicates() {
+        assert!(matches!(
+            IndexMap::<u8, u8>::decode(&[1, 0, 2, 0, 1, 1][..]),
+            Err(Error::DuplicateKey)
+        ));
+        assert!(matches!(
+            IndexSet::<u8>::decode(&[1, 2, 1][..]),
+            Err(Error::DuplicateKey)
+        ));
+    }
+
+    #[test]
+    fn equal_maps_in_different_orders() {
+        let a: IndexMap<u8, u8> = vec![(1, 0), (2, 0)].into_iter().collect();
+        let mut b: IndexMap<u8, u8> = vec![(2, 0), (1, 0)].into_iter().collect();
+        assert_eq!(a, b);
+        assert_ne!(a.encode().unwrap(), b.encode().unwrap());
+        b.sort_keys();
+        assert_eq!(a.encode().unwrap(), b.encode().unwrap());
+    }
+}
@@ -0,0 +1,296 @@
+//! Integers of widths which don't match a built-in type.
+//!
+//! [`U24`](struct.U24.html), [`I24`](struct.I24.html),
+//! [`U48`](struct.U48.html) and [`I48`](struct.I48.html) are encoded as
+//! exactly 3 or 6 big-endian bytes, as used by the fields of many packet
+//! headers, rather than being padded to the next built-in width. Signed
+//! values use two's complement.
+//!
+//! Since every 3 or 6 bytes decode to a valid value, decoding can only fail if
+//! the input ends early. Instead, values are range-checked on construction:
+//!
+//! ```rust
+//! use ed::{int::U24, Encode};
+//! use std::convert::TryFrom;
+//!
+//! let length = U24::new(0x012345).unwrap();
+//! assert_eq!(length.encode().unwrap(), vec![0x01, 0x23, 0x45]);
+//! assert!(U24::new(1 << 24).is_none());
+//! assert!(U24::try_from(1u32 << 24).is_err());
+//! ```
+//!
+//! [`Usize64`](struct.Usize64.html) and [`Isize64`](struct.Isize64.html) wrap
+//! the platform-sized `usize` and `isize`, which have no encoding of their
+//! own since their width varies between targets, and encode them as 8 bytes
+//! on every target. On 32-bit targets, decoding errors with
+//! `Error::OutOfRange` for values which don't fit:
+//!
+//! ```rust
+//! use ed::{int::Usize64, Decode, Encode};
+//!
+//! let index = Usize64(7);
+//! assert_eq!(index.encode().unwrap(), vec![0, 0, 0, 0, 0, 0, 0, 7]);
+//! assert_eq!(Usize64::decode(&[0, 0, 0, 0, 0, 0, 0, 7][..]).unwrap().get(), 7);
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::convert::TryFrom;
+use core::fmt;
+
+macro_rules! int_impl {
+    ($name:ident, $inner:ty, $length:expr, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name($inner);
+
+        impl $name {
+            const BITS: u32 = $length * 8;
+
+            /// The smallest value of the type.
+            pub const MIN: Self = $name(<$inner>::MIN >> (<$inner>::BITS - Self::BITS));
+
+            /// The largest value of the type.
+            pub const MAX: Self = $name(<$inner>::MAX >> (<$inner>::BITS - Self::BITS));
+
+            /// Returns the value, or `None` if it is out of the range of the
+            /// type.
+            pub const fn new(value: $inner) -> Option<Self> {
+                if value < Self::MIN.0 || value > Self::MAX.0 {
+                    None
+                } else {
+                    Some($name(value))
+                }
+            }
+
+            /// Returns the value as the next largest built-in type.
+            pub const fn get(self) -> $inner {
+                self.0
+            }
+        }
+
+        impl TryFrom<$inner> for $name {
+            type Error = Error;
+
+            /// Errors with `Error::OutOfRange` if the value is out of the
+            /// range of the type.
+            fn try_from(value: $inner) -> Result<Self> {
+                $name::new(value).ok_or(Error::OutOfRange)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Encode for $name {
+            #[doc = "Encodes the integer as "]
+            #[doc = stringify!($length)]
+            #[doc = " big-endian bytes."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                let bytes = self.0.to_be_bytes();
+                dest.write_all(&bytes[bytes.len() - $length..])?;
+                Ok(())
+            }
+
+            #[doc = "Always returns Ok("]
+            #[doc = stringify!($length)]
+            #[doc = ")."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok($length)
+            }
+        }
+
+        impl Decode for $name {
+            #[doc = "Decodes "]
+            #[doc = stringify!($length)]
+            #[doc = " big-endian bytes."]
+            #[inline]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let mut bytes = [0; core::mem::size_of::<$inner>()];
+                let offset = bytes.len() - $length;
+                input.read_exact(&mut bytes[offset..])?;
+                // Sign-extend negative values of signed types.
+                if <$inner>::MIN != 0 && bytes[offset] & 0x80 != 0 {
+                    bytes[..offset].fill(0xff);
+                }
+                Ok($name(<$inner>::from_be_bytes(bytes)))
+            }
+        }
+
+        impl Terminated for $name {}
+
+        impl FixedLength for $name {
+            const ENCODING_LENGTH: usize = $length;
+        }
+    };
+}
+
+int_impl!(U24, u32, 3, "An unsigned 24-bit integer.");
+int_impl!(I24, i32, 3, "A signed 24-bit integer.");
+int_impl!(U48, u64, 6, "An unsigned 48-bit integer.");
+int_impl!(I48, i64, 6, "A signed 48-bit integer.");
+
+macro_rules! size_impl {
+    ($name:ident, $size:ty, $inner:ty, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub $size);
+
+        impl $name {
+            /// Returns the wrapped integer.
+            pub const fn get(self) -> $size {
+                self.0
+            }
+        }
+
+        impl From<$size> for $name {
+            fn from(value: $size) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for $size {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Encode for $name {
+            #[doc = "Encodes the integer as 8 big-endian bytes."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                // Can only fail on targets with pointers wider than 64 bits.
+                <$inner>::try_from(self.0)
+                    .map_err(|_| Error::OutOfRange)?
+                    .encode_into(dest)
+            }
+
+            #[doc = "Always returns Ok(8)."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(8)
+            }
+        }
+
+        impl Decode for $name {
+            #[doc = "Decodes 8 big-endian bytes. Errors with"]
+            #[doc = " `Error::OutOfRange` if the value doesn't fit in the"]
+            #[doc = " platform's pointer width."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                let value = <$inner>::decode(input)?;
+                <$size>::try_from(value)
+                    .map($name)
+                    .map_err(|_| Error::OutOfRange)
+            }
+        }
+
+        impl Terminated for $name {}
+
+        impl FixedLength for $name {
+            const ENCODING_LENGTH: usize = 8;
+        }
+    };
+}
+
+size_impl!(Usize64, usize, u64, "A `usize` encoded as a `u64`.");
+size_impl!(Isize64, isize, i64, "An `isize` encoded as an `i64`.");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(U24::MIN.get(), 0);
+        assert_eq!(U24::MAX.get(), 0xff_ffff);
+        assert_eq!(I24::MIN.get(), -0x80_0000);
+        assert_eq!(I24::MAX.get(), 0x7f_ffff);
+        assert_eq!(U48::MAX.get(), 0xffff_ffff_ffff);
+        assert_eq!(I48::MIN.get(), -0x8000_0000_0000);
+
+        assert!(U24::new(0xff_ffff).is_some());
+        assert!(U24::new(0x100_0000).is_none());
+        assert!(I24::new(-0x80_0001).is_none());
+        assert!(I48::new(0x8000_0000_0000).is_none());
+        assert!(matches!(U48::try_from(1u64 << 48), Err(Error::OutOfRange)));
+        assert_eq!(u32::from(U24::try_from(7).unwrap()), 7);
+    }
+
+    #[test]
+    fn encode_decode() {
+        let value = U24::new(0x01_2345).unwrap();
+        assert_eq!(value.encode().unwrap(), vec![0x01, 0x23, 0x45]);
+        assert_eq!(value.encoding_length().unwrap(), 3);
+        assert_eq!(U24::decode(&[0x01, 0x23, 0x45][..]).unwrap(), value);
+
+        let value = U48::new(0x0102_0304_0506).unwrap();
+        assert_eq!(value.encode().unwrap(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(U48::decode(&[1, 2, 3, 4, 5, 6][..]).unwrap(), value);
+        assert_eq!(<U48 as FixedLength>::ENCODING_LENGTH, 6);
+
+        assert!(U24::decode(&[1, 2][..]).unwrap_err().is_unexpected_eof());
+    }
+
+    #[test]
+    fn encode_decode_signed() {
+        let cases = [
+            (0, vec![0, 0, 0]),
+            (1, vec![0, 0, 1]),
+            (-1, vec![0xff, 0xff, 0xff]),
+            (-0x80_0000, vec![0x80, 0, 0]),
+            (0x7f_ffff, vec![0x7f, 0xff, 0xff]),
+        ];
+        for (value, bytes) in cases.iter() {
+            let value = I24::new(*value).unwrap();
+            assert_eq!(&value.encode().unwrap(), bytes);
+            assert_eq!(I24::decode(bytes.as_slice()).unwrap(), value);
+        }
+
+        let value = I48::new(-2).unwrap();
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]);
+        assert_eq!(I48::decode(bytes.as_slice()).unwrap(), value);
+    }
+
+    #[test]
+    fn encode_decode_size() {
+        let value = Usize64(0x0102);
+        assert_eq!(value.encode().unwrap(), vec![0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!(value.encoding_length().unwrap(), 8);
+        assert_eq!(
+            Usize64::decode(&[0, 0, 0, 0, 0, 0, 1, 2][..]).unwrap(),
+            value
+        );
+
+        let value = Isize64(-2);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]);
+        assert_eq!(Isize64::decode(bytes.as_slice()).unwrap(), value);
+        assert_eq!(<Isize64 as FixedLength>::ENCODING_LENGTH, 8);
+
+        let max = Usize64::decode(&[0xff; 8][..]);
+        if cfg!(target_pointer_width = "64") {
+            assert_eq!(max.unwrap(), Usize64(usize::MAX));
+        } else {
+            assert!(matches!(max, Err(Error::OutOfRange)));
+        }
+    }
+}
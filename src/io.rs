@@ -0,0 +1,238 @@
+//! The reader and writer traits which values are decoded from and encoded
+//! into.
+//!
+//! With the `std` feature (enabled by default), these are re-exports of the
+//! `std::io` items, so any `std::io` reader or writer can be used directly.
+//! Without it, this module provides minimal versions of them for `no_std`
+//! targets, implemented for byte slices and `Vec<u8>`, so that values can
+//! still be encoded into and decoded from in-memory buffers.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// A specialized `Result` type for I/O operations.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// The kinds of I/O error, a subset of those of `std::io::ErrorKind`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// Data not valid for the operation was encountered.
+        InvalidData,
+        /// The input ended before the requested number of bytes were read.
+        UnexpectedEof,
+        /// A write returned `Ok(0)` before all of the bytes were written.
+        WriteZero,
+        /// The operation was interrupted and can typically be retried.
+        Interrupted,
+        /// Any other kind of error.
+        Other,
+    }
+
+    impl ErrorKind {
+        fn as_str(&self) -> &'static str {
+            match self {
+                ErrorKind::InvalidData => "invalid data",
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::Interrupted => "operation interrupted",
+                ErrorKind::Other => "other error",
+            }
+        }
+    }
+
+    /// An I/O error, identified only by its kind.
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+    }
+
+    impl Error {
+        /// Returns the kind of the error.
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Error { kind }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(self.kind.as_str())
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// A source of bytes, like `std::io::Read`.
+    pub trait Read {
+        /// Reads some bytes into `buf`, returning how many were read. A return
+        /// value of 0 means the end of the input was reached, unless `buf` is
+        /// empty.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        /// Reads exactly enough bytes to fill `buf`, erroring with
+        /// `ErrorKind::UnexpectedEof` if the input ends first.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf) {
+                    Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                    Ok(n) => buf = &mut buf[n..],
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+
+        /// Reads the rest of the input, appending it to `buf` and returning
+        /// the number of bytes read.
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let start = buf.len();
+            let mut chunk = [0; 256];
+            loop {
+                match self.read(&mut chunk) {
+                    Ok(0) => return Ok(buf.len() - start),
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        #[inline]
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+
+        #[inline]
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            (**self).read_exact(buf)
+        }
+
+        #[inline]
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            (**self).read_to_end(buf)
+        }
+    }
+
+    impl Read for &[u8] {
+        #[inline]
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = tail;
+            Ok(n)
+        }
+
+        #[inline]
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            if buf.len() > self.len() {
+                *self = &self[self.len()..];
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            let (head, tail) = self.split_at(buf.len());
+            buf.copy_from_slice(head);
+            *self = tail;
+            Ok(())
+        }
+
+        #[inline]
+        fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+            let n = self.len();
+            buf.extend_from_slice(self);
+            *self = &self[n..];
+            Ok(n)
+        }
+    }
+
+    /// A sink for bytes, like `std::io::Write`.
+    pub trait Write {
+        /// Writes some bytes from `buf`, returning how many were written.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        /// Flushes any buffered bytes to their destination.
+        fn flush(&mut self) -> Result<()>;
+
+        /// Writes all of `buf`, erroring with `ErrorKind::WriteZero` if the
+        /// writer stops accepting bytes first.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(n) => buf = &buf[n..],
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        #[inline]
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        #[inline]
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+
+        #[inline]
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        #[inline]
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        #[inline]
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        #[inline]
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl Write for &mut [u8] {
+        #[inline]
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = core::mem::take(self).split_at_mut(n);
+            head.copy_from_slice(&buf[..n]);
+            *self = tail;
+            Ok(n)
+        }
+
+        #[inline]
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
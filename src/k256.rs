@@ -0,0 +1,201 @@
+//! Encoding support for secp256k1 keys and signatures, using the `k256`
+//! crate.
+//!
+//! `PublicKey` and ECDSA `VerifyingKey` are encoded as 33-byte compressed
+//! SEC1 points: a 0x02 or 0x03 byte for the parity of `y`, followed by `x` as
+//! 32 big-endian bytes. The uncompressed form is not accepted, so every key
+//! has exactly one encoding. Decoding errors with `Error::UnexpectedByte` for
+//! any other first byte, and with `Error::InvalidKey` if the bytes are not a
+//! valid point.
+//!
+//! An ECDSA `Signature` is encoded as 64 bytes, `r` followed by `s`, each as
+//! 32 big-endian bytes. Since `(r, -s)` is also valid for any valid
+//! signature `(r, s)`, only the "low-S" form, with `s` at most half the group
+//! order, is accepted, so that signatures can't be altered without
+//! invalidating them. Decoding errors with `Error::InvalidSignature` if `r`
+//! or `s` are out of range or `s` is high. Call `Signature::normalize_s`
+//! before encoding a signature from a signer which doesn't produce low-S
+//! signatures.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::k256::ecdsa::{Signature, VerifyingKey};
+use ::k256::elliptic_curve::sec1::ToEncodedPoint;
+use ::k256::PublicKey;
+
+/// Reads a 33-byte compressed SEC1 point, erroring with
+/// `Error::UnexpectedByte` if the first byte isn't 0x02 or 0x03.
+fn decode_compressed<R: Read>(input: R) -> Result<[u8; 33]> {
+    let bytes = <[u8; 33]>::decode(input)?;
+    match bytes[0] {
+        0x02 | 0x03 => Ok(bytes),
+        byte => Err(Error::UnexpectedByte(byte)),
+    }
+}
+
+impl Encode for PublicKey {
+    /// Encodes the compressed SEC1 point as 33 bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.to_encoded_point(true).as_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(33).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(33)
+    }
+}
+
+impl Decode for PublicKey {
+    /// Decodes a compressed SEC1 point from 33 bytes. Errors with
+    /// `Error::UnexpectedByte` if the point is not compressed, or with
+    /// `Error::InvalidKey` if it is not valid.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let bytes = decode_compressed(input)?;
+        PublicKey::from_sec1_bytes(&bytes).map_err(|_| Error::InvalidKey)
+    }
+}
+
+impl Terminated for PublicKey {}
+
+impl FixedLength for PublicKey {
+    const ENCODING_LENGTH: usize = 33;
+}
+
+impl Encode for VerifyingKey {
+    /// Encodes the compressed SEC1 point as 33 bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.to_encoded_point(true).as_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(33).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(33)
+    }
+}
+
+impl Decode for VerifyingKey {
+    /// Decodes a compressed SEC1 point from 33 bytes. Errors with
+    /// `Error::UnexpectedByte` if the point is not compressed, or with
+    /// `Error::InvalidKey` if it is not valid.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let bytes = decode_compressed(input)?;
+        VerifyingKey::from_sec1_bytes(&bytes).map_err(|_| Error::InvalidKey)
+    }
+}
+
+impl Terminated for VerifyingKey {}
+
+impl FixedLength for VerifyingKey {
+    const ENCODING_LENGTH: usize = 33;
+}
+
+impl Encode for Signature {
+    /// Encodes `r` followed by `s`, as 64 bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(64).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(64)
+    }
+}
+
+impl Decode for Signature {
+    /// Decodes `r` followed by `s` from 64 bytes. Errors with
+    /// `Error::InvalidSignature` if either is zero or not less than the group
+    /// order, or if `s` is more than half the group order.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let bytes = <[u8; 64]>::decode(input)?;
+        let signature = Signature::from_slice(&bytes).map_err(|_| Error::InvalidSignature)?;
+        // `normalize_s` returns the low-S form only if `s` is high.
+        if signature.normalize_s().is_some() {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(signature)
+    }
+}
+
+impl Terminated for Signature {}
+
+impl FixedLength for Signature {
+    const ENCODING_LENGTH: usize = 64;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::k256::ecdsa::signature::{Signer, Verifier};
+    use ::k256::ecdsa::SigningKey;
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&[7; 32]).unwrap()
+    }
+
+    #[test]
+    fn encode_decode_keys() {
+        let key = *signing_key().verifying_key();
+        let bytes = key.encode().unwrap();
+        assert_eq!(bytes.len(), 33);
+        assert!(bytes[0] == 0x02 || bytes[0] == 0x03);
+        assert_eq!(key.encoding_length().unwrap(), 33);
+        assert_eq!(VerifyingKey::decode(bytes.as_slice()).unwrap(), key);
+
+        let public_key = PublicKey::from(&key);
+        assert_eq!(public_key.encode().unwrap(), bytes);
+        assert_eq!(PublicKey::decode(bytes.as_slice()).unwrap(), public_key);
+    }
+
+    #[test]
+    fn encode_decode_signature() {
+        let signature: Signature = signing_key().sign(b"message");
+        let bytes = signature.encode().unwrap();
+        assert_eq!(bytes.len(), 64);
+        let decoded = Signature::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, signature);
+        let key = *signing_key().verifying_key();
+        assert!(key.verify(b"message", &decoded).is_ok());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let mut bytes = signing_key().verifying_key().encode().unwrap();
+        bytes[0] = 0x04;
+        assert!(matches!(
+            PublicKey::decode(bytes.as_slice()),
+            Err(Error::UnexpectedByte(0x04))
+        ));
+        // No point has an x-coordinate of 5.
+        let mut bytes = [0; 33];
+        bytes[0] = 0x02;
+        bytes[32] = 5;
+        assert!(matches!(
+            VerifyingKey::decode(&bytes[..]),
+            Err(Error::InvalidKey)
+        ));
+
+        assert!(matches!(
+            Signature::decode(&[0; 64][..]),
+            Err(Error::InvalidSignature)
+        ));
+        let signature: Signature = signing_key().sign(b"message");
+        let (r, s) = signature.split_scalars();
+        let high = Signature::from_scalars(r, -*s).unwrap();
+        assert!(matches!(
+            Signature::decode(high.encode().unwrap().as_slice()),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}
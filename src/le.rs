@@ -0,0 +1,106 @@
+//! Little-endian integers, for foreign wire formats.
+//!
+//! Integers are encoded big-endian by default, so that encodings sort in the
+//! same order as the values they encode. Some foreign formats mandate
+//! little-endian integers instead: [`Le`](struct.Le.html) wraps an integer to
+//! encode it as fixed-size little-endian bytes, leaving the default encoding
+//! untouched.
+//!
+//! ```rust
+//! use ed::{le::Le, Decode, Encode};
+//!
+//! assert_eq!(Le(0x0102u16).encode().unwrap(), vec![0x02, 0x01]);
+//! assert_eq!(Le::<u32>::decode(&[1, 0, 0, 0][..]).unwrap(), Le(1));
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, FixedLength, Result, Terminated};
+
+/// An integer encoded as fixed-size little-endian bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Le<T>(pub T);
+
+impl<T> Le<T> {
+    /// Returns the wrapped integer.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Le<T> {
+    fn from(value: T) -> Self {
+        Le(value)
+    }
+}
+
+macro_rules! le_impl {
+    ($type:ty) => {
+        impl Encode for Le<$type> {
+            #[doc = "Encodes the integer as fixed-size little-endian bytes."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                dest.write_all(&self.0.to_le_bytes())?;
+                Ok(())
+            }
+
+            #[doc = "Returns the size of the integer in bytes. Will always"]
+            #[doc = " return an `Ok` result."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(core::mem::size_of::<$type>())
+            }
+        }
+
+        impl Decode for Le<$type> {
+            #[doc = "Decodes the integer from fixed-size little-endian bytes."]
+            #[inline]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let mut bytes = [0; core::mem::size_of::<$type>()];
+                input.read_exact(&mut bytes)?;
+                Ok(Le(<$type>::from_le_bytes(bytes)))
+            }
+        }
+
+        impl Terminated for Le<$type> {}
+
+        impl FixedLength for Le<$type> {
+            const ENCODING_LENGTH: usize = core::mem::size_of::<$type>();
+        }
+    };
+}
+
+le_impl!(u16);
+le_impl!(u32);
+le_impl!(u64);
+le_impl!(u128);
+le_impl!(i16);
+le_impl!(i32);
+le_impl!(i64);
+le_impl!(i128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let value = Le(0x0102_0304u32);
+        assert_eq!(value.encode().unwrap(), vec![4, 3, 2, 1]);
+        assert_eq!(value.encoding_length().unwrap(), 4);
+        assert_eq!(Le::<u32>::decode(&[4, 3, 2, 1][..]).unwrap(), value);
+
+        let value = Le(-2i64);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Le::<i64>::decode(bytes.as_slice()).unwrap(), value);
+        assert_eq!(<Le<u128> as FixedLength>::ENCODING_LENGTH, 16);
+
+        assert!(Le::<u16>::decode(&[1][..]).unwrap_err().is_unexpected_eof());
+    }
+
+    #[test]
+    fn in_sequence() {
+        let values: (Le<u16>, u16) = Decode::decode(&[1, 0, 0, 1][..]).unwrap();
+        assert_eq!(values, (Le(1), 1));
+    }
+}
@@ -0,0 +1,215 @@
+//! Error-accumulating decoding, for debugging malformed encodings.
+//!
+//! Normal decoding stops at the first error. When debugging hand-crafted or
+//! corrupted fixtures it is more useful to see every malformed field at once,
+//! so a [`Decoder`](struct.Decoder.html) keeps decoding after a field fails
+//! (as long as input remains) and records each failure along with the byte
+//! offset of the field.
+//!
+//! Types opt in by implementing [`DecodeLenient`](trait.DecodeLenient.html),
+//! which can be derived:
+//!
+//! ```rust
+//! use ed::{Encode, Decode, DecodeLenient};
+//!
+//! #[derive(Encode, Decode, DecodeLenient)]
+//! struct Foo {
+//!     a: bool,
+//!     b: u16,
+//!     c: bool,
+//! }
+//!
+//! let errors = Foo::decode_lenient(&[2, 0, 1, 3]).err().unwrap();
+//! assert_eq!(errors.0.len(), 2);
+//! assert_eq!(errors.0[1].field, "c");
+//! assert_eq!(errors.0[1].offset, 3);
+//! ```
+
+use super::{Decode, Error};
+use std::fmt;
+use std::io::ErrorKind;
+
+/// A failure to decode a single field.
+#[derive(Debug)]
+pub struct FieldError {
+    /// The name of the field, e.g. `header` or, for enum variants,
+    /// `Variant.field`.
+    pub field: String,
+    /// The offset of the first byte of the field in the input.
+    pub offset: usize,
+    /// The error returned when decoding the field.
+    pub error: Error,
+}
+
+/// All of the field errors encountered during a lenient decode.
+#[derive(Debug)]
+pub struct DecodeErrors(pub Vec<FieldError>);
+
+impl fmt::Display for DecodeErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} field(s) failed to decode", self.0.len())?;
+        for err in self.0.iter() {
+            write!(
+                f,
+                "\n  {} at offset {}: {}",
+                err.field, err.offset, err.error
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeErrors {}
+
+/// Decodes fields one after another from a byte slice, recording errors
+/// rather than stopping at the first one.
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    errors: Vec<FieldError>,
+    exhausted: bool,
+}
+
+impl<'a> Decoder<'a> {
+    /// Creates a decoder which reads from the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Decoder {
+            bytes,
+            offset: 0,
+            errors: vec![],
+            exhausted: false,
+        }
+    }
+
+    /// Returns the offset of the next byte to be read.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Decodes the next field, returning `None` and recording the error if
+    /// decoding fails.
+    ///
+    /// Once the input has been exhausted, no further fields are decoded (and
+    /// no further errors are recorded).
+    pub fn field<T: Decode>(&mut self, name: &str) -> Option<T> {
+        self.field_with(name, |input| T::decode(input))
+    }
+
+    /// Like [`field`](#method.field), but decodes the field with `decode`,
+    /// e.g. the `decode` function of a type given in `#[ed(with = "...")]`.
+    pub fn field_with<T, F>(&mut self, name: &str, decode: F) -> Option<T>
+    where
+        F: FnOnce(&mut &[u8]) -> Result<T, Error>,
+    {
+        if self.exhausted {
+            return None;
+        }
+
+        let start = self.offset;
+        let mut input = self.bytes.get(start..).unwrap_or_default();
+        let result = decode(&mut input);
+        self.offset = self.bytes.len() - input.len();
+
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                if let Error::IOError(io_err) = &err {
+                    if io_err.kind() == ErrorKind::UnexpectedEof {
+                        self.exhausted = true;
+                    }
+                }
+                self.record(start, name, err);
+                None
+            }
+        }
+    }
+
+    /// Records an error found by the caller, e.g. an unknown enum variant.
+    /// If the layout of the rest of the input is unknown as a result, the
+    /// caller should also call [`halt`](#method.halt).
+    pub fn record(&mut self, offset: usize, name: &str, error: Error) {
+        self.errors.push(FieldError {
+            field: name.to_string(),
+            offset,
+            error,
+        });
+    }
+
+    /// Stops decoding any further fields.
+    pub fn halt(&mut self) {
+        self.exhausted = true;
+    }
+
+    /// Returns the decoded value if no errors were recorded, otherwise
+    /// returns all of the recorded errors.
+    pub fn finish<T>(self, value: Option<T>) -> Result<T, DecodeErrors> {
+        match value {
+            Some(value) if self.errors.is_empty() => Ok(value),
+            _ => Err(DecodeErrors(self.errors)),
+        }
+    }
+}
+
+/// A trait for types which can be decoded field by field, accumulating
+/// errors. Usually implemented with `#[derive(DecodeLenient)]`.
+pub trait DecodeLenient: Decode {
+    /// Decodes each field of the value from the decoder, returning `None` if
+    /// any field failed.
+    fn decode_fields(decoder: &mut Decoder) -> Option<Self>;
+
+    /// Decodes the value, returning every field error encountered rather
+    /// than only the first.
+    fn decode_lenient(bytes: &[u8]) -> Result<Self, DecodeErrors> {
+        let mut decoder = Decoder::new(bytes);
+        let value = Self::decode_fields(&mut decoder);
+        decoder.finish(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulates_errors() {
+        let bytes = [2, 0, 1, 3, 1];
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.field::<bool>("a"), None);
+        assert_eq!(decoder.field::<u16>("b"), Some(1));
+        assert_eq!(decoder.field::<bool>("c"), None);
+        assert_eq!(decoder.field::<bool>("d"), Some(true));
+        assert_eq!(decoder.offset(), 5);
+
+        let errors = decoder.finish(None::<()>).unwrap_err();
+        assert_eq!(errors.0.len(), 2);
+        assert_eq!(errors.0[0].field, "a");
+        assert_eq!(errors.0[0].offset, 0);
+        assert_eq!(errors.0[1].field, "c");
+        assert_eq!(errors.0[1].offset, 3);
+        assert_eq!(
+            errors.to_string(),
+            "2 field(s) failed to decode\n  a at offset 0: Unexpected byte: 2\n  c at offset 3: Unexpected byte: 3"
+        );
+    }
+
+    #[test]
+    fn stops_at_eof() {
+        let bytes = [0, 1, 0];
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.field::<u16>("a"), Some(1));
+        assert_eq!(decoder.field::<u16>("b"), None);
+        assert_eq!(decoder.field::<u8>("c"), None);
+
+        let errors = decoder.finish(None::<()>).unwrap_err();
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(errors.0[0].field, "b");
+    }
+
+    #[test]
+    fn finish_ok() {
+        let bytes = [1];
+        let mut decoder = Decoder::new(&bytes);
+        let value = decoder.field::<bool>("a");
+        assert!(decoder.finish(value).unwrap());
+    }
+}
@@ -1,4 +1,4 @@
-#![feature(fundamental)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 //! *`ed` is a minimalist crate for deterministic binary encodings.*
 //!
@@ -26,11 +26,14 @@
 //! Another property of this crate is a focus on determinism (important for
 //! cryptographically hashed types) - built-in encodings are always big-endian
 //! and there are no provided encodings for floating point numbers or `usize`.
+//! Floats can be opted into with the wrappers in [`float`](float/index.html),
+//! which give every value a single canonical encoding, and `usize` with
+//! [`int::Usize64`](int/struct.Usize64.html), which has the same width on
+//! every target.
 //!
 //! ## Usage
 //!
 //! ```rust
-//! #![feature(trivial_bounds)]
 //! use ed::{Encode, Decode};
 //!
 //! # fn main() -> ed::Result<()> {
@@ -68,12 +71,152 @@
 //! # Ok(())
 //! # }
 //! ```
-
-use std::convert::TryInto;
-use std::io::{Read, Write};
+//!
+//! ## `no_std`
+//!
+//! `ed` depends on `std` through its default `std` feature. With
+//! `default-features = false`, it only requires `alloc`: values are encoded
+//! into and decoded from the minimal [`io::Read`](io/trait.Read.html) and
+//! [`io::Write`](io/trait.Write.html) traits, which are implemented for byte
+//! slices and `Vec<u8>`. Modules which need `std` (e.g. `graph`, `timestamp`
+//! and `position`) and the impls for `std`-only types such as `HashMap` and
+//! `PathBuf` are unavailable, and decoding is not depth-limited by default.
+
+extern crate alloc;
+
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+#[cfg(not(feature = "std"))]
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use io::{Read, Write};
 
 pub use ed_derive::*;
 
+#[cfg(feature = "std")]
+pub mod any;
+#[cfg(feature = "arrayvec")]
+mod arrayvec;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+#[macro_use]
+mod async_io;
+#[cfg(feature = "no-panic")]
+pub mod audit;
+#[cfg(feature = "bitvec")]
+mod bitvec;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+#[cfg(feature = "std")]
+pub mod cancel;
+#[cfg(feature = "std")]
+pub mod capabilities;
+pub mod cbor;
+#[cfg(feature = "std")]
+pub mod chain;
+#[cfg(feature = "chrono")]
+mod chrono;
+#[cfg(feature = "std")]
+pub mod columnar;
+#[cfg(feature = "std")]
+pub mod compat;
+#[cfg(feature = "std")]
+pub mod cost;
+pub mod counted;
+pub mod ct;
+pub mod decimal;
+#[cfg(feature = "ed25519-dalek")]
+mod ed25519_dalek;
+#[cfg(feature = "either")]
+mod either;
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+mod ext;
+pub mod flags;
+pub mod float;
+#[cfg(feature = "futures-io")]
+pub mod futures_io;
+#[cfg(feature = "generic-array")]
+mod generic_array;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "digest")]
+pub mod hash;
+#[cfg(feature = "heapless")]
+mod heapless;
+#[cfg(feature = "indexmap")]
+mod indexmap;
+pub mod int;
+pub mod io;
+#[cfg(feature = "k256")]
+mod k256;
+pub mod le;
+#[cfg(feature = "std")]
+pub mod lenient;
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod migrate;
+pub mod net;
+#[cfg(feature = "num-bigint")]
+mod num_bigint;
+pub mod order;
+#[cfg(feature = "std")]
+pub mod position;
+#[cfg(feature = "primitive-types")]
+mod primitive_types;
+#[cfg(feature = "std")]
+pub mod profile;
+pub mod proto;
+mod range;
+pub mod remaining;
+pub mod rlp;
+#[cfg(feature = "rust_decimal")]
+mod rust_decimal;
+#[cfg(feature = "secrecy")]
+pub mod secret;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "smallvec")]
+mod smallvec;
+pub mod ssz;
+pub mod string;
+#[cfg(feature = "time")]
+mod time;
+#[cfg(feature = "std")]
+pub mod timestamp;
+#[cfg(feature = "tinyvec")]
+mod tinyvec;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+pub mod tracked;
+#[cfg(feature = "uuid")]
+mod uuid;
+pub mod varint;
+#[doc(hidden)]
+pub mod wipe;
+#[cfg(feature = "std")]
+pub use any::Any;
+pub use cbor::Cbor;
+#[cfg(feature = "std")]
+pub use columnar::Columns;
+#[cfg(feature = "std")]
+pub use cost::EncodingCost;
+pub use ext::{fmt_encoding, DecodeExt, EncodeExt, HexDebug};
+#[cfg(feature = "std")]
+pub use lenient::DecodeLenient;
+#[cfg(feature = "std")]
+pub use profile::Profile;
+pub use remaining::Remaining;
+#[cfg(feature = "std")]
+pub use timestamp::Timestamp;
+pub use tracked::Track;
+pub use varint::Varint;
+
 /// An enum that defines the `ed` error types.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -81,16 +224,191 @@ pub enum Error {
     UnexpectedByte(u8),
     #[error("Unencodable variant")]
     UnencodableVariant,
+    #[error("Cannot decode an uninhabited type")]
+    Uninhabited,
+    #[error("Unknown type ID: {0}")]
+    UnknownTypeId(u32),
+    #[error("Unexpected type ID: {0}")]
+    UnexpectedTypeId(u32),
+    #[error("Type ID {0} registered more than once")]
+    DuplicateTypeId(u32),
+    #[error("Non-canonical capability set")]
+    NonCanonicalCapabilities,
+    #[error("Length {0} exceeds the maximum encodable length")]
+    LengthOverflow(usize),
+    #[error("Value is already mutably borrowed")]
+    AlreadyBorrowed,
+    #[error("Reference cycle detected")]
+    CycleDetected,
+    #[error("Invalid back-reference: {0}")]
+    InvalidBackReference(u32),
+    #[error("Invalid offset: {0}")]
+    InvalidOffset(u32),
+    #[error("Verification failed")]
+    VerificationFailed,
+    #[error("Invalid public key")]
+    InvalidKey,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Unknown version: {0}")]
+    UnknownVersion(u8),
+    #[error("Invalid timestamp")]
+    InvalidTimestamp,
+    #[error("Invalid duration")]
+    InvalidDuration,
+    #[error("Invalid hex string")]
+    InvalidHex,
+    #[error("Invalid base64 string")]
+    InvalidBase64,
+    #[error("Invalid decimal string")]
+    InvalidDecimal,
+    #[error("Invalid UTF-8")]
+    InvalidUtf8,
+    #[error("Unexpected zero value")]
+    UnexpectedZero,
+    #[error("Keys are not in strictly increasing order")]
+    NonCanonicalOrder,
+    #[error("Duplicate key")]
+    DuplicateKey,
+    #[error("Non-canonical varint encoding")]
+    NonCanonicalVarint,
+    #[error("Varint overflows the integer type")]
+    VarintOverflow,
+    #[error("Value is out of range")]
+    OutOfRange,
+    #[error("Non-canonical NaN")]
+    NonCanonicalNan,
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+    #[error("Input exceeds the maximum of {0} bytes")]
+    MaxBytesExceeded(u64),
+    #[error("Input exceeds the maximum of {0} elements")]
+    MaxElementsExceeded(usize),
+    #[error("Input exceeds the maximum nesting depth of {0}")]
+    MaxDepthExceeded(usize),
+    #[error("Buffer of {0} bytes is too small for the encoding")]
+    BufferTooSmall(usize),
+    #[error("Input exceeds the capacity of {0}")]
+    CapacityExceeded(usize),
+    #[error("Count of {0} elements exceeds the input")]
+    CountExceedsInput(usize),
+    #[error("{0}")]
+    Custom(String),
     #[error(transparent)]
-    IOError(#[from] std::io::Error),
+    IOError(#[from] io::Error),
+    #[error("{source} (at byte {offset})")]
+    At {
+        offset: u64,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{type_name}.{}: {source}", .path.join("."))]
+    Context {
+        type_name: &'static str,
+        path: Vec<&'static str>,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Returns an error with a custom message, for failures specific to a
+    /// type's own `Encode` or `Decode` impl, e.g. a value failing validation.
+    pub fn custom<T: core::fmt::Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+
+    /// Returns the error, recording that it occurred at the given byte offset
+    /// in the input. If the error already has an offset, it is replaced.
+    pub fn at(self, offset: u64) -> Error {
+        let source = match self {
+            Error::At { source, .. } => source,
+            err => Box::new(err),
+        };
+        Error::At { offset, source }
+    }
+
+    /// Returns the byte offset in the input at which the error occurred, if
+    /// it was recorded with [`at`](#method.at).
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Error::At { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the error, recording that it occurred while decoding the given
+    /// field of the given type. Derived `Decode` impls call this for errors
+    /// from their fields, so that an error from a nested field reads e.g.
+    /// `Block.header.timestamp: Unexpected byte: 7`.
+    ///
+    /// If the error already has a context, the field is prepended to its
+    /// path and the type replaces its type name.
+    pub fn in_field(self, type_name: &'static str, field: &'static str) -> Error {
+        match self {
+            Error::Context {
+                mut path, source, ..
+            } => {
+                path.insert(0, field);
+                Error::Context {
+                    type_name,
+                    path,
+                    source,
+                }
+            }
+            err => Error::Context {
+                type_name,
+                path: vec![field],
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// Returns the path of the field in which the error occurred, starting
+    /// with the outermost type, if it was recorded with
+    /// [`in_field`](#method.in_field).
+    pub fn path(&self) -> Option<String> {
+        match self {
+            Error::At { source, .. } => source.path(),
+            Error::Context {
+                type_name, path, ..
+            } => Some(format!("{}.{}", type_name, path.join("."))),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the error was caused by the input ending in the middle
+    /// of a value.
+    pub fn is_unexpected_eof(&self) -> bool {
+        match self.inner() {
+            Error::IOError(err) => err.kind() == io::ErrorKind::UnexpectedEof,
+            _ => false,
+        }
+    }
+
+    /// Returns the underlying error, without any recorded offset or context.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::At { source, .. } | Error::Context { source, .. } => source.inner(),
+            err => err,
+        }
+    }
 }
 
 /// A Result bound to the standard `ed` error type.
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 /// A trait for values that can be encoded into bytes deterministically.
-#[fundamental]
 pub trait Encode {
+    /// Whether the encoding of this type may contain sensitive data such as
+    /// private keys. When true and the `zeroize` feature is enabled, temporary
+    /// buffers used while encoding values of this type are wiped after use.
+    ///
+    /// Defaults to false. Derived impls are sensitive if the type is marked
+    /// with `#[ed(sensitive)]` or any of its fields are sensitive. Types whose
+    /// fields hold secrets in plain types such as `Vec<u8>` should be marked.
+    const SENSITIVE: bool = false;
+
     /// Writes the encoded representation of the value to the destination
     /// writer. Can error due to either a write error from `dest`, or an
     /// encoding error for types where invalid values are possible.
@@ -105,6 +423,17 @@ pub trait Encode {
     /// types where invalid values are possible.
     fn encoding_length(&self) -> Result<usize>;
 
+    /// Whether [`encode`](#method.encode) should encode in a single pass,
+    /// growing the output buffer as needed, rather than first calling
+    /// `encoding_length` to allocate a buffer of the exact size.
+    ///
+    /// Computing the length up front walks the whole value twice, which is
+    /// slower for types whose `encoding_length` does as much work as encoding
+    /// them, such as `Serde<T>`, which serializes the value just to count its
+    /// bytes. Defaults to false. Ignored for sensitive types, since buffers
+    /// outgrown in a single pass can't be wiped.
+    const SINGLE_PASS: bool = false;
+
     /// Returns the encoded representation of the value as a `Vec<u8>`.
     ///
     /// While this method is convenient, it will often be more efficient to call
@@ -113,16 +442,76 @@ pub trait Encode {
     #[inline]
     #[cfg_attr(test, mutate)]
     fn encode(&self) -> Result<Vec<u8>> {
+        if Self::SINGLE_PASS && !Self::SENSITIVE {
+            return self.encode_single_pass();
+        }
+
         let length = self.encoding_length()?;
         let mut bytes = Vec::with_capacity(length);
         self.encode_into(&mut bytes)?;
         Ok(bytes)
     }
+
+    /// Returns the encoded representation of the value as a `Vec<u8>`,
+    /// without calling `encoding_length` first. The buffer starts small and
+    /// grows exponentially.
+    #[inline]
+    fn encode_single_pass(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(64);
+        self.encode_into(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Writes the encoded representation of the value into the start of
+    /// `buf`, returning the length of the encoding. Errors with
+    /// `Error::BufferTooSmall` if the encoding does not fit, in which case
+    /// `buf` may have been partially written.
+    ///
+    /// Unlike [`encode`](#method.encode), this never allocates, so it can be
+    /// used to encode onto the stack on hot paths or on targets without a
+    /// heap.
+    #[inline]
+    fn encode_into_slice(&self, buf: &mut [u8]) -> Result<usize> {
+        let capacity = buf.len();
+        let mut dest = buf;
+        match self.encode_into(&mut dest) {
+            Ok(()) => Ok(capacity - dest.len()),
+            Err(Error::IOError(err)) if err.kind() == io::ErrorKind::WriteZero => {
+                Err(Error::BufferTooSmall(capacity))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Encodes the elements of the slice one after another, in order.
+    ///
+    /// Used by the slice, array and vector impls, and overridden by `u8` to
+    /// write the whole slice at once rather than one byte at a time. There is no need to
+    /// override or call this outside of `ed`.
+    #[doc(hidden)]
+    #[inline]
+    fn encode_slice_into<W: Write>(slice: &[Self], dest: &mut W) -> Result<()>
+    where
+        Self: Sized,
+    {
+        for element in slice.iter() {
+            element.encode_into(dest)?;
+        }
+        Ok(())
+    }
 }
 
 /// A trait for values that can be decoded from bytes deterministically.
-#[fundamental]
 pub trait Decode: Sized {
+    /// Whether the encoding of this type may contain sensitive data such as
+    /// private keys. When true and the `zeroize` feature is enabled, temporary
+    /// buffers used while decoding values of this type are wiped after use.
+    ///
+    /// Defaults to false. Derived impls are sensitive if the type is marked
+    /// with `#[ed(sensitive)]` or any of its fields are sensitive. Types whose
+    /// fields hold secrets in plain types such as `Vec<u8>` should be marked.
+    const SENSITIVE: bool = false;
+
     /// Reads bytes from the reader and returns the decoded value.
     ///
     /// When possible, calling [`decode_into`](#method.decode_into) will often
@@ -149,14 +538,82 @@ pub trait Decode: Sized {
         *self = value;
         Ok(())
     }
+
+    /// Decodes `N` values one after another, in order.
+    ///
+    /// Used by the array impls, and overridden by `u8` to read the whole
+    /// array at once rather than one byte at a time. There is no need to
+    /// override or call this outside of `ed`.
+    #[doc(hidden)]
+    #[inline]
+    fn decode_array<R: Read, const N: usize>(mut input: R) -> Result<[Self; N]> {
+        // Decoded on the stack, since arrays are often used to avoid
+        // allocating.
+        let mut elements: [Option<Self>; N] = core::array::from_fn(|_| None);
+        for element in elements.iter_mut() {
+            *element = Some(Self::decode(&mut input)?);
+        }
+        // Every element was set above, which the optimizer can see for arrays
+        // short enough to unroll, so this path stays free of panics (see the
+        // `audit` module).
+        Ok(elements.map(|element| match element {
+            Some(element) => element,
+            None => unreachable!(),
+        }))
+    }
+
+    /// Decodes values into each element of the slice one after another, in
+    /// order.
+    ///
+    /// Used by the array impls, and overridden by `u8` to read the whole
+    /// slice at once rather than one byte at a time. There is no need to
+    /// override or call this outside of `ed`.
+    #[doc(hidden)]
+    #[inline]
+    fn decode_slice_into<R: Read>(slice: &mut [Self], mut input: R) -> Result<()> {
+        for element in slice.iter_mut() {
+            element.decode_into(&mut input)?;
+        }
+        Ok(())
+    }
+
+    /// Decodes values one after another until the end of the input, replacing
+    /// the contents of the vector.
+    ///
+    /// Used by the vector impl, and overridden by `u8` to read the rest of
+    /// the input straight into the vector. There is no need to override or
+    /// call this outside of `ed`.
+    #[doc(hidden)]
+    #[inline]
+    fn decode_vec_into<R: Read>(vec: &mut Vec<Self>, mut input: R) -> Result<()>
+    where
+        Self: Terminated,
+    {
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match vec.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => {
+                    let element = Self::decode(&mut element_input)?;
+                    // Reserving fallibly keeps the capacity overflow panic of
+                    // `push` off this path, which is audited by `audit`.
+                    vec.try_reserve(1).map_err(|_| Error::CapacityExceeded(i))?;
+                    vec.push(element);
+                }
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        vec.truncate(i);
+
+        Ok(())
+    }
 }
 
 /// A type is `Terminated` the length of the value being read can be determined
 /// when decoding.
 ///
-/// Since `Terminated` is an auto trait, it is automatically present for any
-/// type made of fields which are all `Terminated`.
-///
 /// Consider a type like `u32` - it is always 4 bytes long. If a slice of length
 /// 5 was passed to its `decode` method, it would know to stop reading after the
 /// 4th byte, which means it is `Terminated`.
@@ -164,8 +621,69 @@ pub trait Decode: Sized {
 /// For an example of something which is NOT terminated, consider `Vec<u8>`. Its
 /// encoding and decoding do not use a length prefix or end with a null byte, so
 /// `decode` would have no way to know where to stop reading.
+///
+/// `#[derive(Encode)]` implements `Terminated` for types whose fields are all
+/// `Terminated`. Since fields encoded `with` a custom encoding are assumed to be
+/// terminated, and types with hand-written impls can't be inspected, this can
+/// be overridden on the type:
+///
+/// - `#[ed(terminated)]` implements `Terminated` regardless of the fields,
+///   e.g. for a type whose encoding is length-prefixed.
+/// - `#[ed(not_terminated)]` leaves `Terminated` unimplemented.
+///
+/// Types with hand-written `Encode` and `Decode` impls can implement it with
+/// `#[derive(Terminated)]`, which follows the same rules.
 pub trait Terminated {}
 
+/// A type is `FixedLength` if every value of the type has an encoding of the
+/// same length, known at compile time.
+///
+/// Consider a type like `u32` - it is always encoded as 4 bytes, so its
+/// `ENCODING_LENGTH` is 4. Types such as `Option<T>` or `Vec<T>` whose
+/// encoding length depends on the value are not `FixedLength`.
+///
+/// Knowing the length of a type statically allows the offsets of fields to be
+/// computed without decoding them, e.g. for the accessors generated by
+/// `#[ed(accessor)]`.
+///
+/// Fields encoded with `#[ed(with = "...")]` have no known length, so an
+/// accessor can't follow one:
+///
+/// ```compile_fail
+/// # mod foreign { pub struct Point { pub x: u32 } }
+/// use ed::{Decode, Encode};
+///
+/// #[derive(Encode, Decode)]
+/// #[ed(remote = "foreign::Point")]
+/// struct PointDef {
+///     x: u32,
+/// }
+///
+/// #[derive(Encode, Decode)]
+/// struct Anchor {
+///     #[ed(with = "PointDef")]
+///     origin: foreign::Point,
+///     #[ed(accessor)]
+///     height: u64,
+/// }
+/// ```
+pub trait FixedLength: Terminated {
+    /// The length of the encoding of every value of this type, in bytes.
+    const ENCODING_LENGTH: usize;
+
+    /// Returns the encoded representation of the value in a single pass,
+    /// using `ENCODING_LENGTH` as the exact capacity of the buffer.
+    #[inline]
+    fn encode_fixed(&self) -> Result<Vec<u8>>
+    where
+        Self: Encode,
+    {
+        let mut bytes = Vec::with_capacity(Self::ENCODING_LENGTH);
+        self.encode_into(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
 macro_rules! int_impl {
     ($type:ty, $length:expr) => {
         impl Encode for $type {
@@ -196,10 +714,71 @@ macro_rules! int_impl {
         }
 
         impl Terminated for $type {}
+
+        impl FixedLength for $type {
+            const ENCODING_LENGTH: usize = $length;
+        }
     };
 }
 
-int_impl!(u8, 1);
+impl Encode for u8 {
+    #[doc = "Encodes the integer as a single byte."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&[*self])?;
+        Ok(())
+    }
+
+    #[doc = "Always returns Ok(1)."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(1)
+    }
+
+    #[inline]
+    fn encode_slice_into<W: Write>(slice: &[Self], dest: &mut W) -> Result<()> {
+        dest.write_all(slice)?;
+        Ok(())
+    }
+}
+
+impl Decode for u8 {
+    #[doc = "Decodes the integer from a single byte."]
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut buf = [0; 1];
+        input.read_exact(&mut buf[..])?;
+        let [byte] = buf;
+        Ok(byte)
+    }
+
+    #[inline]
+    fn decode_array<R: Read, const N: usize>(mut input: R) -> Result<[Self; N]> {
+        let mut bytes = [0; N];
+        input.read_exact(&mut bytes[..])?;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn decode_slice_into<R: Read>(slice: &mut [Self], mut input: R) -> Result<()> {
+        input.read_exact(slice)?;
+        Ok(())
+    }
+
+    #[inline]
+    fn decode_vec_into<R: Read>(vec: &mut Vec<Self>, input: R) -> Result<()> {
+        vec.clear();
+        wipe::read_to_end(input, vec, wipe::active())?;
+        limits::count_elements(vec.len())
+    }
+}
+
+impl Terminated for u8 {}
+
+impl FixedLength for u8 {
+    const ENCODING_LENGTH: usize = 1;
+}
+
 int_impl!(u16, 2);
 int_impl!(u32, 4);
 int_impl!(u64, 8);
@@ -210,6 +789,51 @@ int_impl!(i32, 4);
 int_impl!(i64, 8);
 int_impl!(i128, 16);
 
+macro_rules! nonzero_impl {
+    ($type:ty, $inner:ty) => {
+        impl Encode for $type {
+            #[doc = "Encodes the integer as fixed-size big-endian bytes."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                self.get().encode_into(dest)
+            }
+
+            #[doc = "Returns the size of the integer in bytes. Will always"]
+            #[doc = " return an `Ok` result."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(<$inner as FixedLength>::ENCODING_LENGTH)
+            }
+        }
+
+        impl Decode for $type {
+            #[doc = "Decodes the integer from fixed-size big-endian bytes."]
+            #[doc = " Errors with `Error::UnexpectedZero` if it is zero."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                Self::new(<$inner>::decode(input)?).ok_or(Error::UnexpectedZero)
+            }
+        }
+
+        impl Terminated for $type {}
+
+        impl FixedLength for $type {
+            const ENCODING_LENGTH: usize = <$inner as FixedLength>::ENCODING_LENGTH;
+        }
+    };
+}
+
+nonzero_impl!(core::num::NonZeroU8, u8);
+nonzero_impl!(core::num::NonZeroU16, u16);
+nonzero_impl!(core::num::NonZeroU32, u32);
+nonzero_impl!(core::num::NonZeroU64, u64);
+nonzero_impl!(core::num::NonZeroU128, u128);
+nonzero_impl!(core::num::NonZeroI8, i8);
+nonzero_impl!(core::num::NonZeroI16, i16);
+nonzero_impl!(core::num::NonZeroI32, i32);
+nonzero_impl!(core::num::NonZeroI64, i64);
+nonzero_impl!(core::num::NonZeroI128, i128);
+
 impl Encode for bool {
     /// Encodes the boolean as a single byte: 0 for false or 1 for true.
     #[inline]
@@ -236,7 +860,8 @@ impl Decode for bool {
     fn decode<R: Read>(mut input: R) -> Result<Self> {
         let mut buf = [0; 1];
         input.read_exact(&mut buf[..])?;
-        match buf[0] {
+        let [byte] = buf;
+        match byte {
             0 => Ok(false),
             1 => Ok(true),
             byte => Err(Error::UnexpectedByte(byte)),
@@ -246,7 +871,13 @@ impl Decode for bool {
 
 impl Terminated for bool {}
 
+impl FixedLength for bool {
+    const ENCODING_LENGTH: usize = 1;
+}
+
 impl<T: Encode> Encode for Option<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
     /// Encodes as a 0 byte for `None`, or as a 1 byte followed by the encoding of
     /// the inner value for `Some`.
     #[inline]
@@ -274,6 +905,8 @@ impl<T: Encode> Encode for Option<T> {
 }
 
 impl<T: Decode> Decode for Option<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
     /// Decodes a 0 byte as `None`, or a 1 byte followed by the encoding of the
     /// inner value as `Some`. Errors for all other values.
     #[inline]
@@ -293,10 +926,11 @@ impl<T: Decode> Decode for Option<T> {
     #[inline]
     #[cfg_attr(test, mutate)]
     fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
-        let mut byte = [0; 1];
-        input.read_exact(&mut byte[..])?;
+        let mut buf = [0; 1];
+        input.read_exact(&mut buf[..])?;
+        let [byte] = buf;
 
-        match byte[0] {
+        match byte {
             0 => *self = None,
             1 => match self {
                 None => *self = Some(T::decode(input)?),
@@ -313,6 +947,85 @@ impl<T: Decode> Decode for Option<T> {
 
 impl<T: Terminated> Terminated for Option<T> {}
 
+impl Encode for core::cmp::Ordering {
+    /// Encodes the ordering as a single byte: 0 for `Less`, 1 for `Equal` or
+    /// 2 for `Greater`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let byte = (*self as i8 + 1) as u8;
+        dest.write_all(&[byte])?;
+        Ok(())
+    }
+
+    /// Always returns Ok(1).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(1)
+    }
+}
+
+impl Decode for core::cmp::Ordering {
+    /// Decodes a 0 byte as `Less`, a 1 byte as `Equal` or a 2 byte as
+    /// `Greater`. Errors for all other values.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        use core::cmp::Ordering;
+        match u8::decode(input)? {
+            0 => Ok(Ordering::Less),
+            1 => Ok(Ordering::Equal),
+            2 => Ok(Ordering::Greater),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+}
+
+impl Terminated for core::cmp::Ordering {}
+
+impl FixedLength for core::cmp::Ordering {
+    const ENCODING_LENGTH: usize = 1;
+}
+
+impl<T: Encode> Encode for core::cmp::Reverse<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+    const SINGLE_PASS: bool = T::SINGLE_PASS;
+
+    #[doc = "Encodes the inner value."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.0.encode_into(dest)
+    }
+
+    #[doc = "Returns the encoding length of the inner value."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.0.encoding_length()
+    }
+}
+
+impl<T: Decode> Decode for core::cmp::Reverse<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the inner value."]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        T::decode(input).map(core::cmp::Reverse)
+    }
+
+    #[doc = "Decodes the inner value into the existing value."]
+    #[doc = ""]
+    #[doc = "Recursively calls `decode_into` on the inner value."]
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        self.0.decode_into(input)
+    }
+}
+
+impl<T: Terminated> Terminated for core::cmp::Reverse<T> {}
+
+impl<T: FixedLength> FixedLength for core::cmp::Reverse<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
 impl Encode for () {
     /// Encoding a unit tuple is a no-op.
     #[inline]
@@ -340,9 +1053,40 @@ impl Decode for () {
 
 impl Terminated for () {}
 
+impl FixedLength for () {
+    const ENCODING_LENGTH: usize = 0;
+}
+
+impl Encode for core::convert::Infallible {
+    /// Unreachable, since no value of this type can exist.
+    #[inline]
+    fn encode_into<W: Write>(&self, _: &mut W) -> Result<()> {
+        match *self {}
+    }
+
+    /// Unreachable, since no value of this type can exist.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        match *self {}
+    }
+}
+
+impl Decode for core::convert::Infallible {
+    /// Always errors with `Error::Uninhabited` without reading any bytes,
+    /// since no value of this type can exist.
+    #[inline]
+    fn decode<R: Read>(_: R) -> Result<Self> {
+        Err(Error::Uninhabited)
+    }
+}
+
+impl Terminated for core::convert::Infallible {}
+
 macro_rules! tuple_impl {
     ($( $type:ident ),*; $last_type:ident) => {
         impl<$($type: Encode + Terminated,)* $last_type: Encode> Encode for ($($type,)* $last_type,) {
+            const SENSITIVE: bool = $($type::SENSITIVE ||)* $last_type::SENSITIVE;
+
             #[doc = "Encodes the fields of the tuple one after another, in"]
             #[doc = " order."]
             #[allow(non_snake_case, unused_mut)]
@@ -368,6 +1112,8 @@ macro_rules! tuple_impl {
         }
 
         impl<$($type: Decode + Terminated,)* $last_type: Decode> Decode for ($($type,)* $last_type,) {
+            const SENSITIVE: bool = $($type::SENSITIVE ||)* $last_type::SENSITIVE;
+
             #[doc = "Decodes the fields of the tuple one after another, in"]
             #[doc = " order."]
             #[allow(unused_mut)]
@@ -394,6 +1140,10 @@ macro_rules! tuple_impl {
         }
 
         impl<$($type: Terminated,)* $last_type: Terminated> Terminated for ($($type,)* $last_type,) {}
+
+        impl<$($type: FixedLength,)* $last_type: FixedLength> FixedLength for ($($type,)* $last_type,) {
+            const ENCODING_LENGTH: usize = $($type::ENCODING_LENGTH +)* $last_type::ENCODING_LENGTH;
+        }
     }
 }
 
@@ -409,16 +1159,21 @@ tuple_impl!(A, B, C, D, E, F, G, H; I);
 tuple_impl!(A, B, C, D, E, F, G, H, I; J);
 tuple_impl!(A, B, C, D, E, F, G, H, I, J; K);
 tuple_impl!(A, B, C, D, E, F, G, H, I, J, K; L);
+tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L; M);
+tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M; N);
+tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N; O);
+tuple_impl!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O; P);
 
 impl<T: Encode + Terminated, const N: usize> Encode for [T; N] {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes the elements of the array one after another, in order."]
     #[inline]
-    fn encode_into<W: Write>(&self, mut dest: &mut W) -> Result<()> {
-        for element in self[..].iter() {
-            element.encode_into(&mut dest)?;
-        }
-        Ok(())
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(&self[..], dest)
     }
 
+    #[doc = "Returns the sum of the encoding lengths of the elements."]
     #[inline]
     fn encoding_length(&self) -> Result<usize> {
         let mut sum = 0;
@@ -430,36 +1185,118 @@ impl<T: Encode + Terminated, const N: usize> Encode for [T; N] {
 }
 
 impl<T: Decode + Terminated, const N: usize> Decode for [T; N] {
-    #[allow(unused_variables, unused_mut)]
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes `N` elements one after another, in order."]
     #[inline]
-    fn decode<R: Read>(mut input: R) -> Result<Self> {
-        let mut v: Vec<T> = Vec::with_capacity(N);
-        for i in 0..N {
-            v.push(T::decode(&mut input)?);
-        }
-        Ok(v.try_into()
-            .unwrap_or_else(|v: Vec<T>| panic!("Input Vec not of length {}", N)))
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        T::decode_array(input)
     }
 
+    #[doc = "Decodes `N` elements one after another, in order."]
+    #[doc = ""]
+    #[doc = "Recursively calls `decode_into` for each element."]
     #[inline]
-    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
-        for item in self.iter_mut().take(N) {
-            T::decode_into(item, &mut input)?;
-        }
-        Ok(())
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        T::decode_slice_into(&mut self[..], input)
     }
 }
 
 impl<T: Terminated, const N: usize> Terminated for [T; N] {}
 
-impl<T: Encode + Terminated> Encode for Vec<T> {
-    #[doc = "Encodes the elements of the vector one after another, in order."]
-    #[inline]
-    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
-        for element in self.iter() {
-            element.encode_into(dest)?;
+impl<T: FixedLength, const N: usize> FixedLength for [T; N] {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH * N;
+}
+
+/// Reads the first byte of the next element of an unterminated sequence,
+/// returning `None` at the end of the input or otherwise a reader which yields
+/// that byte followed by the rest of the input.
+///
+/// This lets sequences be decoded element by element straight from the
+/// reader, rather than first buffering the rest of the input to find its end.
+pub(crate) fn next_element<R: Read>(mut input: R) -> Result<Option<Peeked<R>>> {
+    let mut byte = [0; 1];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => {
+                return Ok(Some(Peeked {
+                    byte: Some(byte[0]),
+                    input,
+                }))
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// A reader which yields a byte already read by `next_element`, followed by
+/// the rest of the input.
+pub(crate) struct Peeked<R> {
+    byte: Option<u8>,
+    input: R,
+}
+
+impl<R> Peeked<R> {
+    /// Errors with `Error::UnexpectedByte` if the peeked byte hasn't been
+    /// read, i.e. the element decoded from this reader has an empty encoding.
+    ///
+    /// Otherwise the byte would be silently dropped, so e.g. `Vec<()>` would
+    /// decode one element per byte of any input and not re-encode to it.
+    #[inline]
+    pub(crate) fn check_consumed(&self) -> Result<()> {
+        match self.byte {
+            Some(byte) => Err(Error::UnexpectedByte(byte)),
+            None => Ok(()),
         }
-        Ok(())
+    }
+}
+
+impl<R: Read> Read for Peeked<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.byte, buf.first_mut()) {
+            (Some(byte), Some(first)) => {
+                *first = byte;
+                self.byte = None;
+                Ok(1)
+            }
+            _ => self.input.read(buf),
+        }
+    }
+}
+
+/// Reads the rest of the input into `buf`, returning the number of bytes read.
+/// Errors with `Error::CapacityExceeded` if the input doesn't fit.
+///
+/// This lets fixed-capacity types be decoded without allocating.
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+pub(crate) fn read_to_capacity<R: Read>(mut input: R, buf: &mut [u8]) -> Result<usize> {
+    let mut len = 0;
+    loop {
+        if len == buf.len() {
+            return match next_element(&mut input)? {
+                Some(_) => Err(Error::CapacityExceeded(buf.len())),
+                None => Ok(len),
+            };
+        }
+        match input.read(&mut buf[len..]) {
+            Ok(0) => return Ok(len),
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+impl<T: Encode + Terminated> Encode for Vec<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes the elements of the vector one after another, in order."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(self, dest)
     }
 
     #[doc = "Returns the sum of the encoding lengths of all elements."]
@@ -475,11 +1312,17 @@ impl<T: Encode + Terminated> Encode for Vec<T> {
 }
 
 impl<T: Decode + Terminated> Decode for Vec<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
     #[doc = "Decodes the elements of the vector one after another, in order."]
+    #[doc = ""]
+    #[doc = "Nothing is allocated up front. The vector grows as elements are"]
+    #[doc = " decoded, so its capacity is bounded by the length of the input"]
+    #[doc = " rather than by a fixed guess."]
     #[cfg_attr(test, mutate)]
     #[inline]
     fn decode<R: Read>(input: R) -> Result<Self> {
-        let mut vec = Vec::with_capacity(128);
+        let mut vec = Vec::new();
         vec.decode_into(input)?;
         Ok(vec)
     }
@@ -489,129 +1332,660 @@ impl<T: Decode + Terminated> Decode for Vec<T> {
     #[doc = "Recursively calls `decode_into` for each element."]
     #[cfg_attr(test, mutate)]
     #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        T::decode_vec_into(self, input)
+    }
+}
+
+impl<T: Encode + Terminated> Encode for alloc::collections::VecDeque<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes the elements of the deque one after another, from front"]
+    #[doc = " to back."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for element in self.iter() {
+            element.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    #[doc = "Returns the sum of the encoding lengths of all elements."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Decode + Terminated> Decode for alloc::collections::VecDeque<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the elements of the deque one after another, from front"]
+    #[doc = " to back."]
+    #[doc = ""]
+    #[doc = "Nothing is allocated up front. The deque grows as elements are"]
+    #[doc = " decoded."]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut deque = alloc::collections::VecDeque::new();
+        deque.decode_into(input)?;
+        Ok(deque)
+    }
+
+    #[doc = "Decodes the elements of the deque one after another, from front"]
+    #[doc = " to back, reusing the existing allocation."]
+    #[doc = ""]
+    #[doc = "Recursively calls `decode_into` for each existing element."]
+    #[inline]
     fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
-        let old_len = self.len();
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => self.push_back(T::decode(&mut element_input)?),
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+impl<T: Encode + Terminated> Encode for [T] {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes the elements of the slice one after another, in order."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        T::encode_slice_into(self, dest)
+    }
+
+    #[doc = "Returns the sum of the encoding lengths of all elements."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self[..].iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl Encode for String {
+    #[doc = "Encodes the UTF-8 bytes of the string."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_str().encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the string in bytes."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl Encode for str {
+    #[doc = "Encodes the UTF-8 bytes of the string."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.as_bytes())?;
+        Ok(())
+    }
+
+    #[doc = "Returns the length of the string in bytes."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.len())
+    }
+}
+
+impl Encode for &str {
+    #[doc = "Encodes the UTF-8 bytes of the string."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the string in bytes."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        (**self).encoding_length()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Encode for std::path::Path {
+    #[doc = "Encodes the path as UTF-8 bytes. Errors with `Error::InvalidUtf8`"]
+    #[doc = " if the path is not valid UTF-8."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.to_str().ok_or(Error::InvalidUtf8)?.encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the path in bytes. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the path is not valid UTF-8."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.to_str().ok_or(Error::InvalidUtf8)?.encoding_length()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Encode for &std::path::Path {
+    #[doc = "Encodes the path as UTF-8 bytes. Errors with `Error::InvalidUtf8`"]
+    #[doc = " if the path is not valid UTF-8."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the path in bytes. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the path is not valid UTF-8."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        (**self).encoding_length()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Encode for std::path::PathBuf {
+    #[doc = "Encodes the path as UTF-8 bytes. Errors with `Error::InvalidUtf8`"]
+    #[doc = " if the path is not valid UTF-8."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_path().encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the path in bytes. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the path is not valid UTF-8."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.as_path().encoding_length()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Decode for std::path::PathBuf {
+    #[doc = "Decodes the rest of the input as a UTF-8 path. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the bytes are not valid UTF-8."]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        String::decode(input).map(std::path::PathBuf::from)
+    }
+}
+
+impl Encode for core::ffi::CStr {
+    #[doc = "Encodes the bytes of the string followed by a NUL byte."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.to_bytes_with_nul())?;
+        Ok(())
+    }
+
+    #[doc = "Returns the length of the string in bytes, plus 1 for the NUL"]
+    #[doc = " byte."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.to_bytes_with_nul().len())
+    }
+}
+
+impl Terminated for core::ffi::CStr {}
+
+impl Encode for &core::ffi::CStr {
+    #[doc = "Encodes the bytes of the string followed by a NUL byte."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the string in bytes, plus 1 for the NUL"]
+    #[doc = " byte."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        (**self).encoding_length()
+    }
+}
+
+impl Terminated for &core::ffi::CStr {}
+
+impl Encode for alloc::ffi::CString {
+    #[doc = "Encodes the bytes of the string followed by a NUL byte."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_c_str().encode_into(dest)
+    }
+
+    #[doc = "Returns the length of the string in bytes, plus 1 for the NUL"]
+    #[doc = " byte."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.as_c_str().encoding_length()
+    }
+}
+
+impl Decode for alloc::ffi::CString {
+    #[doc = "Decodes bytes up to and including the first NUL byte. Errors if"]
+    #[doc = " the input ends before a NUL byte is found."]
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut bytes = Vec::new();
+        loop {
+            match u8::decode(&mut input)? {
+                0 => break,
+                byte => bytes.push(byte),
+            }
+        }
+        // The conversion can't fail since `bytes` contains no NUL bytes, but
+        // mapping the error keeps this path free of panics.
+        alloc::ffi::CString::new(bytes).map_err(|_| Error::UnexpectedByte(0))
+    }
+}
+
+impl Terminated for alloc::ffi::CString {}
+
+impl Decode for String {
+    #[doc = "Decodes the rest of the input as a UTF-8 string. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the bytes are not valid UTF-8."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut string = String::new();
+        string.decode_into(input)?;
+        Ok(string)
+    }
+
+    #[doc = "Decodes the rest of the input as a UTF-8 string, reusing the"]
+    #[doc = " existing allocation."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        let mut bytes = core::mem::take(self).into_bytes();
+        bytes.clear();
+        *self = decode_utf8(bytes, input)?;
+        Ok(())
+    }
+}
+
+/// Reads the rest of the input into `bytes` and converts it to a string,
+/// wiping the bytes if they turn out not to be valid UTF-8.
+fn decode_utf8<R: Read>(mut bytes: Vec<u8>, input: R) -> Result<String> {
+    let sensitive = wipe::active();
+    wipe::read_to_end(input, &mut bytes, sensitive)?;
+    String::from_utf8(bytes).map_err(|err| {
+        wipe::wipe(&mut err.into_bytes(), sensitive);
+        Error::InvalidUtf8
+    })
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+    const SINGLE_PASS: bool = T::SINGLE_PASS;
+
+    #[doc = "Encodes the inner value."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).encode_into(dest)
+    }
+
+    #[doc = "Returns the encoding length of the inner value."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        (**self).encoding_length()
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the inner value into a new Box."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let _depth = limits::descend()?;
+        T::decode(input).map(|v| v.into())
+    }
+
+    #[doc = "Decodes the inner value into the existing Box."]
+    #[doc = ""]
+    #[doc = "Recursively calls `decode_into` on the inner value."]
+    #[cfg_attr(test, mutate)]
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        (**self).decode_into(input)
+    }
+}
+
+impl<T: Decode + Terminated> Decode for Box<[T]> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the elements of the slice one after another, in order."]
+    #[doc = ""]
+    #[doc = "The number of elements isn't known until the input ends, so they"]
+    #[doc = " are decoded into a `Vec<T>` first, like those of a vector. Converting"]
+    #[doc = " it into the boxed slice reallocates to drop its spare capacity,"]
+    #[doc = " unless the vector happens to be full."]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let _depth = limits::descend()?;
+        let mut elements = Vec::new();
+        T::decode_vec_into(&mut elements, input)?;
+        Ok(elements.into_boxed_slice())
+    }
+}
+
+impl Decode for Box<str> {
+    #[doc = "Decodes the rest of the input as a UTF-8 string. Errors with"]
+    #[doc = " `Error::InvalidUtf8` if the bytes are not valid UTF-8."]
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        decode_utf8(Vec::new(), input).map(String::into_boxed_str)
+    }
+}
+
+impl<T: Terminated + ?Sized> Terminated for Box<T> {}
+
+impl<T: FixedLength> FixedLength for Box<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
+macro_rules! shared_ptr_impl {
+    ($ptr:ident, $name:expr) => {
+        impl<T: Encode + ?Sized> Encode for $ptr<T> {
+            const SENSITIVE: bool = T::SENSITIVE;
+            const SINGLE_PASS: bool = T::SINGLE_PASS;
+
+            #[doc = "Encodes the inner value."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                (**self).encode_into(dest)
+            }
+
+            #[doc = "Returns the encoding length of the inner value."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                (**self).encoding_length()
+            }
+        }
+
+        impl<T: Decode> Decode for $ptr<T> {
+            const SENSITIVE: bool = T::SENSITIVE;
+
+            #[doc = concat!("Decodes the inner value into a new ", $name, ".")]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                let _depth = limits::descend()?;
+                T::decode(input).map($ptr::new)
+            }
+
+            #[doc = concat!("Decodes the inner value into the existing ", $name, " if it")]
+            #[doc = concat!(" is the only reference to its value, otherwise into a new ", $name, ".")]
+            #[doc = ""]
+            #[doc = "Recursively calls `decode_into` on the inner value when it is"]
+            #[doc = " uniquely owned."]
+            #[inline]
+            fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+                match $ptr::get_mut(self) {
+                    Some(value) => {
+                        let _depth = limits::descend()?;
+                        value.decode_into(input)
+                    }
+                    None => {
+                        *self = Self::decode(input)?;
+                        Ok(())
+                    }
+                }
+            }
+        }
 
-        let mut bytes = Vec::with_capacity(256);
-        input.read_to_end(&mut bytes)?;
+        impl<T: Decode + Terminated> Decode for $ptr<[T]> {
+            const SENSITIVE: bool = T::SENSITIVE;
 
-        let mut slice = bytes.as_slice();
-        let mut i = 0;
-        while !slice.is_empty() {
-            if i < old_len {
-                self[i].decode_into(&mut slice)?;
-            } else {
-                let el = T::decode(&mut slice)?;
-                self.push(el);
+            #[doc = "Decodes the elements of the slice one after another, in order."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                Box::<[T]>::decode(input).map($ptr::from)
             }
+        }
 
-            i += 1;
+        impl Decode for $ptr<str> {
+            #[doc = "Decodes the rest of the input as a UTF-8 string. Errors with"]
+            #[doc = " `Error::InvalidUtf8` if the bytes are not valid UTF-8."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                Box::<str>::decode(input).map($ptr::from)
+            }
         }
 
-        if i < old_len {
-            self.truncate(i);
+        impl<T: Terminated + ?Sized> Terminated for $ptr<T> {}
+
+        impl<T: FixedLength> FixedLength for $ptr<T> {
+            const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
         }
+    };
+}
 
-        Ok(())
+shared_ptr_impl!(Rc, "Rc");
+shared_ptr_impl!(Arc, "Arc");
+
+impl<'a, B: Encode + ToOwned + ?Sized> Encode for alloc::borrow::Cow<'a, B> {
+    const SENSITIVE: bool = B::SENSITIVE;
+    const SINGLE_PASS: bool = B::SINGLE_PASS;
+
+    #[doc = "Encodes the borrowed or owned value."]
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        (**self).encode_into(dest)
+    }
+
+    #[doc = "Returns the encoding length of the borrowed or owned value."]
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        (**self).encoding_length()
     }
 }
 
-impl<T: Encode + Terminated> Encode for [T] {
-    #[doc = "Encodes the elements of the slice one after another, in order."]
-    #[cfg_attr(test, mutate)]
+impl<'a, B: ToOwned + ?Sized> Decode for alloc::borrow::Cow<'a, B>
+where
+    B::Owned: Decode,
+{
+    const SENSITIVE: bool = B::Owned::SENSITIVE;
+
+    #[doc = "Decodes an owned value."]
     #[inline]
-    fn encode_into<W: Write>(&self, mut dest: &mut W) -> Result<()> {
-        for element in self[..].iter() {
-            element.encode_into(&mut dest)?;
-        }
-        Ok(())
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        B::Owned::decode(input).map(alloc::borrow::Cow::Owned)
     }
 
-    #[doc = "Returns the sum of the encoding lengths of all elements."]
-    #[cfg_attr(test, mutate)]
+    #[doc = "Decodes an owned value. If the existing value is already owned,"]
+    #[doc = " `decode_into` is called on it."]
     #[inline]
-    fn encoding_length(&self) -> Result<usize> {
-        let mut sum = 0;
-        for element in self[..].iter() {
-            sum += element.encoding_length()?;
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        match self {
+            alloc::borrow::Cow::Owned(value) => value.decode_into(input),
+            alloc::borrow::Cow::Borrowed(_) => {
+                *self = Self::decode(input)?;
+                Ok(())
+            }
         }
-        Ok(sum)
     }
 }
 
-impl<T: Encode> Encode for Box<T> {
-    #[doc = "Encodes the inner value."]
+impl<'a, B: Terminated + ToOwned + ?Sized> Terminated for alloc::borrow::Cow<'a, B> {}
+
+impl<T: Encode> Encode for core::cell::RefCell<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes the inner value. Errors with `Error::AlreadyBorrowed`"]
+    #[doc = " if the value is currently mutably borrowed."]
     #[cfg_attr(test, mutate)]
     #[inline]
     fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
-        (**self).encode_into(dest)
+        self.try_borrow()
+            .map_err(|_| Error::AlreadyBorrowed)?
+            .encode_into(dest)
     }
 
-    #[doc = "Returns the encoding length of the inner value."]
+    #[doc = "Returns the encoding length of the inner value. Errors with"]
+    #[doc = " `Error::AlreadyBorrowed` if the value is currently mutably"]
+    #[doc = " borrowed."]
     #[cfg_attr(test, mutate)]
     #[inline]
     fn encoding_length(&self) -> Result<usize> {
-        (**self).encoding_length()
+        self.try_borrow()
+            .map_err(|_| Error::AlreadyBorrowed)?
+            .encoding_length()
     }
 }
 
-impl<T: Decode> Decode for Box<T> {
-    #[doc = "Decodes the inner value into a new Box."]
+impl<T: Decode> Decode for core::cell::RefCell<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the inner value into a new RefCell."]
     #[cfg_attr(test, mutate)]
     #[inline]
     fn decode<R: Read>(input: R) -> Result<Self> {
-        T::decode(input).map(|v| v.into())
+        T::decode(input).map(core::cell::RefCell::new)
     }
 
-    #[doc = "Decodes the inner value into the existing Box."]
+    #[doc = "Decodes the inner value into the existing RefCell."]
     #[doc = ""]
-    #[doc = "Recursively calls `decode_into` on the inner value."]
+    #[doc = "Recursively calls `decode_into` on the inner value. Since `self`"]
+    #[doc = " is borrowed mutably, this never fails to borrow the inner value."]
     #[cfg_attr(test, mutate)]
     #[inline]
     fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
-        (**self).decode_into(input)
+        self.get_mut().decode_into(input)
     }
 }
 
-impl<T: Terminated> Terminated for Box<T> {}
+impl<T: Terminated> Terminated for core::cell::RefCell<T> {}
 
-impl<T: Encode> Encode for std::cell::RefCell<T> {
-    #[doc = "Encodes the inner value."]
-    #[cfg_attr(test, mutate)]
+impl<T: FixedLength> FixedLength for core::cell::RefCell<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
+impl<T: Encode + Copy> Encode for core::cell::Cell<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Encodes a copy of the inner value."]
     #[inline]
     fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
-        self.borrow().encode_into(dest)
+        self.get().encode_into(dest)
     }
 
     #[doc = "Returns the encoding length of the inner value."]
-    #[cfg_attr(test, mutate)]
     #[inline]
     fn encoding_length(&self) -> Result<usize> {
-        self.borrow().encoding_length()
+        self.get().encoding_length()
     }
 }
 
-impl<T: Decode> Decode for std::cell::RefCell<T> {
-    #[doc = "Decodes the inner value into a new RefCell."]
-    #[cfg_attr(test, mutate)]
+impl<T: Decode + Copy> Decode for core::cell::Cell<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    #[doc = "Decodes the inner value into a new Cell."]
     #[inline]
     fn decode<R: Read>(input: R) -> Result<Self> {
-        T::decode(input).map(std::cell::RefCell::new)
+        T::decode(input).map(core::cell::Cell::new)
     }
 
-    #[doc = "Decodes the inner value into the existing Box."]
+    #[doc = "Decodes the inner value into the existing Cell."]
     #[doc = ""]
     #[doc = "Recursively calls `decode_into` on the inner value."]
-    #[cfg_attr(test, mutate)]
     #[inline]
     fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
-        self.borrow_mut().decode_into(input)
+        self.get_mut().decode_into(input)
     }
 }
 
-impl<T: Terminated> Terminated for std::cell::RefCell<T> {}
+impl<T: Terminated + Copy> Terminated for core::cell::Cell<T> {}
+
+impl<T: FixedLength + Copy> FixedLength for core::cell::Cell<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
+macro_rules! atomic_impl {
+    ($type:ty, $inner:ty) => {
+        impl Encode for $type {
+            #[doc = "Encodes the value loaded with `SeqCst` ordering."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                self.load(core::sync::atomic::Ordering::SeqCst)
+                    .encode_into(dest)
+            }
+
+            #[doc = "Returns the size of the value in bytes. Will always"]
+            #[doc = " return an `Ok` result."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(<$inner as FixedLength>::ENCODING_LENGTH)
+            }
+        }
+
+        impl Decode for $type {
+            #[doc = "Decodes the value into a new atomic."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                <$inner>::decode(input).map(<$type>::new)
+            }
+
+            #[doc = "Decodes the value into the existing atomic."]
+            #[inline]
+            fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+                *self.get_mut() = <$inner>::decode(input)?;
+                Ok(())
+            }
+        }
+
+        impl Terminated for $type {}
+
+        impl FixedLength for $type {
+            const ENCODING_LENGTH: usize = <$inner as FixedLength>::ENCODING_LENGTH;
+        }
+    };
+}
 
-impl<T> Encode for std::marker::PhantomData<T> {
+// Each atomic type only exists on targets which support atomics of its width.
+#[cfg(target_has_atomic = "8")]
+atomic_impl!(core::sync::atomic::AtomicBool, bool);
+#[cfg(target_has_atomic = "8")]
+atomic_impl!(core::sync::atomic::AtomicU8, u8);
+#[cfg(target_has_atomic = "16")]
+atomic_impl!(core::sync::atomic::AtomicU16, u16);
+#[cfg(target_has_atomic = "32")]
+atomic_impl!(core::sync::atomic::AtomicU32, u32);
+#[cfg(target_has_atomic = "64")]
+atomic_impl!(core::sync::atomic::AtomicU64, u64);
+#[cfg(target_has_atomic = "8")]
+atomic_impl!(core::sync::atomic::AtomicI8, i8);
+#[cfg(target_has_atomic = "16")]
+atomic_impl!(core::sync::atomic::AtomicI16, i16);
+#[cfg(target_has_atomic = "32")]
+atomic_impl!(core::sync::atomic::AtomicI32, i32);
+#[cfg(target_has_atomic = "64")]
+atomic_impl!(core::sync::atomic::AtomicI64, i64);
+
+impl<T: ?Sized> Encode for core::marker::PhantomData<T> {
     /// Encoding PhantomData is a no-op.
     #[inline]
     #[cfg_attr(test, mutate)]
@@ -627,7 +2001,7 @@ impl<T> Encode for std::marker::PhantomData<T> {
     }
 }
 
-impl<T> Decode for std::marker::PhantomData<T> {
+impl<T: ?Sized> Decode for core::marker::PhantomData<T> {
     /// Returns a PhantomData without reading any bytes.
     #[inline]
     #[cfg_attr(test, mutate)]
@@ -636,7 +2010,11 @@ impl<T> Decode for std::marker::PhantomData<T> {
     }
 }
 
-impl<T> Terminated for std::marker::PhantomData<T> {}
+impl<T: ?Sized> Terminated for core::marker::PhantomData<T> {}
+
+impl<T: ?Sized> FixedLength for core::marker::PhantomData<T> {
+    const ENCODING_LENGTH: usize = 0;
+}
 
 #[cfg(test)]
 use mutagen::mutate;
@@ -695,6 +2073,37 @@ mod tests {
         assert_eq!(decoded_value, value);
     }
 
+    #[test]
+    fn encode_decode_16_tuple() {
+        type Key = (
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u8,
+            u16,
+        );
+        let value: Key = (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes.len(), 17);
+        assert_eq!(value.encoding_length().unwrap(), 17);
+        assert_eq!(<Key as FixedLength>::ENCODING_LENGTH, 17);
+
+        let decoded = Key::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.15, 15);
+        assert_eq!(decoded.encode().unwrap(), bytes);
+    }
+
     #[test]
     fn encode_decode_array() {
         let value: [u16; 4] = [1, 2, 3, 4];
@@ -704,6 +2113,133 @@ mod tests {
         assert_eq!(decoded_value, value);
     }
 
+    #[test]
+    fn encode_decode_array_any_length() {
+        let value = [7u8; 48];
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![7; 48]);
+        assert_eq!(<[u8; 48]>::decode(bytes.as_slice()).unwrap(), value);
+
+        let mut value = [0u32; 1000];
+        value[999] = 0x01020304;
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes.len(), 4000);
+        assert_eq!(&bytes[3996..], &[1, 2, 3, 4]);
+        assert_eq!(<[u32; 1000]>::decode(bytes.as_slice()).unwrap(), value);
+        assert_eq!(<[u32; 1000] as FixedLength>::ENCODING_LENGTH, 4000);
+    }
+
+    #[test]
+    fn encode_decode_byte_array_in_one_call() {
+        struct CountingWriter(Vec<u8>, usize);
+
+        impl Write for CountingWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.1 += 1;
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let hash = [0xabu8; 32];
+        let mut dest = CountingWriter(vec![], 0);
+        hash.encode_into(&mut dest).unwrap();
+        assert_eq!(dest.0, vec![0xab; 32]);
+        assert_eq!(dest.1, 1);
+
+        let mut input = [1u8; 33];
+        input[32] = 2;
+        let mut slice = &input[..];
+        assert_eq!(<[u8; 32]>::decode(&mut slice).unwrap(), [1; 32]);
+        assert_eq!(slice, &[2]);
+
+        let mut hash = [0u8; 4];
+        hash.decode_into(&[1, 2, 3, 4, 5][..]).unwrap();
+        assert_eq!(hash, [1, 2, 3, 4]);
+        assert!(<[u8; 4]>::decode(&[1, 2, 3][..]).is_err());
+    }
+
+    #[test]
+    fn decode_vec_from_reader() {
+        // Yields at most two bytes per read, so elements span reads.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = buf.len().min(self.0.len()).min(2);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let bytes = [0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3];
+        let vec = Vec::<u32>::decode(Trickle(&bytes)).unwrap();
+        assert_eq!(vec, vec![1, 2, 3]);
+
+        let mut vec = vec![Some(9u16); 5];
+        vec.decode_into(Trickle(&[1, 0, 4, 0])).unwrap();
+        assert_eq!(vec, vec![Some(4), None]);
+
+        let mut deque = std::collections::VecDeque::from(vec![7u16; 3]);
+        deque.decode_into(Trickle(&[0, 5])).unwrap();
+        assert_eq!(deque, vec![5]);
+
+        assert!(Vec::<u32>::decode(Trickle(&bytes[..11])).is_err());
+    }
+
+    #[test]
+    fn decode_vec_capacity() {
+        assert_eq!(Vec::<u32>::decode(&[][..]).unwrap().capacity(), 0);
+        assert_eq!(Vec::<u8>::decode(&[][..]).unwrap().capacity(), 0);
+
+        let vec = Vec::<u32>::decode(&[0, 0, 0, 1, 0, 0, 0, 2][..]).unwrap();
+        assert_eq!(vec, vec![1, 2]);
+        assert!(vec.capacity() < 16);
+
+        let bytes = vec![0; 4000];
+        let vec = Vec::<u32>::decode(bytes.as_slice()).unwrap();
+        assert_eq!(vec.len(), 1000);
+        assert!(vec.capacity() < 2000);
+
+        let deque = std::collections::VecDeque::<u16>::decode(&[0, 1][..]).unwrap();
+        assert!(deque.capacity() < 16);
+    }
+
+    #[test]
+    fn decode_empty_elements() {
+        assert!(Vec::<()>::decode(&[][..]).unwrap().is_empty());
+        assert!(matches!(
+            Vec::<()>::decode(&[7, 7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(Box::<[()]>::decode(&[7][..]).is_err());
+        assert!(std::collections::VecDeque::<()>::decode(&[7][..]).is_err());
+        assert!(matches!(
+            <() as crate::DecodeExt>::decode_next(&[7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+    }
+
+    #[test]
+    fn encode_decode_byte_vec() {
+        let blob: Vec<u8> = (0..=255).collect();
+        let bytes = blob.encode().unwrap();
+        assert_eq!(bytes, blob);
+        assert_eq!(blob[..].encode().unwrap(), blob);
+        assert_eq!(Vec::<u8>::decode(bytes.as_slice()).unwrap(), blob);
+
+        let mut vec: Vec<u8> = Vec::with_capacity(512);
+        vec.extend_from_slice(&[9; 300]);
+        let ptr = vec.as_ptr();
+        vec.decode_into(&[1, 2, 3][..]).unwrap();
+        assert_eq!(vec, vec![1, 2, 3]);
+        assert_eq!(vec.as_ptr(), ptr);
+    }
+
     #[test]
     #[should_panic(expected = "failed to fill whole buffer")]
     fn encode_decode_array_eof_length() {
@@ -718,6 +2254,17 @@ mod tests {
         let _: [u16; 4] = Decode::decode(&bytes[..]).unwrap();
     }
 
+    #[test]
+    fn encode_decode_array_owned_elements() {
+        let value = [Box::new(1u16), Box::new(2), Box::new(3)];
+        let bytes = value.encode().unwrap();
+        assert_eq!(<[Box<u16>; 3]>::decode(bytes.as_slice()).unwrap(), value);
+        assert!(<[Box<u16>; 3]>::decode(&bytes[..5])
+            .unwrap_err()
+            .is_unexpected_eof());
+        assert_eq!(<[Box<u16>; 0]>::decode(&[][..]).unwrap(), []);
+    }
+
     #[test]
     fn encode_decode_vec() {
         let value: Vec<u16> = vec![1, 2, 3, 4];
@@ -734,6 +2281,50 @@ mod tests {
         let _: Vec<u16> = Decode::decode(&bytes[..]).unwrap();
     }
 
+    #[test]
+    fn encode_decode_nonzero() {
+        use std::num::{NonZeroI16, NonZeroU32};
+
+        let value = NonZeroU32::new(0x12345678).unwrap();
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(value.encoding_length().unwrap(), 4);
+        assert_eq!(NonZeroU32::decode(bytes.as_slice()).unwrap(), value);
+
+        let value = NonZeroI16::new(-2).unwrap();
+        assert_eq!(value.encode().unwrap(), vec![0xff, 0xfe]);
+        assert_eq!(NonZeroI16::decode(&[0xff, 0xfe][..]).unwrap(), value);
+
+        assert!(matches!(
+            NonZeroU32::decode(&[0, 0, 0, 0][..]),
+            Err(Error::UnexpectedZero)
+        ));
+        assert!(NonZeroU32::decode(&[0, 0, 1][..]).is_err());
+    }
+
+    #[test]
+    fn encode_decode_ordering() {
+        use std::cmp::{Ordering, Reverse};
+
+        for (ordering, byte) in [
+            (Ordering::Less, 0),
+            (Ordering::Equal, 1),
+            (Ordering::Greater, 2),
+        ] {
+            assert_eq!(ordering.encode().unwrap(), vec![byte]);
+            assert_eq!(Ordering::decode(&[byte][..]).unwrap(), ordering);
+        }
+        assert!(matches!(
+            Ordering::decode(&[3][..]),
+            Err(Error::UnexpectedByte(3))
+        ));
+
+        let reverse = Reverse(0x1234u16);
+        assert_eq!(reverse.encode().unwrap(), vec![0x12, 0x34]);
+        assert_eq!(reverse.encoding_length().unwrap(), 2);
+        assert_eq!(Reverse::<u16>::decode(&[0x12, 0x34][..]).unwrap(), reverse);
+    }
+
     #[test]
     fn test_encode_bool() {
         let value: bool = true;
@@ -851,6 +2442,27 @@ mod tests {
         assert_eq!(vec, vec![12, 13]);
     }
 
+    #[test]
+    fn encode_decode_vec_deque() {
+        let mut deque = std::collections::VecDeque::with_capacity(8);
+        deque.push_back(2u16);
+        deque.push_front(1);
+        let bytes = deque.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2]);
+        assert_eq!(deque.encoding_length().unwrap(), 4);
+
+        let capacity = deque.capacity();
+        deque.decode_into(&[0, 3, 0, 4, 0, 5][..]).unwrap();
+        assert_eq!(deque, vec![3, 4, 5]);
+        assert_eq!(deque.capacity(), capacity);
+        deque.decode_into(&[0, 6][..]).unwrap();
+        assert_eq!(deque, vec![6]);
+
+        let decoded: std::collections::VecDeque<u16> = Decode::decode(&bytes[..]).unwrap();
+        assert_eq!(decoded, vec![1, 2]);
+        assert!(std::collections::VecDeque::<u16>::decode(&[0, 1, 0][..]).is_err());
+    }
+
     #[test]
     fn test_vec_encoding_length() {
         let forty_two: u8 = 42;
@@ -860,6 +2472,205 @@ mod tests {
         assert!(vec_length == indv_num_length * 3);
     }
 
+    #[test]
+    fn encode_decode_string() {
+        let string = "héllo".to_string();
+        let bytes = string.encode().unwrap();
+        assert_eq!(bytes, "héllo".as_bytes());
+        assert_eq!(string.encoding_length().unwrap(), 6);
+
+        let decoded: String = Decode::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, string);
+        let decoded: (u8, String) = Decode::decode(&[1, b'h', b'i'][..]).unwrap();
+        assert_eq!(decoded, (1, "hi".to_string()));
+    }
+
+    #[test]
+    fn encode_str() {
+        let key: &str = "key";
+        assert_eq!(key.encode().unwrap(), b"key");
+        assert_eq!((7u8, key).encode().unwrap(), b"\x07key");
+        assert_eq!((7u8, key).encoding_length().unwrap(), 4);
+
+        let boxed: Box<str> = "boxed".into();
+        assert_eq!(boxed.encode().unwrap(), b"boxed");
+        assert_eq!(boxed.encoding_length().unwrap(), 5);
+    }
+
+    #[test]
+    fn encode_decode_path() {
+        use std::path::{Path, PathBuf};
+
+        let path = PathBuf::from("config/app.toml");
+        let bytes = path.encode().unwrap();
+        assert_eq!(bytes, b"config/app.toml");
+        assert_eq!(path.encoding_length().unwrap(), 15);
+        assert_eq!(PathBuf::decode(bytes.as_slice()).unwrap(), path);
+        let borrowed: &Path = &path;
+        assert_eq!((1u8, borrowed).encode().unwrap(), b"\x01config/app.toml");
+        assert!(matches!(
+            PathBuf::decode(&[0xff][..]),
+            Err(Error::InvalidUtf8)
+        ));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = Path::new(std::ffi::OsStr::from_bytes(&[0xff]));
+            assert!(matches!(path.encode(), Err(Error::InvalidUtf8)));
+        }
+    }
+
+    #[test]
+    fn encode_decode_c_string() {
+        use std::ffi::{CStr, CString};
+
+        let string = CString::new("abc").unwrap();
+        let bytes = string.encode().unwrap();
+        assert_eq!(bytes, b"abc\0");
+        assert_eq!(string.encoding_length().unwrap(), 4);
+        assert_eq!(string.as_c_str().encode().unwrap(), bytes);
+
+        let value: (CString, u8) = Decode::decode(&b"abc\0\x07"[..]).unwrap();
+        assert_eq!(value, (string, 7));
+        let c_str: &CStr = &value.0;
+        assert_eq!((c_str, 7u8).encode().unwrap(), b"abc\0\x07");
+
+        assert_eq!(CString::decode(&[0][..]).unwrap(), CString::default());
+        assert!(CString::decode(&b"abc"[..]).is_err());
+    }
+
+    #[test]
+    fn test_string_decode_into() {
+        let mut string = String::with_capacity(64);
+        string.push_str("old");
+        string.decode_into(&b"new"[..]).unwrap();
+        assert_eq!(string, "new");
+        assert!(string.capacity() >= 64);
+
+        let err = string.decode_into(&[b'a', 0xff][..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidUtf8));
+        assert!(String::decode(&[0xc3][..]).is_err());
+    }
+
+    #[test]
+    fn encode_decode_cow() {
+        use std::borrow::Cow;
+
+        let bytes: Cow<[u8]> = Cow::Borrowed(&[1, 2, 3]);
+        assert_eq!(bytes.encode().unwrap(), vec![1, 2, 3]);
+        let string: Cow<str> = Cow::Borrowed("abc");
+        assert_eq!(string.encode().unwrap(), b"abc");
+        assert_eq!((1u8, string.clone()).encoding_length().unwrap(), 4);
+
+        let decoded: Cow<str> = Decode::decode(&b"abc"[..]).unwrap();
+        assert!(matches!(decoded, Cow::Owned(_)));
+        assert_eq!(decoded, string);
+
+        let mut value: Cow<u16> = Cow::Borrowed(&1);
+        value.decode_into(&[0, 2][..]).unwrap();
+        assert!(matches!(value, Cow::Owned(2)));
+        value.decode_into(&[0, 3][..]).unwrap();
+        assert_eq!(*value, 3);
+    }
+
+    #[test]
+    fn encode_decode_shared_ptr() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let value = Rc::new(0x1234u16);
+        assert_eq!(value.encode().unwrap(), vec![0x12, 0x34]);
+        assert_eq!(Rc::<u16>::decode(&[0x12, 0x34][..]).unwrap(), value);
+        let value = Arc::new(Some(1u8));
+        assert_eq!(value.encode().unwrap(), vec![1, 1]);
+        assert_eq!(Arc::<Option<u8>>::decode(&[1, 1][..]).unwrap(), value);
+
+        let mut unique = Rc::new(Box::new(1u8));
+        let ptr: *const u8 = &**unique;
+        unique.decode_into(&[2][..]).unwrap();
+        assert!(std::ptr::eq(&**unique, ptr));
+
+        let mut shared = Arc::new(1u8);
+        let other = shared.clone();
+        shared.decode_into(&[2][..]).unwrap();
+        assert_eq!((*shared, *other), (2, 1));
+    }
+
+    #[test]
+    fn encode_decode_cells() {
+        use std::cell::{Cell, RefCell};
+
+        let cell = RefCell::new(0x1234u16);
+        assert_eq!(cell.encode().unwrap(), vec![0x12, 0x34]);
+        {
+            let _borrow = cell.borrow_mut();
+            assert!(matches!(cell.encode(), Err(Error::AlreadyBorrowed)));
+            assert!(matches!(
+                cell.encoding_length(),
+                Err(Error::AlreadyBorrowed)
+            ));
+        }
+        assert_eq!(cell.encoding_length().unwrap(), 2);
+
+        let mut cell = Cell::new(7u8);
+        assert_eq!(cell.encode().unwrap(), vec![7]);
+        cell.decode_into(&[8][..]).unwrap();
+        assert_eq!(cell.get(), 8);
+        assert_eq!(Cell::<u8>::decode(&[9][..]).unwrap().get(), 9);
+    }
+
+    #[test]
+    fn encode_decode_boxed_slices() {
+        let boxed: Box<[u16]> = vec![1, 2, 3].into_boxed_slice();
+        let bytes = boxed.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2, 0, 3]);
+        assert_eq!(boxed.encoding_length().unwrap(), 6);
+        assert_eq!(Box::<[u16]>::decode(bytes.as_slice()).unwrap(), boxed);
+        assert!(Box::<[u16]>::decode(&[][..]).unwrap().is_empty());
+        assert!(Box::<[u16]>::decode(&[0, 1, 0][..]).is_err());
+        assert!(Box::<[()]>::decode(&[][..]).unwrap().is_empty());
+
+        let boxed: Box<[Option<u8>]> = Decode::decode(&[0, 1, 5, 0][..]).unwrap();
+        assert_eq!(&*boxed, &[None, Some(5), None]);
+
+        // Lengths on either side of the vector's growth steps, so that some
+        // decodes fill it and others leave spare capacity to drop.
+        for len in 0..=33u16 {
+            let elements: Vec<u16> = (0..len).collect();
+            let bytes = elements.encode().unwrap();
+            let boxed = Box::<[u16]>::decode(bytes.as_slice()).unwrap();
+            assert_eq!(&*boxed, elements.as_slice());
+        }
+
+        let boxed: Box<str> = Decode::decode(&b"boxed"[..]).unwrap();
+        assert_eq!(&*boxed, "boxed");
+        assert!(matches!(
+            Box::<str>::decode(&[0xff][..]),
+            Err(Error::InvalidUtf8)
+        ));
+    }
+
+    #[test]
+    fn encode_decode_shared_slices() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        let key: Arc<str> = Arc::from("key");
+        assert_eq!(key.encode().unwrap(), b"key");
+        assert_eq!(Arc::<str>::decode(&b"key"[..]).unwrap(), key);
+        let key: Rc<str> = Rc::from("key");
+        assert_eq!((1u8, key.clone()).encode().unwrap(), b"\x01key");
+        assert_eq!(Rc::<str>::decode(&b"key"[..]).unwrap(), key);
+
+        let buf: Rc<[u16]> = Rc::from(vec![1, 2]);
+        assert_eq!(buf.encode().unwrap(), vec![0, 1, 0, 2]);
+        assert_eq!(Rc::<[u16]>::decode(&[0, 1, 0, 2][..]).unwrap(), buf);
+        let buf: Arc<[u8]> = Decode::decode(&[3, 4][..]).unwrap();
+        assert_eq!(&*buf, &[3, 4]);
+        assert!(Arc::<str>::decode(&[0xff][..]).is_err());
+    }
+
     #[test]
     fn test_box_encoding_length() {
         let forty_two = Box::new(42);
@@ -908,6 +2719,89 @@ mod tests {
         assert_eq!(size, 12);
     }
 
+    #[test]
+    fn encode_decode_atomics() {
+        use std::sync::atomic::{AtomicBool, AtomicI16, AtomicU64, Ordering};
+
+        let counter = AtomicU64::new(0x0102);
+        counter.fetch_add(1, Ordering::SeqCst);
+        let bytes = counter.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0, 0, 1, 3]);
+        assert_eq!(counter.encoding_length().unwrap(), 8);
+        assert_eq!(
+            AtomicU64::decode(bytes.as_slice())
+                .unwrap()
+                .load(Ordering::SeqCst),
+            0x0103
+        );
+
+        let mut value = AtomicI16::new(0);
+        value.decode_into(&[0xff, 0xfe][..]).unwrap();
+        assert_eq!(value.load(Ordering::SeqCst), -2);
+        assert_eq!(<AtomicI16 as FixedLength>::ENCODING_LENGTH, 2);
+
+        assert_eq!(AtomicBool::new(true).encode().unwrap(), vec![1]);
+        assert!(matches!(
+            AtomicBool::decode(&[2][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+    }
+
+    #[test]
+    fn custom_error() {
+        #[derive(Debug)]
+        struct Even(u8);
+
+        impl Decode for Even {
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                match u8::decode(input)? {
+                    n if n % 2 == 0 => Ok(Even(n)),
+                    n => Err(Error::custom(format_args!("{} is odd", n))),
+                }
+            }
+        }
+
+        assert_eq!(Even::decode(&[4][..]).unwrap().0, 4);
+        let err = Even::decode(&[5][..]).unwrap_err();
+        assert!(matches!(&err, Error::Custom(msg) if msg == "5 is odd"));
+        assert_eq!(err.to_string(), "5 is odd");
+        let _: &dyn std::error::Error = &err;
+    }
+
+    #[test]
+    fn error_context() {
+        let err = Error::UnexpectedByte(7)
+            .in_field("Header", "timestamp")
+            .in_field("Block", "header");
+        assert_eq!(err.path().unwrap(), "Block.header.timestamp");
+        assert_eq!(
+            err.to_string(),
+            "Block.header.timestamp: Unexpected byte: 7"
+        );
+        assert!(matches!(err.inner(), Error::UnexpectedByte(7)));
+
+        let err = err.at(12);
+        assert_eq!(err.path().unwrap(), "Block.header.timestamp");
+        assert!(matches!(err.inner(), Error::UnexpectedByte(7)));
+        assert_eq!(Error::UnexpectedByte(7).path(), None);
+    }
+
+    #[test]
+    fn decode_infallible() {
+        use std::convert::Infallible;
+
+        assert!(matches!(
+            Infallible::decode(&[][..]),
+            Err(Error::Uninhabited)
+        ));
+        assert!(matches!(
+            Option::<Infallible>::decode(&[1][..]),
+            Err(Error::Uninhabited)
+        ));
+        assert!(Option::<Infallible>::decode(&[0][..]).unwrap().is_none());
+        assert_eq!(None::<Infallible>.encode().unwrap(), vec![0]);
+    }
+
     #[test]
     fn test_unit_encoding_length() {
         let unit = ();
@@ -915,6 +2809,75 @@ mod tests {
         assert!(length == 0);
     }
 
+    #[test]
+    fn encode_into_slice() {
+        let mut buf = [0xff; 8];
+        assert_eq!(0x1234_5678u32.encode_into_slice(&mut buf).unwrap(), 4);
+        assert_eq!(buf, [0x12, 0x34, 0x56, 0x78, 0xff, 0xff, 0xff, 0xff]);
+
+        let value = (Some(true), vec![1u16, 2]);
+        let length = value.encode_into_slice(&mut buf).unwrap();
+        assert_eq!(&buf[..length], value.encode().unwrap().as_slice());
+        assert_eq!(length, 6);
+
+        let mut exact = [0; 3];
+        assert_eq!([7u8; 3].encode_into_slice(&mut exact).unwrap(), 3);
+        assert_eq!(exact, [7; 3]);
+        assert_eq!(().encode_into_slice(&mut []).unwrap(), 0);
+
+        let mut short = [0; 3];
+        assert!(matches!(
+            0u32.encode_into_slice(&mut short),
+            Err(Error::BufferTooSmall(3))
+        ));
+        assert!(matches!(
+            vec![0u8; 4].encode_into_slice(&mut short),
+            Err(Error::BufferTooSmall(3))
+        ));
+    }
+
+    #[test]
+    fn encode_single_pass() {
+        let value = vec![Some(1u32), None, Some(2)];
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(value.encode_single_pass().unwrap(), bytes);
+        assert_eq!(Box::new(value).encode().unwrap(), bytes);
+
+        let fixed = (0x1234u16, [true; 3]).encode_fixed().unwrap();
+        assert_eq!(fixed, vec![0x12, 0x34, 1, 1, 1]);
+        assert_eq!(fixed.capacity(), 5);
+    }
+
+    #[test]
+    fn decode_arbitrary_input() {
+        fn check<T: Decode>(bytes: &[u8]) {
+            let _ = T::decode(bytes);
+        }
+
+        for len in 0..=2 {
+            for n in 0..(1u32 << (8 * len)) {
+                let bytes = &n.to_be_bytes()[4 - len..];
+                check::<bool>(bytes);
+                check::<u16>(bytes);
+                check::<Option<Option<bool>>>(bytes);
+                check::<(bool, u8)>(bytes);
+                check::<[bool; 2]>(bytes);
+                check::<Vec<Option<u8>>>(bytes);
+                check::<Box<Option<bool>>>(bytes);
+                check::<String>(bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_length() {
+        assert_eq!(<u32 as FixedLength>::ENCODING_LENGTH, 4);
+        assert_eq!(<(u8, bool, ()) as FixedLength>::ENCODING_LENGTH, 2);
+        assert_eq!(<[u16; 8] as FixedLength>::ENCODING_LENGTH, 16);
+        assert_eq!(<Box<(u64, [u8; 3])> as FixedLength>::ENCODING_LENGTH, 11);
+    }
+
     #[test]
     fn test_phantom_data_encoding_length() {
         use std::marker::PhantomData;
@@ -0,0 +1,519 @@
+//! Resource limits for decoding untrusted input.
+//!
+//! Decoding attacker-controlled bytes can use unbounded memory or stack: an
+//! unterminated `Vec<u8>` reads all of its input, a short input can describe a
+//! huge number of small elements, and deeply nested boxes or collections
+//! recurse once per level. [`DecodeOptions`](struct.DecodeOptions.html)
+//! bounds each of these, failing the decode with an error as soon as a limit
+//! is exceeded.
+//!
+//! ```rust
+//! use ed::{limits::DecodeOptions, DecodeExt, Error};
+//!
+//! let options = DecodeOptions {
+//!     max_bytes: Some(1024),
+//!     max_elements: Some(16),
+//!     ..Default::default()
+//! };
+//!
+//! let value: Vec<u16> = Vec::decode_with(&[0, 1, 0, 2][..], &options).unwrap();
+//! assert_eq!(value, vec![1, 2]);
+//!
+//! let err = Vec::<u16>::decode_with(&[0; 64][..], &options).unwrap_err();
+//! assert!(matches!(err, Error::MaxElementsExceeded(16)));
+//! ```
+//!
+//! Elements are counted by the sequence, set and map impls, and depth is
+//! counted by the impls of types through which a type can contain itself:
+//! boxes, shared pointers and collections.
+//!
+//! The depth limit also applies outside of `DecodeOptions`: every decode is
+//! limited to a nesting depth of [`DEFAULT_MAX_DEPTH`](constant.DEFAULT_MAX_DEPTH.html),
+//! so adversarial input describing a deeply nested recursive type (e.g. a
+//! long chain of boxed enum variants) fails with `Error::MaxDepthExceeded`
+//! rather than overflowing the stack.
+//!
+//! The limits are tracked per thread, so enforcing them requires the `std`
+//! feature. Without it, `DecodeOptions` can't be used to decode and decoding
+//! is not depth-limited.
+
+use super::Result;
+#[cfg(feature = "std")]
+use super::{Decode, Error};
+#[cfg(feature = "std")]
+use std::cell::Cell;
+#[cfg(feature = "std")]
+use std::convert::TryInto;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+/// The nesting depth which decoding is limited to by default.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Limits to enforce while decoding. A limit of `None` means unlimited. By
+/// default, only the depth is limited, to `DEFAULT_MAX_DEPTH`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// The maximum number of bytes to read from the input. Exceeding it
+    /// errors with `Error::MaxBytesExceeded`.
+    pub max_bytes: Option<u64>,
+    /// The maximum total number of elements to decode into sequences, sets
+    /// and maps, including nested ones. Exceeding it errors with
+    /// `Error::MaxElementsExceeded`.
+    pub max_elements: Option<usize>,
+    /// The maximum nesting depth of boxes, shared pointers and collections.
+    /// Exceeding it errors with `Error::MaxDepthExceeded`.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            max_bytes: None,
+            max_elements: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Returns the default options, which only limit the depth.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns options without any limits, not even on the depth.
+    pub fn unlimited() -> Self {
+        DecodeOptions {
+            max_bytes: None,
+            max_elements: None,
+            max_depth: None,
+        }
+    }
+
+    /// Decodes a value from the reader, enforcing the limits.
+    #[cfg(feature = "std")]
+    pub fn decode<T: Decode, R: Read>(&self, input: R) -> Result<T> {
+        let _scope = Scope::open(State {
+            max_elements: self.max_elements,
+            elements: 0,
+            max_depth: self.max_depth,
+            depth: 0,
+        });
+
+        match self.max_bytes {
+            Some(max) => T::decode(Limited {
+                inner: input,
+                remaining: max,
+            })
+            .map_err(|err| bytes_exceeded_error(err, max)),
+            None => T::decode(input),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+struct State {
+    max_elements: Option<usize>,
+    elements: usize,
+    max_depth: Option<usize>,
+    depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl State {
+    const DEFAULT: State = State {
+        max_elements: None,
+        elements: 0,
+        max_depth: Some(DEFAULT_MAX_DEPTH),
+        depth: 0,
+    };
+}
+
+// Always accessed with `try_with`, so that decoding can't panic even from the
+// destructor of another thread local. The limits just aren't enforced then.
+#[cfg(feature = "std")]
+thread_local! {
+    static STATE: Cell<State> = const { Cell::new(State::DEFAULT) };
+}
+
+/// Restores the limits of any enclosing decode when dropped.
+#[cfg(feature = "std")]
+struct Scope(State);
+
+#[cfg(feature = "std")]
+impl Scope {
+    fn open(state: State) -> Self {
+        Scope(
+            STATE
+                .try_with(|s| s.replace(state))
+                .unwrap_or(State::DEFAULT),
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let _ = STATE.try_with(|s| s.set(self.0));
+    }
+}
+
+/// Counts `n` more decoded elements, erroring if the element limit is
+/// exceeded.
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn count_elements(n: usize) -> Result<()> {
+    STATE
+        .try_with(|s| {
+            let mut state = s.get();
+            if let Some(max) = state.max_elements {
+                state.elements = state.elements.saturating_add(n);
+                s.set(state);
+                if state.elements > max {
+                    return Err(Error::MaxElementsExceeded(max));
+                }
+            }
+            Ok(())
+        })
+        .unwrap_or(Ok(()))
+}
+
+/// Enters one more level of nesting, erroring if the depth limit is exceeded.
+/// The level is left when the returned guard is dropped.
+#[cfg(feature = "std")]
+#[inline]
+pub(crate) fn descend() -> Result<Depth> {
+    STATE
+        .try_with(|s| {
+            let mut state = s.get();
+            if let Some(max) = state.max_depth {
+                if state.depth >= max {
+                    return Err(Error::MaxDepthExceeded(max));
+                }
+            }
+            state.depth += 1;
+            s.set(state);
+            Ok(Depth(()))
+        })
+        .unwrap_or_else(|_| Ok(Depth(())))
+}
+
+/// A level of nesting entered by `descend`.
+pub(crate) struct Depth(());
+
+#[cfg(feature = "std")]
+impl Drop for Depth {
+    fn drop(&mut self) {
+        let _ = STATE.try_with(|s| {
+            let mut state = s.get();
+            state.depth = state.depth.saturating_sub(1);
+            s.set(state);
+        });
+    }
+}
+
+// Whether an async decode is being polled on this thread, with its limits
+// installed in `STATE`.
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+thread_local! {
+    static POLLING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The limits of an async decode.
+///
+/// A task may move between threads while it's suspended, so the limits can't
+/// be left in the per-thread state across polls. Instead they are installed on
+/// whichever thread polls the decode, and saved back once the poll returns.
+/// Decodes nested within one being polled share its limits.
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub(crate) struct AsyncLimits(State);
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl AsyncLimits {
+    /// Returns the default limits, since `DecodeOptions` can't be used to
+    /// decode asynchronously.
+    pub(crate) fn new() -> Self {
+        AsyncLimits(State::DEFAULT)
+    }
+
+    /// Calls `poll` with the limits installed, unless they're nested within
+    /// those of another async decode.
+    pub(crate) fn poll<T>(&mut self, poll: impl FnOnce() -> T) -> T {
+        if POLLING.try_with(|polling| polling.replace(true)) != Ok(false) {
+            return poll();
+        }
+
+        struct Polling;
+
+        impl Drop for Polling {
+            fn drop(&mut self) {
+                let _ = POLLING.try_with(|polling| polling.set(false));
+            }
+        }
+
+        let _polling = Polling;
+        let _scope = Scope::open(self.0);
+        let output = poll();
+        if let Ok(state) = STATE.try_with(Cell::get) {
+            self.0 = state;
+        }
+        output
+    }
+}
+
+/// The error returned by a `Limited` reader, wrapped in an I/O error.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct BytesExceeded;
+
+#[cfg(feature = "std")]
+impl fmt::Display for BytesExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Byte limit exceeded")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BytesExceeded {}
+
+/// Replaces the I/O error returned by a `Limited` reader with
+/// `Error::MaxBytesExceeded`, keeping any context added to it.
+#[cfg(feature = "std")]
+fn bytes_exceeded_error(err: Error, max: u64) -> Error {
+    match err {
+        Error::IOError(err) if err.get_ref().is_some_and(|err| err.is::<BytesExceeded>()) => {
+            Error::MaxBytesExceeded(max)
+        }
+        Error::Context {
+            type_name,
+            path,
+            source,
+        } => Error::Context {
+            type_name,
+            path,
+            source: Box::new(bytes_exceeded_error(*source, max)),
+        },
+        err => err,
+    }
+}
+
+/// A reader adapter which fails once more than a limited number of bytes
+/// would be read. Unlike `Read::take`, reaching the limit is an error rather
+/// than the end of the input.
+#[cfg(feature = "std")]
+struct Limited<R> {
+    inner: R,
+    remaining: u64,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            // The input may end exactly at the limit.
+            let mut probe = [0; 1];
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(io::Error::other(BytesExceeded)),
+            };
+        }
+
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+/// Without `std` there is no per-thread state to count elements in, so no
+/// limit is enforced.
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn count_elements(_n: usize) -> Result<()> {
+    Ok(())
+}
+
+/// Without `std` there is no per-thread state to track the depth in, so no
+/// limit is enforced.
+#[cfg(not(feature = "std"))]
+#[inline]
+pub(crate) fn descend() -> Result<Depth> {
+    Ok(Depth(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn options(
+        max_bytes: Option<u64>,
+        max_elements: Option<usize>,
+        max_depth: Option<usize>,
+    ) -> DecodeOptions {
+        DecodeOptions {
+            max_bytes,
+            max_elements,
+            max_depth,
+        }
+    }
+
+    #[test]
+    fn max_bytes() {
+        let bytes = [1, 2, 3, 4];
+        let value: Vec<u8> = options(Some(4), None, None).decode(&bytes[..]).unwrap();
+        assert_eq!(value, vec![1, 2, 3, 4]);
+
+        let err = options(Some(3), None, None)
+            .decode::<Vec<u8>, _>(&bytes[..])
+            .unwrap_err();
+        assert!(matches!(err, Error::MaxBytesExceeded(3)));
+
+        let value: u16 = options(Some(2), None, None).decode(&bytes[..]).unwrap();
+        assert_eq!(value, 0x0102);
+    }
+
+    #[test]
+    fn max_elements() {
+        let bytes = [0, 1, 0, 2, 0, 3];
+        let opts = options(None, Some(3), None);
+        assert_eq!(
+            opts.decode::<Vec<u16>, _>(&bytes[..]).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert!(matches!(
+            opts.decode::<Vec<u8>, _>(&bytes[..]),
+            Err(Error::MaxElementsExceeded(3))
+        ));
+        assert!(matches!(
+            opts.decode::<BTreeMap<u8, u8>, _>(&[1, 0, 2, 0, 3, 0, 4, 0][..]),
+            Err(Error::MaxElementsExceeded(3))
+        ));
+
+        assert!(matches!(
+            opts.decode::<Box<[u16]>, _>(&[0, 1, 0, 2, 0, 3, 0, 4][..]),
+            Err(Error::MaxElementsExceeded(3))
+        ));
+    }
+
+    #[test]
+    fn max_depth() {
+        let bytes = [0, 0, 0, 7];
+        assert_eq!(
+            *options(None, None, Some(3))
+                .decode::<Box<Box<Box<u32>>>, _>(&bytes[..])
+                .unwrap()
+                .as_ref()
+                .as_ref()
+                .as_ref(),
+            7
+        );
+        assert!(matches!(
+            options(None, None, Some(2)).decode::<Box<Box<Box<u32>>>, _>(&bytes[..]),
+            Err(Error::MaxDepthExceeded(2))
+        ));
+        assert!(matches!(
+            options(None, None, Some(2)).decode::<Vec<Box<Box<u8>>>, _>(&[1][..]),
+            Err(Error::MaxDepthExceeded(2))
+        ));
+
+        // Depth is restored after each level, so siblings don't accumulate.
+        let value: (Box<u8>, Box<u8>) = options(None, None, Some(1)).decode(&[1, 2][..]).unwrap();
+        assert_eq!(value, (Box::new(1), Box::new(2)));
+    }
+
+    enum Nested {
+        Leaf,
+        Node(Box<Nested>),
+    }
+
+    impl Decode for Nested {
+        fn decode<R: Read>(mut input: R) -> Result<Self> {
+            match u8::decode(&mut input)? {
+                0 => Ok(Nested::Leaf),
+                1 => Ok(Nested::Node(Box::decode(input)?)),
+                byte => Err(Error::UnexpectedByte(byte)),
+            }
+        }
+    }
+
+    impl Nested {
+        fn depth(&self) -> usize {
+            let mut depth = 0;
+            let mut node = self;
+            while let Nested::Node(child) = node {
+                depth += 1;
+                node = child;
+            }
+            depth
+        }
+    }
+
+    #[test]
+    fn default_max_depth() {
+        let mut bytes = vec![1; DEFAULT_MAX_DEPTH];
+        bytes.push(0);
+        assert_eq!(
+            Nested::decode(bytes.as_slice()).unwrap().depth(),
+            DEFAULT_MAX_DEPTH
+        );
+
+        // Would overflow the stack without a depth limit.
+        let bytes = vec![1; 1_000_000];
+        assert!(matches!(
+            Nested::decode(bytes.as_slice()),
+            Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+        ));
+        assert!(matches!(
+            DecodeOptions::new().decode::<Nested, _>(bytes.as_slice()),
+            Err(Error::MaxDepthExceeded(DEFAULT_MAX_DEPTH))
+        ));
+
+        let mut bytes = vec![1; DEFAULT_MAX_DEPTH + 1];
+        bytes.push(0);
+        let value: Nested = DecodeOptions::unlimited().decode(bytes.as_slice()).unwrap();
+        assert_eq!(value.depth(), DEFAULT_MAX_DEPTH + 1);
+    }
+
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    #[test]
+    fn async_limits_are_installed_per_poll() {
+        let depth = || STATE.with(|s| s.get().depth);
+        let mut limits = AsyncLimits::new();
+        let mut other = AsyncLimits::new();
+
+        // A level entered in one poll is held across polls, without being
+        // left in the state of the thread in between.
+        let level = limits.poll(|| descend().unwrap());
+        assert_eq!(depth(), 0);
+        assert_eq!(limits.poll(depth), 1);
+        assert_eq!(other.poll(depth), 0);
+
+        // Nested decodes share the limits of the one being polled.
+        assert_eq!(limits.poll(|| other.poll(depth)), 1);
+
+        limits.poll(|| drop(level));
+        assert_eq!(limits.poll(depth), 0);
+    }
+
+    #[test]
+    fn limits_are_scoped() {
+        let opts = options(None, Some(1), Some(1));
+        assert!(opts.decode::<Vec<u8>, _>(&[1, 2][..]).is_err());
+        assert_eq!(Vec::<u8>::decode(&[1, 2][..]).unwrap(), vec![1, 2]);
+        assert!(Box::<Box<u8>>::decode(&[1][..]).is_ok());
+
+        let value: Box<Box<u8>> = DecodeOptions::unlimited().decode(&[1][..]).unwrap();
+        assert_eq!(**value, 1);
+    }
+}
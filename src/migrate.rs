@@ -0,0 +1,269 @@
+//! Upgrading historical encodings to the current version of a type.
+//!
+//! A versioned value is encoded as a `u8` version followed by the encoding
+//! of the value. Rather than matching on every old version at each decode
+//! site, a type declares the chain of upgrades from its first version once,
+//! and [`decode_any_version`](fn.decode_any_version.html) accepts the
+//! encoding of any version in the chain:
+//!
+//! ```rust
+//! use ed::migrate::{decode_any_version, encode_versioned, Migrate, Migrator};
+//! use ed::{Decode, Encode};
+//!
+//! #[derive(Encode, Decode)]
+//! struct V1 {
+//!     balance: u32,
+//! }
+//!
+//! #[derive(Encode, Decode, Debug, PartialEq)]
+//! struct V2 {
+//!     balance: u64,
+//!     nonce: u32,
+//! }
+//!
+//! impl Migrate for V2 {
+//!     const VERSION: u8 = 2;
+//!
+//!     fn migrations() -> Migrator<Self> {
+//!         Migrator::<V1>::new(1).then(2, |v1| {
+//!             Ok(V2 {
+//!                 balance: v1.balance as u64,
+//!                 nonce: 0,
+//!             })
+//!         })
+//!     }
+//! }
+//!
+//! let old = [1, 0, 0, 0, 7];
+//! let value: V2 = decode_any_version(&old[..]).unwrap();
+//! assert_eq!(value, V2 { balance: 7, nonce: 0 });
+//!
+//! let bytes = encode_versioned(&value).unwrap();
+//! assert_eq!(bytes[0], 2);
+//! ```
+
+use super::{Decode, Encode, Error, Result};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+type DecodeFn<T> = Box<dyn Fn(&mut dyn Read) -> Result<T>>;
+
+/// A chain of upgrades from the first version of a type to the current one.
+pub struct Migrator<T> {
+    decoders: BTreeMap<u8, DecodeFn<T>>,
+    current: u8,
+}
+
+impl<T: Decode + 'static> Migrator<T> {
+    /// Starts a chain whose first version is `T`, encoded with the given
+    /// version number.
+    pub fn new(version: u8) -> Self {
+        let mut decoders: BTreeMap<u8, DecodeFn<T>> = BTreeMap::new();
+        decoders.insert(version, Box::new(|input| T::decode(input)));
+        Migrator {
+            decoders,
+            current: version,
+        }
+    }
+}
+
+impl<T: 'static> Migrator<T> {
+    /// Adds the next version `U` to the chain, along with the function which
+    /// upgrades a value of the previous version. Every earlier version is
+    /// decoded by upgrading it one step at a time.
+    ///
+    /// Panics if `version` is not greater than the previous version.
+    pub fn then<U, F>(self, version: u8, upgrade: F) -> Migrator<U>
+    where
+        U: Decode + 'static,
+        F: Fn(T) -> Result<U> + 'static,
+    {
+        if version <= self.current {
+            panic!(
+                "Migration to version {} must follow version {}",
+                version, self.current
+            );
+        }
+
+        let upgrade = Rc::new(upgrade);
+        let mut decoders: BTreeMap<u8, DecodeFn<U>> = self
+            .decoders
+            .into_iter()
+            .map(|(v, decode)| {
+                let upgrade = upgrade.clone();
+                let decode: DecodeFn<U> = Box::new(move |input| upgrade(decode(input)?));
+                (v, decode)
+            })
+            .collect();
+        decoders.insert(version, Box::new(|input| U::decode(input)));
+
+        Migrator {
+            decoders,
+            current: version,
+        }
+    }
+
+    /// Returns the version number of the last type in the chain.
+    pub fn current_version(&self) -> u8 {
+        self.current
+    }
+
+    /// Returns the version numbers of every type in the chain, in order.
+    pub fn versions(&self) -> impl Iterator<Item = u8> + '_ {
+        self.decoders.keys().copied()
+    }
+
+    /// Decodes a version number followed by the encoding of that version,
+    /// upgrading it to the current version. Errors with
+    /// `Error::UnknownVersion` if the version is not in the chain.
+    pub fn decode<R: Read>(&self, mut input: R) -> Result<T> {
+        let version = u8::decode(&mut input)?;
+        match self.decoders.get(&version) {
+            Some(decode) => decode(&mut input),
+            None => Err(Error::UnknownVersion(version)),
+        }
+    }
+}
+
+/// A trait for versioned types which can be upgraded from their historical
+/// versions.
+pub trait Migrate: Encode + Decode + 'static {
+    /// The version number of this type, written before its encoding.
+    const VERSION: u8;
+
+    /// Returns the chain of upgrades leading to this type. The last version
+    /// in the chain must be `VERSION`.
+    fn migrations() -> Migrator<Self>;
+}
+
+/// Writes the current version number followed by the encoding of the value.
+pub fn encode_versioned_into<T: Migrate, W: Write>(value: &T, dest: &mut W) -> Result<()> {
+    T::VERSION.encode_into(dest)?;
+    value.encode_into(dest)
+}
+
+/// Returns the current version number followed by the encoding of the value.
+pub fn encode_versioned<T: Migrate>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(1 + value.encoding_length()?);
+    encode_versioned_into(value, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes the encoding of any version in the type's migration chain,
+/// upgrading it to the current version.
+pub fn decode_any_version<T: Migrate, R: Read>(input: R) -> Result<T> {
+    T::migrations().decode(input)
+}
+
+/// Checks that the type's migration chain ends at its current version, and
+/// that each encoding in `fixtures` (including its version number) decodes
+/// through the chain. Intended for use in tests.
+pub fn verify_chain<T: Migrate>(fixtures: &[&[u8]]) -> Result<()> {
+    let migrations = T::migrations();
+    if migrations.current_version() != T::VERSION {
+        return Err(Error::UnknownVersion(T::VERSION));
+    }
+    for fixture in fixtures {
+        migrations.decode(*fixture)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct V3 {
+        balance: u64,
+        frozen: bool,
+    }
+
+    impl Encode for V3 {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            (self.balance, self.frozen).encode_into(dest)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            Ok(9)
+        }
+    }
+
+    impl Decode for V3 {
+        fn decode<R: Read>(input: R) -> Result<Self> {
+            let (balance, frozen) = Decode::decode(input)?;
+            Ok(V3 { balance, frozen })
+        }
+    }
+
+    impl Migrate for V3 {
+        const VERSION: u8 = 3;
+
+        fn migrations() -> Migrator<Self> {
+            Migrator::<u16>::new(1)
+                .then(2, |v1| Ok(v1 as u64))
+                .then(3, |v2| {
+                    Ok(V3 {
+                        balance: v2,
+                        frozen: false,
+                    })
+                })
+        }
+    }
+
+    #[test]
+    fn decode_any_version_chain() {
+        let v1 = [1, 0x12, 0x34];
+        let v2 = [2, 0, 0, 0, 0, 0, 0, 0x12, 0x34];
+        let v3 = [3, 0, 0, 0, 0, 0, 0, 0x12, 0x34, 1];
+
+        let expected = |frozen| V3 {
+            balance: 0x1234,
+            frozen,
+        };
+        assert_eq!(
+            decode_any_version::<V3, _>(&v1[..]).unwrap(),
+            expected(false)
+        );
+        assert_eq!(
+            decode_any_version::<V3, _>(&v2[..]).unwrap(),
+            expected(false)
+        );
+        assert_eq!(
+            decode_any_version::<V3, _>(&v3[..]).unwrap(),
+            expected(true)
+        );
+
+        assert_eq!(encode_versioned(&expected(true)).unwrap(), v3.to_vec());
+        assert_eq!(
+            V3::migrations().versions().collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        verify_chain::<V3>(&[&v1, &v2, &v3]).unwrap();
+    }
+
+    #[test]
+    fn unknown_version() {
+        assert!(matches!(
+            decode_any_version::<V3, _>(&[4, 0][..]),
+            Err(Error::UnknownVersion(4))
+        ));
+    }
+
+    #[test]
+    fn failed_upgrade() {
+        let migrations = Migrator::<u8>::new(1).then(2, |v1| match v1 {
+            0 => Err(Error::UnexpectedByte(0)),
+            n => Ok(n as u16),
+        });
+        assert_eq!(migrations.decode(&[1, 5][..]).unwrap(), 5);
+        assert!(migrations.decode(&[1, 0][..]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Migration to version 1 must follow version 1")]
+    fn non_increasing_version() {
+        Migrator::<u8>::new(1).then(1, |v| Ok(v as u16));
+    }
+}
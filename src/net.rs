@@ -0,0 +1,287 @@
+//! Encodings of network address types.
+//!
+//! All of the encodings are fixed-length for a given address family:
+//!
+//! - `Ipv4Addr`: its 4 octets, in network order.
+//! - `Ipv6Addr`: its 16 octets, in network order.
+//! - `IpAddr`: a 0 byte followed by an `Ipv4Addr`, or a 1 byte followed by an
+//!   `Ipv6Addr`.
+//! - `SocketAddrV4`: the `Ipv4Addr`, then the port as a big-endian `u16` (6
+//!   bytes).
+//! - `SocketAddrV6`: the `Ipv6Addr`, the port as a big-endian `u16`, the flow
+//!   info as a big-endian `u32`, then the scope ID as a big-endian `u32` (26
+//!   bytes).
+//! - `SocketAddr`: a 0 byte followed by a `SocketAddrV4`, or a 1 byte followed
+//!   by a `SocketAddrV6`.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+impl Encode for Ipv4Addr {
+    /// Encodes the 4 octets of the address.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&self.octets())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(4).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+impl Decode for Ipv4Addr {
+    /// Decodes the 4 octets of the address.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        <[u8; 4]>::decode(input).map(Ipv4Addr::from)
+    }
+}
+
+impl Terminated for Ipv4Addr {}
+
+impl FixedLength for Ipv4Addr {
+    const ENCODING_LENGTH: usize = 4;
+}
+
+impl Encode for Ipv6Addr {
+    /// Encodes the 16 octets of the address.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(&self.octets())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(16).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(16)
+    }
+}
+
+impl Decode for Ipv6Addr {
+    /// Decodes the 16 octets of the address.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        <[u8; 16]>::decode(input).map(Ipv6Addr::from)
+    }
+}
+
+impl Terminated for Ipv6Addr {}
+
+impl FixedLength for Ipv6Addr {
+    const ENCODING_LENGTH: usize = 16;
+}
+
+impl Encode for IpAddr {
+    /// Encodes a 0 byte followed by an IPv4 address, or a 1 byte followed by
+    /// an IPv6 address.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match self {
+            IpAddr::V4(addr) => {
+                dest.write_all(&[0])?;
+                addr.encode_into(dest)
+            }
+            IpAddr::V6(addr) => {
+                dest.write_all(&[1])?;
+                addr.encode_into(dest)
+            }
+        }
+    }
+
+    /// Returns 5 for IPv4 addresses or 17 for IPv6 addresses.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        match self {
+            IpAddr::V4(_) => Ok(5),
+            IpAddr::V6(_) => Ok(17),
+        }
+    }
+}
+
+impl Decode for IpAddr {
+    /// Decodes an IPv4 address after a 0 byte or an IPv6 address after a 1
+    /// byte. Errors for all other values.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        match u8::decode(&mut input)? {
+            0 => Ok(IpAddr::V4(Ipv4Addr::decode(input)?)),
+            1 => Ok(IpAddr::V6(Ipv6Addr::decode(input)?)),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+}
+
+impl Terminated for IpAddr {}
+
+impl Encode for SocketAddrV4 {
+    /// Encodes the address followed by the port.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.ip().encode_into(dest)?;
+        self.port().encode_into(dest)
+    }
+
+    /// Always returns Ok(6).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(6)
+    }
+}
+
+impl Decode for SocketAddrV4 {
+    /// Decodes the address followed by the port.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let ip = Ipv4Addr::decode(&mut input)?;
+        let port = u16::decode(input)?;
+        Ok(SocketAddrV4::new(ip, port))
+    }
+}
+
+impl Terminated for SocketAddrV4 {}
+
+impl FixedLength for SocketAddrV4 {
+    const ENCODING_LENGTH: usize = 6;
+}
+
+impl Encode for SocketAddrV6 {
+    /// Encodes the address, port, flow info and scope ID.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.ip().encode_into(dest)?;
+        self.port().encode_into(dest)?;
+        self.flowinfo().encode_into(dest)?;
+        self.scope_id().encode_into(dest)
+    }
+
+    /// Always returns Ok(26).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(26)
+    }
+}
+
+impl Decode for SocketAddrV6 {
+    /// Decodes the address, port, flow info and scope ID.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let ip = Ipv6Addr::decode(&mut input)?;
+        let port = u16::decode(&mut input)?;
+        let flowinfo = u32::decode(&mut input)?;
+        let scope_id = u32::decode(input)?;
+        Ok(SocketAddrV6::new(ip, port, flowinfo, scope_id))
+    }
+}
+
+impl Terminated for SocketAddrV6 {}
+
+impl FixedLength for SocketAddrV6 {
+    const ENCODING_LENGTH: usize = 26;
+}
+
+impl Encode for SocketAddr {
+    /// Encodes a 0 byte followed by an IPv4 socket address, or a 1 byte
+    /// followed by an IPv6 socket address.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match self {
+            SocketAddr::V4(addr) => {
+                dest.write_all(&[0])?;
+                addr.encode_into(dest)
+            }
+            SocketAddr::V6(addr) => {
+                dest.write_all(&[1])?;
+                addr.encode_into(dest)
+            }
+        }
+    }
+
+    /// Returns 7 for IPv4 socket addresses or 27 for IPv6 socket addresses.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        match self {
+            SocketAddr::V4(_) => Ok(7),
+            SocketAddr::V6(_) => Ok(27),
+        }
+    }
+}
+
+impl Decode for SocketAddr {
+    /// Decodes an IPv4 socket address after a 0 byte or an IPv6 socket
+    /// address after a 1 byte. Errors for all other values.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        match u8::decode(&mut input)? {
+            0 => Ok(SocketAddr::V4(SocketAddrV4::decode(input)?)),
+            1 => Ok(SocketAddr::V6(SocketAddrV6::decode(input)?)),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+}
+
+impl Terminated for SocketAddr {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_ip() {
+        let v4 = Ipv4Addr::new(192, 168, 0, 1);
+        assert_eq!(v4.encode().unwrap(), vec![192, 168, 0, 1]);
+        assert_eq!(Ipv4Addr::decode(&[192, 168, 0, 1][..]).unwrap(), v4);
+
+        let v6 = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let bytes = v6.encode().unwrap();
+        assert_eq!(&bytes[..4], &[0x20, 0x01, 0x0d, 0xb8]);
+        assert_eq!(bytes.len(), 16);
+        assert_eq!(Ipv6Addr::decode(bytes.as_slice()).unwrap(), v6);
+
+        for addr in [IpAddr::V4(v4), IpAddr::V6(v6)] {
+            let bytes = addr.encode().unwrap();
+            assert_eq!(bytes.len(), addr.encoding_length().unwrap());
+            assert_eq!(IpAddr::decode(bytes.as_slice()).unwrap(), addr);
+        }
+        assert_eq!(IpAddr::V4(v4).encode().unwrap(), vec![0, 192, 168, 0, 1]);
+    }
+
+    #[test]
+    fn decode_invalid_ip() {
+        assert!(matches!(
+            IpAddr::decode(&[2, 0, 0, 0, 0][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+        assert!(IpAddr::decode(&[1, 0, 0, 0, 0][..]).is_err());
+        assert!(Ipv4Addr::decode(&[1, 2, 3][..]).is_err());
+    }
+
+    #[test]
+    fn encode_decode_socket_addr() {
+        let v4 = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 8080);
+        assert_eq!(v4.encode().unwrap(), vec![10, 0, 0, 1, 0x1f, 0x90]);
+        assert_eq!(
+            SocketAddrV4::decode(&[10, 0, 0, 1, 0x1f, 0x90][..]).unwrap(),
+            v4
+        );
+
+        let v6 = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 443, 0x12345, 7);
+        let bytes = v6.encode().unwrap();
+        assert_eq!(&bytes[16..], &[0x01, 0xbb, 0, 0x01, 0x23, 0x45, 0, 0, 0, 7]);
+        assert_eq!(SocketAddrV6::decode(bytes.as_slice()).unwrap(), v6);
+
+        for addr in [SocketAddr::V4(v4), SocketAddr::V6(v6)] {
+            let bytes = addr.encode().unwrap();
+            assert_eq!(bytes.len(), addr.encoding_length().unwrap());
+            assert_eq!(SocketAddr::decode(bytes.as_slice()).unwrap(), addr);
+        }
+        assert!(matches!(
+            SocketAddr::decode(&[2][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+//! Encoding support for `BigUint` and `BigInt`, using the `num-bigint` crate.
+//!
+//! A `BigUint` is encoded as the length of its magnitude in bytes, as a
+//! big-endian `u32`, followed by the magnitude as minimal big-endian bytes,
+//! so zero has an empty magnitude. A `BigInt` is encoded as a sign byte, 0
+//! for non-negative values and 1 for negative values, followed by its
+//! magnitude encoded as a `BigUint`.
+//!
+//! Decoding errors with `Error::UnexpectedByte(0)` for a magnitude with a
+//! leading zero byte, and with `Error::UnexpectedByte(1)` for a negative
+//! zero, so that every value has exactly one encoding.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::num_bigint::{BigInt, BigUint, Sign};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// The number of bytes read at once while decoding a magnitude, so that a
+/// huge length prefix can't cause a huge allocation before the input runs
+/// out.
+const CHUNK_LENGTH: usize = 4096;
+
+fn magnitude_length(value: &BigUint) -> usize {
+    // `bits` is 0 for zero, which is encoded with an empty magnitude.
+    value.bits().div_ceil(8) as usize
+}
+
+impl Encode for BigUint {
+    /// Encodes the length of the magnitude as a `u32`, followed by the
+    /// magnitude as minimal big-endian bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let length = magnitude_length(self);
+        u32::try_from(length)
+            .map_err(|_| Error::LengthOverflow(length))?
+            .encode_into(dest)?;
+        if length > 0 {
+            dest.write_all(&self.to_bytes_be())?;
+        }
+        Ok(())
+    }
+
+    /// Returns 4 plus the length of the magnitude in bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4 + magnitude_length(self))
+    }
+}
+
+impl Decode for BigUint {
+    /// Decodes a `u32` length followed by that many big-endian bytes. Errors
+    /// with `Error::UnexpectedByte(0)` if the first byte is zero.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let length = u32::decode(&mut input)? as usize;
+
+        let mut bytes = Vec::new();
+        while bytes.len() < length {
+            let start = bytes.len();
+            bytes.resize(length.min(start + CHUNK_LENGTH), 0);
+            input.read_exact(&mut bytes[start..])?;
+        }
+
+        if bytes.first() == Some(&0) {
+            return Err(Error::UnexpectedByte(0));
+        }
+        Ok(BigUint::from_bytes_be(&bytes))
+    }
+}
+
+impl Terminated for BigUint {}
+
+impl Encode for BigInt {
+    /// Encodes a sign byte, 1 if the value is negative and 0 otherwise,
+    /// followed by the magnitude encoded as a `BigUint`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let negative = self.sign() == Sign::Minus;
+        negative.encode_into(dest)?;
+        self.magnitude().encode_into(dest)
+    }
+
+    /// Returns 1 plus the encoding length of the magnitude.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(1 + self.magnitude().encoding_length()?)
+    }
+}
+
+impl Decode for BigInt {
+    /// Decodes a sign byte followed by a magnitude encoded as a `BigUint`.
+    /// Errors with `Error::UnexpectedByte(1)` for a negative zero.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let negative = bool::decode(&mut input)?;
+        let magnitude = BigUint::decode(&mut input)?;
+        let sign = match (magnitude.bits(), negative) {
+            (0, true) => return Err(Error::UnexpectedByte(1)),
+            (0, false) => Sign::NoSign,
+            (_, true) => Sign::Minus,
+            (_, false) => Sign::Plus,
+        };
+        Ok(BigInt::from_biguint(sign, magnitude))
+    }
+}
+
+impl Terminated for BigInt {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_biguint() {
+        let cases: &[(u64, &[u8])] = &[
+            (0, &[0, 0, 0, 0]),
+            (1, &[0, 0, 0, 1, 1]),
+            (0x0100, &[0, 0, 0, 2, 1, 0]),
+            (
+                u64::MAX,
+                &[0, 0, 0, 8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+        ];
+        for (value, bytes) in cases.iter() {
+            let value = BigUint::from(*value);
+            assert_eq!(value.encode().unwrap(), *bytes);
+            assert_eq!(value.encoding_length().unwrap(), bytes.len());
+            assert_eq!(BigUint::decode(*bytes).unwrap(), value);
+        }
+
+        let large = BigUint::from(1u8) << 70_000usize;
+        let bytes = large.encode().unwrap();
+        assert_eq!(bytes.len(), 4 + 8751);
+        assert_eq!(BigUint::decode(bytes.as_slice()).unwrap(), large);
+    }
+
+    #[test]
+    fn encode_decode_bigint() {
+        let cases: &[(i64, &[u8])] = &[
+            (0, &[0, 0, 0, 0, 0]),
+            (5, &[0, 0, 0, 0, 1, 5]),
+            (-5, &[1, 0, 0, 0, 1, 5]),
+            (-256, &[1, 0, 0, 0, 2, 1, 0]),
+        ];
+        for (value, bytes) in cases.iter() {
+            let value = BigInt::from(*value);
+            assert_eq!(value.encode().unwrap(), *bytes);
+            assert_eq!(value.encoding_length().unwrap(), bytes.len());
+            assert_eq!(BigInt::decode(*bytes).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn decode_non_canonical() {
+        assert!(matches!(
+            BigUint::decode(&[0, 0, 0, 2, 0, 1][..]),
+            Err(Error::UnexpectedByte(0))
+        ));
+        assert!(matches!(
+            BigInt::decode(&[1, 0, 0, 0, 0][..]),
+            Err(Error::UnexpectedByte(1))
+        ));
+        assert!(matches!(
+            BigInt::decode(&[2, 0, 0, 0, 0][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+
+        // A huge length fails once the input runs out.
+        let err = BigUint::decode(&[0xff, 0xff, 0xff, 0xff, 1][..]).unwrap_err();
+        assert!(err.is_unexpected_eof());
+    }
+}
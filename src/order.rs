@@ -0,0 +1,578 @@
+//! Canonical ordering of map and set encodings.
+//!
+//! Maps and sets are encoded as their entries one after another, in
+//! increasing key order. So that every value has exactly one accepted
+//! encoding (which matters when hashing state), decoding is strict: entries
+//! whose keys are out of order or duplicated are rejected with
+//! `Error::NonCanonicalOrder`.
+//!
+//! `HashMap` has no key order of its own, so its entries are sorted by the
+//! encodings of their keys instead.
+//!
+//! To accept encodings produced by other, less careful encoders, wrap the
+//! collection in [`Unordered`](struct.Unordered.html), which accepts entries
+//! in any order. Later duplicates replace earlier ones.
+
+use super::io::{Read, Write};
+#[cfg(feature = "std")]
+use super::wipe;
+use super::{limits, next_element, Decode, Encode, Error, Result, Terminated};
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+/// Checks that `key` comes strictly after `prev`, if there is a previous key.
+/// Errors with `Error::NonCanonicalOrder` if the keys are out of order or
+/// equal.
+#[inline]
+pub fn check_order<K: Ord + ?Sized>(prev: Option<&K>, key: &K) -> Result<()> {
+    match prev {
+        Some(prev) if prev >= key => Err(Error::NonCanonicalOrder),
+        _ => Ok(()),
+    }
+}
+
+/// Decodes entries one after another until the input is exhausted. If
+/// `strict` is true, errors unless the key of each entry (as returned by
+/// `key`) comes strictly after the key of the previous entry. Used by the
+/// map and set impls.
+pub fn decode_entries<T, K, R, F>(mut input: R, strict: bool, key: F) -> Result<Vec<T>>
+where
+    T: Decode + Terminated,
+    K: Ord + ?Sized,
+    R: Read,
+    F: Fn(&T) -> &K,
+{
+    let _depth = limits::descend()?;
+    let mut entries: Vec<T> = Vec::new();
+    while let Some(mut entry_input) = next_element(&mut input)? {
+        limits::count_elements(1)?;
+        let entry = T::decode(&mut entry_input)?;
+        entry_input.check_consumed()?;
+        if strict {
+            check_order(entries.last().map(&key), key(&entry))?;
+        }
+        entries
+            .try_reserve(1)
+            .map_err(|_| Error::CapacityExceeded(entries.len()))?;
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// A map or set which is decoded from entries in any order, rather than
+/// requiring its canonical (sorted, duplicate-free) encoding.
+///
+/// Encoding is unaffected, so re-encoding a decoded `Unordered` value always
+/// produces the canonical encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Unordered<T>(pub T);
+
+impl<T> Unordered<T> {
+    /// Returns the inner collection.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Unordered<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Unordered<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Unordered<T> {
+    fn from(value: T) -> Self {
+        Unordered(value)
+    }
+}
+
+impl<T: Encode> Encode for Unordered<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the inner collection.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.0.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the inner collection.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.0.encoding_length()
+    }
+}
+
+impl<K, V> Encode for BTreeMap<K, V>
+where
+    K: Encode + Terminated,
+    V: Encode + Terminated,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Encodes each key followed by its value, in key order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for (key, value) in self.iter() {
+            key.encode_into(dest)?;
+            value.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the encoding lengths of all keys and values.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for (key, value) in self.iter() {
+            sum += key.encoding_length()? + value.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<K, V> Decode for BTreeMap<K, V>
+where
+    K: Decode + Terminated + Ord,
+    V: Decode + Terminated,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Decodes keys and values one after another until the input is
+    /// exhausted. Errors with `Error::NonCanonicalOrder` if the keys are not
+    /// in strictly increasing order.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut map = BTreeMap::new();
+        map.decode_into(input)?;
+        Ok(map)
+    }
+
+    /// Decodes keys and values as in `decode`. Values whose keys are already
+    /// in the map are decoded with `decode_into`, reusing their allocations,
+    /// and keys which are not in the input are removed.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut old = core::mem::take(self);
+        while let Some(mut entry_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            let key = K::decode(&mut entry_input)?;
+            check_order(self.keys().next_back(), &key)?;
+            let value = match old.remove(&key) {
+                Some(mut value) => {
+                    value.decode_into(&mut entry_input)?;
+                    value
+                }
+                None => V::decode(&mut entry_input)?,
+            };
+            entry_input.check_consumed()?;
+            self.insert(key, value);
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> Decode for Unordered<BTreeMap<K, V>>
+where
+    K: Decode + Terminated + Ord,
+    V: Decode + Terminated,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Decodes keys and values one after another until the input is
+    /// exhausted, in any order. Later duplicate keys replace earlier ones.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let entries = decode_entries::<(K, V), K, _, _>(input, false, |(key, _)| key)?;
+        Ok(Unordered(entries.into_iter().collect()))
+    }
+}
+
+impl<T: Encode + Terminated> Encode for BTreeSet<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the elements one after another, in sorted order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for element in self.iter() {
+            element.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<T: Decode + Terminated + Ord> Decode for BTreeSet<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes elements one after another until the input is exhausted.
+    /// Errors with `Error::NonCanonicalOrder` if the elements are not in
+    /// strictly increasing order.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let elements = decode_entries::<T, T, _, _>(input, true, |element| element)?;
+        Ok(elements.into_iter().collect())
+    }
+}
+
+impl<T: Decode + Terminated + Ord> Decode for Unordered<BTreeSet<T>> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes elements one after another until the input is exhausted, in
+    /// any order. Duplicate elements are ignored.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let elements = decode_entries::<T, T, _, _>(input, false, |element| element)?;
+        Ok(Unordered(elements.into_iter().collect()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> Encode for HashMap<K, V, S>
+where
+    K: Encode + Terminated,
+    V: Encode + Terminated,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Encodes each key followed by its value, sorted by the encodings of the
+    /// keys so that the output does not depend on iteration order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let mut entries = Vec::with_capacity(self.len());
+        for (key, value) in self.iter() {
+            entries.push((key.encode()?, value));
+        }
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut res = Ok(());
+        for (key, value) in entries.iter() {
+            res = dest
+                .write_all(key)
+                .map_err(Error::from)
+                .and_then(|_| value.encode_into(dest));
+            if res.is_err() {
+                break;
+            }
+        }
+        for (key, _) in entries.iter_mut() {
+            wipe::wipe(key, K::SENSITIVE);
+        }
+        res
+    }
+
+    /// Returns the sum of the encoding lengths of all keys and values.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for (key, value) in self.iter() {
+            sum += key.encoding_length()? + value.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+/// A reader which appends everything read from it to `bytes`, so that the
+/// encodings of hash map keys can be compared as they were read.
+#[cfg(feature = "std")]
+struct Recording<'a, R> {
+    inner: R,
+    bytes: &'a mut Vec<u8>,
+    sensitive: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Recording<'_, R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        wipe::extend(self.bytes, &buf[..n], self.sensitive);
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+fn decode_hash_map<K, V, S, R>(mut input: R, strict: bool) -> Result<HashMap<K, V, S>>
+where
+    K: Decode + Terminated + Hash + Eq,
+    V: Decode + Terminated,
+    S: BuildHasher + Default,
+    R: Read,
+{
+    let sensitive = K::SENSITIVE || V::SENSITIVE || wipe::active();
+    let _depth = limits::descend()?;
+
+    let mut map = HashMap::default();
+    let mut prev: Option<Vec<u8>> = None;
+    let mut key_bytes = Vec::new();
+    let res = (|| {
+        while let Some(mut entry_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            let key = if strict {
+                wipe::wipe(&mut key_bytes, sensitive);
+                key_bytes.clear();
+                let key = K::decode(Recording {
+                    inner: &mut entry_input,
+                    bytes: &mut key_bytes,
+                    sensitive,
+                })?;
+                check_order(prev.as_deref(), key_bytes.as_slice())?;
+                let prev = prev.get_or_insert_with(Vec::new);
+                core::mem::swap(prev, &mut key_bytes);
+                key
+            } else {
+                K::decode(&mut entry_input)?
+            };
+            let value = V::decode(&mut entry_input)?;
+            entry_input.check_consumed()?;
+            map.insert(key, value);
+        }
+        Ok(())
+    })();
+    wipe::wipe(&mut key_bytes, sensitive);
+    if let Some(prev) = prev.as_mut() {
+        wipe::wipe(prev, sensitive);
+    }
+    res.map(|_| map)
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> Decode for HashMap<K, V, S>
+where
+    K: Decode + Terminated + Hash + Eq,
+    V: Decode + Terminated,
+    S: BuildHasher + Default,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Decodes keys and values one after another until the input is
+    /// exhausted. Errors with `Error::NonCanonicalOrder` if the encodings of
+    /// the keys are not in strictly increasing order, which includes
+    /// duplicate keys.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        decode_hash_map(input, true)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> Decode for Unordered<HashMap<K, V, S>>
+where
+    K: Decode + Terminated + Hash + Eq,
+    V: Decode + Terminated,
+    S: BuildHasher + Default,
+{
+    const SENSITIVE: bool = K::SENSITIVE || V::SENSITIVE;
+
+    /// Decodes keys and values one after another until the input is
+    /// exhausted, in any order. Later duplicate keys replace earlier ones.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        decode_hash_map(input, false).map(Unordered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_order() {
+        let bytes = [0, 1, 9, 0, 2, 8];
+        let entries: Vec<(u16, u8)> = decode_entries(&bytes[..], true, |(k, _)| k).unwrap();
+        assert_eq!(entries, vec![(1, 9), (2, 8)]);
+
+        let bytes = [0, 2, 9, 0, 1, 8];
+        assert!(matches!(
+            decode_entries::<(u16, u8), _, _, _>(&bytes[..], true, |(k, _)| k),
+            Err(Error::NonCanonicalOrder)
+        ));
+        let entries: Vec<(u16, u8)> = decode_entries(&bytes[..], false, |(k, _)| k).unwrap();
+        assert_eq!(entries, vec![(2, 9), (1, 8)]);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert!(matches!(
+            decode_entries::<u8, _, _, _>(&[1, 2, 2][..], true, |k| k),
+            Err(Error::NonCanonicalOrder)
+        ));
+        assert_eq!(
+            decode_entries::<u8, _, _, _>(&[1, 2, 2][..], false, |k| k).unwrap(),
+            vec![1, 2, 2]
+        );
+        assert!(check_order(None, &1).is_ok());
+        assert!(check_order(Some(&1), &1).is_err());
+    }
+
+    #[test]
+    fn truncated_entry() {
+        assert!(decode_entries::<(u16, u8), _, _, _>(&[0, 1, 9, 0][..], true, |(k, _)| k).is_err());
+    }
+
+    #[test]
+    fn empty_entries() {
+        assert!(matches!(
+            Unordered::<BTreeSet<()>>::decode(&[7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(matches!(
+            BTreeSet::<()>::decode(&[7, 7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(matches!(
+            Unordered::<HashMap<(), ()>>::decode(&[7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(matches!(
+            HashMap::<(), ()>::decode(&[7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(matches!(
+            BTreeMap::<(), ()>::decode(&[7][..]),
+            Err(Error::UnexpectedByte(7))
+        ));
+        assert!(Unordered::<BTreeSet<()>>::decode(&[][..])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn short_first_entry_large_type() {
+        // The first entry is a 1-byte `None`, but the input must not be taken
+        // as a hint to allocate a slot of the full type size per byte.
+        let bytes = vec![0; 1 << 20];
+        assert!(matches!(
+            BTreeSet::<Option<[u8; 4096]>>::decode(bytes.as_slice()),
+            Err(Error::NonCanonicalOrder)
+        ));
+        assert!(matches!(
+            HashMap::<Option<[u8; 4096]>, ()>::decode(bytes.as_slice()),
+            Err(Error::NonCanonicalOrder)
+        ));
+    }
+
+    #[test]
+    fn encode_decode_btree_map() {
+        let mut map = BTreeMap::new();
+        map.insert(2u16, Some(8u8));
+        map.insert(1, None);
+        let bytes = map.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 0, 2, 1, 8]);
+        assert_eq!(map.encoding_length().unwrap(), 7);
+        assert_eq!(BTreeMap::decode(bytes.as_slice()).unwrap(), map);
+        assert!(BTreeMap::<u16, u8>::decode(&[][..]).unwrap().is_empty());
+        assert!(BTreeMap::<u16, u8>::decode(&[0, 1][..]).is_err());
+    }
+
+    #[test]
+    fn btree_map_order() {
+        let bytes = [0, 2, 8, 0, 1, 9, 0, 2, 7];
+        assert!(matches!(
+            BTreeMap::<u16, u8>::decode(&bytes[..6]),
+            Err(Error::NonCanonicalOrder)
+        ));
+        assert!(matches!(
+            BTreeMap::<u16, u8>::decode(&[0, 1, 9, 0, 1, 8][..]),
+            Err(Error::NonCanonicalOrder)
+        ));
+
+        let map = Unordered::<BTreeMap<u16, u8>>::decode(&bytes[..]).unwrap();
+        assert_eq!(map.get(&1), Some(&9));
+        assert_eq!(map.get(&2), Some(&7));
+        assert_eq!(map.encode().unwrap(), vec![0, 1, 9, 0, 2, 7]);
+    }
+
+    #[test]
+    fn btree_map_decode_into() {
+        let mut map = BTreeMap::new();
+        map.insert(1u8, Box::new(2u8));
+        map.insert(3, Box::new(4));
+        let ptr: *const u8 = &*map[&1];
+
+        map.decode_into(&[1, 5, 2, 6][..]).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!((*map[&1], *map[&2]), (5, 6));
+        assert!(std::ptr::eq(&*map[&1], ptr));
+        assert!(!map.contains_key(&3));
+    }
+
+    #[test]
+    fn encode_decode_btree_set() {
+        let set: BTreeSet<u16> = vec![3, 1, 2].into_iter().collect();
+        let bytes = set.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2, 0, 3]);
+        assert_eq!(set.encoding_length().unwrap(), 6);
+        assert_eq!(BTreeSet::decode(bytes.as_slice()).unwrap(), set);
+
+        let bytes = [0, 2, 0, 1, 0, 2];
+        assert!(matches!(
+            BTreeSet::<u16>::decode(&bytes[..]),
+            Err(Error::NonCanonicalOrder)
+        ));
+        let unordered = Unordered::<BTreeSet<u16>>::decode(&bytes[..]).unwrap();
+        assert_eq!(unordered.into_inner(), vec![1, 2].into_iter().collect());
+    }
+
+    #[test]
+    fn encode_decode_hash_map() {
+        let mut map = HashMap::new();
+        for i in 0..64u16 {
+            map.insert(i.wrapping_mul(0x9e37), i as u8);
+        }
+        let bytes = map.encode().unwrap();
+        assert_eq!(bytes.len(), map.encoding_length().unwrap());
+
+        let sorted: BTreeMap<u16, u8> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(bytes, sorted.encode().unwrap());
+        assert_eq!(HashMap::decode(bytes.as_slice()).unwrap(), map);
+    }
+
+    #[test]
+    fn hash_map_order() {
+        // Sorted by key encoding rather than by key, so -1 (encoded as 0xff)
+        // comes after 1.
+        let mut map = HashMap::new();
+        map.insert(-1i8, ());
+        map.insert(1, ());
+        let bytes = map.encode().unwrap();
+        assert_eq!(bytes, vec![0x01, 0xff]);
+        assert_eq!(HashMap::decode(bytes.as_slice()).unwrap(), map);
+
+        assert!(matches!(
+            HashMap::<u8, u8>::decode(&[2, 0, 1, 0][..]),
+            Err(Error::NonCanonicalOrder)
+        ));
+        assert!(matches!(
+            HashMap::<u8, u8>::decode(&[1, 0, 1, 1][..]),
+            Err(Error::NonCanonicalOrder)
+        ));
+        let unordered = Unordered::<HashMap<u8, u8>>::decode(&[2, 0, 1, 0, 2, 1][..]).unwrap();
+        assert_eq!(unordered.len(), 2);
+        assert_eq!(unordered[&2], 1);
+    }
+}
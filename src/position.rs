@@ -0,0 +1,103 @@
+//! Reporting the position in the input at which decoding failed.
+//!
+//! Wrapping the input in a [`Positioned`](struct.Positioned.html) reader
+//! counts the bytes read, and [`decode_positioned`](fn.decode_positioned.html)
+//! attaches that count to any error, so a failed decode of a large value
+//! reports where in the input it went wrong:
+//!
+//! ```rust
+//! use ed::position::decode_positioned;
+//!
+//! let bytes = [0, 0, 0, 1, 2];
+//! let err = decode_positioned::<(u32, Option<u8>), _>(&bytes[..]).unwrap_err();
+//! assert_eq!(err.offset(), Some(5));
+//! assert_eq!(err.to_string(), "Unexpected byte: 2 (at byte 5)");
+//! ```
+
+use super::{Decode, Result};
+use std::io::{self, Read};
+
+/// A reader adapter which counts the bytes read from the inner reader.
+pub struct Positioned<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Positioned<R> {
+    /// Wraps the reader, starting at position 0.
+    pub fn new(inner: R) -> Self {
+        Positioned { inner, position: 0 }
+    }
+
+    /// Returns the number of bytes read from the inner reader so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Positioned<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+/// Decodes a value from the reader. On failure, the error records the number
+/// of bytes which had been read when decoding failed (see
+/// [`Error::offset`](../enum.Error.html#method.offset)).
+///
+/// Bytes are read as they are decoded, so for an unexpected byte the offset
+/// is just past it, and for an unexpected end of input it is the length of
+/// the input. Types which buffer the rest of their input before decoding it,
+/// such as maps, report the offset of the end of that input.
+pub fn decode_positioned<T: Decode, R: Read>(input: R) -> Result<T> {
+    let mut input = Positioned::new(input);
+    T::decode(&mut input).map_err(|err| err.at(input.position()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn positioned_reader() {
+        let bytes = [0, 1, 0, 2, 0];
+        let mut reader = Positioned::new(&bytes[..]);
+        assert_eq!(u16::decode(&mut reader).unwrap(), 1);
+        assert_eq!(reader.position(), 2);
+        assert_eq!(u16::decode(&mut reader).unwrap(), 2);
+        assert_eq!(reader.position(), 4);
+        assert!(u16::decode(&mut reader).is_err());
+        assert_eq!(reader.position(), 5);
+    }
+
+    #[test]
+    fn error_offset() {
+        let bytes = [0, 0, 0, 1, 1, 0, 2, 2];
+        let err = decode_positioned::<(u32, Option<u16>, bool), _>(&bytes[..]).unwrap_err();
+        assert_eq!(err.offset(), Some(8));
+        assert!(matches!(err.inner(), Error::UnexpectedByte(2)));
+
+        let err = decode_positioned::<[u16; 5], _>(&bytes[..]).unwrap_err();
+        assert_eq!(err.offset(), Some(8));
+        assert!(matches!(err.inner(), Error::IOError(_)));
+
+        assert_eq!(decode_positioned::<u32, _>(&bytes[..]).unwrap(), 1);
+    }
+
+    #[test]
+    fn at_replaces_offset() {
+        let err = Error::UnexpectedByte(3).at(4).at(10);
+        assert_eq!(err.offset(), Some(10));
+        assert!(matches!(err.inner(), Error::UnexpectedByte(3)));
+        assert_eq!(err.to_string(), "Unexpected byte: 3 (at byte 10)");
+        assert_eq!(Error::UnexpectedByte(3).offset(), None);
+    }
+}
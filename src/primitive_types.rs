@@ -0,0 +1,125 @@
+//! Encoding support for the fixed-width integer and hash types of the
+//! `primitive-types` crate.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, FixedLength, Result, Terminated};
+use ::primitive_types::{H128, H160, H256, H512, U128, U256, U512};
+
+macro_rules! uint_impl {
+    ($type:ident, $length:expr) => {
+        impl Encode for $type {
+            #[doc = "Encodes the integer as fixed-size big-endian bytes."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                let mut bytes = [0; $length];
+                self.to_big_endian(&mut bytes);
+                dest.write_all(&bytes)?;
+                Ok(())
+            }
+
+            #[doc = "Returns the size of the integer in bytes. Will always"]
+            #[doc = " return an `Ok` result."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok($length)
+            }
+        }
+
+        impl Decode for $type {
+            #[doc = "Decodes the integer from fixed-size big-endian bytes."]
+            #[inline]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let mut bytes = [0; $length];
+                input.read_exact(&mut bytes)?;
+                Ok($type::from_big_endian(&bytes))
+            }
+        }
+
+        impl Terminated for $type {}
+
+        impl FixedLength for $type {
+            const ENCODING_LENGTH: usize = $length;
+        }
+    };
+}
+
+uint_impl!(U128, 16);
+uint_impl!(U256, 32);
+uint_impl!(U512, 64);
+
+macro_rules! hash_impl {
+    ($type:ident, $length:expr) => {
+        impl Encode for $type {
+            #[doc = "Encodes the bytes of the hash."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                dest.write_all(self.as_bytes())?;
+                Ok(())
+            }
+
+            #[doc = "Returns the size of the hash in bytes. Will always"]
+            #[doc = " return an `Ok` result."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok($length)
+            }
+        }
+
+        impl Decode for $type {
+            #[doc = "Decodes the bytes of the hash."]
+            #[inline]
+            fn decode<R: Read>(mut input: R) -> Result<Self> {
+                let mut bytes = [0; $length];
+                input.read_exact(&mut bytes)?;
+                Ok($type(bytes))
+            }
+        }
+
+        impl Terminated for $type {}
+
+        impl FixedLength for $type {
+            const ENCODING_LENGTH: usize = $length;
+        }
+    };
+}
+
+hash_impl!(H128, 16);
+hash_impl!(H160, 20);
+hash_impl!(H256, 32);
+hash_impl!(H512, 64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_uint() {
+        let value = U256::from(0x0102u64);
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(&bytes[30..], &[1, 2]);
+        assert!(bytes[..30].iter().all(|byte| *byte == 0));
+        assert_eq!(value.encoding_length().unwrap(), 32);
+        assert_eq!(U256::decode(bytes.as_slice()).unwrap(), value);
+
+        let max = U128::MAX.encode().unwrap();
+        assert_eq!(max, vec![0xff; 16]);
+        assert_eq!(U128::decode(max.as_slice()).unwrap(), U128::MAX);
+
+        // Big-endian encodings sort in the same order as the values.
+        assert!(U512::from(255u64).encode().unwrap() < U512::from(256u64).encode().unwrap());
+        assert!(U256::decode(&[0; 31][..]).unwrap_err().is_unexpected_eof());
+    }
+
+    #[test]
+    fn encode_decode_hash() {
+        let hash = H160([7; 20]);
+        assert_eq!(hash.encode().unwrap(), vec![7; 20]);
+        assert_eq!(H160::decode(&[7; 20][..]).unwrap(), hash);
+        assert_eq!(<H256 as FixedLength>::ENCODING_LENGTH, 32);
+
+        let pair: (H128, u8) = Decode::decode(&[1; 17][..]).unwrap();
+        assert_eq!(pair, (H128([1; 16]), 1));
+        assert_eq!(H512::zero().encoding_length().unwrap(), 64);
+    }
+}
@@ -0,0 +1,311 @@
+//! Reporting where the bytes of an encoding go.
+//!
+//! Before changing an encoding format it helps to know which parts of it
+//! take up the most space, e.g. that 40% of a block is signatures.
+//! [`profile_corpus`](fn.profile_corpus.html) decodes a corpus of encodings
+//! and adds up the encoded size of every field, producing a
+//! [`SizeReport`](struct.SizeReport.html).
+//!
+//! Types describe their fields by implementing [`Profile`](trait.Profile.html),
+//! which can be derived:
+//!
+//! ```rust
+//! use ed::{profile::profile_corpus, Decode, Encode, Profile};
+//!
+//! #[derive(Encode, Decode, Profile)]
+//! struct Tx {
+//!     nonce: u32,
+//!     signature: [u8; 64],
+//! }
+//!
+//! #[derive(Encode, Decode, Profile)]
+//! struct Block {
+//!     height: u64,
+//!     txs: Vec<Tx>,
+//! }
+//!
+//! let block = Block {
+//!     height: 1,
+//!     txs: vec![Tx { nonce: 0, signature: [0; 64] }],
+//! };
+//! let report = profile_corpus::<Block, _, _>(vec![block.encode().unwrap()]).unwrap();
+//! assert_eq!(report.bytes("txs[].signature"), 64);
+//! println!("{}", report);
+//! ```
+
+use super::any::Any;
+use super::{Decode, Encode, Result, Timestamp};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// A trait for values which can report the encoded size of their parts.
+/// Usually implemented with `#[derive(Profile)]`.
+pub trait Profile: Encode {
+    /// Records the encoded size of each part of the value (e.g. each field of
+    /// a struct) in the report, under paths beginning with `path`. Does
+    /// nothing by default, for values with no parts worth reporting.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        let _ = (path, report);
+        Ok(())
+    }
+
+    /// Records the encoded size of the value under `path`, followed by the
+    /// sizes of its parts.
+    fn profile(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        report.record(path, self.encoding_length()?);
+        self.profile_parts(path, report)
+    }
+}
+
+/// Joins a path and the name of one of its parts, e.g. `txs` and `signature`
+/// become `txs.signature`.
+pub fn join(path: &str, part: &str) -> String {
+    if path.is_empty() {
+        part.to_string()
+    } else {
+        format!("{}.{}", path, part)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Entry {
+    bytes: u64,
+    count: u64,
+}
+
+/// The total encoded size of each part of a corpus of values, keyed by path.
+///
+/// Paths name fields by joining field names with `.`, and name the elements
+/// of collections with `[]`, e.g. `txs[].signature`. The empty path is the
+/// whole value.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SizeReport {
+    entries: BTreeMap<String, Entry>,
+}
+
+impl SizeReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the sizes of the value and its parts to the report.
+    pub fn add<T: Profile>(&mut self, value: &T) -> Result<()> {
+        value.profile("", self)
+    }
+
+    /// Records `bytes` bytes under the path.
+    pub fn record(&mut self, path: &str, bytes: usize) {
+        let entry = self.entries.entry(path.to_string()).or_default();
+        entry.bytes += bytes as u64;
+        entry.count += 1;
+    }
+
+    /// Returns the total number of bytes recorded under the path.
+    pub fn bytes(&self, path: &str) -> u64 {
+        self.entries.get(path).map_or(0, |entry| entry.bytes)
+    }
+
+    /// Returns the number of values recorded under the path.
+    pub fn count(&self, path: &str) -> u64 {
+        self.entries.get(path).map_or(0, |entry| entry.count)
+    }
+
+    /// Returns the total size of all of the values added to the report.
+    pub fn total_bytes(&self) -> u64 {
+        self.bytes("")
+    }
+
+    /// Returns the share of the total size taken up by the path, from 0 to 1.
+    pub fn fraction(&self, path: &str) -> f64 {
+        match self.total_bytes() {
+            0 => 0.0,
+            total => self.bytes(path) as f64 / total as f64,
+        }
+    }
+
+    /// Iterates over the recorded paths along with their total sizes, in
+    /// path order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> + '_ {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry.bytes))
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (path, entry) in self.entries.iter() {
+            let percent = self.fraction(path) * 100.0;
+            let path = if path.is_empty() { "(total)" } else { path };
+            writeln!(
+                f,
+                "{:<40} {:>12} bytes {:>6.2}% ({} values)",
+                path, entry.bytes, percent, entry.count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes each encoding in the corpus as a `T` and returns a report of the
+/// sizes of its parts.
+pub fn profile_corpus<T, I, B>(corpus: I) -> Result<SizeReport>
+where
+    T: Decode + Profile,
+    I: IntoIterator<Item = B>,
+    B: AsRef<[u8]>,
+{
+    let mut report = SizeReport::new();
+    for bytes in corpus {
+        let value = T::decode(bytes.as_ref())?;
+        report.add(&value)?;
+    }
+    Ok(report)
+}
+
+macro_rules! leaf_impl {
+    ($($type:ty),*) => {
+        $(impl Profile for $type {})*
+    };
+}
+
+leaf_impl!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    bool,
+    (),
+    String,
+    Duration,
+    Any
+);
+
+impl<const MIN: i64, const MAX: i64> Profile for Timestamp<MIN, MAX> {}
+
+impl<T: ?Sized> Profile for PhantomData<T> {}
+
+impl<T: Profile> Profile for Option<T> {
+    /// Records the parts of the inner value, if any, under the same path.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        match self {
+            Some(value) => value.profile_parts(path, report),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Profile> Profile for Box<T> {
+    /// Records the parts of the inner value under the same path.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        (**self).profile_parts(path, report)
+    }
+}
+
+impl<T: Profile> Profile for RefCell<T> {
+    /// Records the parts of the inner value under the same path.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        self.try_borrow()
+            .map_err(|_| super::Error::AlreadyBorrowed)?
+            .profile_parts(path, report)
+    }
+}
+
+impl<T: Profile + super::Terminated, const N: usize> Profile for [T; N] {
+    /// Records each element under `path[]`.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        let path = format!("{}[]", path);
+        for element in self.iter() {
+            element.profile(&path, report)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Profile + super::Terminated> Profile for Vec<T> {
+    /// Records each element under `path[]`.
+    fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+        let path = format!("{}[]", path);
+        for element in self.iter() {
+            element.profile(&path, report)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($type:ident $index:tt),*) => {
+        impl<$($type: Profile),*> Profile for ($($type,)*)
+        where
+            ($($type,)*): Encode,
+        {
+            /// Records each element under `path.<index>`.
+            fn profile_parts(&self, path: &str, report: &mut SizeReport) -> Result<()> {
+                $(self.$index.profile(&join(path, stringify!($index)), report)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_impl!(A 0);
+tuple_impl!(A 0, B 1);
+tuple_impl!(A 0, B 1, C 2);
+tuple_impl!(A 0, B 1, C 2, D 3);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14);
+tuple_impl!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14, P 15);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_values() {
+        let mut report = SizeReport::new();
+        report.add(&(1u32, vec![Some(2u16), None])).unwrap();
+        report.add(&(3u32, Vec::<Option<u16>>::new())).unwrap();
+
+        assert_eq!(report.total_bytes(), 12);
+        assert_eq!(report.count(""), 2);
+        assert_eq!(report.bytes("0"), 8);
+        assert_eq!(report.bytes("1"), 4);
+        assert_eq!(report.bytes("1[]"), 4);
+        assert_eq!(report.count("1[]"), 2);
+        assert!((report.fraction("0") - 8.0 / 12.0).abs() < 1e-9);
+        assert_eq!(
+            report.iter().collect::<Vec<_>>(),
+            vec![("", 12), ("0", 8), ("1", 4), ("1[]", 4)]
+        );
+    }
+
+    #[test]
+    fn profile_corpus_display() {
+        let corpus = vec![vec![0, 1, 1, 0, 2], vec![0, 3]];
+        let report = profile_corpus::<(u16, Vec<u8>), _, _>(corpus).unwrap();
+        assert_eq!(report.bytes("1[]"), 3);
+        assert_eq!(
+            report.to_string().lines().next().unwrap(),
+            "(total)                                             7 bytes 100.00% (2 values)"
+        );
+    }
+}
@@ -0,0 +1,399 @@
+//! Helpers for hand-implementing protobuf-compatible encodings.
+//!
+//! A protobuf message is a sequence of fields, each a [`Tag`](struct.Tag.html)
+//! followed by a value whose layout depends on the tag's
+//! [`WireType`](enum.WireType.html):
+//!
+//! - `Varint`: a LEB128 varint, used for `int32`, `int64`, `uint32`,
+//!   `uint64`, `bool` and enums. Negative `int32` and `int64` values are
+//!   encoded as their 64-bit two's complement, so take 10 bytes; `sint32` and
+//!   `sint64` are zigzag encoded like [`Varint`](../varint/struct.Varint.html)
+//!   of a signed integer.
+//! - `I64` and `I32`: 8 or 4 little-endian bytes, used for `fixed64`,
+//!   `sfixed64` and `double`, or `fixed32`, `sfixed32` and `float`. See
+//!   [`Le`](../le/struct.Le.html).
+//! - `Len`: a varint length followed by that many bytes, used for `string`,
+//!   `bytes`, embedded messages and packed repeated fields.
+//!
+//! The message itself has no length or terminator, so it is decoded by
+//! reading tags until the end of the input and skipping the fields of unknown
+//! numbers:
+//!
+//! ```rust
+//! use ed::io::{Read, Write};
+//! use ed::proto::{self, Tag, WireType};
+//! use ed::{Decode, Encode, Result};
+//!
+//! #[derive(Debug, Default, PartialEq)]
+//! struct User {
+//!     id: u64,
+//!     name: String,
+//! }
+//!
+//! impl Encode for User {
+//!     fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+//!         Tag::new(1, WireType::Varint).encode_into(dest)?;
+//!         proto::encode_varint(self.id, dest)?;
+//!         Tag::new(2, WireType::Len).encode_into(dest)?;
+//!         proto::encode_length_delimited(&self.name, dest)
+//!     }
+//!
+//!     fn encoding_length(&self) -> Result<usize> {
+//!         Ok(2 + proto::varint_length(self.id) + proto::length_delimited_length(&self.name)?)
+//!     }
+//! }
+//!
+//! impl Decode for User {
+//!     fn decode<R: Read>(mut input: R) -> Result<Self> {
+//!         let mut user = User::default();
+//!         while let Some(tag) = proto::decode_tag(&mut input)? {
+//!             match tag.field {
+//!                 1 => {
+//!                     tag.expect(WireType::Varint)?;
+//!                     user.id = proto::decode_varint(&mut input)?;
+//!                 }
+//!                 2 => {
+//!                     tag.expect(WireType::Len)?;
+//!                     user.name = proto::decode_length_delimited(&mut input)?;
+//!                 }
+//!                 _ => proto::skip_field(tag.wire_type, &mut input)?,
+//!             }
+//!         }
+//!         Ok(user)
+//!     }
+//! }
+//!
+//! let user = User { id: 150, name: "ed".into() };
+//! let bytes = user.encode().unwrap();
+//! assert_eq!(bytes, vec![0x08, 0x96, 0x01, 0x12, 0x02, b'e', b'd']);
+//! assert_eq!(User::decode(bytes.as_slice()).unwrap(), user);
+//! ```
+//!
+//! `Vec<u8>` and `String` encode as their raw bytes, as do messages
+//! implemented this way, so they can be passed to
+//! [`encode_length_delimited`](fn.encode_length_delimited.html) and
+//! [`decode_length_delimited`](fn.decode_length_delimited.html) directly.
+//!
+//! Unlike other protobuf implementations, varints padded with trailing zero
+//! groups are rejected with `Error::NonCanonicalVarint`, as for
+//! [`Varint`](../varint/struct.Varint.html). Protobuf encoders never produce
+//! them. Whether fields with default values are omitted, and whether repeated
+//! fields are packed, is up to the implementation of each message.
+
+use super::io::{Read, Write};
+use super::varint::Varint;
+use super::wipe::wipe;
+use super::{next_element, Decode, DecodeExt, Encode, Error, Result, Terminated};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const CHUNK_LENGTH: usize = 4096;
+
+/// The largest valid field number, 2^29 - 1.
+pub const MAX_FIELD: u32 = (1 << 29) - 1;
+
+/// The layout of a field's value.
+///
+/// The deprecated group wire types, 3 and 4, are not supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WireType {
+    /// A varint.
+    Varint = 0,
+    /// 8 little-endian bytes.
+    I64 = 1,
+    /// A varint length followed by that many bytes.
+    Len = 2,
+    /// 4 little-endian bytes.
+    I32 = 5,
+}
+
+impl TryFrom<u8> for WireType {
+    type Error = Error;
+
+    /// Errors with `Error::UnexpectedByte` for unknown or unsupported wire
+    /// types.
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(WireType::Varint),
+            1 => Ok(WireType::I64),
+            2 => Ok(WireType::Len),
+            5 => Ok(WireType::I32),
+            _ => Err(Error::UnexpectedByte(value)),
+        }
+    }
+}
+
+/// The key preceding each field of a message: the field number and the wire
+/// type of its value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tag {
+    pub field: u32,
+    pub wire_type: WireType,
+}
+
+impl Tag {
+    /// Returns the tag for the given field number and wire type.
+    pub fn new(field: u32, wire_type: WireType) -> Self {
+        Tag { field, wire_type }
+    }
+
+    /// Errors with `Error::UnexpectedByte` if the tag's wire type is not
+    /// `wire_type`.
+    pub fn expect(self, wire_type: WireType) -> Result<()> {
+        if self.wire_type != wire_type {
+            return Err(Error::UnexpectedByte(self.wire_type as u8));
+        }
+        Ok(())
+    }
+
+    fn key(self) -> Result<u32> {
+        if self.field == 0 || self.field > MAX_FIELD {
+            return Err(Error::OutOfRange);
+        }
+        Ok(self.field << 3 | self.wire_type as u32)
+    }
+}
+
+impl Encode for Tag {
+    /// Encodes `field << 3 | wire_type` as a varint. Errors with
+    /// `Error::OutOfRange` if the field number is zero or over `MAX_FIELD`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        Varint(self.key()?).encode_into(dest)
+    }
+
+    /// Returns the length of the varint key.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Varint(self.key()?).encoding_length()
+    }
+}
+
+impl Decode for Tag {
+    /// Decodes the varint key. Errors with `Error::OutOfRange` if the field
+    /// number is zero, and with `Error::UnexpectedByte` for unsupported wire
+    /// types.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let key = Varint::<u32>::decode(input)?.0;
+        let wire_type = WireType::try_from((key & 0b111) as u8)?;
+        let field = key >> 3;
+        if field == 0 {
+            return Err(Error::OutOfRange);
+        }
+        Ok(Tag { field, wire_type })
+    }
+}
+
+impl Terminated for Tag {}
+
+/// Decodes the tag of the next field, or returns `None` at the end of the
+/// input.
+pub fn decode_tag<R: Read>(mut input: R) -> Result<Option<Tag>> {
+    match next_element(&mut input)? {
+        Some(key) => Tag::decode(key).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Encodes a `uint64` as a varint. Encode other integer types by casting them
+/// to `u64`, sign-extending negative values.
+#[inline]
+pub fn encode_varint<W: Write>(value: u64, dest: &mut W) -> Result<()> {
+    Varint(value).encode_into(dest)
+}
+
+/// Decodes a varint as a `uint64`. Decode other integer types by casting the
+/// result, which truncates it as protobuf does.
+#[inline]
+pub fn decode_varint<R: Read>(input: R) -> Result<u64> {
+    Ok(Varint::<u64>::decode(input)?.0)
+}
+
+/// Returns the length of the varint encoding of `value`, from 1 to 10 bytes.
+#[inline]
+pub fn varint_length(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    bits.max(1).div_ceil(7)
+}
+
+/// Encodes the encoding length of `value` as a varint, followed by the
+/// encoding itself.
+pub fn encode_length_delimited<T: Encode + ?Sized, W: Write>(
+    value: &T,
+    dest: &mut W,
+) -> Result<()> {
+    encode_varint(value.encoding_length()? as u64, dest)?;
+    value.encode_into(dest)
+}
+
+/// Returns the length of the length-delimited encoding of `value`.
+pub fn length_delimited_length<T: Encode + ?Sized>(value: &T) -> Result<usize> {
+    let length = value.encoding_length()?;
+    Ok(varint_length(length as u64) + length)
+}
+
+/// Decodes a varint length followed by a value whose encoding is exactly
+/// that many bytes, erroring with `Error::TrailingBytes` if the value doesn't
+/// consume all of them.
+pub fn decode_length_delimited<T: Decode, R: Read>(mut input: R) -> Result<T> {
+    let length = usize::try_from(decode_varint(&mut input)?).map_err(|_| Error::VarintOverflow)?;
+
+    // Read in chunks, so a corrupt length can't make us allocate more than
+    // the input actually contains.
+    let mut bytes = Vec::new();
+    while bytes.len() < length {
+        let start = bytes.len();
+        bytes.resize(length.min(start + CHUNK_LENGTH), 0);
+        if let Err(err) = input.read_exact(&mut bytes[start..]) {
+            wipe(&mut bytes, T::SENSITIVE);
+            return Err(err.into());
+        }
+    }
+
+    let value = T::decode_all(&bytes);
+    wipe(&mut bytes, T::SENSITIVE);
+    value
+}
+
+/// Reads and discards a field value of the given wire type, for skipping
+/// fields with unknown numbers.
+pub fn skip_field<R: Read>(wire_type: WireType, mut input: R) -> Result<()> {
+    let length = match wire_type {
+        WireType::Varint => return decode_varint(input).map(|_| ()),
+        WireType::I64 => 8,
+        WireType::I32 => 4,
+        WireType::Len => decode_varint(&mut input)?,
+    };
+    let mut remaining = length;
+    let mut chunk = [0; 256];
+    while remaining > 0 {
+        let n = remaining.min(chunk.len() as u64) as usize;
+        input.read_exact(&mut chunk[..n])?;
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::le::Le;
+
+    #[test]
+    fn tag() {
+        let tag = Tag::new(1, WireType::Varint);
+        assert_eq!(tag.encode().unwrap(), vec![0x08]);
+        assert_eq!(Tag::new(2, WireType::Len).encode().unwrap(), vec![0x12]);
+        assert_eq!(
+            Tag::new(16, WireType::I32).encode().unwrap(),
+            vec![0x85, 0x01]
+        );
+        assert_eq!(
+            Tag::decode(&[0x85, 0x01][..]).unwrap(),
+            Tag::new(16, WireType::I32)
+        );
+
+        let max = Tag::new(MAX_FIELD, WireType::I64);
+        assert_eq!(max.encoding_length().unwrap(), 5);
+        assert_eq!(Tag::decode(max.encode().unwrap().as_slice()).unwrap(), max);
+
+        assert!(matches!(
+            Tag::new(0, WireType::Varint).encode(),
+            Err(Error::OutOfRange)
+        ));
+        assert!(matches!(
+            Tag::new(MAX_FIELD + 1, WireType::Varint).encode(),
+            Err(Error::OutOfRange)
+        ));
+        assert!(matches!(Tag::decode(&[0x00][..]), Err(Error::OutOfRange)));
+        assert!(matches!(
+            Tag::decode(&[0x0b][..]),
+            Err(Error::UnexpectedByte(3))
+        ));
+
+        assert!(tag.expect(WireType::Varint).is_ok());
+        assert!(matches!(
+            tag.expect(WireType::Len),
+            Err(Error::UnexpectedByte(0))
+        ));
+    }
+
+    #[test]
+    fn varint() {
+        let mut bytes = vec![];
+        encode_varint(150, &mut bytes).unwrap();
+        assert_eq!(bytes, vec![0x96, 0x01]);
+        assert_eq!(decode_varint(bytes.as_slice()).unwrap(), 150);
+
+        // Negative `int64`s take 10 bytes.
+        let mut bytes = vec![];
+        encode_varint(-2i64 as u64, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 10);
+        assert_eq!(varint_length(-2i64 as u64), 10);
+        assert_eq!(decode_varint(bytes.as_slice()).unwrap() as i64, -2);
+
+        assert_eq!(varint_length(0), 1);
+        assert_eq!(varint_length(127), 1);
+        assert_eq!(varint_length(128), 2);
+        assert!(matches!(
+            decode_varint(&[0x96, 0x81, 0x00][..]),
+            Err(Error::NonCanonicalVarint)
+        ));
+    }
+
+    #[test]
+    fn length_delimited() {
+        let mut bytes = vec![];
+        encode_length_delimited("testing", &mut bytes).unwrap();
+        assert_eq!(bytes, b"\x07testing".to_vec());
+        assert_eq!(length_delimited_length("testing").unwrap(), 8);
+        let decoded: String = decode_length_delimited(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, "testing");
+
+        let mut input = &[2, 1, 2, 3][..];
+        let decoded: Vec<u8> = decode_length_delimited(&mut input).unwrap();
+        assert_eq!((decoded, input), (vec![1, 2], &[3][..]));
+
+        assert!(matches!(
+            decode_length_delimited::<u8, _>(&[2, 1, 2][..]),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert!(
+            decode_length_delimited::<Vec<u8>, _>(&[0xff, 0xff, 0x03, 1][..])
+                .unwrap_err()
+                .is_unexpected_eof()
+        );
+    }
+
+    #[test]
+    fn skip_fields() {
+        let mut bytes = vec![];
+        Tag::new(1, WireType::Varint)
+            .encode_into(&mut bytes)
+            .unwrap();
+        encode_varint(300, &mut bytes).unwrap();
+        Tag::new(2, WireType::I64).encode_into(&mut bytes).unwrap();
+        Le(1.5f64.to_bits()).encode_into(&mut bytes).unwrap();
+        Tag::new(3, WireType::Len).encode_into(&mut bytes).unwrap();
+        encode_length_delimited(&vec![1u8, 2, 3], &mut bytes).unwrap();
+        Tag::new(4, WireType::I32).encode_into(&mut bytes).unwrap();
+        Le(7u32).encode_into(&mut bytes).unwrap();
+
+        let mut input = bytes.as_slice();
+        let mut fields = vec![];
+        while let Some(tag) = decode_tag(&mut input).unwrap() {
+            skip_field(tag.wire_type, &mut input).unwrap();
+            fields.push(tag.field);
+        }
+        assert_eq!(fields, vec![1, 2, 3, 4]);
+
+        assert!(skip_field(WireType::I32, &[1, 2, 3][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+        assert!(skip_field(WireType::Len, &[4, 1, 2, 3][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+    }
+}
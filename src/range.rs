@@ -0,0 +1,302 @@
+//! Encodings of range types.
+//!
+//! Ranges are encoded as their bounds one after another: `Range` and
+//! `RangeInclusive` as the start followed by the end, `RangeFrom` as the
+//! start, and `RangeTo` as the end. The bounds are not required to be in
+//! order, since an empty range (e.g. `5..3`) is still a valid value.
+//!
+//! A `Bound` is encoded as a tag byte, 0 for `Included`, 1 for `Excluded` or
+//! 2 for `Unbounded`, followed by the value of the bound (if any).
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use core::ops::{Bound, Range, RangeFrom, RangeInclusive, RangeTo};
+
+impl<T: Encode + Terminated> Encode for Range<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the start followed by the end.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.start.encode_into(dest)?;
+        self.end.encode_into(dest)
+    }
+
+    /// Returns the sum of the encoding lengths of the start and end.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.start.encoding_length()? + self.end.encoding_length()?)
+    }
+}
+
+impl<T: Decode + Terminated> Decode for Range<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the start followed by the end.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let start = T::decode(&mut input)?;
+        let end = T::decode(input)?;
+        Ok(start..end)
+    }
+
+    /// Decodes the start followed by the end into the existing range.
+    ///
+    /// Recursively calls `decode_into` on the start and end.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        self.start.decode_into(&mut input)?;
+        self.end.decode_into(input)
+    }
+}
+
+impl<T: Terminated> Terminated for Range<T> {}
+
+impl<T: FixedLength> FixedLength for Range<T> {
+    const ENCODING_LENGTH: usize = 2 * T::ENCODING_LENGTH;
+}
+
+impl<T: Encode + Terminated> Encode for RangeInclusive<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the start followed by the end.
+    ///
+    /// Whether the range has been exhausted by iteration is not encoded.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.start().encode_into(dest)?;
+        self.end().encode_into(dest)
+    }
+
+    /// Returns the sum of the encoding lengths of the start and end.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.start().encoding_length()? + self.end().encoding_length()?)
+    }
+}
+
+impl<T: Decode + Terminated> Decode for RangeInclusive<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the start followed by the end.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let start = T::decode(&mut input)?;
+        let end = T::decode(input)?;
+        Ok(start..=end)
+    }
+}
+
+impl<T: Terminated> Terminated for RangeInclusive<T> {}
+
+impl<T: FixedLength> FixedLength for RangeInclusive<T> {
+    const ENCODING_LENGTH: usize = 2 * T::ENCODING_LENGTH;
+}
+
+impl<T: Encode> Encode for RangeFrom<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the start.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.start.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the start.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.start.encoding_length()
+    }
+}
+
+impl<T: Decode> Decode for RangeFrom<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the start.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        Ok(T::decode(input)?..)
+    }
+
+    /// Decodes the start into the existing range.
+    ///
+    /// Recursively calls `decode_into` on the start.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        self.start.decode_into(input)
+    }
+}
+
+impl<T: Terminated> Terminated for RangeFrom<T> {}
+
+impl<T: FixedLength> FixedLength for RangeFrom<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
+impl<T: Encode> Encode for RangeTo<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the end.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.end.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the end.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.end.encoding_length()
+    }
+}
+
+impl<T: Decode> Decode for RangeTo<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the end.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        Ok(..T::decode(input)?)
+    }
+
+    /// Decodes the end into the existing range.
+    ///
+    /// Recursively calls `decode_into` on the end.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, input: R) -> Result<()> {
+        self.end.decode_into(input)
+    }
+}
+
+impl<T: Terminated> Terminated for RangeTo<T> {}
+
+impl<T: FixedLength> FixedLength for RangeTo<T> {
+    const ENCODING_LENGTH: usize = T::ENCODING_LENGTH;
+}
+
+impl<T: Encode> Encode for Bound<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the tag byte followed by the value of the bound, if any.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        match self {
+            Bound::Included(value) => {
+                dest.write_all(&[0])?;
+                value.encode_into(dest)
+            }
+            Bound::Excluded(value) => {
+                dest.write_all(&[1])?;
+                value.encode_into(dest)
+            }
+            Bound::Unbounded => {
+                dest.write_all(&[2])?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns 1 plus the encoding length of the value of the bound, if any.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        match self {
+            Bound::Included(value) | Bound::Excluded(value) => Ok(1 + value.encoding_length()?),
+            Bound::Unbounded => Ok(1),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Bound<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the tag byte followed by the value of the bound, if any.
+    /// Errors for tags other than 0, 1 or 2.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        match u8::decode(&mut input)? {
+            0 => Ok(Bound::Included(T::decode(input)?)),
+            1 => Ok(Bound::Excluded(T::decode(input)?)),
+            2 => Ok(Bound::Unbounded),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+
+    /// Decodes the tag byte followed by the value of the bound, if any. If
+    /// the existing bound has a value and the tag is unchanged, the value is
+    /// decoded with `decode_into`.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        match (u8::decode(&mut input)?, &mut *self) {
+            (0, Bound::Included(value)) | (1, Bound::Excluded(value)) => {
+                value.decode_into(input)?
+            }
+            (0, _) => *self = Bound::Included(T::decode(input)?),
+            (1, _) => *self = Bound::Excluded(T::decode(input)?),
+            (2, _) => *self = Bound::Unbounded,
+            (byte, _) => return Err(Error::UnexpectedByte(byte)),
+        }
+        Ok(())
+    }
+}
+
+impl<T: Terminated> Terminated for Bound<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_ranges() {
+        let range = 1u16..0x203;
+        let bytes = range.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 2, 3]);
+        assert_eq!(range.encoding_length().unwrap(), 4);
+        assert_eq!(Range::<u16>::decode(bytes.as_slice()).unwrap(), range);
+        assert_eq!(
+            RangeInclusive::<u16>::decode(bytes.as_slice()).unwrap(),
+            1..=0x203
+        );
+        assert_eq!((1u16..=0x203).encode().unwrap(), bytes);
+
+        assert_eq!((5u8..).encode().unwrap(), vec![5]);
+        assert_eq!(RangeFrom::<u8>::decode(&[5][..]).unwrap(), 5..);
+        assert_eq!((..6u8).encode().unwrap(), vec![6]);
+        assert_eq!(RangeTo::<u8>::decode(&[6][..]).unwrap(), ..6);
+
+        assert_eq!(<Range<u32> as FixedLength>::ENCODING_LENGTH, 8);
+        assert!(Range::<u16>::decode(&[0, 1, 2][..]).is_err());
+    }
+
+    #[test]
+    fn decode_into_range() {
+        let mut range = 0u8..0;
+        range.decode_into(&[3, 1][..]).unwrap();
+        assert_eq!((range.start, range.end), (3, 1));
+        assert!(range.is_empty());
+    }
+
+    #[test]
+    fn encode_decode_bound() {
+        let cases = [
+            (Bound::Included(0x102u16), vec![0, 1, 2]),
+            (Bound::Excluded(3), vec![1, 0, 3]),
+            (Bound::Unbounded, vec![2]),
+        ];
+        for (bound, bytes) in cases.iter() {
+            assert_eq!(&bound.encode().unwrap(), bytes);
+            assert_eq!(bound.encoding_length().unwrap(), bytes.len());
+            assert_eq!(&Bound::<u16>::decode(bytes.as_slice()).unwrap(), bound);
+        }
+        assert!(matches!(
+            Bound::<u16>::decode(&[3][..]),
+            Err(Error::UnexpectedByte(3))
+        ));
+
+        let mut bound = Bound::Included(1u16);
+        bound.decode_into(&[1, 0, 4][..]).unwrap();
+        assert_eq!(bound, Bound::Excluded(4));
+        bound.decode_into(&[1, 0, 5][..]).unwrap();
+        assert_eq!(bound, Bound::Excluded(5));
+        bound.decode_into(&[2][..]).unwrap();
+        assert_eq!(bound, Bound::Unbounded);
+    }
+}
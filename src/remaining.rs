@@ -0,0 +1,177 @@
+//! Values which extend to the end of the input.
+//!
+//! Some types, such as `Vec<T>`, are decoded by reading until the end of the
+//! input, so they can only be the last value in an encoding. This is
+//! otherwise implicit: such a type is simply not `Terminated`.
+//! [`Remaining`](struct.Remaining.html) makes it explicit in the type, and
+//! also errors with `Error::TrailingBytes` if the inner value doesn't consume
+//! the rest of the input.
+//!
+//! `Remaining` is deliberately not `Terminated`, and `#[derive(Encode)]` and
+//! `#[derive(Decode)]` fail to compile if a `Remaining` field is followed by
+//! other fields:
+//!
+//! ```rust
+//! use ed::{Decode, Encode, Remaining};
+//!
+//! #[derive(Encode, Decode, Debug, PartialEq)]
+//! struct Packet {
+//!     kind: u8,
+//!     payload: Remaining<Vec<u8>>,
+//! }
+//!
+//! let packet = Packet::decode(&[1, 2, 3][..]).unwrap();
+//! assert_eq!(packet.payload.0, vec![2, 3]);
+//! assert!(Remaining::<u16>::decode(&[0, 1, 2][..]).is_err());
+//! ```
+//!
+//! ```compile_fail
+//! use ed::{Decode, Encode, Remaining};
+//!
+//! #[derive(Encode, Decode)]
+//! struct Packet {
+//!     payload: Remaining<Vec<u8>>,
+//!     checksum: u32,
+//! }
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result};
+use core::ops::{Deref, DerefMut};
+
+/// A value which is the last in its encoding, consuming the rest of the
+/// input when decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Remaining<T>(pub T);
+
+impl<T> Remaining<T> {
+    /// Returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Remaining<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Remaining<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Remaining<T> {
+    fn from(value: T) -> Self {
+        Remaining(value)
+    }
+}
+
+/// Errors with `Error::TrailingBytes` if any bytes remain in the input.
+fn expect_end<R: Read>(mut input: R) -> Result<()> {
+    let mut trailing = 0;
+    let mut chunk = [0; 64];
+    loop {
+        match input.read(&mut chunk)? {
+            0 if trailing == 0 => return Ok(()),
+            0 => return Err(Error::TrailingBytes(trailing)),
+            n => trailing += n,
+        }
+    }
+}
+
+impl<T: Encode> Encode for Remaining<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+    const SINGLE_PASS: bool = T::SINGLE_PASS;
+
+    /// Encodes the inner value.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.0.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the inner value.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.0.encoding_length()
+    }
+}
+
+impl<T: Decode> Decode for Remaining<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Decodes the inner value, erroring with `Error::TrailingBytes` if it
+    /// doesn't consume the rest of the input.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let value = T::decode(&mut input)?;
+        expect_end(input)?;
+        Ok(Remaining(value))
+    }
+
+    /// Decodes the inner value into the existing value, erroring with
+    /// `Error::TrailingBytes` if it doesn't consume the rest of the input.
+    ///
+    /// Recursively calls `decode_into` on the inner value.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        self.0.decode_into(&mut input)?;
+        expect_end(input)
+    }
+}
+
+/// Rejects `Remaining` fields which aren't the last field of a derived type.
+///
+/// Every type implements `RemainingMustBeLast<()>`, and `Remaining` also
+/// implements `RemainingMustBeLast<IsRemaining>`, so the derives name
+/// `<Field as RemainingMustBeLast<_>>::check` for each field but the last,
+/// which only fails to infer `_` if the field is a `Remaining`. Keying the
+/// check off the type rather than its name catches `Remaining` however it
+/// is imported, and leaves other types named `Remaining` alone.
+#[doc(hidden)]
+pub trait RemainingMustBeLast<A> {
+    fn check() {}
+}
+
+impl<T: ?Sized> RemainingMustBeLast<()> for T {}
+
+#[doc(hidden)]
+pub struct IsRemaining;
+
+impl<T> RemainingMustBeLast<IsRemaining> for Remaining<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let value = Remaining(vec![1u8, 2, 3]);
+        assert_eq!(value.encode().unwrap(), vec![1, 2, 3]);
+        assert_eq!(value.encoding_length().unwrap(), 3);
+        assert_eq!(Remaining::<Vec<u8>>::decode(&[1, 2, 3][..]).unwrap(), value);
+
+        let pair: (u8, Remaining<Vec<u16>>) = Decode::decode(&[7, 0, 1, 0, 2][..]).unwrap();
+        assert_eq!(pair, (7, Remaining(vec![1, 2])));
+    }
+
+    #[test]
+    fn decode_trailing_bytes() {
+        assert_eq!(Remaining::<u16>::decode(&[0, 1][..]).unwrap(), Remaining(1));
+        assert!(matches!(
+            Remaining::<u16>::decode(&[0, 1, 2, 3][..]),
+            Err(Error::TrailingBytes(2))
+        ));
+
+        let mut value = Remaining(0u8);
+        assert!(matches!(
+            value.decode_into(&[5, 6][..]),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert_eq!(value, Remaining(5));
+    }
+}
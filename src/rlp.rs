@@ -0,0 +1,468 @@
+//! Helpers for RLP (Recursive Length Prefix) encodings, as used by Ethereum
+//! transactions and receipts.
+//!
+//! An RLP item is either a byte string or a list of items:
+//!
+//! - A single byte below `0x80` is encoded as itself.
+//! - Other strings of up to 55 bytes are prefixed with `0x80` plus their
+//!   length. Longer strings are prefixed with `0xb7` plus the length of their
+//!   length, followed by the length as a big-endian integer.
+//! - Lists are encoded like strings of the concatenated encodings of their
+//!   items, using `0xc0` and `0xf7` instead.
+//!
+//! Integers are encoded as big-endian strings without leading zeros, so zero
+//! is the empty string.
+//!
+//! [`Rlp`](struct.Rlp.html) wraps an unsigned integer, `Vec<u8>` or byte
+//! array to encode it as an RLP string. RLP items are self-delimiting, so
+//! `Rlp` values are `Terminated`, and a list is encoded with
+//! [`encode_list`](fn.encode_list.html) from any value whose encoding is the
+//! concatenation of its items, such as a tuple or `Vec` of `Rlp` values:
+//!
+//! ```rust
+//! use ed::rlp::{self, Rlp};
+//!
+//! let mut bytes = vec![];
+//! rlp::encode_list(&(Rlp(1u64), Rlp(b"dog".to_vec())), &mut bytes).unwrap();
+//! assert_eq!(bytes, vec![0xc5, 0x01, 0x83, b'd', b'o', b'g']);
+//!
+//! let (nonce, name): (Rlp<u64>, Rlp<Vec<u8>>) = rlp::decode_list(bytes.as_slice()).unwrap();
+//! assert_eq!((nonce.0, name.0), (1, b"dog".to_vec()));
+//! ```
+//!
+//! Only the canonical encoding of each item is accepted: decoding errors with
+//! `Error::UnexpectedByte` for lengths which could have been encoded in a
+//! shorter form, single bytes below `0x80` encoded as strings, and integers
+//! with leading zeros.
+
+use super::io::{Read, Write};
+use super::{limits, Decode, DecodeExt, Encode, Error, Result, Terminated};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+const CHUNK_LENGTH: usize = 4096;
+
+const STRING_OFFSET: u8 = 0x80;
+const LIST_OFFSET: u8 = 0xc0;
+/// The longest payload whose length fits in the prefix byte.
+const MAX_SHORT_LENGTH: usize = 55;
+
+/// A value encoded as an RLP string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rlp<T>(pub T);
+
+impl<T> Rlp<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Rlp<T> {
+    fn from(value: T) -> Self {
+        Rlp(value)
+    }
+}
+
+/// Returns the big-endian bytes of `length` without leading zeros.
+fn length_bytes(length: usize) -> ([u8; 8], usize) {
+    let bytes = (length as u64).to_be_bytes();
+    let start = (length as u64).leading_zeros() as usize / 8;
+    (bytes, start)
+}
+
+fn encode_header<W: Write>(offset: u8, length: usize, dest: &mut W) -> Result<()> {
+    if length <= MAX_SHORT_LENGTH {
+        dest.write_all(&[offset + length as u8])?;
+    } else {
+        let (bytes, start) = length_bytes(length);
+        let prefix = offset + MAX_SHORT_LENGTH as u8 + (8 - start) as u8;
+        dest.write_all(&[prefix])?;
+        dest.write_all(&bytes[start..])?;
+    }
+    Ok(())
+}
+
+fn header_length(length: usize) -> usize {
+    if length <= MAX_SHORT_LENGTH {
+        1
+    } else {
+        1 + 8 - length_bytes(length).1
+    }
+}
+
+/// The prefix of an item, giving its kind and the length of its payload.
+enum Header {
+    Byte(u8),
+    String(usize),
+    List(usize),
+}
+
+/// Decodes the prefix of an item, also returning the prefix byte to report in
+/// errors.
+fn decode_header<R: Read>(mut input: R) -> Result<(u8, Header)> {
+    let prefix = u8::decode(&mut input)?;
+    let (offset, list) = match prefix {
+        0x00..=0x7f => return Ok((prefix, Header::Byte(prefix))),
+        0x80..=0xbf => (STRING_OFFSET, false),
+        0xc0..=0xff => (LIST_OFFSET, true),
+    };
+
+    let short = (prefix - offset) as usize;
+    let length = if short <= MAX_SHORT_LENGTH {
+        short
+    } else {
+        let mut bytes = [0; 8];
+        let start = 8 - (short - MAX_SHORT_LENGTH);
+        input.read_exact(&mut bytes[start..])?;
+        let length = u64::from_be_bytes(bytes);
+        // The length must have no leading zeros and be too long for the
+        // short form.
+        if bytes[start] == 0 || length <= MAX_SHORT_LENGTH as u64 {
+            return Err(Error::UnexpectedByte(prefix));
+        }
+        usize::try_from(length).map_err(|_| Error::OutOfRange)?
+    };
+
+    let header = if list {
+        Header::List(length)
+    } else {
+        Header::String(length)
+    };
+    Ok((prefix, header))
+}
+
+fn read_payload<R: Read>(mut input: R, length: usize) -> Result<Vec<u8>> {
+    // Read in chunks, so a corrupt length can't make us allocate more than
+    // the input actually contains.
+    let mut bytes = Vec::new();
+    while bytes.len() < length {
+        let start = bytes.len();
+        bytes.resize(length.min(start + CHUNK_LENGTH), 0);
+        input.read_exact(&mut bytes[start..])?;
+    }
+    Ok(bytes)
+}
+
+/// Encodes `bytes` as an RLP string.
+pub fn encode_bytes<W: Write>(bytes: &[u8], dest: &mut W) -> Result<()> {
+    if let [byte @ 0x00..=0x7f] = bytes {
+        dest.write_all(&[*byte])?;
+        return Ok(());
+    }
+    encode_header(STRING_OFFSET, bytes.len(), dest)?;
+    dest.write_all(bytes)?;
+    Ok(())
+}
+
+/// Returns the length of the RLP string encoding of `bytes`.
+pub fn bytes_length(bytes: &[u8]) -> usize {
+    match bytes {
+        [0x00..=0x7f] => 1,
+        _ => header_length(bytes.len()) + bytes.len(),
+    }
+}
+
+/// Decodes an RLP string. Errors with `Error::UnexpectedByte` if the item is a
+/// list or not canonically encoded.
+pub fn decode_bytes<R: Read>(mut input: R) -> Result<Vec<u8>> {
+    match decode_header(&mut input)? {
+        (_, Header::Byte(byte)) => Ok([byte].to_vec()),
+        (prefix, Header::String(length)) => {
+            let bytes = read_payload(input, length)?;
+            if let [0x00..=0x7f] = bytes[..] {
+                return Err(Error::UnexpectedByte(prefix));
+            }
+            Ok(bytes)
+        }
+        (prefix, Header::List(_)) => Err(Error::UnexpectedByte(prefix)),
+    }
+}
+
+/// Encodes the encoding of `payload` as an RLP list. The encoding must be the
+/// concatenation of the list's items, e.g. a tuple or `Vec` of `Rlp` values.
+pub fn encode_list<T: Encode + ?Sized, W: Write>(payload: &T, dest: &mut W) -> Result<()> {
+    encode_header(LIST_OFFSET, payload.encoding_length()?, dest)?;
+    payload.encode_into(dest)
+}
+
+/// Returns the length of the RLP list encoding of `payload`.
+pub fn list_length<T: Encode + ?Sized>(payload: &T) -> Result<usize> {
+    let length = payload.encoding_length()?;
+    Ok(header_length(length) + length)
+}
+
+/// Decodes an RLP list, decoding its payload as a `T` which must consume all
+/// of it. Errors with `Error::UnexpectedByte` if the item is a string or not
+/// canonically encoded.
+pub fn decode_list<T: Decode, R: Read>(mut input: R) -> Result<T> {
+    let _depth = limits::descend()?;
+    match decode_header(&mut input)? {
+        (_, Header::List(length)) => {
+            let payload = read_payload(input, length)?;
+            T::decode_all(&payload)
+        }
+        (prefix, _) => Err(Error::UnexpectedByte(prefix)),
+    }
+}
+
+impl Encode for Rlp<Vec<u8>> {
+    /// Encodes the bytes as an RLP string.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        encode_bytes(&self.0, dest)
+    }
+
+    /// Returns the length of the RLP string.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(bytes_length(&self.0))
+    }
+}
+
+impl Decode for Rlp<Vec<u8>> {
+    /// Decodes an RLP string.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        decode_bytes(input).map(Rlp)
+    }
+}
+
+impl Terminated for Rlp<Vec<u8>> {}
+
+impl<const N: usize> Encode for Rlp<[u8; N]> {
+    /// Encodes the bytes as an RLP string.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        encode_bytes(&self.0, dest)
+    }
+
+    /// Returns the length of the RLP string.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(bytes_length(&self.0))
+    }
+}
+
+impl<const N: usize> Decode for Rlp<[u8; N]> {
+    /// Decodes an RLP string, erroring with `Error::UnexpectedByte` if it is
+    /// not exactly `N` bytes long.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let mut bytes = [0; N];
+        match decode_header(&mut input)? {
+            (_, Header::Byte(byte)) if N == 1 => bytes[0] = byte,
+            (prefix, Header::String(length)) if length == N => {
+                input.read_exact(&mut bytes)?;
+                if let [0x00..=0x7f] = bytes[..] {
+                    return Err(Error::UnexpectedByte(prefix));
+                }
+            }
+            (prefix, _) => return Err(Error::UnexpectedByte(prefix)),
+        }
+        Ok(Rlp(bytes))
+    }
+}
+
+impl<const N: usize> Terminated for Rlp<[u8; N]> {}
+
+macro_rules! rlp_uint_impl {
+    ($type:ty) => {
+        impl Encode for Rlp<$type> {
+            #[doc = "Encodes the integer as a big-endian RLP string without"]
+            #[doc = "leading zeros."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                let bytes = self.0.to_be_bytes();
+                let start = self.0.leading_zeros() as usize / 8;
+                encode_bytes(&bytes[start..], dest)
+            }
+
+            #[doc = "Returns the length of the RLP string."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                let bytes = self.0.to_be_bytes();
+                let start = self.0.leading_zeros() as usize / 8;
+                Ok(bytes_length(&bytes[start..]))
+            }
+        }
+
+        impl Decode for Rlp<$type> {
+            #[doc = "Decodes a big-endian RLP string. Errors with"]
+            #[doc = "`Error::UnexpectedByte(0)` if it has leading zeros, and"]
+            #[doc = "with `Error::OutOfRange` if it is too long for the type."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                let bytes = decode_bytes(input)?;
+                if bytes.first() == Some(&0) {
+                    return Err(Error::UnexpectedByte(0));
+                }
+                let mut buf = [0; core::mem::size_of::<$type>()];
+                if bytes.len() > buf.len() {
+                    return Err(Error::OutOfRange);
+                }
+                let start = buf.len() - bytes.len();
+                buf[start..].copy_from_slice(&bytes);
+                Ok(Rlp(<$type>::from_be_bytes(buf)))
+            }
+        }
+
+        impl Terminated for Rlp<$type> {}
+    };
+}
+
+rlp_uint_impl!(u8);
+rlp_uint_impl!(u16);
+rlp_uint_impl!(u32);
+rlp_uint_impl!(u64);
+rlp_uint_impl!(u128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings() {
+        let encode = |bytes: &[u8]| {
+            let mut dest = vec![];
+            encode_bytes(bytes, &mut dest).unwrap();
+            assert_eq!(bytes_length(bytes), dest.len());
+            assert_eq!(decode_bytes(dest.as_slice()).unwrap(), bytes);
+            dest
+        };
+        assert_eq!(encode(b""), vec![0x80]);
+        assert_eq!(encode(&[0x0f]), vec![0x0f]);
+        assert_eq!(encode(&[0x80]), vec![0x81, 0x80]);
+        assert_eq!(encode(b"dog"), vec![0x83, b'd', b'o', b'g']);
+
+        let long = vec![b'a'; 56];
+        let bytes = encode(&long);
+        assert_eq!(&bytes[..2], &[0xb8, 56]);
+        assert_eq!(&bytes[2..], &long[..]);
+
+        let longer = vec![0; 1024];
+        assert_eq!(&encode(&longer)[..3], &[0xb9, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn lists() {
+        let mut bytes = vec![];
+        encode_list(&(), &mut bytes).unwrap();
+        assert_eq!(bytes, vec![0xc0]);
+
+        let items = vec![Rlp(b"cat".to_vec()), Rlp(b"dog".to_vec())];
+        let mut bytes = vec![];
+        encode_list(&items, &mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+        );
+        assert_eq!(list_length(&items).unwrap(), bytes.len());
+        let decoded: Vec<Rlp<Vec<u8>>> = decode_list(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, items);
+
+        // The set-theoretic representation of three, [ [], [[]], [ [], [[]] ] ].
+        let mut empty = vec![];
+        encode_list(&(), &mut empty).unwrap();
+        let mut one = vec![];
+        encode_list(&empty, &mut one).unwrap();
+        let mut two = vec![];
+        encode_list(&[empty.clone(), one.clone()].concat(), &mut two).unwrap();
+        let mut three = vec![];
+        encode_list(&[empty, one, two].concat(), &mut three).unwrap();
+        assert_eq!(three, vec![0xc7, 0xc0, 0xc1, 0xc0, 0xc3, 0xc0, 0xc1, 0xc0]);
+
+        let long = vec![Rlp(0xffu8); 30];
+        let mut bytes = vec![];
+        encode_list(&long, &mut bytes).unwrap();
+        assert_eq!(&bytes[..2], &[0xf8, 60]);
+        let decoded: Vec<Rlp<u8>> = decode_list(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, long);
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(Rlp(0u64).encode().unwrap(), vec![0x80]);
+        assert_eq!(Rlp(15u8).encode().unwrap(), vec![0x0f]);
+        assert_eq!(Rlp(1024u32).encode().unwrap(), vec![0x82, 0x04, 0x00]);
+        assert_eq!(Rlp(1024u32).encoding_length().unwrap(), 3);
+        assert_eq!(
+            Rlp::<u16>::decode(&[0x82, 0x04, 0x00][..]).unwrap(),
+            Rlp(1024)
+        );
+        assert_eq!(Rlp::<u8>::decode(&[0x80][..]).unwrap(), Rlp(0));
+
+        let max = Rlp(u128::MAX).encode().unwrap();
+        assert_eq!(max[0], 0x90);
+        assert_eq!(Rlp::<u128>::decode(max.as_slice()).unwrap(), Rlp(u128::MAX));
+
+        assert!(matches!(
+            Rlp::<u8>::decode(&[0x82, 0x04, 0x00][..]),
+            Err(Error::OutOfRange)
+        ));
+        assert!(matches!(
+            Rlp::<u32>::decode(&[0x82, 0x00, 0x04][..]),
+            Err(Error::UnexpectedByte(0))
+        ));
+        assert!(matches!(
+            Rlp::<u32>::decode(&[0x00][..]),
+            Err(Error::UnexpectedByte(0))
+        ));
+    }
+
+    #[test]
+    fn arrays() {
+        let address = Rlp([0x11u8; 20]);
+        let bytes = address.encode().unwrap();
+        assert_eq!(bytes[0], 0x94);
+        assert_eq!(bytes.len(), 21);
+        assert_eq!(Rlp::<[u8; 20]>::decode(bytes.as_slice()).unwrap(), address);
+
+        assert_eq!(Rlp([0x7fu8]).encode().unwrap(), vec![0x7f]);
+        assert_eq!(Rlp::<[u8; 1]>::decode(&[0x7f][..]).unwrap(), Rlp([0x7f]));
+        assert!(matches!(
+            Rlp::<[u8; 2]>::decode(&[0x83, 1, 2, 3][..]),
+            Err(Error::UnexpectedByte(0x83))
+        ));
+    }
+
+    #[test]
+    fn non_canonical() {
+        // A single byte below 0x80 encoded as a string.
+        assert!(matches!(
+            decode_bytes(&[0x81, 0x7f][..]),
+            Err(Error::UnexpectedByte(0x81))
+        ));
+        // A short length in the long form.
+        assert!(matches!(
+            decode_bytes(&[0xb8, 0x03, 1, 2, 3][..]),
+            Err(Error::UnexpectedByte(0xb8))
+        ));
+        // A long length with a leading zero.
+        let mut bytes = vec![0xb9, 0x00, 0x38];
+        bytes.extend_from_slice(&[0; 56]);
+        assert!(matches!(
+            decode_bytes(bytes.as_slice()),
+            Err(Error::UnexpectedByte(0xb9))
+        ));
+        // The wrong kind of item.
+        assert!(matches!(
+            decode_bytes(&[0xc0][..]),
+            Err(Error::UnexpectedByte(0xc0))
+        ));
+        assert!(matches!(
+            decode_list::<Vec<Rlp<u8>>, _>(&[0x80][..]),
+            Err(Error::UnexpectedByte(0x80))
+        ));
+        assert!(matches!(
+            decode_list::<Rlp<u8>, _>(&[0xc2, 0x01, 0x02][..]),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert!(
+            decode_bytes(&[0xbf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff][..])
+                .unwrap_err()
+                .is_unexpected_eof()
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! Encoding support for `Decimal`, using the `rust_decimal` crate.
+//!
+//! A `Decimal` is a 96-bit integer mantissa with a sign and a scale, the
+//! power of 10 which the mantissa is divided by. It is encoded as 16 bytes:
+//!
+//! - a big-endian `u32` of flags, with the sign in bit 31 (set if negative)
+//!   and the scale, at most 28, in bits 16 to 23. All other bits are zero.
+//! - the mantissa as a 12-byte big-endian integer.
+//!
+//! Decoding errors with `Error::OutOfRange` if any of the unused flag bits
+//! are set or the scale is over 28.
+//!
+//! The scale is part of the encoding, so values which are numerically equal
+//! but have different scales, such as `1.0` and `1.00`, have different
+//! encodings, as they also have different string representations. Call
+//! `Decimal::normalize` before encoding if equal values must always have the
+//! same encoding.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::rust_decimal::Decimal;
+
+const SIGN_MASK: u32 = 0x8000_0000;
+const SCALE_MASK: u32 = 0x00ff_0000;
+const SCALE_SHIFT: u32 = 16;
+const MAX_SCALE: u32 = 28;
+
+/// Returns the words of the decimal in encoding order: the flags followed by
+/// the high, middle and low words of the mantissa.
+fn to_words(decimal: &Decimal) -> [u32; 4] {
+    // `serialize` lays out the flags, low, middle and high words as
+    // little-endian `u32`s.
+    let bytes = decimal.serialize();
+    let word = |i: usize| {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[i..i + 4]);
+        u32::from_le_bytes(word)
+    };
+    [word(0), word(12), word(8), word(4)]
+}
+
+fn from_words(words: [u32; 4]) -> Decimal {
+    let [flags, hi, mid, lo] = words;
+    let mut bytes = [0; 16];
+    bytes[0..4].copy_from_slice(&flags.to_le_bytes());
+    bytes[4..8].copy_from_slice(&lo.to_le_bytes());
+    bytes[8..12].copy_from_slice(&mid.to_le_bytes());
+    bytes[12..16].copy_from_slice(&hi.to_le_bytes());
+    Decimal::deserialize(bytes)
+}
+
+impl Encode for Decimal {
+    /// Encodes the flags followed by the mantissa, as 16 big-endian bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        for word in to_words(self).iter() {
+            word.encode_into(dest)?;
+        }
+        Ok(())
+    }
+
+    /// Always returns Ok(16).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(16)
+    }
+}
+
+impl Decode for Decimal {
+    /// Decodes the flags followed by the mantissa. Errors with
+    /// `Error::OutOfRange` if any unused flag bits are set or the scale is
+    /// over 28.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let words = <[u32; 4]>::decode(input)?;
+        let flags = words[0];
+        let scale = (flags & SCALE_MASK) >> SCALE_SHIFT;
+        if flags & !(SIGN_MASK | SCALE_MASK) != 0 || scale > MAX_SCALE {
+            return Err(Error::OutOfRange);
+        }
+        Ok(from_words(words))
+    }
+}
+
+impl Terminated for Decimal {}
+
+impl FixedLength for Decimal {
+    const ENCODING_LENGTH: usize = 16;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let value = Decimal::from_i128_with_scale(-12_345, 2);
+        let bytes = value.encode().unwrap();
+        assert_eq!(
+            bytes,
+            vec![0x80, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x30, 0x39]
+        );
+        assert_eq!(value.encoding_length().unwrap(), 16);
+        assert_eq!(Decimal::decode(bytes.as_slice()).unwrap(), value);
+
+        let max = Decimal::MAX.encode().unwrap();
+        assert_eq!(&max[..4], &[0, 0, 0, 0]);
+        assert_eq!(&max[4..], &[0xff; 12]);
+        assert_eq!(Decimal::decode(max.as_slice()).unwrap(), Decimal::MAX);
+    }
+
+    #[test]
+    fn scale_is_preserved() {
+        let one = Decimal::from_i128_with_scale(10, 1);
+        let decoded = Decimal::decode(one.encode().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded.scale(), 1);
+        assert_ne!(one.encode().unwrap(), Decimal::ONE.encode().unwrap());
+        assert_eq!(
+            one.normalize().encode().unwrap(),
+            Decimal::ONE.encode().unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let mut bytes = [0; 16];
+        bytes[1] = 29;
+        assert!(matches!(
+            Decimal::decode(&bytes[..]),
+            Err(Error::OutOfRange)
+        ));
+
+        let mut bytes = [0; 16];
+        bytes[3] = 1;
+        assert!(matches!(
+            Decimal::decode(&bytes[..]),
+            Err(Error::OutOfRange)
+        ));
+
+        let mut bytes = [0; 16];
+        bytes[1] = 28;
+        assert_eq!(Decimal::decode(&bytes[..]).unwrap().scale(), 28);
+    }
+}
@@ -0,0 +1,149 @@
+//! Encoding support for secret values, using the `secrecy` crate.
+//!
+//! The impls in this module write secrets directly to the destination writer
+//! and wipe any intermediate buffers they allocate, so private keys and seeds
+//! can be encoded and decoded without leaving copies in freed memory. Note
+//! that [`encode`](../trait.Encode.html#method.encode) returns a plain
+//! `Vec<u8>` which is not wiped on drop; prefer
+//! [`encode_into`](../trait.Encode.html#method.encode_into) with a zeroizing
+//! destination when encoding secrets.
+
+use super::io::{Read, Write};
+use super::{wipe, Decode, Encode, Result, Terminated};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use secrecy::{ExposeSecret, Secret, SecretVec};
+use zeroize::Zeroize;
+
+impl<T: Encode + Zeroize> Encode for Secret<T> {
+    const SENSITIVE: bool = true;
+
+    /// Encodes the inner value.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.expose_secret().encode_into(dest)
+    }
+
+    /// Returns the encoding length of the inner value.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.expose_secret().encoding_length()
+    }
+}
+
+impl<T: Decode + Zeroize> Decode for Secret<T> {
+    const SENSITIVE: bool = true;
+
+    /// Decodes the inner value into a new `Secret`. Buffers read to the end of
+    /// the input while decoding it, e.g. for a `Secret<Vec<u8>>`, are wiped
+    /// as they grow.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let _sensitive = wipe::sensitive(true);
+        T::decode(input).map(Secret::new)
+    }
+}
+
+impl<T: Terminated + Zeroize> Terminated for Secret<T> {}
+
+/// A byte string which is wiped from memory when dropped.
+///
+/// Like `Vec<u8>`, `SecretBytes` is encoded as its raw bytes and decoding
+/// consumes the rest of the input. Decoding reads the input through a
+/// zeroizing scratch buffer and wipes every intermediate allocation as the
+/// buffer grows.
+pub struct SecretBytes(SecretVec<u8>);
+
+impl SecretBytes {
+    /// Wraps the given bytes.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(Secret::new(bytes))
+    }
+
+    /// Returns the secret bytes.
+    pub fn expose(&self) -> &[u8] {
+        self.0.expose_secret().as_slice()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes::new(bytes)
+    }
+}
+
+impl Encode for SecretBytes {
+    const SENSITIVE: bool = true;
+
+    /// Writes the raw bytes.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.expose())?;
+        Ok(())
+    }
+
+    /// Returns the number of bytes.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.expose().len())
+    }
+}
+
+impl Decode for SecretBytes {
+    const SENSITIVE: bool = true;
+
+    /// Reads the rest of the input into a new `SecretBytes`.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut bytes = vec![];
+        wipe::read_to_end(input, &mut bytes, true)?;
+        Ok(SecretBytes::new(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_secret() {
+        let secret = Secret::new(0x1234u16);
+        let mut bytes = vec![];
+        secret.encode_into(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![0x12, 0x34]);
+        let decoded: Secret<u16> = Decode::decode(bytes.as_slice()).unwrap();
+        assert_eq!(*decoded.expose_secret(), 0x1234);
+    }
+
+    #[test]
+    fn decode_secret_vec() {
+        // Records whether each read happened within a sensitive value.
+        struct Probe<'a>(&'a [u8], Vec<bool>);
+
+        impl Read for Probe<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+                self.1.push(wipe::active());
+                self.0.read(buf)
+            }
+        }
+
+        let input: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut probe = Probe(input.as_slice(), vec![]);
+        let secret: Secret<Vec<u8>> = Decode::decode(&mut probe).unwrap();
+        assert_eq!(secret.expose_secret(), &input);
+        assert!(probe.1.iter().all(|&sensitive| sensitive));
+        assert!(!wipe::active());
+    }
+
+    #[test]
+    fn encode_decode_secret_bytes() {
+        let input: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let secret = SecretBytes::decode(input.as_slice()).unwrap();
+        assert_eq!(secret.expose(), input.as_slice());
+        assert_eq!(secret.encoding_length().unwrap(), 1000);
+
+        let mut bytes = vec![];
+        secret.encode_into(&mut bytes).unwrap();
+        assert_eq!(bytes, input);
+    }
+}
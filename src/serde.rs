@@ -0,0 +1,928 @@
+//! Interoperability with `serde`.
+//!
+//! Codebases which use both `ed` and `serde` would otherwise need two impls
+//! for types which cross between them. Two wrappers bridge the gap:
+//!
+//! - [`Ed<T>`](struct.Ed.html) implements `Serialize` and `Deserialize` for
+//!   any `ed` type, as its `ed` encoding. Binary formats get the encoding as
+//!   bytes, and human-readable formats such as JSON get it as a lowercase hex
+//!   string.
+//! - [`Serde<T>`](struct.Serde.html) implements `Encode` and `Decode` for any
+//!   `serde` type, so it can be stored as a field of an `ed` type.
+//!
+//! ```rust
+//! use ed::serde::Serde;
+//! use ed::{Decode, Encode};
+//!
+//! let value = Serde((1u16, Some("hi".to_string()), vec![true]));
+//! let bytes = value.encode().unwrap();
+//! assert_eq!(bytes, vec![0, 1, 1, 0, 0, 0, 2, b'h', b'i', 0, 0, 0, 1, 1]);
+//! assert_eq!(Serde::decode(bytes.as_slice()).unwrap(), value);
+//! ```
+//!
+//! `Serde<T>` encodes values through the `serde` data model in a format
+//! which follows `ed`'s own encodings where it can:
+//!
+//! - `bool`, integers and `Option` are encoded as in `ed`, and floats and
+//!   `char` as their bits, as big-endian integers.
+//! - Tuples, structs and newtypes are encoded as their fields one after
+//!   another, like derived `ed` structs, and enums as the index of the
+//!   variant as a byte followed by its fields, like derived `ed` enums.
+//! - Strings, byte strings, sequences and maps are prefixed with their length
+//!   (in bytes for strings, elements or entries otherwise) as a big-endian
+//!   `u32`. Unlike `ed`'s `String` and `Vec`, every value is terminated, so
+//!   `Serde<T>` is `Terminated` and can appear anywhere in an `ed` type.
+//!
+//! The format is not self-describing, so types which call
+//! `deserialize_any`, e.g. because of `#[serde(untagged)]` or
+//! `#[serde(flatten)]`, can't be decoded.
+
+use super::ext::hex_to_bytes;
+use super::io::{self, Read, Write};
+use super::{limits, wipe, DecodeExt, EncodeExt};
+use super::{Decode, Encode, Error, Result, Terminated};
+use ::serde::de::{self, IntoDeserializer};
+use ::serde::ser;
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// The number of bytes read at once while decoding a string or byte string,
+/// so that a huge length prefix can't cause a huge allocation before the
+/// input runs out.
+const CHUNK_LENGTH: usize = 4096;
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::custom(msg)
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::custom(msg)
+    }
+}
+
+/// An `ed` type which implements `Serialize` and `Deserialize` as its `ed`
+/// encoding.
+///
+/// Deserializing errors if any bytes remain after the decoded value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ed<T>(pub T);
+
+impl<T> Ed<T> {
+    /// Returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Ed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Ed<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Ed<T> {
+    fn from(value: T) -> Self {
+        Ed(value)
+    }
+}
+
+impl<T: Encode> ser::Serialize for Ed<T> {
+    fn serialize<S: ser::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use ser::Error as _;
+
+        if serializer.is_human_readable() {
+            let hex = self.0.encode_hex().map_err(S::Error::custom)?;
+            return serializer.serialize_str(&hex);
+        }
+        let mut bytes = self.0.encode().map_err(S::Error::custom)?;
+        let res = serializer.serialize_bytes(&bytes);
+        wipe::wipe(&mut bytes, T::SENSITIVE);
+        res
+    }
+}
+
+impl<'de, T: Decode> de::Deserialize<'de> for Ed<T> {
+    fn deserialize<D: de::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(EdVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_bytes(EdVisitor(PhantomData))
+        }
+    }
+}
+
+struct EdVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Decode> de::Visitor<'de> for EdVisitor<T> {
+    type Value = Ed<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an ed encoding")
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> core::result::Result<Self::Value, E> {
+        self.visit_byte_buf(hex_to_bytes(hex).map_err(E::custom)?)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> core::result::Result<Self::Value, E> {
+        T::decode_all(bytes).map(Ed).map_err(E::custom)
+    }
+
+    fn visit_byte_buf<E: de::Error>(
+        self,
+        mut bytes: Vec<u8>,
+    ) -> core::result::Result<Self::Value, E> {
+        let res = self.visit_bytes(&bytes);
+        wipe::wipe(&mut bytes, T::SENSITIVE);
+        res
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> core::result::Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        self.visit_byte_buf(bytes)
+    }
+}
+
+/// A `serde` type which implements `Encode` and `Decode`, using the format
+/// described in the [module docs](index.html).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Serde<T>(pub T);
+
+impl<T> Serde<T> {
+    /// Returns the inner value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Serde<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Serde<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Serde<T> {
+    fn from(value: T) -> Self {
+        Serde(value)
+    }
+}
+
+impl<T: ser::Serialize> Encode for Serde<T> {
+    const SINGLE_PASS: bool = true;
+
+    /// Serializes the value into the writer.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.0.serialize(&mut Serializer { dest })
+    }
+
+    /// Serializes the value without writing it, counting the bytes it would
+    /// take.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut counter = Counter(0);
+        self.0.serialize(&mut Serializer { dest: &mut counter })?;
+        Ok(counter.0)
+    }
+}
+
+impl<T: de::DeserializeOwned> Decode for Serde<T> {
+    /// Deserializes a value from the reader.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        T::deserialize(&mut Deserializer { input }).map(Serde)
+    }
+}
+
+impl<T> Terminated for Serde<T> {}
+
+/// A writer which discards its input, counting the bytes written.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Serializer<W> {
+    dest: W,
+}
+
+impl<W: Write> Serializer<W> {
+    fn write_len(&mut self, len: usize) -> Result<()> {
+        u32::try_from(len)
+            .map_err(|_| Error::LengthOverflow(len))?
+            .encode_into(&mut self.dest)
+    }
+
+    fn write_variant(&mut self, index: u32) -> Result<()> {
+        u8::try_from(index)
+            .map_err(|_| Error::UnencodableVariant)?
+            .encode_into(&mut self.dest)
+    }
+}
+
+impl<W: Write> ser::Serializer for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        v.encode_into(&mut self.dest)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        v.to_bits().encode_into(&mut self.dest)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        v.to_bits().encode_into(&mut self.dest)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        (v as u32).encode_into(&mut self.dest)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_len(v.len())?;
+        self.dest.write_all(v)?;
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        0u8.encode_into(&mut self.dest)
+    }
+
+    fn serialize_some<T: ser::Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        1u8.encode_into(&mut self.dest)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.write_variant(index)
+    }
+
+    fn serialize_newtype_struct<T: ser::Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ser::Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.write_variant(index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        let len = len.ok_or_else(|| Error::custom("sequence length must be known"))?;
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.write_variant(index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        let len = len.ok_or_else(|| Error::custom("map length must be known"))?;
+        self.write_len(len)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.write_variant(index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeMap for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ser::Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Deserializer<R> {
+    input: R,
+}
+
+impl<R: Read> Deserializer<R> {
+    fn read_len(&mut self) -> Result<usize> {
+        Ok(u32::decode(&mut self.input)? as usize)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let length = self.read_len()?;
+        let mut bytes = Vec::new();
+        while bytes.len() < length {
+            let start = bytes.len();
+            bytes.resize(length.min(start + CHUNK_LENGTH), 0);
+            self.input.read_exact(&mut bytes[start..])?;
+        }
+        Ok(bytes)
+    }
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::custom(
+            "the format of `Serde` is not self-describing",
+        ))
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(bool::decode(&mut self.input)?)
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(i8::decode(&mut self.input)?)
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(i16::decode(&mut self.input)?)
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(i32::decode(&mut self.input)?)
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(i64::decode(&mut self.input)?)
+    }
+
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(i128::decode(&mut self.input)?)
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(u8::decode(&mut self.input)?)
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(u16::decode(&mut self.input)?)
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(u32::decode(&mut self.input)?)
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(u64::decode(&mut self.input)?)
+    }
+
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(u128::decode(&mut self.input)?)
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(f32::from_bits(u32::decode(&mut self.input)?))
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(f64::from_bits(u64::decode(&mut self.input)?))
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let c = char::from_u32(u32::decode(&mut self.input)?).ok_or(Error::OutOfRange)?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bytes = self.read_bytes()?;
+        visitor.visit_string(String::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)?)
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match u8::decode(&mut self.input)? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            byte => Err(Error::UnexpectedByte(byte)),
+        }
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let _depth = limits::descend()?;
+        let len = self.read_len()?;
+        visitor.visit_seq(Access::counted(self, len))
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access::fields(self, len))
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access::fields(self, len))
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let _depth = limits::descend()?;
+        let len = self.read_len()?;
+        visitor.visit_map(Access::counted(self, len))
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access::fields(self, fields.len()))
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::custom("the format of `Serde` has no identifiers"))
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Gives the visitor of a sequence, map, tuple or struct access to its
+/// `remaining` elements, entries or fields.
+struct Access<'a, R> {
+    de: &'a mut Deserializer<R>,
+    remaining: usize,
+    /// Whether the elements count towards the element limit, which they do
+    /// for sequences and maps but not for fields.
+    counted: bool,
+}
+
+impl<'a, R: Read> Access<'a, R> {
+    fn counted(de: &'a mut Deserializer<R>, remaining: usize) -> Self {
+        Access {
+            de,
+            remaining,
+            counted: true,
+        }
+    }
+
+    fn fields(de: &'a mut Deserializer<R>, remaining: usize) -> Self {
+        Access {
+            de,
+            remaining,
+            counted: false,
+        }
+    }
+
+    /// Returns whether there is another element, counting it if so.
+    fn next(&mut self) -> Result<bool> {
+        if self.remaining == 0 {
+            return Ok(false);
+        }
+        self.remaining -= 1;
+        if self.counted {
+            limits::count_elements(1)?;
+        }
+        Ok(true)
+    }
+}
+
+impl<'de, 'a, R: Read> de::SeqAccess<'de> for Access<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        if !self.next()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, 'a, R: Read> de::MapAccess<'de> for Access<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if !self.next()? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = u8::decode(&mut self.input)? as u32;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for &mut Deserializer<R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access::fields(self, len))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(Access::fields(self, fields.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn encode_decode() {
+        let value = Serde((
+            true,
+            -2i8,
+            'é',
+            1.5f32,
+            Some(vec![(); 2]),
+            Ok::<u16, String>(3),
+            Err::<u16, String>("no".to_string()),
+        ));
+        let bytes = value.encode().unwrap();
+        assert_eq!(
+            bytes,
+            vec![
+                1, 0xfe, 0, 0, 0, 0xe9, 0x3f, 0xc0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 3, 1, 0, 0, 0, 2,
+                b'n', b'o',
+            ]
+        );
+        assert_eq!(value.encoding_length().unwrap(), bytes.len());
+        assert_eq!(Serde::decode(bytes.as_slice()).unwrap(), value);
+    }
+
+    #[test]
+    fn matches_ed_encoding() {
+        let value = (1u32, Some(2u64), [3u8; 2]);
+        assert_eq!(Serde(value).encode().unwrap(), value.encode().unwrap());
+    }
+
+    #[test]
+    fn terminated_field() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), vec![1u8, 2]);
+        map.insert("b".to_string(), vec![]);
+        let value = (Serde(map), 7u8);
+        let bytes = value.encode().unwrap();
+        assert_eq!(
+            bytes,
+            vec![0, 0, 0, 2, 0, 0, 0, 1, b'a', 0, 0, 0, 2, 1, 2, 0, 0, 0, 1, b'b', 0, 0, 0, 0, 7]
+        );
+        assert_eq!(
+            <(Serde<BTreeMap<String, Vec<u8>>>, u8)>::decode(bytes.as_slice()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(matches!(
+            Serde::<bool>::decode(&[2][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+        assert!(matches!(
+            Serde::<Option<u8>>::decode(&[2][..]),
+            Err(Error::UnexpectedByte(2))
+        ));
+        assert!(matches!(
+            Serde::<String>::decode(&[0, 0, 0, 1, 0xff][..]),
+            Err(Error::InvalidUtf8)
+        ));
+        assert!(matches!(
+            Serde::<char>::decode(&[0, 0, 0xd8, 0][..]),
+            Err(Error::OutOfRange)
+        ));
+        assert!(matches!(
+            Serde::<core::result::Result<u8, u8>>::decode(&[2, 0][..]),
+            Err(Error::Custom(_))
+        ));
+
+        // A huge length fails once the input runs out.
+        let err = Serde::<String>::decode(&[0xff, 0xff, 0xff, 0xff, b'a'][..]).unwrap_err();
+        assert!(err.is_unexpected_eof());
+    }
+
+    #[test]
+    fn ed_wrapper() {
+        let value = Serde(Ed((1u16, 2u8)));
+        let bytes = value.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 3, 0, 1, 2]);
+        assert_eq!(Serde::decode(bytes.as_slice()).unwrap(), value);
+
+        let trailing = [0, 0, 0, 4, 0, 1, 2, 3];
+        assert!(Serde::<Ed<(u16, u8)>>::decode(&trailing[..]).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Encoding support for `SmallVec`, using the `smallvec` crate.
+//!
+//! A `SmallVec` is encoded exactly like a `Vec` of the same elements: the
+//! elements one after another, read until the end of the input when decoding.
+//! Whether the elements are stored inline or on the heap doesn't affect the
+//! encoding.
+
+use super::io::{Read, Write};
+use super::{limits, next_element, Decode, Encode, Result, Terminated};
+use ::smallvec::{Array, SmallVec};
+
+impl<A: Array> Encode for SmallVec<A>
+where
+    A::Item: Encode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Encodes the elements of the vector one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        A::Item::encode_slice_into(self, dest)
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<A: Array> Decode for SmallVec<A>
+where
+    A::Item: Decode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Decodes the elements of the vector one after another, in order.
+    ///
+    /// Nothing is allocated up front. Elements are stored inline until they
+    /// outgrow the inline capacity, after which the vector grows as elements
+    /// are decoded.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut vec = SmallVec::new();
+        vec.decode_into(input)?;
+        Ok(vec)
+    }
+
+    /// Decodes the elements of the vector one after another, in order,
+    /// reusing the existing allocation.
+    ///
+    /// Recursively calls `decode_into` for each existing element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => self.push(A::Item::decode(&mut element_input)?),
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::smallvec::smallvec;
+
+    #[test]
+    fn encode_decode() {
+        let vec: SmallVec<[u16; 2]> = smallvec![1, 2];
+        let bytes = vec.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2]);
+        assert_eq!(bytes, vec.to_vec().encode().unwrap());
+        assert_eq!(vec.encoding_length().unwrap(), 4);
+        assert_eq!(SmallVec::<[u16; 2]>::decode(bytes.as_slice()).unwrap(), vec);
+
+        let spilled = SmallVec::<[u16; 2]>::decode(&[0, 1, 0, 2, 0, 3][..]).unwrap();
+        assert!(spilled.spilled());
+        assert_eq!(spilled.as_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn decode_into() {
+        let mut vec: SmallVec<[u8; 2]> = smallvec![9, 9, 9];
+        vec.decode_into(&[1, 2][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 2]);
+        vec.decode_into(&[3, 4, 5, 6][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[3, 4, 5, 6]);
+    }
+}
@@ -0,0 +1,379 @@
+//! Helpers for hand-implementing SSZ (SimpleSerialize) encodings.
+//!
+//! SSZ is the encoding of Ethereum consensus objects. Many of its layouts
+//! already match ed encodings, given the right field types:
+//!
+//! - Unsigned integers are little-endian, so wrap them in
+//!   [`Le`](../le/struct.Le.html). `bool` and `u8` are encoded as in ed.
+//! - Vectors and fixed-size containers are the concatenation of their
+//!   elements, like arrays and tuples of `Terminated` types.
+//! - Lists of fixed-size elements are the concatenation of their elements,
+//!   with the count implied by the length, like `Vec<T>`.
+//!
+//! Containers with variable-size fields are laid out differently: the fixed
+//! part holds the fixed-size fields in order, with a 4-byte little-endian
+//! offset in place of each variable-size field, and the variable-size fields
+//! follow it in order. Each offset is the position of its field relative to
+//! the start of the container. [`ContainerEncoder`](struct.ContainerEncoder.html)
+//! builds this layout and [`split_container`](fn.split_container.html) splits
+//! it back into fields. Lists of variable-size elements are laid out like a
+//! container of their elements, see [`encode_list`](fn.encode_list.html) and
+//! [`decode_list`](fn.decode_list.html).
+//!
+//! Like `Vec<T>`, containers are not `Terminated`: the last variable-size
+//! field runs to the end of the input.
+//!
+//! ```rust
+//! use ed::io::{Read, Write};
+//! use ed::le::Le;
+//! use ed::ssz::{self, ContainerEncoder, Field, OFFSET_LENGTH};
+//! use ed::{Decode, DecodeExt, Encode, Result};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Block {
+//!     slot: u64,
+//!     body: Vec<u8>,
+//!     parent: [u8; 4],
+//! }
+//!
+//! impl Encode for Block {
+//!     fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+//!         let mut container = ContainerEncoder::new();
+//!         container.fixed(&Le(self.slot))?;
+//!         container.variable(&self.body)?;
+//!         container.fixed(&self.parent)?;
+//!         container.finish(dest)
+//!     }
+//!
+//!     fn encoding_length(&self) -> Result<usize> {
+//!         Ok(8 + OFFSET_LENGTH + self.body.len() + 4)
+//!     }
+//! }
+//!
+//! impl Decode for Block {
+//!     fn decode<R: Read>(mut input: R) -> Result<Self> {
+//!         let mut bytes = vec![];
+//!         input.read_to_end(&mut bytes)?;
+//!         let fields = [Field::Fixed(8), Field::Variable, Field::Fixed(4)];
+//!         let parts = ssz::split_container(&bytes, &fields)?;
+//!         Ok(Block {
+//!             slot: Le::<u64>::decode_all(parts[0])?.0,
+//!             body: parts[1].to_vec(),
+//!             parent: <[u8; 4]>::decode_all(parts[2])?,
+//!         })
+//!     }
+//! }
+//!
+//! let block = Block { slot: 1, body: vec![0xaa, 0xbb], parent: [9; 4] };
+//! let bytes = block.encode().unwrap();
+//! assert_eq!(
+//!     bytes,
+//!     vec![1, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0, 9, 9, 9, 9, 0xaa, 0xbb]
+//! );
+//! assert_eq!(Block::decode(bytes.as_slice()).unwrap(), block);
+//! ```
+
+use super::io::{self, Write};
+use super::{limits, Decode, DecodeExt, Encode, Error, Result};
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// The length of an offset in the fixed part of a container.
+pub const OFFSET_LENGTH: usize = 4;
+
+/// The kind of a container field, for splitting a container into fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    /// A fixed-size field of the given length, stored in the fixed part.
+    Fixed(usize),
+    /// A variable-size field, stored after the fixed part and located by an
+    /// offset in it.
+    Variable,
+}
+
+/// Builds the encoding of a container with variable-size fields.
+///
+/// Fields must be added in order. The encoding is buffered until
+/// [`finish`](#method.finish), since the offsets depend on the length of the
+/// whole fixed part.
+#[derive(Clone, Debug, Default)]
+pub struct ContainerEncoder {
+    fixed: Vec<u8>,
+    variable: Vec<u8>,
+    offsets: Vec<(usize, usize)>,
+}
+
+impl ContainerEncoder {
+    /// Returns an encoder for an empty container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a fixed-size field to the fixed part.
+    pub fn fixed<T: Encode + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.encode_into(&mut self.fixed)
+    }
+
+    /// Appends a variable-size field, leaving space for its offset in the
+    /// fixed part.
+    pub fn variable<T: Encode + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.offsets.push((self.fixed.len(), self.variable.len()));
+        self.fixed.extend_from_slice(&[0; OFFSET_LENGTH]);
+        value.encode_into(&mut self.variable)
+    }
+
+    /// Returns the length of the container's encoding so far.
+    pub fn encoding_length(&self) -> usize {
+        self.fixed.len() + self.variable.len()
+    }
+
+    /// Fills in the offsets and writes the container to `dest`. Errors with
+    /// `Error::LengthOverflow` if an offset doesn't fit in a `u32`.
+    pub fn finish<W: Write>(mut self, dest: &mut W) -> Result<()> {
+        for &(position, start) in self.offsets.iter() {
+            let offset = self.fixed.len() + start;
+            let offset = u32::try_from(offset).map_err(|_| Error::LengthOverflow(offset))?;
+            self.fixed[position..position + OFFSET_LENGTH].copy_from_slice(&offset.to_le_bytes());
+        }
+        dest.write_all(&self.fixed)?;
+        dest.write_all(&self.variable)?;
+        Ok(())
+    }
+}
+
+fn read_offset(bytes: &[u8], position: usize) -> Result<usize> {
+    match bytes.get(position..position + OFFSET_LENGTH) {
+        Some(offset) => {
+            let mut buf = [0; OFFSET_LENGTH];
+            buf.copy_from_slice(offset);
+            Ok(u32::from_le_bytes(buf) as usize)
+        }
+        None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+    }
+}
+
+/// Checks that an offset is at least the previous one and within the
+/// container, as SSZ requires.
+fn check_offset(offset: usize, previous: usize, length: usize) -> Result<()> {
+    if offset < previous || offset > length {
+        return Err(Error::InvalidOffset(offset as u32));
+    }
+    Ok(())
+}
+
+/// Splits the encoding of a container into the encodings of its fields,
+/// given the kind of each field.
+///
+/// Errors with `Error::InvalidOffset` if the first offset is not the end of
+/// the fixed part, or an offset is less than the one before it or past the
+/// end of the container. Errors with `Error::TrailingBytes` if the container
+/// has no variable-size fields and `bytes` is longer than its fixed part.
+pub fn split_container<'a>(bytes: &'a [u8], fields: &[Field]) -> Result<Vec<&'a [u8]>> {
+    let fixed_length: usize = fields
+        .iter()
+        .map(|field| match field {
+            Field::Fixed(length) => *length,
+            Field::Variable => OFFSET_LENGTH,
+        })
+        .sum();
+    if bytes.len() < fixed_length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let mut parts = Vec::with_capacity(fields.len());
+    let mut variable = Vec::new();
+    let mut position = 0;
+    for field in fields {
+        match *field {
+            Field::Fixed(length) => {
+                parts.push(&bytes[position..position + length]);
+                position += length;
+            }
+            Field::Variable => {
+                let offset = read_offset(bytes, position)?;
+                match variable.last() {
+                    None if offset != fixed_length => {
+                        return Err(Error::InvalidOffset(offset as u32))
+                    }
+                    None => {}
+                    Some(&(_, previous)) => check_offset(offset, previous, bytes.len())?,
+                }
+                variable.push((parts.len(), offset));
+                parts.push(&[][..]);
+                position += OFFSET_LENGTH;
+            }
+        }
+    }
+
+    if variable.is_empty() && bytes.len() > fixed_length {
+        return Err(Error::TrailingBytes(bytes.len() - fixed_length));
+    }
+    for (i, &(index, start)) in variable.iter().enumerate() {
+        let end = variable.get(i + 1).map_or(bytes.len(), |&(_, end)| end);
+        parts[index] = &bytes[start..end];
+    }
+    Ok(parts)
+}
+
+/// Encodes a list of variable-size elements: an offset for each element,
+/// followed by the elements. Errors with `Error::LengthOverflow` if an offset
+/// doesn't fit in a `u32`.
+///
+/// Lists of fixed-size elements have no offsets, so are encoded like
+/// `Vec<T>`.
+pub fn encode_list<T: Encode, W: Write>(items: &[T], dest: &mut W) -> Result<()> {
+    let mut offset = items.len() * OFFSET_LENGTH;
+    for item in items {
+        let value = u32::try_from(offset).map_err(|_| Error::LengthOverflow(offset))?;
+        dest.write_all(&value.to_le_bytes())?;
+        offset += item.encoding_length()?;
+    }
+    for item in items {
+        item.encode_into(dest)?;
+    }
+    Ok(())
+}
+
+/// Returns the length of the encoding of a list of variable-size elements.
+pub fn list_encoding_length<T: Encode>(items: &[T]) -> Result<usize> {
+    let mut length = items.len() * OFFSET_LENGTH;
+    for item in items {
+        length += item.encoding_length()?;
+    }
+    Ok(length)
+}
+
+/// Decodes a list of variable-size elements, each of which must consume its
+/// whole encoding.
+///
+/// The number of elements is given by the first offset, so errors with
+/// `Error::InvalidOffset` if it is zero or not a multiple of the offset
+/// length, as well as for the same invalid offsets as
+/// [`split_container`](fn.split_container.html).
+pub fn decode_list<T: Decode>(bytes: &[u8]) -> Result<Vec<T>> {
+    if bytes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let first = read_offset(bytes, 0)?;
+    if first == 0 || first % OFFSET_LENGTH != 0 || first > bytes.len() {
+        return Err(Error::InvalidOffset(first as u32));
+    }
+    let count = first / OFFSET_LENGTH;
+    limits::count_elements(count)?;
+
+    let mut items = Vec::with_capacity(count);
+    let mut start = first;
+    for i in 1..=count {
+        let end = if i < count {
+            let end = read_offset(bytes, i * OFFSET_LENGTH)?;
+            check_offset(end, start, bytes.len())?;
+            end
+        } else {
+            bytes.len()
+        };
+        items.push(T::decode_all(&bytes[start..end])?);
+        start = end;
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::le::Le;
+
+    #[test]
+    fn container() {
+        let mut container = ContainerEncoder::new();
+        container.fixed(&Le(0x0102u16)).unwrap();
+        container.variable(&vec![1u8, 2, 3]).unwrap();
+        container.fixed(&true).unwrap();
+        container.variable(&vec![4u8]).unwrap();
+        assert_eq!(container.encoding_length(), 15);
+        let mut bytes = vec![];
+        container.finish(&mut bytes).unwrap();
+        assert_eq!(
+            bytes,
+            vec![0x02, 0x01, 11, 0, 0, 0, 1, 14, 0, 0, 0, 1, 2, 3, 4]
+        );
+
+        let fields = [
+            Field::Fixed(2),
+            Field::Variable,
+            Field::Fixed(1),
+            Field::Variable,
+        ];
+        let parts = split_container(&bytes, &fields).unwrap();
+        assert_eq!(
+            parts,
+            vec![&[0x02, 0x01][..], &[1, 2, 3][..], &[1][..], &[4][..]]
+        );
+    }
+
+    #[test]
+    fn fixed_container() {
+        let fields = [Field::Fixed(1), Field::Fixed(2)];
+        let parts = split_container(&[1, 2, 3], &fields).unwrap();
+        assert_eq!(parts, vec![&[1][..], &[2, 3][..]]);
+        assert!(matches!(
+            split_container(&[1, 2, 3, 4], &fields),
+            Err(Error::TrailingBytes(1))
+        ));
+        assert!(split_container(&[1, 2], &fields)
+            .unwrap_err()
+            .is_unexpected_eof());
+    }
+
+    #[test]
+    fn invalid_offsets() {
+        let fields = [Field::Variable, Field::Variable];
+        assert_eq!(
+            split_container(&[8, 0, 0, 0, 8, 0, 0, 0], &fields).unwrap(),
+            vec![&[][..], &[][..]]
+        );
+        // The first offset must be the end of the fixed part.
+        assert!(matches!(
+            split_container(&[9, 0, 0, 0, 9, 0, 0, 0, 1], &fields),
+            Err(Error::InvalidOffset(9))
+        ));
+        // Offsets must not decrease.
+        assert!(matches!(
+            split_container(&[8, 0, 0, 0, 7, 0, 0, 0, 1], &fields),
+            Err(Error::InvalidOffset(7))
+        ));
+        // Offsets must be within the container.
+        assert!(matches!(
+            split_container(&[8, 0, 0, 0, 10, 0, 0, 0, 1], &fields),
+            Err(Error::InvalidOffset(10))
+        ));
+    }
+
+    #[test]
+    fn list() {
+        let items = vec![vec![1u8, 2], vec![], vec![3]];
+        let mut bytes = vec![];
+        encode_list(&items, &mut bytes).unwrap();
+        assert_eq!(bytes, vec![12, 0, 0, 0, 14, 0, 0, 0, 14, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(list_encoding_length(&items).unwrap(), bytes.len());
+        assert_eq!(decode_list::<Vec<u8>>(&bytes).unwrap(), items);
+
+        let empty: Vec<Vec<u8>> = vec![];
+        assert_eq!(list_encoding_length(&empty).unwrap(), 0);
+        assert!(decode_list::<Vec<u8>>(&[]).unwrap().is_empty());
+
+        assert!(matches!(
+            decode_list::<Vec<u8>>(&[6, 0, 0, 0, 6, 0]),
+            Err(Error::InvalidOffset(6))
+        ));
+        assert!(matches!(
+            decode_list::<Vec<u8>>(&[8, 0, 0, 0, 4, 0, 0, 0]),
+            Err(Error::InvalidOffset(4))
+        ));
+        assert!(matches!(
+            decode_list::<u8>(&[4, 0, 0, 0, 1, 2]),
+            Err(Error::TrailingBytes(1))
+        ));
+    }
+}
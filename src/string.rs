@@ -0,0 +1,197 @@
+//! Strings which end with a NUL byte.
+//!
+//! A `String` is encoded as its UTF-8 bytes and decoded by reading until the
+//! end of the input, so it is not `Terminated`.
+//! [`NullTerminatedString`](struct.NullTerminatedString.html) instead ends
+//! its bytes with a 0x00 byte, as C strings do, so it can be placed anywhere
+//! in a struct without a length prefix. In exchange, it can't contain NUL
+//! characters.
+//!
+//! ```rust
+//! use ed::{string::NullTerminatedString, Decode, Encode};
+//!
+//! let name = NullTerminatedString::new("café").unwrap();
+//! assert_eq!(name.encode().unwrap(), b"caf\xc3\xa9\0".to_vec());
+//! assert!(NullTerminatedString::new("a\0b").is_err());
+//!
+//! let (name, age): (NullTerminatedString, u8) =
+//!     Decode::decode(&b"bob\0\x2a"[..]).unwrap();
+//! assert_eq!((name.as_str(), age), ("bob", 42));
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result, Terminated};
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Deref;
+
+/// A UTF-8 string without NUL characters, encoded as its bytes followed by a
+/// NUL byte.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NullTerminatedString(String);
+
+impl NullTerminatedString {
+    /// Returns the string, or errors with `Error::UnexpectedByte(0)` if it
+    /// contains a NUL character.
+    pub fn new<S: Into<String>>(string: S) -> Result<Self> {
+        let string = string.into();
+        if string.as_bytes().contains(&0) {
+            return Err(Error::UnexpectedByte(0));
+        }
+        Ok(NullTerminatedString(string))
+    }
+
+    /// Returns the string as a `&str`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the inner `String`.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for NullTerminatedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for NullTerminatedString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl TryFrom<String> for NullTerminatedString {
+    type Error = Error;
+
+    /// Errors with `Error::UnexpectedByte(0)` if the string contains a NUL
+    /// character.
+    fn try_from(string: String) -> Result<Self> {
+        NullTerminatedString::new(string)
+    }
+}
+
+impl TryFrom<&str> for NullTerminatedString {
+    type Error = Error;
+
+    /// Errors with `Error::UnexpectedByte(0)` if the string contains a NUL
+    /// character.
+    fn try_from(string: &str) -> Result<Self> {
+        NullTerminatedString::new(string)
+    }
+}
+
+impl From<NullTerminatedString> for String {
+    fn from(string: NullTerminatedString) -> Self {
+        string.0
+    }
+}
+
+impl fmt::Display for NullTerminatedString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Encode for NullTerminatedString {
+    /// Encodes the UTF-8 bytes of the string followed by a NUL byte.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.0.as_bytes())?;
+        dest.write_all(&[0])?;
+        Ok(())
+    }
+
+    /// Returns the length of the string in bytes, plus 1 for the NUL byte.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(self.0.len() + 1)
+    }
+}
+
+impl Decode for NullTerminatedString {
+    /// Decodes bytes up to and including the first NUL byte. Errors if the
+    /// input ends before a NUL byte is found, or with `Error::InvalidUtf8` if
+    /// the bytes are not valid UTF-8.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut string = NullTerminatedString::default();
+        string.decode_into(input)?;
+        Ok(string)
+    }
+
+    /// Decodes bytes up to and including the first NUL byte, reusing the
+    /// existing allocation.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let mut bytes = core::mem::take(&mut self.0).into_bytes();
+        bytes.clear();
+        loop {
+            match u8::decode(&mut input)? {
+                0 => break,
+                byte => bytes.push(byte),
+            }
+        }
+        self.0 = String::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)?;
+        Ok(())
+    }
+}
+
+impl Terminated for NullTerminatedString {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let string = NullTerminatedString::new("héllo").unwrap();
+        let bytes = string.encode().unwrap();
+        assert_eq!(bytes, b"h\xc3\xa9llo\0".to_vec());
+        assert_eq!(string.encoding_length().unwrap(), 7);
+        assert_eq!(
+            NullTerminatedString::decode(bytes.as_slice()).unwrap(),
+            string
+        );
+
+        let empty = NullTerminatedString::default();
+        assert_eq!(empty.encode().unwrap(), vec![0]);
+        assert_eq!(NullTerminatedString::decode(&[0][..]).unwrap(), empty);
+
+        let values: Vec<NullTerminatedString> = Decode::decode(&b"a\0bc\0\0"[..]).unwrap();
+        let values: Vec<&str> = values.iter().map(|s| s.as_str()).collect();
+        assert_eq!(values, vec!["a", "bc", ""]);
+    }
+
+    #[test]
+    fn interior_nul() {
+        assert!(matches!(
+            NullTerminatedString::new("a\0"),
+            Err(Error::UnexpectedByte(0))
+        ));
+        assert!(NullTerminatedString::try_from("ab").is_ok());
+        assert!(NullTerminatedString::try_from(String::from("\0")).is_err());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert!(NullTerminatedString::decode(&b"abc"[..])
+            .unwrap_err()
+            .is_unexpected_eof());
+        assert!(matches!(
+            NullTerminatedString::decode(&[0xff, 0][..]),
+            Err(Error::InvalidUtf8)
+        ));
+
+        let mut string = NullTerminatedString::new("abc").unwrap();
+        string.decode_into(&b"de\0"[..]).unwrap();
+        assert_eq!(string.as_str(), "de");
+    }
+}
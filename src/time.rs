@@ -0,0 +1,204 @@
+//! Encoding support for dates and times, using the `time` crate.
+//!
+//! `OffsetDateTime` is encoded as 16 bytes: the seconds since the Unix epoch
+//! as a big-endian `i64`, the nanoseconds past the second as a big-endian
+//! `u32`, and the UTC offset in seconds as a big-endian `i32`. The offset is
+//! part of the encoding, so values at the same instant with different offsets
+//! have different encodings even though they compare equal.
+//!
+//! `PrimitiveDateTime` is treated as a time in UTC and encoded like
+//! [`Timestamp`](../struct.Timestamp.html), as 12 bytes: the seconds since the
+//! Unix epoch as a big-endian `i64`, followed by the nanoseconds as a
+//! big-endian `u32`.
+//!
+//! `Date` is encoded as the number of days since the Unix epoch, as a
+//! big-endian `i32`.
+//!
+//! Decoding errors with `Error::InvalidTimestamp` for values which `time`
+//! can't represent, so every value has exactly one encoding.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::time::{Date, OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// The Julian day of the Unix epoch, 1970-01-01.
+const EPOCH_JULIAN_DAY: i32 = 2_440_588;
+
+fn encode_utc<W: Write>(time: OffsetDateTime, dest: &mut W) -> Result<()> {
+    time.unix_timestamp().encode_into(dest)?;
+    time.nanosecond().encode_into(dest)
+}
+
+fn decode_utc<R: Read>(mut input: R) -> Result<OffsetDateTime> {
+    let seconds = i64::decode(&mut input)?;
+    let nanos = u32::decode(&mut input)?;
+    OffsetDateTime::from_unix_timestamp(seconds)
+        .and_then(|time| time.replace_nanosecond(nanos))
+        .map_err(|_| Error::InvalidTimestamp)
+}
+
+impl Encode for OffsetDateTime {
+    /// Encodes the seconds since the Unix epoch, the nanoseconds and the UTC
+    /// offset in seconds.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        encode_utc(*self, dest)?;
+        self.offset().whole_seconds().encode_into(dest)
+    }
+
+    /// Always returns Ok(16).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(16)
+    }
+}
+
+impl Decode for OffsetDateTime {
+    /// Decodes the seconds, nanoseconds and UTC offset, erroring with
+    /// `Error::InvalidTimestamp` if they are out of range.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let time = decode_utc(&mut input)?;
+        let offset = i32::decode(&mut input)?;
+        UtcOffset::from_whole_seconds(offset)
+            .ok()
+            .and_then(|offset| time.checked_to_offset(offset))
+            .ok_or(Error::InvalidTimestamp)
+    }
+}
+
+impl Terminated for OffsetDateTime {}
+
+impl FixedLength for OffsetDateTime {
+    const ENCODING_LENGTH: usize = 16;
+}
+
+impl Encode for PrimitiveDateTime {
+    /// Encodes the seconds since the Unix epoch followed by the nanoseconds,
+    /// treating the time as UTC.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        encode_utc(self.assume_utc(), dest)
+    }
+
+    /// Always returns Ok(12).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(12)
+    }
+}
+
+impl Decode for PrimitiveDateTime {
+    /// Decodes the seconds and nanoseconds, erroring with
+    /// `Error::InvalidTimestamp` if they are out of range.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let time = decode_utc(input)?;
+        Ok(PrimitiveDateTime::new(time.date(), time.time()))
+    }
+}
+
+impl Terminated for PrimitiveDateTime {}
+
+impl FixedLength for PrimitiveDateTime {
+    const ENCODING_LENGTH: usize = 12;
+}
+
+impl Encode for Date {
+    /// Encodes the number of days since the Unix epoch as an `i32`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        // Can't overflow, since `time` limits years to well within the range
+        // of an `i32` of days.
+        (self.to_julian_day() - EPOCH_JULIAN_DAY).encode_into(dest)
+    }
+
+    /// Always returns Ok(4).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+impl Decode for Date {
+    /// Decodes the number of days since the Unix epoch, erroring with
+    /// `Error::InvalidTimestamp` if it is out of range.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let days = i32::decode(input)?;
+        days.checked_add(EPOCH_JULIAN_DAY)
+            .and_then(|day| Date::from_julian_day(day).ok())
+            .ok_or(Error::InvalidTimestamp)
+    }
+}
+
+impl Terminated for Date {}
+
+impl FixedLength for Date {
+    const ENCODING_LENGTH: usize = 4;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_offset() {
+        let offset = UtcOffset::from_whole_seconds(-3600).unwrap();
+        let time = OffsetDateTime::from_unix_timestamp(0x12)
+            .unwrap()
+            .replace_nanosecond(0x3456)
+            .unwrap()
+            .to_offset(offset);
+        let bytes = time.encode().unwrap();
+        assert_eq!(
+            bytes,
+            vec![0, 0, 0, 0, 0, 0, 0, 0x12, 0, 0, 0x34, 0x56, 0xff, 0xff, 0xf1, 0xf0]
+        );
+        assert_eq!(time.encoding_length().unwrap(), 16);
+        let decoded = OffsetDateTime::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, time);
+        assert_eq!(decoded.offset(), offset);
+    }
+
+    #[test]
+    fn encode_decode_primitive() {
+        let utc = OffsetDateTime::from_unix_timestamp(-1).unwrap();
+        let time = PrimitiveDateTime::new(utc.date(), utc.time());
+        let bytes = time.encode().unwrap();
+        assert_eq!(
+            bytes,
+            vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]
+        );
+        assert_eq!(PrimitiveDateTime::decode(bytes.as_slice()).unwrap(), time);
+    }
+
+    #[test]
+    fn encode_decode_date() {
+        let epoch = Date::from_julian_day(EPOCH_JULIAN_DAY).unwrap();
+        assert_eq!(epoch.encode().unwrap(), vec![0, 0, 0, 0]);
+        assert_eq!(epoch, OffsetDateTime::UNIX_EPOCH.date());
+
+        let date = Date::from_julian_day(EPOCH_JULIAN_DAY - 1).unwrap();
+        assert_eq!(date.encode().unwrap(), vec![0xff; 4]);
+        assert_eq!(Date::decode(&[0xff; 4][..]).unwrap(), date);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let nanos = [0, 0, 0, 0, 0, 0, 0, 0, 0x3b, 0x9a, 0xca, 0x00];
+        assert!(matches!(
+            PrimitiveDateTime::decode(&nanos[..]),
+            Err(Error::InvalidTimestamp)
+        ));
+        let offset = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x7f, 0, 0, 0];
+        assert!(matches!(
+            OffsetDateTime::decode(&offset[..]),
+            Err(Error::InvalidTimestamp)
+        ));
+        assert!(matches!(
+            Date::decode(&[0x7f, 0xff, 0xff, 0xff][..]),
+            Err(Error::InvalidTimestamp)
+        ));
+    }
+}
@@ -0,0 +1,236 @@
+//! A canonical timestamp type with validity bounds, and the canonical
+//! encoding of `Duration`.
+
+use super::{Decode, Encode, Error, FixedLength, Result, Terminated};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// A point in time, stored as seconds and nanoseconds since the Unix epoch.
+///
+/// Encoded as 12 bytes: the seconds as a big-endian `i64`, followed by the
+/// nanoseconds as a big-endian `u32`. The nanoseconds must be less than one
+/// billion, so every timestamp has exactly one encoding.
+///
+/// The seconds must lie within `MIN..=MAX`, which default to the full range
+/// of `i64`. Tighter bounds can be used to reject implausible timestamps
+/// when decoding, e.g. for block times:
+///
+/// ```rust
+/// # use ed::{Decode, Encode, Timestamp};
+/// // Timestamps between 2020-01-01 and 2100-01-01.
+/// type BlockTime = Timestamp<1_577_836_800, 4_102_444_800>;
+///
+/// let time = BlockTime::new(1_600_000_000, 0).unwrap();
+/// let bytes = time.encode().unwrap();
+/// assert_eq!(BlockTime::decode(bytes.as_slice()).unwrap(), time);
+///
+/// assert!(BlockTime::new(0, 0).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp<const MIN: i64 = { i64::MIN }, const MAX: i64 = { i64::MAX }> {
+    seconds: i64,
+    nanos: u32,
+}
+
+impl<const MIN: i64, const MAX: i64> Timestamp<MIN, MAX> {
+    /// Creates a timestamp from seconds and nanoseconds since the Unix epoch.
+    /// Errors with `Error::InvalidTimestamp` if `nanos` is not less than one
+    /// billion or `seconds` is outside of the bounds.
+    pub fn new(seconds: i64, nanos: u32) -> Result<Self> {
+        if nanos >= NANOS_PER_SECOND || seconds < MIN || seconds > MAX {
+            return Err(Error::InvalidTimestamp);
+        }
+        Ok(Timestamp { seconds, nanos })
+    }
+
+    /// Returns the current system time.
+    pub fn now() -> Result<Self> {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// Converts from a `SystemTime`, erroring if it is out of bounds.
+    pub fn from_system_time(time: SystemTime) -> Result<Self> {
+        let (seconds, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => (i64::try_from(after.as_secs()).ok(), after.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                let seconds = i64::try_from(before.as_secs()).ok().map(|s| -s);
+                match before.subsec_nanos() {
+                    0 => (seconds, 0),
+                    nanos => (
+                        seconds.and_then(|s| s.checked_sub(1)),
+                        NANOS_PER_SECOND - nanos,
+                    ),
+                }
+            }
+        };
+        Self::new(seconds.ok_or(Error::InvalidTimestamp)?, nanos)
+    }
+
+    /// Converts to a `SystemTime`, erroring if it can't be represented on
+    /// this platform.
+    pub fn to_system_time(&self) -> Result<SystemTime> {
+        let time = if self.seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(self.seconds as u64, self.nanos))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(self.seconds.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::from_nanos(self.nanos as u64)))
+        };
+        time.ok_or(Error::InvalidTimestamp)
+    }
+
+    /// Returns the whole seconds since the Unix epoch.
+    pub fn seconds(&self) -> i64 {
+        self.seconds
+    }
+
+    /// Returns the nanoseconds past the whole seconds, always less than one
+    /// billion.
+    pub fn nanos(&self) -> u32 {
+        self.nanos
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> fmt::Display for Timestamp<MIN, MAX> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:09}", self.seconds, self.nanos)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> Encode for Timestamp<MIN, MAX> {
+    /// Encodes the seconds followed by the nanoseconds.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.seconds.encode_into(dest)?;
+        self.nanos.encode_into(dest)
+    }
+
+    /// Always returns Ok(12).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(12)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> Decode for Timestamp<MIN, MAX> {
+    /// Decodes the seconds and nanoseconds, erroring with
+    /// `Error::InvalidTimestamp` if they are out of range.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let seconds = i64::decode(&mut input)?;
+        let nanos = u32::decode(&mut input)?;
+        Self::new(seconds, nanos)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64> Terminated for Timestamp<MIN, MAX> {}
+
+impl<const MIN: i64, const MAX: i64> FixedLength for Timestamp<MIN, MAX> {
+    const ENCODING_LENGTH: usize = 12;
+}
+
+impl Encode for Duration {
+    /// Encodes the whole seconds as a big-endian `u64`, followed by the
+    /// nanoseconds past the whole seconds as a big-endian `u32`.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.as_secs().encode_into(dest)?;
+        self.subsec_nanos().encode_into(dest)
+    }
+
+    /// Always returns Ok(12).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(12)
+    }
+}
+
+impl Decode for Duration {
+    /// Decodes the seconds and nanoseconds, erroring with
+    /// `Error::InvalidDuration` if the nanoseconds are not less than one
+    /// billion.
+    #[inline]
+    fn decode<R: Read>(mut input: R) -> Result<Self> {
+        let seconds = u64::decode(&mut input)?;
+        let nanos = u32::decode(&mut input)?;
+        if nanos >= NANOS_PER_SECOND {
+            return Err(Error::InvalidDuration);
+        }
+        Ok(Duration::new(seconds, nanos))
+    }
+}
+
+impl Terminated for Duration {}
+
+impl FixedLength for Duration {
+    const ENCODING_LENGTH: usize = 12;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_timestamp() {
+        let time = Timestamp::<0, 100>::new(0x12, 0x3456).unwrap();
+        let bytes = time.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0, 0, 0, 0x12, 0, 0, 0x34, 0x56]);
+        assert_eq!(Timestamp::<0, 100>::decode(bytes.as_slice()).unwrap(), time);
+        assert_eq!(time.to_string(), "18.000013398");
+    }
+
+    #[test]
+    fn decode_invalid() {
+        let too_late = [0, 0, 0, 0, 0, 0, 0, 101, 0, 0, 0, 0];
+        let negative = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        let nanos = [0, 0, 0, 0, 0, 0, 0, 1, 0x3b, 0x9a, 0xca, 0x00];
+        for bytes in [too_late, negative, nanos] {
+            assert!(matches!(
+                Timestamp::<0, 100>::decode(&bytes[..]),
+                Err(Error::InvalidTimestamp)
+            ));
+        }
+        assert!(Timestamp::<{ i64::MIN }, { i64::MAX }>::decode(&negative[..]).is_ok());
+    }
+
+    #[test]
+    fn system_time() {
+        let before = UNIX_EPOCH - Duration::new(2, 250_000_000);
+        let time: Timestamp = Timestamp::from_system_time(before).unwrap();
+        assert_eq!((time.seconds(), time.nanos()), (-3, 750_000_000));
+        assert_eq!(time.to_system_time().unwrap(), before);
+
+        let after = UNIX_EPOCH + Duration::new(5, 1);
+        let time: Timestamp = Timestamp::from_system_time(after).unwrap();
+        assert_eq!((time.seconds(), time.nanos()), (5, 1));
+        assert_eq!(time.to_system_time().unwrap(), after);
+
+        assert!(Timestamp::<0, 4>::from_system_time(after).is_err());
+    }
+
+    #[test]
+    fn encode_decode_duration() {
+        let duration = Duration::new(0x12, 0x3456);
+        let bytes = duration.encode().unwrap();
+        assert_eq!(bytes, vec![0, 0, 0, 0, 0, 0, 0, 0x12, 0, 0, 0x34, 0x56]);
+        assert_eq!(duration.encoding_length().unwrap(), 12);
+        assert_eq!(Duration::decode(bytes.as_slice()).unwrap(), duration);
+
+        let max = Duration::new(u64::MAX, NANOS_PER_SECOND - 1);
+        assert_eq!(
+            Duration::decode(max.encode().unwrap().as_slice()).unwrap(),
+            max
+        );
+
+        let nanos = [0, 0, 0, 0, 0, 0, 0, 1, 0x3b, 0x9a, 0xca, 0x00];
+        assert!(matches!(
+            Duration::decode(&nanos[..]),
+            Err(Error::InvalidDuration)
+        ));
+    }
+}
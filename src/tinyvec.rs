@@ -0,0 +1,177 @@
+//! Encoding support for `TinyVec` and `ArrayVec`, using the `tinyvec` crate.
+//!
+//! Both are encoded like a `Vec` of the same elements: the elements one after
+//! another, read until the end of the input when decoding. Whether a
+//! `TinyVec` stores its elements inline or on the heap doesn't affect the
+//! encoding.
+//!
+//! Decoding an `ArrayVec` never allocates. It errors with
+//! `Error::CapacityExceeded` if the input holds more elements than fit in the
+//! capacity.
+
+use super::io::{Read, Write};
+use super::{limits, next_element, Decode, Encode, Error, Result, Terminated};
+use ::tinyvec::{Array, ArrayVec, TinyVec};
+
+impl<A: Array> Encode for TinyVec<A>
+where
+    A::Item: Encode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Encodes the elements of the vector one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        A::Item::encode_slice_into(self, dest)
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<A: Array> Decode for TinyVec<A>
+where
+    A::Item: Decode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Decodes the elements of the vector one after another, in order.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut vec = TinyVec::default();
+        vec.decode_into(input)?;
+        Ok(vec)
+    }
+
+    /// Decodes the elements of the vector one after another, in order,
+    /// reusing the existing allocation.
+    ///
+    /// Recursively calls `decode_into` for each existing element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => self.push(A::Item::decode(&mut element_input)?),
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+impl<A: Array> Encode for ArrayVec<A>
+where
+    A::Item: Encode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Encodes the elements of the vector one after another, in order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        A::Item::encode_slice_into(self, dest)
+    }
+
+    /// Returns the sum of the encoding lengths of all elements.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        let mut sum = 0;
+        for element in self.iter() {
+            sum += element.encoding_length()?;
+        }
+        Ok(sum)
+    }
+}
+
+impl<A: Array> Decode for ArrayVec<A>
+where
+    A::Item: Decode + Terminated,
+{
+    const SENSITIVE: bool = A::Item::SENSITIVE;
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more elements than fit in
+    /// the capacity.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        let mut vec = ArrayVec::default();
+        vec.decode_into(input)?;
+        Ok(vec)
+    }
+
+    /// Decodes the elements of the vector one after another, in order. Errors
+    /// with `Error::CapacityExceeded` if there are more elements than fit in
+    /// the capacity.
+    ///
+    /// Recursively calls `decode_into` for each existing element.
+    #[inline]
+    fn decode_into<R: Read>(&mut self, mut input: R) -> Result<()> {
+        let _depth = limits::descend()?;
+        let mut i = 0;
+        while let Some(mut element_input) = next_element(&mut input)? {
+            limits::count_elements(1)?;
+            match self.get_mut(i) {
+                Some(element) => element.decode_into(&mut element_input)?,
+                None => {
+                    let element = A::Item::decode(&mut element_input)?;
+                    if self.try_push(element).is_some() {
+                        return Err(Error::CapacityExceeded(A::CAPACITY));
+                    }
+                }
+            }
+            element_input.check_consumed()?;
+            i += 1;
+        }
+        self.truncate(i);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::tinyvec::{array_vec, tiny_vec};
+
+    #[test]
+    fn encode_decode_tinyvec() {
+        let vec: TinyVec<[u16; 2]> = tiny_vec![1, 2];
+        let bytes = vec.encode().unwrap();
+        assert_eq!(bytes, vec![0, 1, 0, 2]);
+        assert_eq!(bytes, vec.to_vec().encode().unwrap());
+        assert_eq!(vec.encoding_length().unwrap(), 4);
+        assert_eq!(TinyVec::<[u16; 2]>::decode(bytes.as_slice()).unwrap(), vec);
+
+        let heap = TinyVec::<[u16; 2]>::decode(&[0, 1, 0, 2, 0, 3][..]).unwrap();
+        assert!(heap.is_heap());
+        assert_eq!(heap.as_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn encode_decode_arrayvec() {
+        let mut vec: ArrayVec<[u8; 3]> = array_vec![9, 9];
+        assert_eq!(vec.encode().unwrap(), vec![9, 9]);
+        vec.decode_into(&[1, 2, 3][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 2, 3]);
+        vec.decode_into(&[4][..]).unwrap();
+        assert_eq!(vec.as_slice(), &[4]);
+
+        assert!(matches!(
+            ArrayVec::<[u8; 3]>::decode(&[1, 2, 3, 4][..]),
+            Err(Error::CapacityExceeded(3))
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+//! Async encoding and decoding over `tokio`'s I/O traits.
+//!
+//! Services built on `tokio` read and write through `AsyncRead` and
+//! `AsyncWrite`, so without async traits each message has to be read into a
+//! buffer in full before it can be decoded with the sync API, and encoded
+//! into a buffer before it can be written. [`AsyncEncode`](trait.AsyncEncode.html)
+//! and [`AsyncDecode`](trait.AsyncDecode.html) encode and decode values field
+//! by field straight over the async reader or writer instead.
+//!
+//! With the `tokio` feature, `#[derive(Encode)]` and `#[derive(Decode)]` also
+//! implement the async traits, for types whose fields all implement them.
+//! Types with fields encoded `with` a custom encoding are left out, since the
+//! custom encoding functions are sync.
+//!
+//! ```rust
+//! use ed::tokio::{AsyncDecode, AsyncEncode};
+//! use ed::{Decode, Encode};
+//!
+//! #[derive(Encode, Decode, Debug, PartialEq)]
+//! struct Ping {
+//!     id: u32,
+//!     payload: Option<Vec<u8>>,
+//! }
+//!
+//! async fn roundtrip<S>(stream: &mut S) -> ed::Result<Ping>
+//! where
+//!     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send,
+//! {
+//!     let ping = Ping { id: 7, payload: None };
+//!     ping.encode_into_async(stream).await?;
+//!     Ping::decode_async(stream).await
+//! }
+//! ```
+//!
+//! Async decoding counts elements and depth like sync decoding, but only the
+//! default [`limits`](../limits/index.html) apply, since `DecodeOptions`
+//! decodes from a sync reader. Bound the length of untrusted input with
+//! `AsyncReadExt::take` instead.
+
+use super::{limits, wipe, Decode, Encode, Error, FixedLength, Result, Terminated};
+use ::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use core::convert::TryInto;
+use core::future::Future;
+
+pub use ::tokio::io::{AsyncRead, AsyncWrite};
+
+async_traits!("tokio");
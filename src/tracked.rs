@@ -0,0 +1,355 @@
+//! Incremental re-encoding of large, mostly unchanged structs.
+//!
+//! Some values are re-encoded (e.g. to be hashed) far more often than most
+//! of their fields change, such as state which is re-hashed every block.
+//! [`Tracked`](struct.Tracked.html) keeps the encoding of a struct alongside
+//! it, records which fields have been modified, and re-encodes only those
+//! fields the next time the encoding is needed.
+//!
+//! The struct must implement [`Track`](trait.Track.html), which can be
+//! derived. The derive also adds an associated constant to the struct for
+//! each field, named after the field in upper case (`FIELD_0`, `FIELD_1`, ...
+//! for tuple structs), which selects that field to
+//! [`modify`](struct.Tracked.html#method.modify):
+//!
+//! ```rust
+//! use ed::{tracked::Tracked, Encode, Track};
+//!
+//! #[derive(Encode, Track)]
+//! struct State {
+//!     height: u64,
+//!     validators: [u8; 1024],
+//! }
+//!
+//! let mut state = Tracked::new(State {
+//!     height: 1,
+//!     validators: [0; 1024],
+//! });
+//! assert_eq!(&state.encoded().unwrap()[..8], &[0, 0, 0, 0, 0, 0, 0, 1]);
+//!
+//! // Only the `height` field is re-encoded.
+//! state.modify(State::HEIGHT, |height| *height += 1);
+//! assert_eq!(&state.encoded().unwrap()[..8], &[0, 0, 0, 0, 0, 0, 0, 2]);
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Result, Terminated};
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::fmt;
+use core::ops::Deref;
+
+/// A trait for structs whose fields can be encoded individually. Usually
+/// implemented with `#[derive(Track)]`.
+///
+/// The encoding of the struct must be the encodings of its fields one after
+/// another, in order.
+pub trait Track: Encode {
+    /// The names of the fields, in encoding order.
+    const FIELDS: &'static [&'static str];
+
+    /// Writes the encoding of the field at `index` in `FIELDS`. Errors if
+    /// there is no such field.
+    fn encode_field_into<W: Write>(&self, index: usize, dest: &mut W) -> Result<()>;
+
+    /// Returns the encoding length of the field at `index` in `FIELDS`.
+    /// Errors if there is no such field.
+    fn field_encoding_length(&self, index: usize) -> Result<usize>;
+}
+
+/// Selects a field of `T` of type `F` to modify through a
+/// [`Tracked`](struct.Tracked.html), so that only that field can be changed.
+///
+/// Usually generated by `#[derive(Track)]` as an associated constant of `T`.
+pub struct Field<T, F> {
+    index: usize,
+    get: fn(&mut T) -> &mut F,
+}
+
+impl<T, F> Field<T, F> {
+    /// Creates a selector for the field at `index` in `T::FIELDS`, which `get`
+    /// must return a reference to.
+    pub const fn new(index: usize, get: fn(&mut T) -> &mut F) -> Self {
+        Field { index, get }
+    }
+
+    /// Returns the index of the field in `T::FIELDS`.
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T, F> Clone for Field<T, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, F> Copy for Field<T, F> {}
+
+impl<T, F> fmt::Debug for Field<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Field").field("index", &self.index).finish()
+    }
+}
+
+/// A struct along with its encoding, which is updated field by field as the
+/// struct is modified.
+///
+/// Reading the struct is possible through `Deref`, but modifying it requires
+/// [`modify`](#method.modify) (or [`get_mut`](#method.get_mut)) so that the
+/// changed fields can be recorded.
+pub struct Tracked<T> {
+    value: T,
+    dirty: Vec<bool>,
+    bytes: Vec<u8>,
+    // The offset of the start of each field in `bytes`, followed by the total
+    // length. Empty until the value is first encoded.
+    offsets: Vec<usize>,
+}
+
+impl<T: Track> Tracked<T> {
+    /// Wraps the value. Its encoding is computed the first time it is needed.
+    pub fn new(value: T) -> Self {
+        Tracked {
+            value,
+            dirty: vec![true; T::FIELDS.len()],
+            bytes: vec![],
+            offsets: vec![],
+        }
+    }
+
+    /// Returns the index of the named field in `T::FIELDS`.
+    pub fn field_index(field: &str) -> Option<usize> {
+        T::FIELDS.iter().position(|name| *name == field)
+    }
+
+    /// Calls `f` with a mutable reference to the selected field, marking it
+    /// as modified.
+    pub fn modify<F, R>(&mut self, field: Field<T, F>, f: impl FnOnce(&mut F) -> R) -> R {
+        self.mark_dirty(field.index);
+        f((field.get)(&mut self.value))
+    }
+
+    /// Marks the field at `index` in `T::FIELDS` as modified, e.g. after
+    /// modifying it through interior mutability. Out-of-range indexes are
+    /// ignored.
+    pub fn mark_dirty(&mut self, index: usize) {
+        if let Some(dirty) = self.dirty.get_mut(index) {
+            *dirty = true;
+        }
+    }
+
+    /// Returns a mutable reference to the value, marking every field as
+    /// modified.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.dirty.iter_mut().for_each(|dirty| *dirty = true);
+        &mut self.value
+    }
+
+    /// Returns true if any field has been modified since the value was last
+    /// encoded.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.contains(&true)
+    }
+
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the encoding of the value, first re-encoding any fields which
+    /// have been modified since it was last encoded.
+    ///
+    /// A modified field whose encoding length is unchanged is overwritten in
+    /// place. Otherwise the bytes after it are shifted to make room.
+    pub fn encoded(&mut self) -> Result<&[u8]> {
+        if self.offsets.is_empty() {
+            let mut bytes = Vec::with_capacity(self.value.encoding_length()?);
+            let mut offsets = Vec::with_capacity(T::FIELDS.len() + 1);
+            for index in 0..T::FIELDS.len() {
+                offsets.push(bytes.len());
+                self.value.encode_field_into(index, &mut bytes)?;
+            }
+            offsets.push(bytes.len());
+
+            self.bytes = bytes;
+            self.offsets = offsets;
+            self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+            return Ok(&self.bytes);
+        }
+
+        for index in 0..T::FIELDS.len() {
+            if !self.dirty[index] {
+                continue;
+            }
+
+            let (start, end) = (self.offsets[index], self.offsets[index + 1]);
+            let length = self.value.field_encoding_length(index)?;
+            if length == end - start {
+                let mut dest = &mut self.bytes[start..end];
+                self.value.encode_field_into(index, &mut dest)?;
+            } else {
+                let mut field = Vec::with_capacity(length);
+                self.value.encode_field_into(index, &mut field)?;
+                self.bytes.splice(start..end, field);
+                for offset in self.offsets[index + 1..].iter_mut() {
+                    *offset = *offset + length - (end - start);
+                }
+            }
+            self.dirty[index] = false;
+        }
+
+        Ok(&self.bytes)
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Track> From<T> for Tracked<T> {
+    fn from(value: T) -> Self {
+        Tracked::new(value)
+    }
+}
+
+impl<T: Encode> Encode for Tracked<T> {
+    const SENSITIVE: bool = T::SENSITIVE;
+
+    /// Encodes the inner value.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.value.encode_into(dest)
+    }
+
+    /// Returns the encoding length of the inner value.
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        self.value.encoding_length()
+    }
+}
+
+impl<T: Track + Decode> Decode for Tracked<T> {
+    const SENSITIVE: bool = <T as Decode>::SENSITIVE;
+
+    /// Decodes the inner value. Its encoding is recomputed the first time it
+    /// is needed.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        T::decode(input).map(Tracked::new)
+    }
+}
+
+impl<T: Terminated> Terminated for Tracked<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[derive(Debug, PartialEq)]
+    struct Account {
+        nonce: u32,
+        flags: Option<u8>,
+        balance: u64,
+    }
+
+    impl Encode for Account {
+        fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+            (self.nonce, self.flags, self.balance).encode_into(dest)
+        }
+
+        fn encoding_length(&self) -> Result<usize> {
+            (self.nonce, self.flags, self.balance).encoding_length()
+        }
+    }
+
+    impl Track for Account {
+        const FIELDS: &'static [&'static str] = &["nonce", "flags", "balance"];
+
+        fn encode_field_into<W: Write>(&self, index: usize, dest: &mut W) -> Result<()> {
+            match index {
+                0 => self.nonce.encode_into(dest),
+                1 => self.flags.encode_into(dest),
+                2 => self.balance.encode_into(dest),
+                _ => Err(Error::UnencodableVariant),
+            }
+        }
+
+        fn field_encoding_length(&self, index: usize) -> Result<usize> {
+            match index {
+                0 => self.nonce.encoding_length(),
+                1 => self.flags.encoding_length(),
+                2 => self.balance.encoding_length(),
+                _ => Err(Error::UnencodableVariant),
+            }
+        }
+    }
+
+    impl Account {
+        const NONCE: Field<Self, u32> = Field::new(0, |account| &mut account.nonce);
+        const FLAGS: Field<Self, Option<u8>> = Field::new(1, |account| &mut account.flags);
+        const BALANCE: Field<Self, u64> = Field::new(2, |account| &mut account.balance);
+    }
+
+    fn check(account: &mut Tracked<Account>) {
+        let expected = account.encode().unwrap();
+        assert_eq!(account.encoded().unwrap(), expected.as_slice());
+    }
+
+    #[test]
+    fn reencode_modified_fields() {
+        let mut account = Tracked::new(Account {
+            nonce: 1,
+            flags: None,
+            balance: 2,
+        });
+        assert!(account.is_dirty());
+        check(&mut account);
+        assert!(!account.is_dirty());
+
+        account.modify(Account::NONCE, |nonce| *nonce += 1);
+        assert!(account.is_dirty());
+        check(&mut account);
+
+        // Changes the encoding length, shifting the following field.
+        account.modify(Account::FLAGS, |flags| *flags = Some(7));
+        check(&mut account);
+        account.modify(Account::BALANCE, |balance| *balance = 3);
+        check(&mut account);
+
+        account.get_mut().flags = None;
+        account.get_mut().balance = 4;
+        check(&mut account);
+        assert_eq!(
+            account.encoded().unwrap(),
+            &[0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 4]
+        );
+    }
+
+    #[test]
+    fn mark_dirty() {
+        let mut account = Tracked::new(Account {
+            nonce: 1,
+            flags: None,
+            balance: 2,
+        });
+        account.encoded().unwrap();
+
+        // Changes made around the tracking aren't re-encoded until the field
+        // is marked as modified.
+        account.value.balance = 5;
+        assert_eq!(account.encoded().unwrap()[12], 2);
+
+        account.mark_dirty(Tracked::<Account>::field_index("balance").unwrap());
+        assert_eq!(account.encoded().unwrap()[12], 5);
+        account.mark_dirty(3);
+        assert!(!account.is_dirty());
+        assert_eq!(Account::BALANCE.index(), 2);
+    }
+}
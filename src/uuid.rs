@@ -0,0 +1,55 @@
+//! Encoding support for `Uuid`, using the `uuid` crate.
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, FixedLength, Result, Terminated};
+use ::uuid::Uuid;
+
+impl Encode for Uuid {
+    /// Encodes the 16 bytes of the UUID, in their standard big-endian order.
+    #[inline]
+    fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+        dest.write_all(self.as_bytes())?;
+        Ok(())
+    }
+
+    /// Always returns Ok(16).
+    #[inline]
+    fn encoding_length(&self) -> Result<usize> {
+        Ok(16)
+    }
+}
+
+impl Decode for Uuid {
+    /// Decodes the 16 bytes of the UUID. Every 16 bytes are a valid UUID.
+    #[inline]
+    fn decode<R: Read>(input: R) -> Result<Self> {
+        <[u8; 16]>::decode(input).map(Uuid::from_bytes)
+    }
+}
+
+impl Terminated for Uuid {}
+
+impl FixedLength for Uuid {
+    const ENCODING_LENGTH: usize = 16;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let bytes = [
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ];
+        let uuid = Uuid::from_bytes(bytes);
+        assert_eq!(uuid.encode().unwrap(), bytes.to_vec());
+        assert_eq!(uuid.encoding_length().unwrap(), 16);
+        assert_eq!(Uuid::decode(&bytes[..]).unwrap(), uuid);
+
+        let pair: (Uuid, u8) = Decode::decode(&[0; 17][..]).unwrap();
+        assert_eq!(pair, (Uuid::nil(), 0));
+        assert!(Uuid::decode(&[0; 15][..]).unwrap_err().is_unexpected_eof());
+    }
+}
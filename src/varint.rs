@@ -0,0 +1,279 @@
+//! A compact, variable-length encoding of integers.
+//!
+//! [`Varint`](struct.Varint.html) wraps an integer and encodes it as LEB128:
+//! 7 bits at a time, least significant group first, with the high bit of each
+//! byte set if more bytes follow. Small values take fewer bytes, e.g. values
+//! below 128 take a single byte, at the cost of up to one extra byte for the
+//! largest values.
+//!
+//! Signed integers are first mapped to unsigned ones with zigzag encoding
+//! (0, -1, 1, -2, ... become 0, 1, 2, 3, ...) so that small negative values are
+//! also short.
+//!
+//! Every value has exactly one accepted encoding: decoding errors with
+//! `Error::NonCanonicalVarint` for encodings padded with trailing zero groups,
+//! and with `Error::VarintOverflow` for values too large for the type.
+//!
+//! ```rust
+//! use ed::{varint::Varint, Decode, Encode};
+//!
+//! assert_eq!(Varint(300u32).encode().unwrap(), vec![0xac, 0x02]);
+//! assert_eq!(Varint(-2i64).encode().unwrap(), vec![0x03]);
+//! assert_eq!(Varint::<u32>::decode(&[0xac, 0x02][..]).unwrap(), Varint(300));
+//! ```
+
+use super::io::{Read, Write};
+use super::{Decode, Encode, Error, Result, Terminated};
+use core::convert::TryFrom;
+
+/// An integer encoded with the variable-length LEB128 encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Varint<T>(pub T);
+
+impl<T> Varint<T> {
+    /// Returns the wrapped integer.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Varint<T> {
+    fn from(value: T) -> Self {
+        Varint(value)
+    }
+}
+
+/// The maximum encoding length of a varint, for a 128-bit value.
+const MAX_LENGTH: usize = 19;
+
+fn encode_u128<W: Write>(mut value: u128, dest: &mut W) -> Result<()> {
+    let mut bytes = [0; MAX_LENGTH];
+    let mut length = 0;
+    loop {
+        let group = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes[length] = group;
+            length += 1;
+            break;
+        }
+        bytes[length] = group | 0x80;
+        length += 1;
+    }
+    dest.write_all(&bytes[..length])?;
+    Ok(())
+}
+
+fn encoding_length_u128(value: u128) -> usize {
+    let bits = 128 - value.leading_zeros() as usize;
+    bits.max(1).div_ceil(7)
+}
+
+/// Decodes a varint of at most `bits` bits, erroring if it is too large or
+/// has trailing zero groups.
+fn decode_u128<R: Read>(mut input: R, bits: u32) -> Result<u128> {
+    let mut value = 0u128;
+    let mut shift = 0;
+    loop {
+        let byte = u8::decode(&mut input)?;
+        let group = u128::from(byte & 0x7f);
+
+        if shift >= bits || (bits - shift < 7 && group >> (bits - shift) != 0) {
+            return Err(Error::VarintOverflow);
+        }
+        value |= group << shift;
+
+        if byte & 0x80 == 0 {
+            if byte == 0 && shift > 0 {
+                return Err(Error::NonCanonicalVarint);
+            }
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+macro_rules! varint_impl {
+    ($type:ty) => {
+        impl Encode for Varint<$type> {
+            #[doc = "Encodes the integer as LEB128."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                encode_u128(u128::from(self.0), dest)
+            }
+
+            #[doc = "Returns the number of 7-bit groups needed to represent the"]
+            #[doc = " integer, which is at least 1."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                Ok(encoding_length_u128(u128::from(self.0)))
+            }
+        }
+
+        impl Decode for Varint<$type> {
+            #[doc = "Decodes a LEB128 integer. Errors if it has trailing zero"]
+            #[doc = " groups or does not fit in the type."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                let value = decode_u128(input, <$type>::BITS)?;
+                // The conversion can't fail since the value has at most
+                // `BITS` bits, but mapping the error keeps this path free of
+                // panics.
+                <$type>::try_from(value)
+                    .map(Varint)
+                    .map_err(|_| Error::VarintOverflow)
+            }
+        }
+
+        impl Terminated for Varint<$type> {}
+    };
+}
+
+varint_impl!(u16);
+varint_impl!(u32);
+varint_impl!(u64);
+varint_impl!(u128);
+
+macro_rules! signed_varint_impl {
+    ($type:ty, $unsigned:ty) => {
+        impl Varint<$type> {
+            fn zigzag(self) -> Varint<$unsigned> {
+                Varint(((self.0 << 1) ^ (self.0 >> (<$type>::BITS - 1))) as $unsigned)
+            }
+
+            fn unzigzag(value: Varint<$unsigned>) -> Self {
+                Varint((value.0 >> 1) as $type ^ -((value.0 & 1) as $type))
+            }
+        }
+
+        impl Encode for Varint<$type> {
+            #[doc = "Encodes the zigzag-encoded integer as LEB128."]
+            #[inline]
+            fn encode_into<W: Write>(&self, dest: &mut W) -> Result<()> {
+                self.zigzag().encode_into(dest)
+            }
+
+            #[doc = "Returns the encoding length of the zigzag-encoded integer."]
+            #[inline]
+            fn encoding_length(&self) -> Result<usize> {
+                self.zigzag().encoding_length()
+            }
+        }
+
+        impl Decode for Varint<$type> {
+            #[doc = "Decodes a zigzag-encoded LEB128 integer. Errors if it has"]
+            #[doc = " trailing zero groups or does not fit in the type."]
+            #[inline]
+            fn decode<R: Read>(input: R) -> Result<Self> {
+                Varint::<$unsigned>::decode(input).map(Self::unzigzag)
+            }
+        }
+
+        impl Terminated for Varint<$type> {}
+    };
+}
+
+signed_varint_impl!(i16, u16);
+signed_varint_impl!(i32, u32);
+signed_varint_impl!(i64, u64);
+signed_varint_impl!(i128, u128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_unsigned() {
+        let cases: &[(u64, &[u8])] = &[
+            (0, &[0]),
+            (1, &[1]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (300, &[0xac, 0x02]),
+            (16_384, &[0x80, 0x80, 0x01]),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+        ];
+        for (value, bytes) in cases.iter() {
+            let varint = Varint(*value);
+            assert_eq!(varint.encode().unwrap(), *bytes);
+            assert_eq!(varint.encoding_length().unwrap(), bytes.len());
+            assert_eq!(Varint::<u64>::decode(*bytes).unwrap(), varint);
+        }
+
+        let max = Varint(u128::MAX).encode().unwrap();
+        assert_eq!(max.len(), MAX_LENGTH);
+        assert_eq!(
+            Varint::<u128>::decode(max.as_slice()).unwrap(),
+            Varint(u128::MAX)
+        );
+    }
+
+    #[test]
+    fn encode_decode_signed() {
+        let cases: &[(i32, &[u8])] = &[
+            (0, &[0]),
+            (-1, &[1]),
+            (1, &[2]),
+            (-2, &[3]),
+            (-64, &[0x7f]),
+            (64, &[0x80, 0x01]),
+            (i32::MAX, &[0xfe, 0xff, 0xff, 0xff, 0x0f]),
+            (i32::MIN, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        ];
+        for (value, bytes) in cases.iter() {
+            let varint = Varint(*value);
+            assert_eq!(varint.encode().unwrap(), *bytes);
+            assert_eq!(varint.encoding_length().unwrap(), bytes.len());
+            assert_eq!(Varint::<i32>::decode(*bytes).unwrap(), varint);
+        }
+    }
+
+    #[test]
+    fn decode_non_canonical() {
+        assert!(matches!(
+            Varint::<u32>::decode(&[0x80, 0x00][..]),
+            Err(Error::NonCanonicalVarint)
+        ));
+        assert!(matches!(
+            Varint::<u32>::decode(&[0xac, 0x82, 0x00][..]),
+            Err(Error::NonCanonicalVarint)
+        ));
+    }
+
+    #[test]
+    fn decode_overflow() {
+        assert_eq!(
+            Varint::<u16>::decode(&[0xff, 0xff, 0x03][..]).unwrap(),
+            Varint(u16::MAX)
+        );
+        assert!(matches!(
+            Varint::<u16>::decode(&[0xff, 0xff, 0x04][..]),
+            Err(Error::VarintOverflow)
+        ));
+        assert!(matches!(
+            Varint::<u16>::decode(&[0x80, 0x80, 0x80, 0x01][..]),
+            Err(Error::VarintOverflow)
+        ));
+        assert!(matches!(
+            Varint::<i32>::decode(&[0xff, 0xff, 0xff, 0xff, 0x1f][..]),
+            Err(Error::VarintOverflow)
+        ));
+        assert!(Varint::<u64>::decode(&[0x80][..])
+            .unwrap_err()
+            .is_unexpected_eof());
+    }
+
+    #[test]
+    fn decode_in_sequence() {
+        let values = vec![Varint(1u32), Varint(300), Varint(0)];
+        let bytes = values.encode().unwrap();
+        assert_eq!(bytes, vec![0x01, 0xac, 0x02, 0x00]);
+        assert_eq!(
+            Vec::<Varint<u32>>::decode(bytes.as_slice()).unwrap(),
+            values
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Helpers for wiping temporary buffers which may hold sensitive data.
+//!
+//! Wiping only happens when the `zeroize` feature is enabled and the type
+//! being encoded or decoded is marked as sensitive (see
+//! [`Encode::SENSITIVE`](../trait.Encode.html#associatedconstant.SENSITIVE)),
+//! otherwise these helpers are equivalent to their plain `std` counterparts.
+//!
+//! Values which aren't sensitive themselves, such as a `Vec<u8>` field of a
+//! sensitive struct, are wiped when decoded within a sensitive value. This is
+//! tracked per thread with the `std` feature. Without it, every buffer read
+//! to the end of the input is wiped.
+
+#[cfg(feature = "zeroize")]
+use super::io;
+use super::io::Read;
+use super::Result;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+#[cfg(all(feature = "zeroize", feature = "std"))]
+use std::cell::Cell;
+#[cfg(feature = "zeroize")]
+use zeroize::{Zeroize, Zeroizing};
+
+// Whether a sensitive value is being decoded on this thread.
+#[cfg(all(feature = "zeroize", feature = "std"))]
+thread_local! {
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks decoding on this thread as sensitive until dropped. Returned by
+/// [`sensitive`](fn.sensitive.html).
+#[doc(hidden)]
+#[must_use]
+pub struct Sensitive {
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    entered: bool,
+}
+
+/// Marks decoding on this thread as sensitive until the returned guard is
+/// dropped, if `sensitive` is true. Called by sensitive `Decode` impls, such
+/// as derived ones, before decoding their fields.
+#[doc(hidden)]
+#[inline]
+pub fn sensitive(sensitive: bool) -> Sensitive {
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    {
+        let entered = sensitive && ACTIVE.try_with(|active| !active.replace(true)) == Ok(true);
+        Sensitive { entered }
+    }
+    #[cfg(not(all(feature = "zeroize", feature = "std")))]
+    {
+        let _ = sensitive;
+        Sensitive {}
+    }
+}
+
+#[cfg(all(feature = "zeroize", feature = "std"))]
+impl Drop for Sensitive {
+    fn drop(&mut self) {
+        if self.entered {
+            let _ = ACTIVE.try_with(|active| active.set(false));
+        }
+    }
+}
+
+/// Returns whether a sensitive value is being decoded on this thread, in
+/// which case buffers should be wiped even if their own type isn't sensitive.
+#[inline]
+pub(crate) fn active() -> bool {
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    return ACTIVE.try_with(Cell::get).unwrap_or(true);
+    #[cfg(all(feature = "zeroize", not(feature = "std")))]
+    return true;
+    #[cfg(not(feature = "zeroize"))]
+    return false;
+}
+
+/// Wipes the contents of the buffer if `sensitive` is true.
+#[inline]
+pub(crate) fn wipe(buf: &mut Vec<u8>, sensitive: bool) {
+    #[cfg(feature = "zeroize")]
+    if sensitive {
+        buf.zeroize();
+    }
+    #[cfg(not(feature = "zeroize"))]
+    let _ = (buf, sensitive);
+}
+
+/// Reads the rest of the input, appending it to `buf`.
+///
+/// If `sensitive` is true, the input is read through a zeroizing scratch
+/// buffer, allocations outgrown by `buf` are wiped before being freed, and
+/// `buf` is wiped if reading fails.
+pub(crate) fn read_to_end<R: Read>(mut input: R, buf: &mut Vec<u8>, sensitive: bool) -> Result<()> {
+    #[cfg(feature = "zeroize")]
+    if sensitive {
+        return read_to_end_zeroizing(input, buf);
+    }
+
+    let _ = sensitive;
+    input.read_to_end(buf)?;
+    Ok(())
+}
+
+#[cfg(feature = "zeroize")]
+fn read_to_end_zeroizing<R: Read>(mut input: R, buf: &mut Vec<u8>) -> Result<()> {
+    let mut chunk = Zeroizing::new([0u8; 256]);
+
+    loop {
+        let n = match input.read(&mut chunk[..]) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => {
+                buf.zeroize();
+                return Err(err.into());
+            }
+        };
+
+        extend(buf, &chunk[..n], true);
+    }
+
+    Ok(())
+}
+
+/// Appends `bytes` to `buf`. If `sensitive` is true, allocations outgrown by
+/// `buf` are wiped before being freed.
+#[cfg(any(feature = "zeroize", feature = "std"))]
+#[inline]
+pub(crate) fn extend(buf: &mut Vec<u8>, bytes: &[u8], sensitive: bool) {
+    #[cfg(feature = "zeroize")]
+    if sensitive && buf.len() + bytes.len() > buf.capacity() {
+        let capacity = (buf.len() + bytes.len()).max(buf.capacity() * 2);
+        let mut grown = Vec::with_capacity(capacity);
+        grown.extend_from_slice(buf);
+        let mut outgrown = core::mem::replace(buf, grown);
+        outgrown.zeroize();
+    }
+    let _ = sensitive;
+    buf.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_to_end_sensitive() {
+        let input: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        for sensitive in [false, true] {
+            let mut buf = vec![1, 2, 3];
+            read_to_end(input.as_slice(), &mut buf, sensitive).unwrap();
+            assert_eq!(&buf[..3], &[1, 2, 3]);
+            assert_eq!(&buf[3..], input.as_slice());
+        }
+    }
+
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    #[test]
+    fn sensitive_scope() {
+        assert!(!active());
+        {
+            let _outer = sensitive(true);
+            assert!(active());
+            {
+                let _inner = sensitive(true);
+                let _plain = sensitive(false);
+                assert!(active());
+            }
+            assert!(active());
+        }
+        assert!(!active());
+        let _plain = sensitive(false);
+        assert!(!active());
+    }
+
+    // Records whether each read happened within a sensitive value.
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    struct Probe<'a> {
+        bytes: &'a [u8],
+        reads: Vec<bool>,
+    }
+
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    impl Read for Probe<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads.push(active());
+            self.bytes.read(buf)
+        }
+    }
+
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    struct Seed(Vec<u8>, String);
+
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    impl crate::Decode for Seed {
+        const SENSITIVE: bool = true;
+
+        fn decode<R: Read>(mut input: R) -> Result<Self> {
+            let _sensitive = sensitive(Self::SENSITIVE);
+            let bytes = crate::Decode::decode((&mut input).take(4))?;
+            let string = crate::Decode::decode(input)?;
+            Ok(Seed(bytes, string))
+        }
+    }
+
+    #[cfg(all(feature = "zeroize", feature = "std"))]
+    #[test]
+    fn nested_slurps_sensitive() {
+        use crate::Decode;
+
+        let mut probe = Probe {
+            bytes: b"\x01\x02\x03\x04seed",
+            reads: vec![],
+        };
+        let seed = Seed::decode(&mut probe).unwrap();
+        assert_eq!(seed.0, vec![1, 2, 3, 4]);
+        assert_eq!(seed.1, "seed");
+        assert!(!probe.reads.is_empty());
+        assert!(probe.reads.iter().all(|&sensitive| sensitive));
+
+        let mut probe = Probe {
+            bytes: b"plain",
+            reads: vec![],
+        };
+        String::decode(&mut probe).unwrap();
+        assert!(probe.reads.iter().all(|&sensitive| !sensitive));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn wipe_sensitive() {
+        let mut buf = vec![1, 2, 3];
+        wipe(&mut buf, false);
+        assert_eq!(buf, vec![1, 2, 3]);
+        wipe(&mut buf, true);
+        assert!(buf.is_empty());
+    }
+}
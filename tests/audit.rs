@@ -0,0 +1,48 @@
+// The `#[no_panic]` checks only hold with optimizations, so this test is
+// built in release mode only (see the `audit` module).
+#![cfg(all(feature = "no-panic", not(debug_assertions)))]
+
+use ed::audit;
+
+#[test]
+fn audit() {
+    // Linking each entry point runs its check, and decoding a few inputs
+    // makes sure they error rather than panic at run time too.
+    for bytes in [
+        &[][..],
+        &[0; 64][..],
+        &[2, 0, 1, 0, 2, 0, 3, 0][..],
+        &[9][..],
+    ] {
+        let bytes = std::hint::black_box(bytes);
+        let _ = audit::decode_u8(bytes);
+        let _ = audit::decode_u16(bytes);
+        let _ = audit::decode_u32(bytes);
+        let _ = audit::decode_u64(bytes);
+        let _ = audit::decode_u128(bytes);
+        let _ = audit::decode_i8(bytes);
+        let _ = audit::decode_i16(bytes);
+        let _ = audit::decode_i32(bytes);
+        let _ = audit::decode_i64(bytes);
+        let _ = audit::decode_i128(bytes);
+        let _ = audit::decode_bool(bytes);
+        let _ = audit::decode_unit(bytes);
+        let _ = audit::decode_option(bytes);
+        let _ = audit::decode_tuple(bytes);
+        let _ = audit::decode_array(bytes);
+        let _ = audit::decode_byte_array(bytes);
+        let _ = audit::decode_nested(bytes);
+        let _ = audit::decode_vec(bytes);
+        let _ = audit::decode_bytes(bytes);
+        let _ = audit::decode_string(bytes);
+        let _ = audit::decode_map_entries(bytes);
+        let _ = audit::decode::<(u16, Option<[u8; 2]>, Vec<u16>)>(bytes);
+    }
+
+    assert_eq!(
+        audit::decode::<(u16, Option<[u8; 2]>, Vec<u16>)>(&[2, 0, 1, 0, 2, 0, 3, 0, 4][..])
+            .unwrap(),
+        (0x0200, Some([0, 2]), vec![3, 4])
+    );
+    assert!(audit::decode::<(u16, Option<[u8; 2]>, Vec<u16>)>(&[2, 0, 2][..]).is_err());
+}
@@ -0,0 +1,49 @@
+// Installs a counting global allocator, so the accounting is checked against
+// the allocations decoding actually makes.
+#![cfg(feature = "std")]
+
+use ed::cost::{decode_with_accounting, record_allocation, EncodingCost};
+use ed::Encode;
+use std::alloc::{GlobalAlloc, Layout, System};
+
+struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[test]
+fn counts_allocations() {
+    let (value, allocated): (u64, usize) = decode_with_accounting(&[0; 8][..]).unwrap();
+    assert_eq!((value, allocated), (0, 0));
+
+    // The vector grows while decoding, so the reallocations are counted as
+    // well as the retained capacity.
+    let value: Vec<u32> = (0..100).collect();
+    let bytes = value.encode().unwrap();
+    let (decoded, allocated): (Vec<u32>, usize) = decode_with_accounting(bytes.as_slice()).unwrap();
+    assert_eq!(decoded, value);
+    assert!(allocated > decoded.heap_bytes());
+
+    let value = (Box::new(1u64), vec![2u8; 20]);
+    let bytes = value.encode().unwrap();
+    let (decoded, allocated): ((Box<u64>, Vec<u8>), usize) =
+        decode_with_accounting(bytes.as_slice()).unwrap();
+    assert_eq!(decoded, value);
+    assert!(allocated >= decoded.heap_bytes());
+}
@@ -1,4 +1,7 @@
-use ed::{Decode, Encode};
+use ed::{
+    cbor::to_cbor, columnar::Columnar, Cbor, Columns, Decode, DecodeLenient, Encode, EncodingCost,
+    Profile, Remaining, Terminated, Track,
+};
 
 #[derive(Encode, Decode)]
 struct Foo {
@@ -22,6 +25,12 @@ enum Bar {
     C,
 }
 
+#[derive(Encode, Decode)]
+enum Outcome<E> {
+    Done(u32),
+    Failed(E),
+}
+
 trait Subtype {
     type Subtype;
 }
@@ -33,3 +42,723 @@ enum Bar2<T: Subtype, U> {
     C,
     D(T::Subtype, U),
 }
+
+#[derive(Encode, Decode)]
+#[ed(sensitive)]
+struct Key([u8; 32]);
+
+#[derive(Encode, Decode)]
+#[ed(sensitive)]
+enum Keys {
+    One(Key),
+    Two(Key, Key),
+}
+
+#[derive(Encode, Decode)]
+struct Wallet {
+    key: Key,
+    label: Vec<u8>,
+}
+
+#[test]
+#[allow(clippy::assertions_on_constants)]
+fn sensitive() {
+    assert!(<Key as Encode>::SENSITIVE);
+    assert!(<Key as Decode>::SENSITIVE);
+    assert!(<Keys as Encode>::SENSITIVE);
+    assert!(<Vec<Keys> as Decode>::SENSITIVE);
+    assert!(<Wallet as Encode>::SENSITIVE);
+    assert!(<Wallet as Decode>::SENSITIVE);
+    assert!(!<Foo as Encode>::SENSITIVE);
+    assert!(!<Bar as Decode>::SENSITIVE);
+}
+
+#[derive(Encode, Decode, DecodeLenient, Debug)]
+struct Lenient {
+    a: bool,
+    b: u16,
+    c: bool,
+}
+
+#[derive(Encode, Decode, DecodeLenient, Debug)]
+enum LenientEnum {
+    A(bool, bool),
+    B { x: u8 },
+}
+
+#[test]
+fn decode_lenient() {
+    let errors = Lenient::decode_lenient(&[2, 0, 1, 3]).unwrap_err();
+    assert_eq!(errors.0.len(), 2);
+    assert_eq!((errors.0[0].field.as_str(), errors.0[0].offset), ("a", 0));
+    assert_eq!((errors.0[1].field.as_str(), errors.0[1].offset), ("c", 3));
+
+    let value = Lenient::decode_lenient(&[1, 0, 1, 0]).unwrap();
+    assert_eq!((value.a, value.b, value.c), (true, 1, false));
+
+    let errors = LenientEnum::decode_lenient(&[0, 2, 3]).unwrap_err();
+    assert_eq!(errors.0.len(), 2);
+    assert_eq!(errors.0[1].field, "A.1");
+
+    let errors = LenientEnum::decode_lenient(&[5, 0]).unwrap_err();
+    assert_eq!(errors.0[0].field, "variant");
+}
+
+#[derive(Encode, Decode)]
+struct Header {
+    version: u8,
+    #[ed(accessor)]
+    height: u64,
+    #[ed(accessor)]
+    timestamp: (u32, u32),
+    #[ed(accessor)]
+    data: Vec<u8>,
+}
+
+#[test]
+fn accessors() {
+    let header = Header {
+        version: 1,
+        height: 1234,
+        timestamp: (5, 6),
+        data: vec![7, 8, 9],
+    };
+    let bytes = header.encode().unwrap();
+    assert_eq!(Header::decode_height(&bytes).unwrap(), 1234);
+    assert_eq!(Header::decode_timestamp(&bytes).unwrap(), (5, 6));
+    assert_eq!(Header::decode_data(&bytes).unwrap(), vec![7, 8, 9]);
+    assert!(Header::decode_timestamp(&bytes[..10]).is_err());
+}
+
+#[derive(Columns, Debug, PartialEq)]
+struct Candle {
+    time: u64,
+    price: Option<u16>,
+}
+
+#[derive(Columns, Debug, PartialEq)]
+struct Pair(u8, bool);
+
+#[derive(Columns, Debug, PartialEq)]
+struct Marker;
+
+#[test]
+fn columns() {
+    let table = Columnar(vec![
+        Candle {
+            time: 1,
+            price: Some(2),
+        },
+        Candle {
+            time: 3,
+            price: None,
+        },
+    ]);
+    let bytes = table.encode().unwrap();
+    assert_eq!(
+        bytes,
+        vec![0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 3, 1, 0, 2, 0]
+    );
+    assert_eq!(table.encoding_length().unwrap(), bytes.len());
+    assert_eq!(
+        Columnar::<Vec<Candle>>::decode(bytes.as_slice()).unwrap(),
+        table
+    );
+
+    let pairs = Columnar(vec![Pair(1, true), Pair(2, false)]);
+    let bytes = pairs.encode().unwrap();
+    assert_eq!(bytes, vec![0, 0, 0, 2, 1, 2, 1, 0]);
+    assert_eq!(
+        Columnar::<Vec<Pair>>::decode(bytes.as_slice()).unwrap(),
+        pairs
+    );
+
+    // Rows without fields read nothing, so a count alone can't describe them.
+    assert!(matches!(
+        Columnar::<Vec<Marker>>::decode(&[0xff; 4][..]),
+        Err(ed::Error::CountExceedsInput(0xffff_ffff))
+    ));
+    assert_eq!(
+        Columnar::<Vec<Marker>>::decode(&[0; 4][..]).unwrap(),
+        Columnar(vec![])
+    );
+}
+
+mod foreign {
+    pub struct Point {
+        pub x: u32,
+        pub y: u32,
+    }
+
+    pub enum Shape {
+        Dot(Point),
+        Line { from: Point, to: Point },
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Encode, Decode)]
+#[ed(remote = "foreign::Point")]
+struct PointDef {
+    x: u32,
+    y: u32,
+}
+
+#[derive(Encode, Decode)]
+struct Anchor {
+    #[ed(accessor)]
+    #[ed(with = "PointDef")]
+    origin: foreign::Point,
+    label: Vec<u8>,
+}
+
+#[test]
+fn accessor_with() {
+    let anchor = Anchor {
+        origin: foreign::Point { x: 1, y: 2 },
+        label: vec![3],
+    };
+    let bytes = anchor.encode().unwrap();
+    let origin = Anchor::decode_origin(&bytes).unwrap();
+    assert_eq!((origin.x, origin.y), (1, 2));
+}
+
+#[allow(dead_code)]
+#[derive(Encode, Decode)]
+#[ed(remote = "foreign::Shape")]
+enum ShapeDef {
+    Dot(#[ed(with = "PointDef")] foreign::Point),
+    Line {
+        #[ed(with = "PointDef")]
+        from: foreign::Point,
+        #[ed(with = "PointDef")]
+        to: foreign::Point,
+    },
+}
+
+#[derive(Encode, Decode)]
+struct Drawing {
+    id: u8,
+    #[ed(with = "ShapeDef")]
+    shape: foreign::Shape,
+    #[ed(with = "PointDef")]
+    origin: foreign::Point,
+}
+
+#[test]
+fn remote() {
+    let drawing = Drawing {
+        id: 1,
+        shape: foreign::Shape::Line {
+            from: foreign::Point { x: 2, y: 3 },
+            to: foreign::Point { x: 4, y: 5 },
+        },
+        origin: foreign::Point { x: 6, y: 7 },
+    };
+    let bytes = drawing.encode().unwrap();
+    assert_eq!(
+        bytes,
+        vec![1, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 5, 0, 0, 0, 6, 0, 0, 0, 7]
+    );
+    assert_eq!(drawing.encoding_length().unwrap(), bytes.len());
+
+    let decoded = Drawing::decode(bytes.as_slice()).unwrap();
+    assert_eq!(decoded.id, 1);
+    match decoded.shape {
+        foreign::Shape::Line { from, to } => assert_eq!((from.x, to.y), (2, 5)),
+        _ => panic!("Expected a line"),
+    }
+    assert_eq!((decoded.origin.x, decoded.origin.y), (6, 7));
+
+    let mut dot = vec![];
+    ShapeDef::encode_into(
+        &foreign::Shape::Dot(foreign::Point { x: 8, y: 9 }),
+        &mut dot,
+    )
+    .unwrap();
+    assert_eq!(dot, vec![0, 0, 0, 0, 8, 0, 0, 0, 9]);
+    assert!(matches!(
+        ShapeDef::decode(dot.as_slice()).unwrap(),
+        foreign::Shape::Dot(foreign::Point { x: 8, y: 9 })
+    ));
+}
+
+#[derive(Encode, Decode, DecodeLenient, Columns)]
+struct Waypoint {
+    id: u8,
+    #[ed(with = "PointDef")]
+    at: foreign::Point,
+}
+
+#[test]
+fn remote_lenient_and_columns() {
+    let waypoint = Waypoint::decode_lenient(&[1, 0, 0, 0, 2, 0, 0, 0, 3]).unwrap();
+    assert_eq!((waypoint.id, waypoint.at.x, waypoint.at.y), (1, 2, 3));
+    let errors = Waypoint::decode_lenient(&[1, 0, 0]).err().unwrap();
+    assert_eq!(errors.0[0].field, "at");
+
+    let table = Columnar(vec![
+        Waypoint {
+            id: 1,
+            at: foreign::Point { x: 2, y: 3 },
+        },
+        Waypoint {
+            id: 4,
+            at: foreign::Point { x: 5, y: 6 },
+        },
+    ]);
+    let bytes = table.encode().unwrap();
+    assert_eq!(
+        bytes,
+        vec![0, 0, 0, 2, 1, 4, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0, 6]
+    );
+    assert_eq!(table.encoding_length().unwrap(), bytes.len());
+    let decoded = Columnar::<Vec<Waypoint>>::decode(bytes.as_slice()).unwrap();
+    assert_eq!((decoded.0[1].id, decoded.0[1].at.y), (4, 6));
+}
+
+#[derive(Encode, Decode, Profile)]
+struct SignedTx {
+    nonce: u32,
+    signature: [u8; 8],
+}
+
+#[derive(Encode, Decode, Profile)]
+enum Message {
+    Txs(Vec<SignedTx>),
+    Ping { id: u16 },
+}
+
+#[test]
+fn profile() {
+    let corpus = vec![
+        Message::Txs(vec![
+            SignedTx {
+                nonce: 1,
+                signature: [0; 8],
+            },
+            SignedTx {
+                nonce: 2,
+                signature: [0; 8],
+            },
+        ])
+        .encode()
+        .unwrap(),
+        Message::Ping { id: 3 }.encode().unwrap(),
+    ];
+
+    let report = ed::profile::profile_corpus::<Message, _, _>(corpus).unwrap();
+    assert_eq!(report.total_bytes(), 28);
+    assert_eq!(report.bytes("Txs"), 25);
+    assert_eq!(report.bytes("Txs.0[]"), 24);
+    assert_eq!(report.bytes("Txs.0[].signature"), 16);
+    assert_eq!(report.count("Txs.0[].signature[]"), 16);
+    assert_eq!(report.bytes("Ping.id"), 2);
+}
+
+#[derive(Encode, Decode, EncodingCost)]
+struct Batch<T> {
+    height: u64,
+    txs: Vec<T>,
+}
+
+#[derive(EncodingCost)]
+enum Payload {
+    Empty,
+    Text(String),
+    Pair { left: Box<u32>, right: Vec<u8> },
+}
+
+#[test]
+fn encoding_cost() {
+    let batch = Batch {
+        height: 1,
+        txs: vec![Box::new(3u32), Box::new(5)],
+    };
+    assert_eq!(
+        batch.heap_bytes(),
+        2 * std::mem::size_of::<Box<u32>>() + 4 + 4
+    );
+
+    assert_eq!(Payload::Empty.heap_bytes(), 0);
+    assert_eq!(Payload::Text(String::with_capacity(6)).heap_bytes(), 6);
+    let pair = Payload::Pair {
+        left: Box::new(1),
+        right: vec![1, 2],
+    };
+    assert_eq!(pair.heap_bytes(), 4 + 2);
+
+    let bytes = batch.encode().unwrap();
+    let (decoded, retained): (Batch<Box<u32>>, usize) =
+        ed::cost::decode_with_retained_size(bytes.as_slice()).unwrap();
+    assert_eq!(retained, decoded.heap_bytes());
+}
+
+#[derive(Encode, Decode, Track)]
+struct Ledger {
+    height: u64,
+    memo: Option<u32>,
+    #[ed(with = "PointDef")]
+    origin: foreign::Point,
+    balances: Vec<u16>,
+}
+
+#[test]
+fn track() {
+    use ed::tracked::Tracked;
+
+    assert_eq!(Ledger::FIELDS, &["height", "memo", "origin", "balances"]);
+
+    let mut ledger = Tracked::new(Ledger {
+        height: 1,
+        memo: None,
+        origin: foreign::Point { x: 2, y: 3 },
+        balances: vec![4],
+    });
+    let expected = ledger.encode().unwrap();
+    assert_eq!(ledger.encoded().unwrap(), expected.as_slice());
+
+    ledger.modify(Ledger::MEMO, |memo| *memo = Some(5));
+    ledger.modify(Ledger::ORIGIN, |origin| origin.y = 6);
+    ledger.modify(Ledger::BALANCES, |balances| balances.push(7));
+    assert_eq!(Ledger::HEIGHT.index(), 0);
+    let expected = ledger.encode().unwrap();
+    assert_eq!(ledger.encoded().unwrap(), expected.as_slice());
+
+    let decoded = Tracked::<Ledger>::decode(expected.as_slice()).unwrap();
+    assert_eq!(decoded.balances, vec![4, 7]);
+}
+
+#[derive(Debug, PartialEq)]
+struct NotEncodable;
+
+#[derive(Encode, Decode, Debug, PartialEq)]
+struct Handle<T: ?Sized> {
+    _marker: std::marker::PhantomData<T>,
+    id: u32,
+}
+
+#[test]
+fn phantom_data() {
+    let handle: Handle<NotEncodable> = Handle {
+        _marker: std::marker::PhantomData,
+        id: 7,
+    };
+    let bytes = handle.encode().unwrap();
+    assert_eq!(bytes, vec![0, 0, 0, 7]);
+    assert_eq!(
+        Handle::<NotEncodable>::decode(bytes.as_slice()).unwrap(),
+        handle
+    );
+
+    let handle: Handle<dyn Fn()> = Handle {
+        _marker: std::marker::PhantomData,
+        id: 8,
+    };
+    assert_eq!(handle.encoding_length().unwrap(), 4);
+}
+
+#[test]
+fn infallible_variant() {
+    use std::convert::Infallible;
+
+    let outcome: Outcome<Infallible> = Outcome::Done(7);
+    let bytes = outcome.encode().unwrap();
+    assert_eq!(bytes, vec![0, 0, 0, 0, 7]);
+    assert!(matches!(
+        Outcome::<Infallible>::decode(bytes.as_slice()).unwrap(),
+        Outcome::Done(7)
+    ));
+    let err = Outcome::<Infallible>::decode(&[1][..]).err().unwrap();
+    assert!(matches!(err.inner(), ed::Error::Uninhabited));
+    assert_eq!(err.path().unwrap(), "Outcome::Failed.0");
+}
+
+#[derive(Encode, Decode, Debug)]
+struct Block {
+    height: u64,
+    header: BlockHeader,
+}
+
+#[derive(Encode, Decode, Debug)]
+struct BlockHeader {
+    version: u8,
+    sealed: bool,
+}
+
+#[test]
+fn decode_error_context() {
+    let mut bytes = Block {
+        height: 1,
+        header: BlockHeader {
+            version: 2,
+            sealed: true,
+        },
+    }
+    .encode()
+    .unwrap();
+    bytes[9] = 7;
+
+    let err = Block::decode(bytes.as_slice()).unwrap_err();
+    assert_eq!(err.path().unwrap(), "Block.header.sealed");
+    assert_eq!(err.to_string(), "Block.header.sealed: Unexpected byte: 7");
+    assert!(matches!(err.inner(), ed::Error::UnexpectedByte(7)));
+
+    let mut block = Block::decode(&[0; 10][..]).unwrap();
+    let err = block.decode_into(bytes.as_slice()).unwrap_err();
+    assert_eq!(err.path().unwrap(), "Block.header.sealed");
+}
+
+#[derive(Encode, Decode, Debug, PartialEq)]
+struct Datagram {
+    id: u16,
+    body: Vec<u8>,
+}
+
+#[test]
+fn unterminated_last_field() {
+    let message = Datagram {
+        id: 1,
+        body: vec![2, 3],
+    };
+    let bytes = message.encode().unwrap();
+    assert_eq!(bytes, vec![0, 1, 2, 3]);
+    assert_eq!(Datagram::decode(bytes.as_slice()).unwrap(), message);
+}
+
+/// Bytes prefixed with their length, which are terminated even though their
+/// only field is not.
+#[derive(Terminated, Debug, PartialEq)]
+#[ed(terminated)]
+struct Prefixed(Vec<u8>);
+
+impl Encode for Prefixed {
+    fn encode_into<W: std::io::Write>(&self, dest: &mut W) -> ed::Result<()> {
+        (self.0.len() as u8).encode_into(dest)?;
+        self.0.encode_into(dest)
+    }
+
+    fn encoding_length(&self) -> ed::Result<usize> {
+        Ok(1 + self.0.len())
+    }
+}
+
+impl Decode for Prefixed {
+    fn decode<R: std::io::Read>(mut input: R) -> ed::Result<Self> {
+        let len = u8::decode(&mut input)?;
+        let mut bytes = vec![0; len as usize];
+        input.read_exact(&mut bytes)?;
+        Ok(Prefixed(bytes))
+    }
+}
+
+#[derive(Encode, Decode)]
+#[ed(not_terminated)]
+struct Trailer(u32);
+
+struct Probe<T>(std::marker::PhantomData<T>);
+
+impl<T: Terminated> Probe<T> {
+    fn terminated(&self) -> bool {
+        true
+    }
+}
+
+trait Fallback {
+    fn terminated(&self) -> bool {
+        false
+    }
+}
+
+impl<T> Fallback for Probe<T> {}
+
+// Resolves to the inherent method only if the type is `Terminated`, so must be
+// expanded for a concrete type rather than called generically.
+macro_rules! is_terminated {
+    ($ty:ty) => {
+        Probe::<$ty>(std::marker::PhantomData).terminated()
+    };
+}
+
+#[test]
+fn terminated() {
+    let values = vec![Prefixed(vec![1, 2]), Prefixed(vec![])];
+    let bytes = values.encode().unwrap();
+    assert_eq!(bytes, vec![2, 1, 2, 0]);
+    assert_eq!(Vec::<Prefixed>::decode(bytes.as_slice()).unwrap(), values);
+
+    assert!(is_terminated!(Prefixed));
+    assert!(is_terminated!(Foo));
+    assert!(!is_terminated!(Trailer));
+    assert!(!is_terminated!(Datagram));
+}
+
+#[derive(Encode, Decode, Debug, PartialEq)]
+enum Frame {
+    Data {
+        stream: u8,
+        payload: Remaining<Vec<u8>>,
+    },
+    Close(Remaining<u16>),
+}
+
+#[test]
+fn remaining_last_field() {
+    let frame = Frame::Data {
+        stream: 3,
+        payload: Remaining(vec![4, 5]),
+    };
+    let bytes = frame.encode().unwrap();
+    assert_eq!(bytes, vec![0, 3, 4, 5]);
+    assert_eq!(Frame::decode(bytes.as_slice()).unwrap(), frame);
+
+    assert_eq!(
+        Frame::decode(&[1, 0, 9][..]).unwrap(),
+        Frame::Close(Remaining(9))
+    );
+    let err = Frame::decode(&[1, 0, 9, 0][..]).unwrap_err();
+    assert!(matches!(err.inner(), ed::Error::TrailingBytes(1)));
+
+    assert!(!is_terminated!(Frame));
+}
+
+mod framing {
+    use ed::{Decode, Encode};
+
+    // Not `ed::Remaining`, so it may come before other fields.
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    pub struct Remaining(pub u8);
+}
+
+#[derive(Encode, Decode, Debug, PartialEq)]
+struct Tagged {
+    rest: framing::Remaining,
+    flags: u8,
+}
+
+#[test]
+fn remaining_named_type() {
+    let tagged = Tagged {
+        rest: framing::Remaining(1),
+        flags: 2,
+    };
+    let bytes = tagged.encode().unwrap();
+    assert_eq!(bytes, vec![1, 2]);
+    assert_eq!(Tagged::decode(bytes.as_slice()).unwrap(), tagged);
+}
+
+#[derive(Cbor)]
+struct Envelope {
+    kind: Kind,
+    body: Vec<u8>,
+    id: (u8, bool),
+}
+
+#[derive(Cbor)]
+enum Kind {
+    Ping,
+    Data(u16),
+    Pair(u8, u8),
+    Named { x: i8 },
+}
+
+#[test]
+fn cbor() {
+    let envelope = Envelope {
+        kind: Kind::Ping,
+        body: vec![1, 2],
+        id: (3, true),
+    };
+    // {"id": [3, true], "body": h'0102', "kind": "Ping"}
+    assert_eq!(
+        to_cbor(&envelope).unwrap(),
+        vec![
+            0xa3, 0x62, b'i', b'd', 0x82, 0x03, 0xf5, 0x64, b'b', b'o', b'd', b'y', 0x42, 0x01,
+            0x02, 0x64, b'k', b'i', b'n', b'd', 0x64, b'P', b'i', b'n', b'g',
+        ]
+    );
+
+    assert_eq!(
+        to_cbor(&Kind::Data(500)).unwrap(),
+        vec![0xa1, 0x64, b'D', b'a', b't', b'a', 0x19, 0x01, 0xf4]
+    );
+    assert_eq!(
+        to_cbor(&Kind::Pair(1, 2)).unwrap(),
+        vec![0xa1, 0x64, b'P', b'a', b'i', b'r', 0x82, 0x01, 0x02]
+    );
+    assert_eq!(
+        to_cbor(&Kind::Named { x: -1 }).unwrap(),
+        vec![0xa1, 0x65, b'N', b'a', b'm', b'e', b'd', 0xa1, 0x61, b'x', 0x20]
+    );
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn tokio_async() {
+    use ed::tokio::{AsyncDecode, AsyncEncode};
+
+    let bar = Bar::A { x: 1, y: (2, 3) };
+    let mut bytes = vec![];
+    block_on(bar.encode_into_async(&mut bytes)).unwrap();
+    assert_eq!(bytes, bar.encode().unwrap());
+    let decoded = block_on(Bar::decode_async(bytes.as_slice())).unwrap();
+    assert_eq!(decoded.encode().unwrap(), bytes);
+
+    let foo = Foo2(4, (5, 6));
+    let mut bytes = vec![];
+    block_on(foo.encode_into_async(&mut bytes)).unwrap();
+    assert_eq!(bytes, foo.encode().unwrap());
+    let decoded = block_on(Foo2::decode_async(bytes.as_slice())).unwrap();
+    assert_eq!((decoded.0, decoded.1), (4, (5, 6)));
+
+    let err = block_on(Bar::decode_async(&[1, 0, 0, 0, 7, 0][..]))
+        .err()
+        .unwrap();
+    assert_eq!(err.path().unwrap(), "Bar::B.1");
+    assert!(err.is_unexpected_eof());
+
+    let err = block_on(Bar::decode_async(&[3][..])).err().unwrap();
+    assert!(matches!(err, ed::Error::UnexpectedByte(3)));
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn futures_io_async() {
+    use ed::futures_io::{AsyncDecode, AsyncEncode};
+
+    let bar = Bar::B(1, (2, 3));
+    let mut bytes = vec![];
+    block_on(bar.encode_into_async(&mut bytes)).unwrap();
+    assert_eq!(bytes, bar.encode().unwrap());
+    let decoded = block_on(Bar::decode_async(bytes.as_slice())).unwrap();
+    assert_eq!(decoded.encode().unwrap(), bytes);
+
+    let err = block_on(Foo::decode_async(&[0, 0, 0, 1, 0][..]))
+        .err()
+        .unwrap();
+    assert_eq!(err.path().unwrap(), "Foo.y");
+    assert!(err.is_unexpected_eof());
+}